@@ -649,6 +649,11 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 self.window.load_end(back, forward, root);
             }
 
+            (Msg::LoadTimedOut(_, url), ShutdownState::NotShuttingDown) => {
+                // Let the embedder decide whether to abort, reload or keep waiting.
+                self.window.load_timed_out(url);
+            }
+
             (Msg::DelayedCompositionTimeout(timestamp), ShutdownState::NotShuttingDown) => {
                 debug!("delayed composition timeout!");
                 if let CompositionRequest::DelayedComposite(this_timestamp) =
@@ -1302,6 +1307,26 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 self.on_key_event(key, state, modifiers);
             }
 
+            WindowEvent::PowerSaving(enabled) => {
+                self.on_power_saving_event(enabled);
+            }
+
+            WindowEvent::Visibility(visible) => {
+                self.on_visibility_event(visible);
+            }
+
+            WindowEvent::SuspendTimers(pipeline_id, suspended) => {
+                self.on_suspend_timers_event(pipeline_id, suspended);
+            }
+
+            WindowEvent::NotifyWhenQuiescent(pipeline_id, window_ms, sender) => {
+                self.on_notify_when_quiescent_event(pipeline_id, window_ms, sender);
+            }
+
+            WindowEvent::IsQuiescent(pipeline_id, window_ms, sender) => {
+                self.on_is_quiescent_event(pipeline_id, window_ms, sender);
+            }
+
             WindowEvent::Quit => {
                 if self.shutdown_state == ShutdownState::NotShuttingDown {
                     debug!("Shutting down the constellation for WindowEvent::Quit");
@@ -1814,6 +1839,41 @@ impl<Window: WindowMethods> IOCompositor<Window> {
         }
     }
 
+    fn on_power_saving_event(&self, enabled: bool) {
+        let msg = ConstellationMsg::PowerSaving(enabled);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending power saving state to constellation failed ({}).", e);
+        }
+    }
+
+    fn on_visibility_event(&self, visible: bool) {
+        let msg = ConstellationMsg::Visibility(visible);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending visibility to constellation failed ({}).", e);
+        }
+    }
+
+    fn on_suspend_timers_event(&self, pipeline_id: PipelineId, suspended: bool) {
+        let msg = ConstellationMsg::SuspendTimers(pipeline_id, suspended);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending timer suspension to constellation failed ({}).", e);
+        }
+    }
+
+    fn on_notify_when_quiescent_event(&self, pipeline_id: PipelineId, window_ms: u64, sender: IpcSender<()>) {
+        let msg = ConstellationMsg::NotifyWhenQuiescent(pipeline_id, window_ms, sender);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending quiescence subscription to constellation failed ({}).", e);
+        }
+    }
+
+    fn on_is_quiescent_event(&self, pipeline_id: PipelineId, window_ms: u64, sender: IpcSender<bool>) {
+        let msg = ConstellationMsg::IsQuiescent(pipeline_id, window_ms, sender);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Sending quiescence query to constellation failed ({}).", e);
+        }
+    }
+
     fn fill_paint_request_with_cached_layer_buffers(&mut self, paint_request: &mut PaintRequest) {
         for buffer_request in &mut paint_request.buffer_requests {
             if self.surface_map.mem() == 0 {
@@ -94,6 +94,18 @@ pub enum CompositorMsg {
     TickAnimation(PipelineId, AnimationTickType),
     /// Dispatch a webdriver command
     WebDriverCommand(WebDriverCommandMsg),
+    /// The system entered or left a power saving mode
+    PowerSaving(bool),
+    /// The window was shown or hidden
+    Visibility(bool),
+    /// The embedder wants the timers of a pipeline suspended or resumed
+    SuspendTimers(PipelineId, bool),
+    /// Requests a reply once the documents of a pipeline, and those nested in it, have
+    /// loaded and have no tasks waiting and no timers due within the given number of ms.
+    NotifyWhenQuiescent(PipelineId, u64, IpcSender<()>),
+    /// Requests an immediate reply saying whether the documents of a pipeline, and those
+    /// nested in it, have loaded and have no timers due within the given number of ms.
+    IsQuiescent(PipelineId, u64, IpcSender<bool>),
 }
 
 pub struct SendableFrameTree {
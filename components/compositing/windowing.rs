@@ -9,11 +9,12 @@ use euclid::point::TypedPoint2D;
 use euclid::scale_factor::ScaleFactor;
 use euclid::size::TypedSize2D;
 use euclid::{Point2D, Size2D};
+use ipc_channel::ipc::IpcSender;
 use layers::geometry::DevicePixel;
 use layers::platform::surface::NativeDisplay;
-use msg::constellation_msg::{Key, KeyModifiers, KeyState};
+use msg::constellation_msg::{Key, KeyModifiers, KeyState, PipelineId};
 use net_traits::net_error_list::NetError;
-use script_traits::{MouseButton, TouchpadPressurePhase, TouchEventType, TouchId};
+use script_traits::{MouseButton, TimerSchedulerBackend, TouchpadPressurePhase, TouchEventType, TouchId};
 use std::fmt::{Debug, Error, Formatter};
 use style_traits::cursor::Cursor;
 use url::Url;
@@ -77,6 +78,20 @@ pub enum WindowEvent {
     Quit,
     /// Sent when a key input state changes
     KeyEvent(Key, KeyState, KeyModifiers),
+    /// Sent when the system enters or leaves a power saving mode, e.g. when running on battery.
+    PowerSaving(bool),
+    /// Sent when the window is shown or hidden, e.g. when it is minimized.
+    Visibility(bool),
+    /// Sent to suspend or resume the timers of a pipeline and the documents nested in it, e.g.
+    /// while it is shown in a webview that is off-screen.
+    SuspendTimers(PipelineId, bool),
+    /// Sent to be told once a pipeline and the documents nested in it have loaded and have
+    /// no tasks waiting and no timers due within the given number of ms, e.g. before a
+    /// reftest takes its screenshot.
+    NotifyWhenQuiescent(PipelineId, u64, IpcSender<()>),
+    /// Sent to be told right away whether a pipeline and the documents nested in it have
+    /// loaded and have no timers due within the given number of ms.
+    IsQuiescent(PipelineId, u64, IpcSender<bool>),
 }
 
 impl Debug for WindowEvent {
@@ -99,6 +114,11 @@ impl Debug for WindowEvent {
             WindowEvent::ResetZoom => write!(f, "ResetZoom"),
             WindowEvent::Navigation(..) => write!(f, "Navigation"),
             WindowEvent::Quit => write!(f, "Quit"),
+            WindowEvent::PowerSaving(..) => write!(f, "PowerSaving"),
+            WindowEvent::Visibility(..) => write!(f, "Visibility"),
+            WindowEvent::SuspendTimers(..) => write!(f, "SuspendTimers"),
+            WindowEvent::NotifyWhenQuiescent(..) => write!(f, "NotifyWhenQuiescent"),
+            WindowEvent::IsQuiescent(..) => write!(f, "IsQuiescent"),
         }
     }
 }
@@ -130,6 +150,8 @@ pub trait WindowMethods {
     fn load_end(&self, back: bool, forward: bool, root: bool);
     /// Called when the browser encounters an error while loading a URL
     fn load_error(&self, code: NetError, url: String);
+    /// Called when a page hasn't finished loading within the page load timeout
+    fn load_timed_out(&self, url: Url);
     /// Called when the <head> tag has finished parsing
     fn head_parsed(&self);
 
@@ -161,6 +183,9 @@ pub trait WindowMethods {
     /// Does this window support a clipboard
     fn supports_clipboard(&self) -> bool;
 
+    /// The timer scheduler to use instead of the built-in one, if any. Called once, on startup.
+    fn timer_scheduler_backend(&self) -> Option<Box<TimerSchedulerBackend>>;
+
     /// Add a favicon
     fn set_favicon(&self, url: Url);
 }
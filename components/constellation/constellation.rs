@@ -51,10 +51,11 @@ use rand::{random, Rng, SeedableRng, StdRng};
 use sandboxing::content_process_sandbox_profile;
 use script_traits::{AnimationState, CompositorEvent, ConstellationControlMsg};
 use script_traits::{DocumentState, LayoutControlMsg};
-use script_traits::{IFrameLoadInfo, IFrameSandboxState, TimerEventRequest};
+use script_traits::{IFrameLoadInfo, IFrameSandboxState, TimerSchedulerBackend, TimerSchedulerMsg};
 use script_traits::{LayoutMsg as FromLayoutMsg, ScriptMsg as FromScriptMsg, ScriptThreadFactory};
-use script_traits::{MozBrowserEvent, MozBrowserErrorType};
+use script_traits::{MozBrowserEvent, MozBrowserErrorType, TimerDiagnostics};
 use std::borrow::ToOwned;
+use std::cmp;
 use std::collections::HashMap;
 #[cfg(not(target_os = "windows"))]
 use std::env;
@@ -176,7 +177,7 @@ pub struct Constellation<LTF, STF> {
     /// Bits of state used to interact with the webdriver implementation
     webdriver: WebDriverData,
 
-    scheduler_chan: IpcSender<TimerEventRequest>,
+    scheduler_chan: IpcSender<TimerSchedulerMsg>,
 
     /// A list of child content processes.
     #[cfg_attr(target_os = "windows", allow(dead_code))]
@@ -194,6 +195,9 @@ pub struct Constellation<LTF, STF> {
     /// The random number generator and probability for closing pipelines.
     /// This is for testing the hardening of the constellation.
     random_pipeline_closure: Option<(StdRng, f32)>,
+
+    /// Whether the window is visible, as last reported by the compositor.
+    visible: bool,
 }
 
 /// State needed to construct a constellation.
@@ -218,6 +222,8 @@ pub struct InitialConstellationState {
     pub supports_clipboard: bool,
     /// Optional webrender API reference (if enabled).
     pub webrender_api_sender: Option<webrender_traits::RenderApiSender>,
+    /// The embedder's timer scheduler, if it has one.
+    pub timer_scheduler_backend: Option<Box<TimerSchedulerBackend>>,
 }
 
 /// Stores the navigation context for a single frame in the frame tree.
@@ -374,7 +380,7 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
                     None
                 },
                 webdriver: WebDriverData::new(),
-                scheduler_chan: TimerScheduler::start(),
+                scheduler_chan: TimerScheduler::start(state.timer_scheduler_backend),
                 child_processes: Vec::new(),
                 document_states: HashMap::new(),
                 webrender_api_sender: state.webrender_api_sender,
@@ -386,6 +392,7 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
                     info!("Using seed {} for random pipeline closure.", seed);
                     (rng, prob)
                 }),
+                visible: true,
             };
             let namespace_id = constellation.next_pipeline_namespace_id();
             PipelineNamespace::install(namespace_id);
@@ -622,6 +629,26 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
                 debug!("constellation got key event message");
                 self.handle_key_msg(key, state, modifiers);
             }
+            Request::Compositor(FromCompositorMsg::PowerSaving(enabled)) => {
+                debug!("constellation got power saving message");
+                self.handle_power_saving_msg(enabled);
+            }
+            Request::Compositor(FromCompositorMsg::Visibility(visible)) => {
+                debug!("constellation got visibility message");
+                self.handle_visibility_msg(visible);
+            }
+            Request::Compositor(FromCompositorMsg::SuspendTimers(pipeline_id, suspended)) => {
+                debug!("constellation got suspend timers message");
+                self.handle_suspend_timers_msg(pipeline_id, suspended);
+            }
+            Request::Compositor(FromCompositorMsg::NotifyWhenQuiescent(pipeline_id, window_ms, sender)) => {
+                debug!("constellation got quiescence subscription message");
+                self.handle_notify_when_quiescent_msg(pipeline_id, window_ms, sender);
+            }
+            Request::Compositor(FromCompositorMsg::IsQuiescent(pipeline_id, window_ms, sender)) => {
+                debug!("constellation got quiescence query message");
+                self.handle_is_quiescent_msg(pipeline_id, window_ms, sender);
+            }
             // Load a new page from a typed url
             // If there is already a pending page (self.pending_frames), it will not be overridden;
             // However, if the id is not encompassed by another change, it will be.
@@ -688,6 +715,15 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
                 debug!("constellation got load complete message");
                 self.handle_load_complete_msg(&pipeline_id)
             }
+            // The page-load watchdog of a pipeline expired
+            Request::Script(FromScriptMsg::LoadTimedOut(pipeline_id)) => {
+                debug!("constellation got load timeout message");
+                self.handle_load_timed_out(pipeline_id);
+            }
+            Request::Script(FromScriptMsg::GetTimerDiagnostics(sender)) => {
+                debug!("constellation got timer diagnostics message");
+                self.handle_get_timer_diagnostics_msg(sender);
+            }
             // The DOM load event fired on a document
             Request::Script(FromScriptMsg::DOMLoad(pipeline_id)) => {
                 debug!("constellation got dom load message");
@@ -770,6 +806,10 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
                     }
                 }
             }
+            Request::Script(FromScriptMsg::ChangeFrameVisibility(pipeline_id, visible)) => {
+                debug!("constellation got frame visibility message");
+                self.handle_frame_visibility_msg(pipeline_id, visible);
+            }
             Request::Script(FromScriptMsg::RemoveIFrame(pipeline_id, sender)) => {
                 debug!("constellation got remove iframe message");
                 self.handle_remove_iframe_msg(pipeline_id);
@@ -854,9 +894,22 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
             warn!("Exit bluetooth thread failed ({})", e);
         }
         self.font_cache_thread.exit();
+        self.exit_timer_scheduler();
         self.compositor_proxy.send(ToCompositorMsg::ShutdownComplete);
     }
 
+    /// Shuts down the timer scheduler and waits until it has dropped its pending events,
+    /// so that nothing is delivered to a pipeline that is on its way out.
+    fn exit_timer_scheduler(&mut self) {
+        let (sender, receiver) = ipc::channel().unwrap();
+        match self.scheduler_chan.send(TimerSchedulerMsg::Exit(sender)) {
+            Ok(()) => if let Err(e) = receiver.recv() {
+                warn!("Exit timer scheduler wasn't acknowledged ({})", e);
+            },
+            Err(e) => warn!("Exit timer scheduler failed ({})", e),
+        }
+    }
+
     fn handle_send_error(&mut self, pipeline_id: PipelineId, err: IOError) {
         // Treat send error the same as receiving a panic message
         debug!("Pipeline {:?} send error ({}).", pipeline_id, err);
@@ -1222,6 +1275,159 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
         self.handle_subframe_loaded(pipeline_id);
     }
 
+    fn handle_power_saving_msg(&mut self, enabled: bool) {
+        // While saving power, wakeups for normal priority timers are batched on a coarse grid.
+        let alignment = if enabled {
+            let alignment = prefs::get_pref("dom.timers.power_saving_alignment_ms").as_i64().unwrap_or(16);
+            Some(cmp::max(alignment, 1) as u64)
+        } else {
+            None
+        };
+        if let Err(e) = self.scheduler_chan.send(TimerSchedulerMsg::AlignWakeups(alignment)) {
+            warn!("Sending wakeup alignment to the timer scheduler failed ({}).", e);
+        }
+    }
+
+    fn handle_visibility_msg(&mut self, visible: bool) {
+        self.visible = visible;
+        for frame in self.current_frame_tree_iter(self.root_frame_id) {
+            match self.pipelines.get(&frame.current) {
+                Some(pipeline) => pipeline.change_visibility(visible),
+                None => warn!("Pipeline {:?} visibility changed after closure.", frame.current),
+            }
+        }
+    }
+
+    /// Throttles or unthrottles the timers of a cross-origin frame, including
+    /// the documents nested in it.
+    fn handle_frame_visibility_msg(&mut self, pipeline_id: PipelineId, visible: bool) {
+        let frame_id = match self.pipeline_to_frame_map.get(&pipeline_id) {
+            Some(frame_id) => *frame_id,
+            None => {
+                // The frame is still loading its first document.
+                match self.pipelines.get(&pipeline_id) {
+                    Some(pipeline) => pipeline.change_frame_visibility(visible),
+                    None => warn!("Pipeline {:?} frame visibility changed after closure.", pipeline_id),
+                }
+                return;
+            }
+        };
+        for frame in self.current_frame_tree_iter(Some(frame_id)) {
+            match self.pipelines.get(&frame.current) {
+                Some(pipeline) => pipeline.change_frame_visibility(visible),
+                None => warn!("Pipeline {:?} frame visibility changed after closure.", frame.current),
+            }
+        }
+    }
+
+    /// Suspends or resumes the timers of a pipeline and of the documents nested in it.
+    fn handle_suspend_timers_msg(&mut self, pipeline_id: PipelineId, suspended: bool) {
+        let frame_id = match self.pipeline_to_frame_map.get(&pipeline_id) {
+            Some(frame_id) => *frame_id,
+            None => return warn!("Timer suspension for pipeline {:?} without a frame.", pipeline_id),
+        };
+        for frame in self.current_frame_tree_iter(Some(frame_id)) {
+            match self.pipelines.get(&frame.current) {
+                Some(pipeline) => pipeline.suspend_timers(suspended),
+                None => warn!("Pipeline {:?} timers suspended after closure.", frame.current),
+            }
+        }
+    }
+
+    /// Replies to `sender` once a pipeline and the documents nested in it have all settled.
+    fn handle_notify_when_quiescent_msg(&mut self, pipeline_id: PipelineId, window_ms: u64, sender: IpcSender<()>) {
+        let frame_id = match self.pipeline_to_frame_map.get(&pipeline_id) {
+            Some(frame_id) => *frame_id,
+            None => return warn!("Quiescence subscription for pipeline {:?} without a frame.", pipeline_id),
+        };
+        let mut receivers = vec![];
+        for frame in self.current_frame_tree_iter(Some(frame_id)) {
+            match self.pipelines.get(&frame.current) {
+                Some(pipeline) => {
+                    let (pipeline_sender, receiver) = ipc::channel().expect("ipc channel failure");
+                    pipeline.notify_when_quiescent(window_ms, pipeline_sender);
+                    receivers.push(receiver);
+                }
+                None => warn!("Pipeline {:?} quiescence subscription after closure.", frame.current),
+            }
+        }
+        // A pipeline that closes before it settles drops its sender, and with it the subscription.
+        spawn_named("QuiescenceWaiter".to_owned(), move || {
+            if receivers.iter().all(|receiver| receiver.recv().is_ok()) {
+                let _ = sender.send(());
+            }
+        });
+    }
+
+    /// Replies to `sender` whether a pipeline and the documents nested in it have all settled.
+    fn handle_is_quiescent_msg(&mut self, pipeline_id: PipelineId, window_ms: u64, sender: IpcSender<bool>) {
+        let frame_id = match self.pipeline_to_frame_map.get(&pipeline_id) {
+            Some(frame_id) => *frame_id,
+            None => {
+                warn!("Quiescence query for pipeline {:?} without a frame.", pipeline_id);
+                let _ = sender.send(false);
+                return;
+            },
+        };
+        let mut receivers = vec![];
+        for frame in self.current_frame_tree_iter(Some(frame_id)) {
+            match self.pipelines.get(&frame.current) {
+                Some(pipeline) => {
+                    let (pipeline_sender, receiver) = ipc::channel().expect("ipc channel failure");
+                    pipeline.is_quiescent(window_ms, pipeline_sender);
+                    receivers.push(receiver);
+                }
+                None => warn!("Pipeline {:?} quiescence query after closure.", frame.current),
+            }
+        }
+        // A pipeline that closes before it replies hasn't settled.
+        spawn_named("QuiescenceQuery".to_owned(), move || {
+            let _ = sender.send(receivers.iter().all(|receiver| receiver.recv().unwrap_or(false)));
+        });
+    }
+
+    /// Replies to `sender` with the statistics of the timers of every pipeline and of the scheduler.
+    fn handle_get_timer_diagnostics_msg(&mut self, sender: IpcSender<TimerDiagnostics>) {
+        let receivers: Vec<_> = self.pipelines.values().map(|pipeline| {
+            let (pipeline_sender, receiver) = ipc::channel().expect("ipc channel failure");
+            pipeline.get_timer_stats(pipeline_sender);
+            receiver
+        }).collect();
+        let (stats_sender, stats_receiver) = ipc::channel().expect("ipc channel failure");
+        if let Err(e) = self.scheduler_chan.send(TimerSchedulerMsg::GetStats(stats_sender)) {
+            warn!("Asking the timer scheduler for its statistics failed ({}).", e);
+        }
+        // A pipeline that closes before it replies drops its sender, and is left out.
+        spawn_named("TimerDiagnostics".to_owned(), move || {
+            let diagnostics = TimerDiagnostics {
+                pipelines: receivers.iter().filter_map(|receiver| receiver.recv().ok()).collect(),
+                scheduler: stats_receiver.recv().ok(),
+            };
+            let _ = sender.send(diagnostics);
+        });
+    }
+
+    fn handle_load_timed_out(&mut self, pipeline_id: PipelineId) {
+        let url = match self.pipelines.get(&pipeline_id) {
+            Some(pipeline) => pipeline.url.clone(),
+            None => return warn!("Load timeout for closed pipeline {:?}.", pipeline_id),
+        };
+        warn!("Pipeline {:?} did not finish loading {} in time.", pipeline_id, url);
+
+        let mut webdriver_reset = false;
+        if let Some((expected_pipeline_id, ref reply_chan)) = self.webdriver.load_channel {
+            if expected_pipeline_id == pipeline_id {
+                let _ = reply_chan.send(webdriver_msg::LoadStatus::LoadTimeout);
+                webdriver_reset = true;
+            }
+        }
+        if webdriver_reset {
+            self.webdriver.load_channel = None;
+        }
+
+        self.compositor_proxy.send(ToCompositorMsg::LoadTimedOut(pipeline_id, url));
+    }
+
     fn handle_navigate_msg(&mut self,
                            pipeline_info: Option<(PipelineId, SubpageId)>,
                            direction: constellation_msg::NavigationDirection) {
@@ -1299,6 +1505,8 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
         }
         if let Some(next_pipeline) = self.pipelines.get(&next_pipeline_id) {
             next_pipeline.thaw();
+            // The window may have been shown or hidden since the pipeline was frozen.
+            next_pipeline.change_visibility(self.visible);
         }
 
         // Set paint permissions correctly for the compositor layers.
@@ -1486,6 +1694,27 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
         // Find the script channel for the given parent pipeline,
         // and pass the event to that script thread.
         match msg {
+            WebDriverCommandMsg::AdvanceTimers(pipeline_id, ms) => {
+                // The timers of the documents in frames move along with the page.
+                let frame_id = match self.pipeline_to_frame_map.get(&pipeline_id) {
+                    Some(frame_id) => *frame_id,
+                    None => return warn!("Pipeline {:?} AdvanceTimers without a frame.", pipeline_id),
+                };
+                let mut failed = vec![];
+                for frame in self.current_frame_tree_iter(Some(frame_id)) {
+                    let command = webdriver_msg::WebDriverScriptCommand::AdvanceTimers(ms);
+                    let control_msg = ConstellationControlMsg::WebDriverScriptCommand(frame.current, command);
+                    match self.pipelines.get(&frame.current) {
+                        Some(pipeline) => if let Err(e) = pipeline.script_chan.send(control_msg) {
+                            failed.push((frame.current, e));
+                        },
+                        None => warn!("Pipeline {:?} AdvanceTimers after closure.", frame.current),
+                    }
+                }
+                for (pipeline_id, e) in failed {
+                    self.handle_send_error(pipeline_id, e);
+                }
+            },
             WebDriverCommandMsg::GetWindowSize(_, reply) => {
                let _ = reply.send(self.window_size);
             },
@@ -1596,6 +1825,13 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
             }
         }
 
+        // Documents start out visible.
+        if !self.visible {
+            if let Some(pipeline) = self.pipelines.get(&frame_change.new_pipeline_id) {
+                pipeline.change_visibility(false);
+            }
+        }
+
         // Build frame tree and send permission
         self.send_frame_tree_and_grant_paint_permission();
 
@@ -1927,6 +2163,11 @@ impl<LTF: LayoutThreadFactory, STF: ScriptThreadFactory> Constellation<LTF, STF>
             self.pending_frames.remove(pending_index);
         }
 
+        // Drop the timer events the pipeline and its workers are still waiting for.
+        if let Err(e) = self.scheduler_chan.send(TimerSchedulerMsg::ExitPipeline(pipeline_id)) {
+            warn!("Sending pipeline exit to the timer scheduler failed ({}).", e);
+        }
+
         // Inform script, compositor that this pipeline has exited.
         match exit_mode {
             ExitPipelineMode::Normal => pipeline.exit(),
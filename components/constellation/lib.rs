@@ -46,8 +46,10 @@ mod pipeline;
 #[cfg(not(target_os = "windows"))]
 mod sandboxing;
 mod timer_scheduler;
+pub mod timer_wheel;
 
 pub use constellation::{Constellation, InitialConstellationState};
 pub use pipeline::UnprivilegedPipelineContent;
+pub use timer_scheduler::TimerScheduler;
 #[cfg(not(target_os = "windows"))]
 pub use sandboxing::content_process_sandbox_profile;
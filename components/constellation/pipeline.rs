@@ -23,8 +23,8 @@ use net_traits::image_cache_thread::ImageCacheThread;
 use profile_traits::mem as profile_mem;
 use profile_traits::time;
 use script_traits::{ConstellationControlMsg, InitialScriptState, MozBrowserEvent};
-use script_traits::{LayoutControlMsg, LayoutMsg, NewLayoutInfo, ScriptMsg};
-use script_traits::{ScriptToCompositorMsg, ScriptThreadFactory, TimerEventRequest};
+use script_traits::{LayoutControlMsg, LayoutMsg, NewLayoutInfo, PipelineTimerStats, ScriptMsg};
+use script_traits::{ScriptToCompositorMsg, ScriptThreadFactory, TimerSchedulerMsg};
 use std::collections::HashMap;
 use std::mem;
 use std::sync::mpsc::{Receiver, Sender, channel};
@@ -78,7 +78,7 @@ pub struct InitialPipelineState {
     /// A channel to report panics
     pub panic_chan: IpcSender<PanicMsg>,
     /// A channel to schedule timer events.
-    pub scheduler_chan: IpcSender<TimerEventRequest>,
+    pub scheduler_chan: IpcSender<TimerSchedulerMsg>,
     /// A channel to the compositor.
     pub compositor_proxy: Box<CompositorProxy + 'static + Send>,
     /// A channel to the developer tools, if applicable.
@@ -327,6 +327,43 @@ impl Pipeline {
         }
     }
 
+    pub fn change_visibility(&self, visible: bool) {
+        if let Err(e) = self.script_chan.send(ConstellationControlMsg::ChangeVisibility(self.id, visible)) {
+            warn!("Sending visibility change message failed ({}).", e);
+        }
+    }
+
+    pub fn change_frame_visibility(&self, visible: bool) {
+        if let Err(e) = self.script_chan.send(ConstellationControlMsg::ChangeFrameVisibility(self.id, visible)) {
+            warn!("Sending frame visibility change message failed ({}).", e);
+        }
+    }
+
+    pub fn suspend_timers(&self, suspended: bool) {
+        if let Err(e) = self.script_chan.send(ConstellationControlMsg::SuspendTimers(self.id, suspended)) {
+            warn!("Sending timer suspension message failed ({}).", e);
+        }
+    }
+
+    pub fn notify_when_quiescent(&self, window_ms: u64, sender: IpcSender<()>) {
+        let msg = ConstellationControlMsg::NotifyWhenQuiescent(self.id, window_ms, sender);
+        if let Err(e) = self.script_chan.send(msg) {
+            warn!("Sending quiescence subscription failed ({}).", e);
+        }
+    }
+
+    pub fn is_quiescent(&self, window_ms: u64, sender: IpcSender<bool>) {
+        if let Err(e) = self.script_chan.send(ConstellationControlMsg::IsQuiescent(self.id, window_ms, sender)) {
+            warn!("Sending quiescence query failed ({}).", e);
+        }
+    }
+
+    pub fn get_timer_stats(&self, sender: IpcSender<PipelineTimerStats>) {
+        if let Err(e) = self.script_chan.send(ConstellationControlMsg::GetTimerStats(self.id, sender)) {
+            warn!("Sending timer statistics request failed ({}).", e);
+        }
+    }
+
     pub fn force_exit(&self) {
         if let Err(e) = self.script_chan.send(ConstellationControlMsg::ExitPipeline(self.id)) {
             warn!("Sending script exit message failed ({}).", e);
@@ -380,7 +417,7 @@ pub struct UnprivilegedPipelineContent {
     parent_info: Option<(PipelineId, SubpageId)>,
     constellation_chan: IpcSender<ScriptMsg>,
     layout_to_constellation_chan: IpcSender<LayoutMsg>,
-    scheduler_chan: IpcSender<TimerEventRequest>,
+    scheduler_chan: IpcSender<TimerSchedulerMsg>,
     devtools_chan: Option<IpcSender<ScriptToDevtoolsControlMsg>>,
     script_to_compositor_chan: IpcSender<ScriptToCompositorMsg>,
     bluetooth_thread: IpcSender<BluetoothMethodMsg>,
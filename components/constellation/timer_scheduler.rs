@@ -3,44 +3,553 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use ipc_channel::ipc::{self, IpcSender};
-use script_traits::{TimerEvent, TimerEventRequest};
-use std::cmp::{self, Ord};
-use std::collections::BinaryHeap;
-use std::sync::mpsc;
-use std::sync::mpsc::TryRecvError::{Disconnected, Empty};
-use std::thread;
+use msg::constellation_msg::PipelineId;
+use script_traits::{TimerEvent, TimerEventId, TimerEventRequest, TimerLatencyStats, TimerPriority};
+use script_traits::{TimerSchedulerBackend, TimerSchedulerMsg, TimerSchedulerStats, TimerSource};
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::TryRecvError::{self, Disconnected, Empty};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, Thread};
 use std::time::{Duration, Instant};
+use timer_wheel::TimerWheel;
+use util::opts;
+
+/// How long before the deadline of a user-blocking event the scheduler stops
+/// parking for the whole remaining time. Parking can overshoot by a
+/// millisecond or more, depending on the OS, so from then on it parks for half
+/// of what is left each time.
+const PARK_SLACK_NS: u32 = 1_500_000;
+
+/// How close to the deadline of a user-blocking event parking stops being
+/// worth it, and the scheduler yields instead.
+const YIELD_THRESHOLD_NS: u32 = 50_000;
+
+/// How many messages may wait between the proxy and the scheduler thread
+/// before the proxy stops reading from the IPC channel.
+const MAX_QUEUED_MESSAGES: usize = 256;
 
 pub struct TimerScheduler;
 
 struct ScheduledEvent {
     request: TimerEventRequest,
-    for_time: Instant,
+    deadline: Instant,
 }
 
-impl Ord for ScheduledEvent {
-    fn cmp(&self, other: &ScheduledEvent) -> cmp::Ordering {
-        self.for_time.cmp(&other.for_time).reverse()
+impl ScheduledEvent {
+    fn pipeline_id(&self) -> PipelineId {
+        let TimerEventRequest(_, _, _, _, _, pipeline_id, _) = self.request;
+        pipeline_id
+    }
+
+    fn is_for_window(&self, pipeline_id: PipelineId, id: TimerEventId) -> bool {
+        match self.request {
+            TimerEventRequest(_, TimerSource::FromWindow(source_id), event_id, _, _, _, _) => {
+                source_id == pipeline_id && event_id == id
+            },
+            TimerEventRequest(_, TimerSource::FromWorker, _, _, _, _, _) => false,
+        }
     }
 }
 
-impl PartialOrd for ScheduledEvent {
-    fn partial_cmp(&self, other: &ScheduledEvent) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+/// Where a pending window event is kept, so that it can be found again when it gets cancelled.
+#[derive(Clone, Copy)]
+enum EventSlot {
+    Wheel(u64),
+    Precise,
+}
+
+/// How late events of one priority were delivered.
+struct DeliveryLatency {
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl DeliveryLatency {
+    fn new() -> DeliveryLatency {
+        DeliveryLatency {
+            count: 0,
+            total: Duration::from_secs(0),
+            max: Duration::from_secs(0),
+        }
+    }
+
+    fn record(&mut self, lateness: Duration) {
+        self.count += 1;
+        self.total = self.total + lateness;
+        if lateness > self.max {
+            self.max = lateness;
+        }
+    }
+
+    fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::from_secs(0);
+        }
+        // Dividing the Duration itself would truncate the count to a u32.
+        let total_ns = self.total.as_secs() * 1_000_000_000 + self.total.subsec_nanos() as u64;
+        let mean_ns = total_ns / self.count;
+        Duration::new(mean_ns / 1_000_000_000, (mean_ns % 1_000_000_000) as u32)
+    }
+
+    fn stats(&self) -> TimerLatencyStats {
+        TimerLatencyStats {
+            events: self.count,
+            mean_ms: duration_ms(self.mean()),
+            max_ms: duration_ms(self.max),
+        }
     }
 }
 
-impl Eq for ScheduledEvent {}
-impl PartialEq for ScheduledEvent {
-    fn eq(&self, other: &ScheduledEvent) -> bool {
-        self as *const ScheduledEvent == other as *const ScheduledEvent
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000. + duration.subsec_nanos() as f64 / 1_000_000.
+}
+
+/// Counts how often the scheduler thread wakes up, in windows of about a second.
+struct WakeupRate {
+    window_start: Instant,
+    wakeups: u64,
+    last_rate: f64,
+}
+
+impl WakeupRate {
+    fn new() -> WakeupRate {
+        WakeupRate {
+            window_start: Instant::now(),
+            wakeups: 0,
+            last_rate: 0.,
+        }
+    }
+
+    fn record(&mut self) {
+        self.roll();
+        self.wakeups += 1;
+    }
+
+    /// The rate over the last complete window.
+    fn rate(&mut self) -> f64 {
+        self.roll();
+        self.last_rate
+    }
+
+    fn roll(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.last_rate = self.wakeups as f64 * 1000. / duration_ms(elapsed);
+            self.window_start = Instant::now();
+            self.wakeups = 0;
+        }
     }
 }
 
-impl TimerScheduler {
-    pub fn start() -> IpcSender<TimerEventRequest> {
-        let (req_ipc_sender, req_ipc_receiver) = ipc::channel().unwrap();
-        let (req_sender, req_receiver) = mpsc::sync_channel(1);
+/// The events waiting to be dispatched by the scheduler thread.
+struct ScheduledEvents {
+    start: Instant,
+    /// Events with normal priority, keyed by their due time in milliseconds
+    /// since the scheduler started.
+    wheel: TimerWheel<ScheduledEvent>,
+    /// User-blocking events, which are dispatched at their exact deadline
+    /// rather than on a millisecond tick. There are only ever a few of them.
+    precise: Vec<ScheduledEvent>,
+    window_events: HashMap<(PipelineId, TimerEventId), EventSlot>,
+    /// If set, normal priority events are due on multiples of this many ticks only.
+    alignment: Option<u64>,
+    normal_latency: DeliveryLatency,
+    user_blocking_latency: DeliveryLatency,
+    wakeups: WakeupRate,
+    /// Events that were cancelled or whose pipeline exited before they were due.
+    dropped: u64,
+    /// Events whose receiver was gone when they were due.
+    stale: u64,
+    /// How far the scheduler's clock has been moved ahead of real time.
+    skipped: Duration,
+    /// How much further the clock may still be moved ahead, in virtual time mode.
+    virtual_time_budget: Option<Duration>,
+}
+
+impl ScheduledEvents {
+    fn new(virtual_time_budget: Option<Duration>) -> ScheduledEvents {
+        ScheduledEvents {
+            start: Instant::now(),
+            wheel: TimerWheel::new(),
+            precise: vec![],
+            window_events: HashMap::new(),
+            alignment: None,
+            normal_latency: DeliveryLatency::new(),
+            user_blocking_latency: DeliveryLatency::new(),
+            wakeups: WakeupRate::new(),
+            dropped: 0,
+            stale: 0,
+            skipped: Duration::from_secs(0),
+            virtual_time_budget: virtual_time_budget,
+        }
+    }
+
+    /// The current time on the scheduler's clock.
+    fn now(&self) -> Instant {
+        Instant::now() + self.skipped
+    }
+
+    /// Milliseconds elapsed since the scheduler started, rounded down.
+    fn ticks(&self) -> u64 {
+        let elapsed = self.now() - self.start;
+        elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1_000_000) as u64
+    }
+
+    /// Schedules a request that the proxy received at `received_at`. The delay
+    /// is measured from then rather than from now, so that time spent in the
+    /// message queue doesn't make the event late.
+    fn schedule(&mut self, request: TimerEventRequest, received_at: Instant) {
+        let deadline = received_at + self.skipped + Duration::from_millis(request.3.get());
+        self.insert(ScheduledEvent { request: request, deadline: deadline });
+    }
+
+    fn insert(&mut self, event: ScheduledEvent) {
+        let TimerEventRequest(_, source, id, _, priority, _, _) = event.request;
+        let slot = match priority {
+            TimerPriority::Normal => {
+                // Round the due time up, so that events are never delivered early.
+                let due = event.deadline - self.start;
+                let mut ticks = due.as_secs() * 1000 + (due.subsec_nanos() / 1_000_000) as u64;
+                if due.subsec_nanos() % 1_000_000 != 0 {
+                    ticks += 1;
+                }
+                // Share the wakeup with every other event in the same interval.
+                if let Some(alignment) = self.alignment {
+                    ticks = (ticks + alignment - 1) / alignment * alignment;
+                }
+                EventSlot::Wheel(self.wheel.insert(ticks, event))
+            },
+            TimerPriority::UserBlocking => {
+                self.precise.push(event);
+                EventSlot::Precise
+            },
+        };
+        if let TimerSource::FromWindow(pipeline_id) = source {
+            self.window_events.insert((pipeline_id, id), slot);
+        }
+    }
+
+    fn cancel(&mut self, pipeline_id: PipelineId, id: TimerEventId) {
+        match self.window_events.remove(&(pipeline_id, id)) {
+            Some(EventSlot::Wheel(due)) => {
+                self.wheel.remove(due, |event| event.is_for_window(pipeline_id, id));
+            },
+            Some(EventSlot::Precise) => {
+                self.precise.retain(|event| !event.is_for_window(pipeline_id, id));
+            },
+            None => return,
+        }
+        self.dropped += 1;
+    }
+
+    fn exit_pipeline(&mut self, pipeline_id: PipelineId) {
+        let before = self.wheel.len() + self.precise.len();
+        self.wheel.retain(|event| event.pipeline_id() != pipeline_id);
+        self.precise.retain(|event| event.pipeline_id() != pipeline_id);
+        self.dropped += (before - self.wheel.len() - self.precise.len()) as u64;
+        let window_events: Vec<_> = self.window_events.keys()
+                                                      .filter(|&&(event_pipeline_id, _)| event_pipeline_id == pipeline_id)
+                                                      .cloned()
+                                                      .collect();
+        for key in window_events {
+            self.window_events.remove(&key);
+        }
+    }
+
+    /// Sends every event whose deadline has passed.
+    fn dispatch_due_events(&mut self) {
+        let mut due_events = self.wheel.advance(self.ticks());
+        let now = self.now();
+        let (due_precise, precise): (Vec<_>, Vec<_>) =
+            self.precise.drain(..).partition(|event| event.deadline <= now);
+        self.precise = precise;
+        due_events.extend(due_precise);
+
+        for mut event in due_events {
+            let TimerEventRequest(_, source, id, _, priority, _, repeat) = event.request;
+            let delivered = event.request.0.send(TimerEvent(source, id, priority)).is_ok();
+
+            let lateness = self.now() - event.deadline;
+            match priority {
+                TimerPriority::Normal => self.normal_latency.record(lateness),
+                TimerPriority::UserBlocking => self.user_blocking_latency.record(lateness),
+            }
+
+            match repeat {
+                // A repeating event stays scheduled until it is cancelled,
+                // or until nobody listens for it any more.
+                Some(period) if delivered => {
+                    event.deadline = event.deadline + Duration::from_millis(cmp::max(period.get(), 1));
+                    self.insert(event);
+                },
+                _ => {
+                    if let TimerSource::FromWindow(pipeline_id) = source {
+                        self.window_events.remove(&(pipeline_id, id));
+                    }
+                    if !delivered {
+                        self.stale += 1;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Blocks until the next event is due or the thread gets unparked.
+    fn wait(&mut self) {
+        let now = self.now();
+        let next_precise = self.precise.iter().map(|event| event.deadline).min();
+
+        if let Some(budget) = self.virtual_time_budget {
+            let next_wheel = self.wheel.next_due().map(|due| self.start + Duration::from_millis(due));
+            let next = match (next_precise, next_wheel) {
+                (Some(precise), Some(wheel)) => Some(cmp::min(precise, wheel)),
+                (next, None) | (None, next) => next,
+            };
+            if let Some(next) = next {
+                if next > now && budget > Duration::from_secs(0) {
+                    // Skip ahead to the next event instead of waiting for it.
+                    let skip = cmp::min(next - now, budget);
+                    self.skipped = self.skipped + skip;
+                    self.virtual_time_budget = Some(budget - skip);
+                    if budget == skip {
+                        debug!("Virtual time budget exhausted, timers wait for real time again.");
+                    }
+                    return;
+                }
+            }
+        }
+        if let Some(deadline) = next_precise {
+            if deadline <= now + Duration::new(0, YIELD_THRESHOLD_NS) {
+                // Too close to the deadline to risk oversleeping.
+                return thread::yield_now();
+            }
+        }
+
+        let until_precise = next_precise.map(|deadline| {
+            let remaining = deadline - now;
+            let slack = Duration::new(0, PARK_SLACK_NS);
+            if remaining > slack { remaining - slack } else { remaining / 2 }
+        });
+        let until_wheel = self.wheel.next_due().map(|due| {
+            let due = self.start + Duration::from_millis(due);
+            if due > now { due - now } else { Duration::from_secs(0) }
+        });
+        match (until_precise, until_wheel) {
+            (None, None) => thread::park(),
+            (Some(timeout), None) | (None, Some(timeout)) => thread::park_timeout(timeout),
+            (Some(precise), Some(wheel)) => thread::park_timeout(if precise < wheel { precise } else { wheel }),
+        }
+        self.wakeups.record();
+    }
+
+    fn stats(&mut self) -> TimerSchedulerStats {
+        let mut pending = HashMap::new();
+        {
+            let mut count = |event: &ScheduledEvent| *pending.entry(event.pipeline_id()).or_insert(0) += 1;
+            self.wheel.for_each(&mut count);
+            for event in &self.precise {
+                count(event);
+            }
+        }
+        TimerSchedulerStats {
+            pending: pending.into_iter().collect(),
+            wakeups_per_second: self.wakeups.rate(),
+            normal_latency: self.normal_latency.stats(),
+            user_blocking_latency: self.user_blocking_latency.stats(),
+            dropped: self.dropped,
+            stale: self.stale,
+            queued: 0,
+            max_queued: 0,
+        }
+    }
+
+    /// Drops every pending event without delivering it, returning how many there were.
+    fn drain(&mut self) -> usize {
+        let pending = self.wheel.len() + self.precise.len();
+        self.wheel.retain(|_| false);
+        self.precise.clear();
+        self.window_events.clear();
+        self.dropped += pending as u64;
+        pending
+    }
+
+    fn log_latency(&self) {
+        debug!("Timer event lateness: normal mean {:?} max {:?} over {} events, \
+                user-blocking mean {:?} max {:?} over {} events.",
+               self.normal_latency.mean(), self.normal_latency.max, self.normal_latency.count,
+               self.user_blocking_latency.mean(), self.user_blocking_latency.max,
+               self.user_blocking_latency.count);
+    }
+}
+
+/// How the message queue coped with the rate of incoming messages.
+struct QueueStats {
+    received: u64,
+    /// Window requests that were superseded or cancelled before the scheduler saw them.
+    coalesced: u64,
+    /// The most messages that were waiting at once.
+    high_water_mark: usize,
+    /// How often the proxy had to wait for the scheduler to make room.
+    full_waits: u64,
+}
+
+struct QueuedMessage {
+    received_at: Instant,
+    msg: TimerSchedulerMsg,
+}
+
+struct QueueState {
+    messages: VecDeque<QueuedMessage>,
+    closed: bool,
+    stats: QueueStats,
+}
+
+impl QueueState {
+    /// Folds `msg` into the queued messages, returning it if it still has to be queued.
+    fn coalesce(&mut self, msg: TimerSchedulerMsg) -> Option<TimerSchedulerMsg> {
+        let superseded = match msg {
+            // A window only ever waits for its latest request.
+            TimerSchedulerMsg::Request(TimerEventRequest(_, TimerSource::FromWindow(pipeline_id), _, _, _, _, _)) => {
+                self.messages.iter().position(|queued| match queued.msg {
+                    TimerSchedulerMsg::Request(TimerEventRequest(_, TimerSource::FromWindow(queued_id), _, _, _, _, _)) => {
+                        queued_id == pipeline_id
+                    },
+                    _ => false,
+                })
+            },
+            TimerSchedulerMsg::Cancel(pipeline_id, id) => {
+                let position = self.messages.iter().position(|queued| match queued.msg {
+                    TimerSchedulerMsg::Request(TimerEventRequest(_, TimerSource::FromWindow(queued_id),
+                                                                 queued_event_id, _, _, _, _)) => {
+                        queued_id == pipeline_id && queued_event_id == id
+                    },
+                    _ => false,
+                });
+                if let Some(index) = position {
+                    // The cancelled request never reaches the scheduler, so neither does the cancellation.
+                    self.messages.remove(index);
+                    self.stats.coalesced += 1;
+                    return None;
+                }
+                // Otherwise the request is already scheduled; pass the cancellation on.
+                None
+            },
+            // Requests from the pipeline that haven't reached the scheduler yet can go right away.
+            TimerSchedulerMsg::ExitPipeline(pipeline_id) => {
+                let before = self.messages.len();
+                self.messages.retain(|queued| match queued.msg {
+                    TimerSchedulerMsg::Request(TimerEventRequest(_, _, _, _, _, queued_id, _)) => queued_id != pipeline_id,
+                    _ => true,
+                });
+                self.stats.coalesced += (before - self.messages.len()) as u64;
+                None
+            },
+            TimerSchedulerMsg::Request(_) |
+            TimerSchedulerMsg::AlignWakeups(_) |
+            TimerSchedulerMsg::GetStats(_) |
+            TimerSchedulerMsg::Exit(_) => None,
+        };
+        if let Some(index) = superseded {
+            self.messages.remove(index);
+            self.stats.coalesced += 1;
+        }
+        Some(msg)
+    }
+}
+
+/// A bounded queue of messages from the proxy to the scheduler thread.
+struct MessageQueue {
+    state: Mutex<QueueState>,
+    not_full: Condvar,
+}
+
+impl MessageQueue {
+    fn new() -> MessageQueue {
+        MessageQueue {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::new(),
+                closed: false,
+                stats: QueueStats {
+                    received: 0,
+                    coalesced: 0,
+                    high_water_mark: 0,
+                    full_waits: 0,
+                },
+            }),
+            not_full: Condvar::new(),
+        }
+    }
+
+    /// Queues a message, blocking while the queue is full.
+    fn push(&self, msg: TimerSchedulerMsg) {
+        let received_at = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.stats.received += 1;
+        let msg = match state.coalesce(msg) {
+            Some(msg) => msg,
+            None => return,
+        };
+        if state.messages.len() >= MAX_QUEUED_MESSAGES {
+            state.stats.full_waits += 1;
+            debug!("Timer scheduler queue is full, waiting for the scheduler to catch up.");
+        }
+        while state.messages.len() >= MAX_QUEUED_MESSAGES {
+            state = self.not_full.wait(state).unwrap();
+        }
+        state.messages.push_back(QueuedMessage { received_at: received_at, msg: msg });
+        let queued = state.messages.len();
+        if queued > state.stats.high_water_mark {
+            state.stats.high_water_mark = queued;
+        }
+    }
+
+    fn try_pop(&self) -> Result<QueuedMessage, TryRecvError> {
+        let mut state = self.state.lock().unwrap();
+        match state.messages.pop_front() {
+            Some(msg) => {
+                self.not_full.notify_one();
+                Ok(msg)
+            },
+            None if state.closed => Err(Disconnected),
+            None => Err(Empty),
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+    }
+
+    /// The number of messages that never reached the scheduler.
+    fn coalesced(&self) -> u64 {
+        self.state.lock().unwrap().stats.coalesced
+    }
+
+    /// The number of messages waiting to be handled, and the most there ever were.
+    fn depth(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        (state.messages.len(), state.stats.high_water_mark)
+    }
+
+    fn log_stats(&self) {
+        let state = self.state.lock().unwrap();
+        debug!("Timer scheduler queue: {} messages received, {} coalesced, at most {} queued, full {} times.",
+               state.stats.received, state.stats.coalesced, state.stats.high_water_mark, state.stats.full_waits);
+    }
+}
+
+/// The backend used unless the embedder supplies one: a thread of its own,
+/// which parks until the next event is due.
+struct ThreadBackend {
+    queue: Arc<MessageQueue>,
+    thread: Thread,
+}
+
+impl ThreadBackend {
+    fn start() -> ThreadBackend {
+        let queue = Arc::new(MessageQueue::new());
+        let req_receiver = queue.clone();
 
         // We could do this much more directly with recv_timeout
         // (https://github.com/rust-lang/rfcs/issues/962).
@@ -50,64 +559,145 @@ impl TimerScheduler {
         let timeout_thread = thread::Builder::new()
             .name(String::from("TimerScheduler"))
             .spawn(move || {
-                // We maintain a priority queue of future events, sorted by due time.
-                let mut scheduled_events = BinaryHeap::<ScheduledEvent>::new();
+                let budget = opts::get().virtual_time_budget.map(Duration::from_millis);
+                let mut scheduled_events = ScheduledEvents::new(budget);
+                let mut exit_ack = None;
                 loop {
-                    let now = Instant::now();
                     // Dispatch any events whose due time is past
-                    loop {
-                        match scheduled_events.peek() {
-                            // Dispatch the event if its due time is past
-                            Some(event) if event.for_time <= now => {
-                                let TimerEventRequest(ref sender, source, id, _) = event.request;
-                                let _ = sender.send(TimerEvent(source, id));
-                            },
-                            // Otherwise, we're done dispatching events
-                            _ => break,
-                        }
-                        // Remove the event from the priority queue
-                        // (Note this only executes when the first event has been dispatched
-                        scheduled_events.pop();
-                    }
+                    scheduled_events.dispatch_due_events();
                     // Look to see if there are any incoming events
-                    match req_receiver.try_recv() {
-                        // If there is an event, add it to the priority queue
-                        Ok(req) => {
-                            let TimerEventRequest(_, _, _, delay) = req;
-                            let schedule = Instant::now() + Duration::from_millis(delay.get());
-                            let event = ScheduledEvent { request: req, for_time: schedule };
-                            scheduled_events.push(event);
+                    match req_receiver.try_pop() {
+                        // If there is an event, schedule it
+                        Ok(QueuedMessage { received_at, msg: TimerSchedulerMsg::Request(req) }) => {
+                            scheduled_events.schedule(req, received_at)
+                        },
+                        // If an event is no longer expected, forget about it
+                        Ok(QueuedMessage { msg: TimerSchedulerMsg::Cancel(pipeline_id, id), .. }) => {
+                            scheduled_events.cancel(pipeline_id, id)
+                        },
+                        // If a pipeline has exited, forget about all of its events
+                        Ok(QueuedMessage { msg: TimerSchedulerMsg::ExitPipeline(pipeline_id), .. }) => {
+                            scheduled_events.exit_pipeline(pipeline_id)
+                        },
+                        // If the power saving mode changed, adjust the alignment of future events
+                        Ok(QueuedMessage { msg: TimerSchedulerMsg::AlignWakeups(alignment), .. }) => {
+                            scheduled_events.alignment = alignment
+                        },
+                        // If someone wants to know how the scheduler is doing, tell them
+                        Ok(QueuedMessage { msg: TimerSchedulerMsg::GetStats(sender), .. }) => {
+                            let mut stats = scheduled_events.stats();
+                            stats.dropped += req_receiver.coalesced();
+                            let (queued, max_queued) = req_receiver.depth();
+                            stats.queued = queued;
+                            stats.max_queued = max_queued;
+                            let _ = sender.send(stats);
+                        },
+                        // If we are asked to shut down, stop here and acknowledge once the
+                        // pending events, and the channels they hold, have been dropped
+                        Ok(QueuedMessage { msg: TimerSchedulerMsg::Exit(sender), .. }) => {
+                            exit_ack = Some(sender);
+                            break;
                         },
                         // If there is no incoming event, park the thread,
                         // it will either be unparked when a new event arrives,
                         // or by a timeout.
-                        Err(Empty) => match scheduled_events.peek() {
-                            None => thread::park(),
-                            Some(event) => thread::park_timeout(event.for_time - now),
-                        },
+                        Err(Empty) => scheduled_events.wait(),
                         // If the channel is closed, we are done.
                         Err(Disconnected) => break,
                     }
                 }
-                // This thread can terminate if the req_ipc_sender is dropped.
-                warn!("TimerScheduler thread terminated.");
+                let dropped = scheduled_events.drain();
+                if dropped > 0 {
+                    debug!("Timer scheduler dropped {} pending events on exit.", dropped);
+                }
+                scheduled_events.log_latency();
+                req_receiver.log_stats();
+                match exit_ack {
+                    Some(sender) => {
+                        let _ = sender.send(());
+                    },
+                    None => warn!("TimerScheduler thread terminated."),
+                }
             })
             .unwrap()
             .thread()
             .clone();
 
-        // A proxy that just routes incoming IPC requests over the MPSC channel to the timeout thread,
-        // and unparks the timeout thread each time. Note that if unpark is called while the timeout
-        // thread isn't parked, this causes the next call to thread::park by the timeout thread
-        // not to block. This means that the timeout thread won't park when there is a request
-        // waiting in the MPSC channel buffer.
+        ThreadBackend {
+            queue: queue,
+            thread: timeout_thread,
+        }
+    }
+
+    /// Passes a message over the message queue to the timeout thread, and unparks it. Note that
+    /// if unpark is called while the timeout thread isn't parked, this causes the next call to
+    /// thread::park by the timeout thread not to block. This means that the timeout thread won't
+    /// park when there is a request waiting in the message queue.
+    fn push(&self, msg: TimerSchedulerMsg) {
+        self.queue.push(msg);
+        self.thread.unpark();
+    }
+}
+
+impl TimerSchedulerBackend for ThreadBackend {
+    fn arm(&mut self, request: TimerEventRequest) {
+        self.push(TimerSchedulerMsg::Request(request));
+    }
+
+    fn cancel(&mut self, pipeline_id: PipelineId, id: TimerEventId) {
+        self.push(TimerSchedulerMsg::Cancel(pipeline_id, id));
+    }
+
+    fn shutdown(&mut self) {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.push(TimerSchedulerMsg::Exit(sender));
+        self.queue.close();
+        let _ = receiver.recv();
+    }
+
+    fn exit_pipeline(&mut self, pipeline_id: PipelineId) {
+        self.push(TimerSchedulerMsg::ExitPipeline(pipeline_id));
+    }
+
+    fn align_wakeups(&mut self, alignment: Option<u64>) {
+        self.push(TimerSchedulerMsg::AlignWakeups(alignment));
+    }
+
+    fn report_stats(&mut self, sender: IpcSender<TimerSchedulerStats>) {
+        self.push(TimerSchedulerMsg::GetStats(sender));
+    }
+}
+
+impl TimerScheduler {
+    /// Starts routing timer requests to `backend`, or to a scheduler thread of
+    /// our own if there is none.
+    pub fn start(backend: Option<Box<TimerSchedulerBackend>>) -> IpcSender<TimerSchedulerMsg> {
+        let (req_ipc_sender, req_ipc_receiver) = ipc::channel().unwrap();
+        let mut backend: Box<TimerSchedulerBackend> = match backend {
+            Some(backend) => backend,
+            None => box ThreadBackend::start(),
+        };
+
+        // A proxy that just routes incoming IPC requests to the backend.
         thread::Builder::new()
             .name(String::from("TimerProxy"))
             .spawn(move || {
                 while let Ok(req) = req_ipc_receiver.recv() {
-                    req_sender.send(req).unwrap();
-                    timeout_thread.unpark();
+                    match req {
+                        TimerSchedulerMsg::Request(request) => backend.arm(request),
+                        TimerSchedulerMsg::Cancel(pipeline_id, id) => backend.cancel(pipeline_id, id),
+                        TimerSchedulerMsg::ExitPipeline(pipeline_id) => backend.exit_pipeline(pipeline_id),
+                        TimerSchedulerMsg::AlignWakeups(alignment) => backend.align_wakeups(alignment),
+                        TimerSchedulerMsg::GetStats(sender) => backend.report_stats(sender),
+                        TimerSchedulerMsg::Exit(sender) => {
+                            backend.shutdown();
+                            let _ = sender.send(());
+                            // Dropping the receiver makes any later requests fail right away.
+                            return;
+                        },
+                    }
                 }
+                backend.shutdown();
                 // This thread can terminate if the req_ipc_sender is dropped.
                 warn!("TimerProxy thread terminated.");
             })
@@ -0,0 +1,188 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A hierarchical timer wheel with millisecond ticks.
+//!
+//! Level 0 has one slot per tick for the next `SLOTS` ticks, and each level
+//! above it covers `SLOTS` times the span of the level below. Entries are
+//! inserted in O(1) and moved down one level at a time as their deadline
+//! approaches, so the cost per tick doesn't depend on how many timers are
+//! outstanding. Deadlines beyond the top level wait in an overflow list.
+
+use std::cmp;
+use std::mem;
+
+const LEVEL_BITS: u64 = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+const LEVELS: usize = 4;
+
+pub struct TimerWheel<T> {
+    /// The next tick to be dispatched; every earlier tick has been handled.
+    current: u64,
+    /// `levels[level][slot]` holds the entries for one slot along with their due tick.
+    levels: Vec<Vec<Vec<(u64, T)>>>,
+    /// The number of entries stored in each level.
+    counts: [usize; LEVELS],
+    /// Entries that are due after the span covered by the top level.
+    overflow: Vec<(u64, T)>,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new() -> TimerWheel<T> {
+        TimerWheel {
+            current: 0,
+            levels: (0..LEVELS).map(|_| (0..SLOTS).map(|_| vec![]).collect()).collect(),
+            counts: [0; LEVELS],
+            overflow: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.iter().fold(self.overflow.len(), |sum, count| sum + count)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an entry due at tick `due`. Entries whose tick has already passed
+    /// are dispatched by the next call to `advance`. Returns the tick the entry
+    /// was filed under, which is needed to `remove` it again.
+    pub fn insert(&mut self, due: u64, entry: T) -> u64 {
+        let due = cmp::max(due, self.current);
+        self.place(due, entry);
+        due
+    }
+
+    /// Removes the first entry filed under tick `due` for which `matches` returns true.
+    pub fn remove<F>(&mut self, due: u64, matches: F) -> Option<T> where F: Fn(&T) -> bool {
+        for level in 0..LEVELS {
+            let slot = ((due >> (LEVEL_BITS * level as u64)) & SLOT_MASK) as usize;
+            let entries = &mut self.levels[level][slot];
+            if let Some(index) = entries.iter().position(|&(d, ref entry)| d == due && matches(entry)) {
+                self.counts[level] -= 1;
+                return Some(entries.swap_remove(index).1);
+            }
+        }
+        self.overflow.iter()
+                     .position(|&(d, ref entry)| d == due && matches(entry))
+                     .map(|index| self.overflow.swap_remove(index).1)
+    }
+
+    /// Drops every entry for which `keep` returns false.
+    pub fn retain<F>(&mut self, keep: F) where F: Fn(&T) -> bool {
+        for level in 0..LEVELS {
+            for slot in &mut self.levels[level] {
+                let before = slot.len();
+                slot.retain(|&(_, ref entry)| keep(entry));
+                self.counts[level] -= before - slot.len();
+            }
+        }
+        self.overflow.retain(|&(_, ref entry)| keep(entry));
+    }
+
+    /// Calls `f` with every entry, in no particular order.
+    pub fn for_each<F>(&self, mut f: F) where F: FnMut(&T) {
+        for level in &self.levels {
+            for slot in level {
+                for &(_, ref entry) in slot {
+                    f(entry);
+                }
+            }
+        }
+        for &(_, ref entry) in &self.overflow {
+            f(entry);
+        }
+    }
+
+    /// Returns the earliest tick at which an entry is due, if any.
+    pub fn next_due(&self) -> Option<u64> {
+        let mut earliest = self.overflow.iter().map(|&(due, _)| due).min();
+        for level in 0..LEVELS {
+            if self.counts[level] == 0 {
+                continue;
+            }
+            // The first non-empty slot after the current position holds the
+            // earliest entries of this level. Above level 0, the slot of the
+            // current position was already cascaded, so anything left in it
+            // belongs to the next turn of the wheel and is checked last.
+            let start = ((self.current >> (LEVEL_BITS * level as u64)) & SLOT_MASK) as usize;
+            let first = if level == 0 { 0 } else { 1 };
+            let slot = (first..first + SLOTS).map(|offset| &self.levels[level][(start + offset) % SLOTS])
+                                             .find(|slot| !slot.is_empty());
+            if let Some(due) = slot.and_then(|slot| slot.iter().map(|&(due, _)| due).min()) {
+                earliest = Some(earliest.map_or(due, |earliest| cmp::min(earliest, due)));
+            }
+        }
+        earliest
+    }
+
+    /// Removes and returns every entry due at or before tick `now`, in tick order.
+    pub fn advance(&mut self, now: u64) -> Vec<T> {
+        let mut due = vec![];
+        while self.current <= now {
+            if self.is_empty() {
+                self.current = now + 1;
+                break;
+            }
+
+            if self.counts[0] == 0 {
+                // Nothing can fire before the next level 0 wrap-around; skip to it.
+                let boundary = (self.current | SLOT_MASK) + 1;
+                self.current = cmp::min(boundary, now + 1);
+                if self.current == boundary {
+                    self.cascade();
+                }
+                continue;
+            }
+
+            let slot = (self.current & SLOT_MASK) as usize;
+            let entries = mem::replace(&mut self.levels[0][slot], vec![]);
+            self.counts[0] -= entries.len();
+            due.extend(entries.into_iter().map(|(_, entry)| entry));
+
+            self.current += 1;
+            if self.current & SLOT_MASK == 0 {
+                self.cascade();
+            }
+        }
+        due
+    }
+
+    fn place(&mut self, due: u64, entry: T) {
+        let delta = due - self.current;
+        for level in 0..LEVELS {
+            let shift = LEVEL_BITS * level as u64;
+            if delta < 1 << (shift + LEVEL_BITS) {
+                let slot = ((due >> shift) & SLOT_MASK) as usize;
+                self.levels[level][slot].push((due, entry));
+                self.counts[level] += 1;
+                return;
+            }
+        }
+        self.overflow.push((due, entry));
+    }
+
+    /// Called whenever `current` crosses a level 0 boundary: redistributes the
+    /// slots of the higher levels whose span has just begun.
+    fn cascade(&mut self) {
+        for level in 1..LEVELS {
+            let shift = LEVEL_BITS * level as u64;
+            let slot = ((self.current >> shift) & SLOT_MASK) as usize;
+            let entries = mem::replace(&mut self.levels[level][slot], vec![]);
+            self.counts[level] -= entries.len();
+            for (due, entry) in entries {
+                self.place(due, entry);
+            }
+            if slot != 0 {
+                return;
+            }
+        }
+        let overflow = mem::replace(&mut self.overflow, vec![]);
+        for (due, entry) in overflow {
+            self.place(due, entry);
+        }
+    }
+}
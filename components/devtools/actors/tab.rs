@@ -66,6 +66,7 @@ pub struct TabActorMsg {
     timelineActor: String,
     profilerActor: String,
     performanceActor: String,
+    timersActor: String,
 }
 
 pub struct TabActor {
@@ -78,6 +79,7 @@ pub struct TabActor {
     pub profiler: String,
     pub performance: String,
     pub thread: String,
+    pub timers: String,
 }
 
 impl Actor for TabActor {
@@ -156,6 +158,7 @@ impl TabActor {
             timelineActor: self.timeline.clone(),
             profilerActor: self.profiler.clone(),
             performanceActor: self.performance.clone(),
+            timersActor: self.timers.clone(),
         }
     }
 }
@@ -3,8 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use ipc_channel::ipc::IpcSender;
 use protocol::JsonPacketStream;
 use serde_json::Value;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::net::TcpStream;
 
@@ -18,6 +20,15 @@ struct ThreadAttachedReply {
     why: WhyMsg,
 }
 
+#[derive(Serialize)]
+struct ThreadPausedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    actor: String,
+    why: WhyMsg,
+}
+
 #[derive(Serialize)]
 enum PoppedFrameMsg {}
 
@@ -41,13 +52,40 @@ struct ReconfigureReply {
 
 pub struct ThreadActor {
     name: String,
+    /// The clients attached to the thread, which are told when it pauses.
+    streams: RefCell<Vec<TcpStream>>,
+    /// Resumes the script, if it is paused.
+    resume_chan: RefCell<Option<IpcSender<()>>>,
 }
 
 impl ThreadActor {
     pub fn new(name: String) -> ThreadActor {
         ThreadActor {
             name: name,
+            streams: RefCell::new(vec![]),
+            resume_chan: RefCell::new(None),
+        }
+    }
+
+    /// Tells the attached clients that the script paused for the reason `why`,
+    /// until one of them resumes it with `resume_chan`. Without a client to
+    /// resume it, the script goes on right away.
+    pub fn pause(&self, registry: &ActorRegistry, why: &str, resume_chan: IpcSender<()>) {
+        let mut streams = self.streams.borrow_mut();
+        if streams.is_empty() {
+            let _ = resume_chan.send(());
+            return;
+        }
+        let msg = ThreadPausedMsg {
+            from: self.name(),
+            type_: "paused".to_owned(),
+            actor: registry.new_name("pause"),
+            why: WhyMsg { type_: why.to_owned() },
+        };
+        for stream in &mut *streams {
+            stream.write_json_packet(&msg);
         }
+        *self.resume_chan.borrow_mut() = Some(resume_chan);
     }
 }
 
@@ -70,11 +108,15 @@ impl Actor for ThreadActor {
                     poppedFrames: vec![],
                     why: WhyMsg { type_: "attached".to_owned() },
                 };
+                self.streams.borrow_mut().push(stream.try_clone().unwrap());
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             },
 
             "resume" => {
+                if let Some(resume_chan) = self.resume_chan.borrow_mut().take() {
+                    let _ = resume_chan.send(());
+                }
                 let msg = ThreadResumedReply {
                     from: self.name(),
                     type_: "resumed".to_owned(),
@@ -7,7 +7,7 @@ use actors::framerate::FramerateActor;
 use actors::memory::{MemoryActor, TimelineMemoryReply};
 use devtools_traits::DevtoolScriptControlMsg;
 use devtools_traits::DevtoolScriptControlMsg::{DropTimelineMarkers, SetTimelineMarkers};
-use devtools_traits::{PreciseTime, TimelineMarker, TimelineMarkerType};
+use devtools_traits::{PreciseTime, TimelineMarker, TimelineMarkerType, TimerMarkerDetail};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use msg::constellation_msg::PipelineId;
 use protocol::JsonPacketStream;
@@ -68,6 +68,7 @@ struct TimelineMarkerReply {
     end: HighResolutionStamp,
     stack: Option<Vec<()>>,
     endStack: Option<Vec<()>>,
+    timer: Option<TimerMarkerDetail>,
 }
 
 #[derive(Serialize)]
@@ -129,7 +130,8 @@ impl TimelineActor {
                script_sender: IpcSender<DevtoolScriptControlMsg>) -> TimelineActor {
 
         let marker_types = vec!(TimelineMarkerType::Reflow,
-                                TimelineMarkerType::DOMEvent);
+                                TimelineMarkerType::DOMEvent,
+                                TimelineMarkerType::Timer);
 
         TimelineActor {
             name: name,
@@ -289,6 +291,7 @@ impl Emitter {
             end: HighResolutionStamp::new(self.start_stamp, payload.end_time),
             stack: payload.start_stack,
             endStack: payload.end_stack,
+            timer: payload.timer,
         }
     }
 
@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Lists the timers a page has scheduled, so that the timeouts and intervals
+//! keeping it busy can be told apart, sets breakpoints on their callbacks,
+//! slows them down to show how the page does on a slow device, and cancels
+//! those that run away.
+
+use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use devtools_traits::{DevtoolScriptControlMsg, TimerInfo, TimerKind};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use protocol::JsonPacketStream;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::TcpStream;
+
+#[derive(Serialize)]
+struct TimerMsg {
+    handle: Option<i32>,
+    #[serde(rename = "type")]
+    type_: String,
+    remaining: u64,
+    period: Option<u64>,
+    nestingLevel: u32,
+    installedFrom: Option<String>,
+    installedStack: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GetTimersReply {
+    from: String,
+    timers: Vec<TimerMsg>,
+}
+
+#[derive(Serialize)]
+struct SetBreakpointReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct PauseBeforeNextReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct SetDelayMultiplierReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct CancelReply {
+    from: String,
+    cancelled: bool,
+}
+
+pub struct TimersActor {
+    pub name: String,
+    pub pipeline: PipelineId,
+    pub script_chan: IpcSender<DevtoolScriptControlMsg>,
+}
+
+impl Actor for TimersActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      _registry: &ActorRegistry,
+                      msg_type: &str,
+                      msg: &BTreeMap<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getTimers" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::GetTimers(self.pipeline, tx)).unwrap();
+                let timers = try!(rx.recv().map_err(|_| ()));
+
+                let msg = GetTimersReply {
+                    from: self.name(),
+                    timers: timers.into_iter().map(encode_timer).collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            // Pauses before the callback of the timer with the given handle, or of any timer.
+            "setBreakpoint" => {
+                let handle = msg.get("handle").and_then(&Value::as_i64).map(|handle| handle as i32);
+                let enabled = msg.get("enabled").and_then(&Value::as_boolean).unwrap_or(true);
+                self.script_chan.send(DevtoolScriptControlMsg::SetTimerBreakpoint(self.pipeline, handle, enabled))
+                                .unwrap();
+
+                let msg = SetBreakpointReply {
+                    from: self.name(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            // Pauses once, before whichever timer callback runs next.
+            "pauseBeforeNext" => {
+                self.script_chan.send(DevtoolScriptControlMsg::PauseBeforeNextTimer(self.pipeline)).unwrap();
+
+                let msg = PauseBeforeNextReply {
+                    from: self.name(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            // Stretches the delays of the timers, to emulate a slow device.
+            "setDelayMultiplier" => {
+                let multiplier = msg.get("multiplier").and_then(&Value::as_u64).unwrap_or(1);
+                self.script_chan.send(DevtoolScriptControlMsg::SetTimerDelayMultiplier(self.pipeline, multiplier))
+                                .unwrap();
+
+                let msg = SetDelayMultiplierReply {
+                    from: self.name(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            // Clears the timeout or interval with the given handle, e.g. to stop one that runs away.
+            "cancel" => {
+                let handle = try!(msg.get("handle").and_then(&Value::as_i64).ok_or(())) as i32;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::CancelTimer(self.pipeline, handle, tx)).unwrap();
+                let cancelled = try!(rx.recv().map_err(|_| ()));
+
+                let msg = CancelReply {
+                    from: self.name(),
+                    cancelled: cancelled,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+fn encode_timer(timer: TimerInfo) -> TimerMsg {
+    TimerMsg {
+        handle: timer.handle,
+        type_: match timer.kind {
+            TimerKind::Timeout => "timeout",
+            TimerKind::Interval => "interval",
+            TimerKind::Internal => "internal",
+        }.to_owned(),
+        remaining: timer.remaining,
+        period: timer.period,
+        nestingLevel: timer.nesting_level,
+        installedFrom: timer.installed_from,
+        installedStack: timer.installed_stack,
+    }
+}
@@ -41,6 +41,7 @@ use actors::root::RootActor;
 use actors::tab::TabActor;
 use actors::thread::ThreadActor;
 use actors::timeline::TimelineActor;
+use actors::timers::TimersActor;
 use actors::worker::WorkerActor;
 use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsControlMsg};
 use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NetworkEvent};
@@ -73,6 +74,7 @@ mod actors {
     pub mod tab;
     pub mod thread;
     pub mod timeline;
+    pub mod timers;
     pub mod worker;
 }
 mod protocol;
@@ -256,7 +258,7 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         let (pipeline, worker_id) = ids;
 
         //TODO: move all this actor creation into a constructor method on TabActor
-        let (tab, console, inspector, timeline, profiler, performance, thread) = {
+        let (tab, console, inspector, timeline, profiler, performance, thread, timers) = {
             let console = ConsoleActor {
                 name: actors.new_name("console"),
                 script_chan: script_sender.clone(),
@@ -272,6 +274,12 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 pipeline: pipeline,
             };
 
+            let timers = TimersActor {
+                name: actors.new_name("timers"),
+                pipeline: pipeline,
+                script_chan: script_sender.clone(),
+            };
+
             let timeline = TimelineActor::new(actors.new_name("timeline"),
                                               pipeline,
                                               script_sender);
@@ -292,12 +300,13 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 profiler: profiler.name(),
                 performance: performance.name(),
                 thread: thread.name(),
+                timers: timers.name(),
             };
 
             let root = actors.find_mut::<RootActor>("root");
             root.tabs.push(tab.name.clone());
 
-            (tab, console, inspector, timeline, profiler, performance, thread)
+            (tab, console, inspector, timeline, profiler, performance, thread, timers)
         };
 
         if let Some(id) = worker_id {
@@ -318,6 +327,7 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         actors.register(box profiler);
         actors.register(box performance);
         actors.register(box thread);
+        actors.register(box timers);
     }
 
     fn handle_console_message(actors: Arc<Mutex<ActorRegistry>>,
@@ -356,6 +366,18 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         }
     }
 
+    fn handle_paused_before_timer(actors: Arc<Mutex<ActorRegistry>>,
+                                  id: PipelineId,
+                                  resume_chan: IpcSender<()>,
+                                  actor_pipelines: &HashMap<PipelineId, String>) {
+        let actors = actors.lock().unwrap();
+        // Without a tab to pause in, dropping `resume_chan` lets the script go on.
+        if let Some(tab_name) = actor_pipelines.get(&id) {
+            let thread_actor = actors.find::<ThreadActor>(&actors.find::<TabActor>(tab_name).thread);
+            thread_actor.pause(&actors, "breakpoint", resume_chan);
+        }
+    }
+
     fn find_console_actor(actors: Arc<Mutex<ActorRegistry>>,
                           id: PipelineId,
                           worker_id: Option<WorkerId>,
@@ -561,6 +583,12 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 handle_console_message(actors.clone(), id, None, console_message,
                                        &actor_pipelines, &actor_workers)
             },
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::PausedBeforeTimer(
+                        id,
+                        _worker_id,
+                        _handle,
+                        resume_chan)) =>
+                handle_paused_before_timer(actors.clone(), id, resume_chan, &actor_pipelines),
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(
                         request_id, network_event)) => {
                 // copy the accepted_connections vector
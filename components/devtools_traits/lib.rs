@@ -88,6 +88,10 @@ pub enum ScriptToDevtoolsControlMsg {
 
     /// Report a CSS parse error for the given pipeline
     ReportCSSError(PipelineId, CSSError),
+    /// The script of a pipeline paused right before the callback of the timer with the
+    /// given handle, on a timer breakpoint. It resumes once told to on the given sender,
+    /// or once the sender is dropped.
+    PausedBeforeTimer(PipelineId, Option<WorkerId>, i32, IpcSender<()>),
 }
 
 /// Serialized JS return values
@@ -144,12 +148,25 @@ pub struct TimelineMarker {
     pub start_stack: Option<Vec<()>>,
     pub end_time: PreciseTime,
     pub end_stack: Option<Vec<()>>,
+    /// Which timer the marker is about, for the markers of timers.
+    pub timer: Option<TimerMarkerDetail>,
+}
+
+/// The timer a timeline marker is about.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TimerMarkerDetail {
+    /// The handle `setTimeout` or `setInterval` returned.
+    pub handle: i32,
+    /// How long the timer was set to wait, in ms.
+    pub delay: u64,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Deserialize, Serialize)]
 pub enum TimelineMarkerType {
     Reflow,
     DOMEvent,
+    /// Timers being set, firing and being cleared.
+    Timer,
 }
 
 /// The properties of a DOM node as computed by layout.
@@ -214,6 +231,50 @@ pub enum DevtoolScriptControlMsg {
     /// Request a callback directed at the given actor name from the next animation frame
     /// executed in the given pipeline.
     RequestAnimationFrame(PipelineId, String),
+    /// Retrieve the timers scheduled by the global of the given pipeline.
+    GetTimers(PipelineId, IpcSender<Vec<TimerInfo>>),
+    /// Pause in the debugger before the callback of the timer with the given handle runs,
+    /// or before that of any timer of the given pipeline, or stop doing so.
+    SetTimerBreakpoint(PipelineId, Option<i32>, bool),
+    /// Pause in the debugger once, right before the next timer callback of the given pipeline runs.
+    PauseBeforeNextTimer(PipelineId),
+    /// Make the timers of the given pipeline wait the given number of times as long as they
+    /// ask for, to emulate a slow device.
+    SetTimerDelayMultiplier(PipelineId, u64),
+    /// Clear the timeout or interval with the given handle, as if the page had, replying
+    /// whether there was such a timer.
+    CancelTimer(PipelineId, i32, IpcSender<bool>),
+}
+
+/// What a timer runs.
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
+pub enum TimerKind {
+    /// A callback passed to `setTimeout`.
+    Timeout,
+    /// A callback passed to `setInterval`.
+    Interval,
+    /// A timer of the engine itself, such as a network timeout.
+    Internal,
+}
+
+/// A timer scheduled by a global, as shown by the devtools.
+#[derive(Deserialize, Serialize)]
+pub struct TimerInfo {
+    /// The handle `setTimeout` or `setInterval` returned for the timer, if page script set it.
+    pub handle: Option<i32>,
+    pub kind: TimerKind,
+    /// How long until the timer is due, in ms. The timers of a document in the session
+    /// history keep how long they had left.
+    pub remaining: u64,
+    /// How often the timer repeats, in ms, if it does.
+    pub period: Option<u64>,
+    /// How deeply the timer is nested in timers that set timers.
+    pub nesting_level: u32,
+    /// Where the script was that set the timer, if it is known.
+    pub installed_from: Option<String>,
+    /// The stack of the script that set the timer, one frame per line, followed by the
+    /// stacks of the timers that led to it, if it is known.
+    pub installed_stack: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -326,6 +387,7 @@ impl StartedTimelineMarker {
             start_stack: self.start_stack,
             end_time: PreciseTime::now(),
             end_stack: None,
+            timer: None,
         }
     }
 }
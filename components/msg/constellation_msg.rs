@@ -188,6 +188,7 @@ bitflags! {
 
 #[derive(Deserialize, Serialize)]
 pub enum WebDriverCommandMsg {
+    AdvanceTimers(PipelineId, u64),
     GetWindowSize(PipelineId, IpcSender<WindowSizeData>),
     LoadUrl(PipelineId, LoadData, IpcSender<LoadStatus>),
     Refresh(PipelineId, IpcSender<LoadStatus>),
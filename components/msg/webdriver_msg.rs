@@ -25,7 +25,8 @@ pub enum WebDriverScriptCommand {
     GetUrl(IpcSender<Url>),
     IsEnabled(String, IpcSender<Result<bool, ()>>),
     IsSelected(String, IpcSender<Result<bool, ()>>),
-    GetTitle(IpcSender<String>)
+    GetTitle(IpcSender<String>),
+    AdvanceTimers(u64),
 }
 
 #[derive(Deserialize, Serialize)]
@@ -7,10 +7,12 @@ use hyper::header::ContentType;
 use hyper::http::RawStatus;
 use hyper::mime::{Mime, SubLevel, TopLevel};
 use mime_classifier::MIMEClassifier;
-use net_traits::ProgressMsg::Done;
+use net_traits::ProgressMsg::{Done, Payload};
 use net_traits::response::HttpsState;
 use net_traits::{LoadConsumer, LoadData, Metadata, NetworkError};
 use resource_thread::{CancellationListener, send_error, start_sending_sniffed_opt};
+use std::fs::File;
+use std::io::Read;
 use std::sync::Arc;
 use url::Url;
 use util::resource_files::resources_dir_path;
@@ -50,6 +52,33 @@ pub fn factory(mut load_data: LoadData,
         "failure" | "not-found" =>
             url_from_non_relative_scheme(&mut load_data, &(url.path().to_owned() + ".html")),
         "sslfail" => url_from_non_relative_scheme(&mut load_data, "badcert.html"),
+        "timers" => {
+            // Served under its about: URL, which is what lets it collect the timer statistics.
+            let mut path = resources_dir_path();
+            path.push("about-timers.html");
+            let mut bytes = vec![];
+            if let Err(e) = File::open(&path).and_then(|mut file| file.read_to_end(&mut bytes)) {
+                send_error(load_data.url, NetworkError::Internal(e.to_string()), start_chan);
+                return
+            }
+            let metadata = Metadata {
+                final_url: load_data.url,
+                content_type: Some(ContentType(Mime(TopLevel::Text, SubLevel::Html, vec![]))),
+                charset: Some("utf-8".to_owned()),
+                headers: None,
+                status: Some(RawStatus(200, "OK".into())),
+                https_state: HttpsState::None,
+            };
+            if let Ok(chan) = start_sending_sniffed_opt(start_chan,
+                                                        metadata,
+                                                        classifier,
+                                                        &bytes,
+                                                        load_data.context) {
+                let _ = chan.send(Payload(bytes));
+                let _ = chan.send(Done(Ok(())));
+            }
+            return
+        }
         _ => {
             send_error(load_data.url, NetworkError::Internal("Unknown about: URL.".to_owned()), start_chan);
             return
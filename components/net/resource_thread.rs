@@ -17,10 +17,12 @@ use http_loader::{self, HttpState};
 use hyper::client::pool::Pool;
 use hyper::header::{ContentType, Header, SetCookie};
 use hyper::mime::{Mime, SubLevel, TopLevel};
+use hyper::net::NetworkConnector;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use mime_classifier::{ApacheBugFlag, MIMEClassifier, NoSniffFlag};
 use net_traits::LoadContext;
 use net_traits::ProgressMsg::Done;
+use net_traits::hosts::replace_hosts;
 use net_traits::{AsyncResponseTarget, Metadata, ProgressMsg, ResponseAction, CoreResourceThread};
 use net_traits::{CoreResourceMsg, CookieSource, LoadConsumer, LoadData, LoadResponse, ResourceId};
 use net_traits::{NetworkError, WebSocketCommunicate, WebSocketConnectData, ResourceThreads};
@@ -194,6 +196,8 @@ impl ResourceChannelManager {
                     self.resource_manager.load(load_data, consumer, id_sender, control_sender.clone()),
                 CoreResourceMsg::WebsocketConnect(connect, connect_data) =>
                     self.resource_manager.websocket_connect(connect, connect_data),
+                CoreResourceMsg::Preconnect(url) =>
+                    self.resource_manager.preconnect(url),
                 CoreResourceMsg::SetCookiesForUrl(request, cookie_list, source) =>
                     self.resource_manager.set_cookies_for_url(request, cookie_list, source),
                 CoreResourceMsg::GetCookiesForUrl(url, consumer, source) => {
@@ -485,4 +489,25 @@ impl CoreResourceManager {
                          connect_data: WebSocketConnectData) {
         websocket_loader::init(connect, connect_data, self.cookie_jar.clone());
     }
+
+    /// Opens a connection to the host of `url` and leaves it in the pool,
+    /// where the next load from that host picks it up.
+    fn preconnect(&self, url: Url) {
+        match url.scheme() {
+            "http" | "https" => {},
+            _ => return,
+        }
+        let connector = self.connector.clone();
+        spawn_named(format!("Preconnect to {}", url), move || {
+            let url = replace_hosts(&url);
+            let host = match url.host_str() {
+                Some(host) => host.to_owned(),
+                None => return,
+            };
+            let port = url.port_or_known_default().unwrap_or(80);
+            if let Err(e) = connector.connect(&host, port, url.scheme()) {
+                debug!("Preconnecting to {} failed ({}).", url, e);
+            }
+        });
+    }
 }
@@ -121,7 +121,10 @@ pub fn init(connect: WebSocketCommunicate, connect_data: WebSocketConnectData, c
                         ws_sender_incoming.lock().unwrap().send_message(&pong).unwrap();
                         continue;
                     },
-                    Type::Pong => continue,
+                    Type::Pong => {
+                        let _ = resource_event_sender.send(WebSocketNetworkEvent::Pong);
+                        continue;
+                    },
                     Type::Close => {
                         ws_sender_incoming.lock().unwrap().send_message(&message).unwrap();
                         let code = message.cd_status_code;
@@ -152,6 +155,13 @@ pub fn init(connect: WebSocketCommunicate, connect_data: WebSocketConnectData, c
                         };
                         ws_sender_outgoing.lock().unwrap().send_message(&message).unwrap();
                     },
+                    WebSocketDomAction::Ping => {
+                        // Keepalive pings go out when the connection may well be gone
+                        // already; the incoming messages fail the connection then.
+                        if let Err(e) = ws_sender_outgoing.lock().unwrap().send_message(&Message::ping(vec![])) {
+                            debug!("Failed to send a WebSocket ping: {:?}", e);
+                        }
+                    },
                 }
             }
         });
@@ -303,6 +303,8 @@ pub enum MessageData {
 pub enum WebSocketDomAction {
     SendMessage(MessageData),
     Close(Option<u16>, Option<String>),
+    /// Checks that the connection is alive, which the server answers with a pong.
+    Ping,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -311,6 +313,8 @@ pub enum WebSocketNetworkEvent {
     MessageReceived(MessageData),
     Close(Option<u16>, String),
     Fail,
+    /// The server answered a ping.
+    Pong,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -332,6 +336,8 @@ pub enum CoreResourceMsg {
     Load(LoadData, LoadConsumer, Option<IpcSender<ResourceId>>),
     /// Try to make a websocket connection to a URL.
     WebsocketConnect(WebSocketCommunicate, WebSocketConnectData),
+    /// Open a connection to the host of a URL, for a load expected to come soon.
+    Preconnect(Url),
     /// Store a set of cookies for a given originating URL
     SetCookiesForUrl(Url, String, CookieSource),
     /// Retrieve the stored cookies for a given URL
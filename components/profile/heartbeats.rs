@@ -51,6 +51,9 @@ pub fn init() {
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptEvent);
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptUpdateReplacedElement);
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptSetViewport);
+    maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptTimerSchedule);
+    maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptTimerFire);
+    maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptTimerCallback);
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptWebSocketEvent);
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ScriptWorkerEvent);
     maybe_create_heartbeat(&mut hbs, ProfilerCategory::ApplicationHeartbeat);
@@ -98,11 +98,14 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::LayoutDispListBuild |
             ProfilerCategory::LayoutDamagePropagate |
             ProfilerCategory::PaintingPerTile |
-            ProfilerCategory::PaintingPrepBuff => "+ ",
+            ProfilerCategory::PaintingPrepBuff |
+            ProfilerCategory::ScriptTimerSchedule |
+            ProfilerCategory::ScriptTimerFire => "+ ",
             ProfilerCategory::LayoutParallelWarmup |
             ProfilerCategory::LayoutSelectorMatch |
             ProfilerCategory::LayoutTreeBuilder |
-            ProfilerCategory::LayoutTextShaping => "| + ",
+            ProfilerCategory::LayoutTextShaping |
+            ProfilerCategory::ScriptTimerCallback => "| + ",
             _ => ""
         };
         let name = match *self {
@@ -145,6 +148,9 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
             ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
             ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
+            ProfilerCategory::ScriptTimerSchedule => "Script Timer Schedule",
+            ProfilerCategory::ScriptTimerFire => "Script Timer Fire",
+            ProfilerCategory::ScriptTimerCallback => "Script Timer Callback",
             ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
             ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
             ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
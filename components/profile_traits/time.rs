@@ -75,6 +75,9 @@ pub enum ProfilerCategory {
     ScriptResize,
     ScriptSetViewport,
     ScriptTimerEvent,
+    ScriptTimerSchedule,
+    ScriptTimerFire,
+    ScriptTimerCallback,
     ScriptStylesheetLoad,
     ScriptUpdateReplacedElement,
     ScriptWebSocketEvent,
@@ -5,7 +5,7 @@
 use devtools_traits::TimelineMarkerType;
 use devtools_traits::{AutoMargins, CONSOLE_API, CachedConsoleMessage, CachedConsoleMessageTypes};
 use devtools_traits::{ComputedNodeLayout, ConsoleAPI, PageError, ScriptToDevtoolsControlMsg};
-use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, PAGE_ERROR, TimelineMarker};
+use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, PAGE_ERROR, TimelineMarker, TimerInfo};
 use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
 use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
@@ -224,6 +224,26 @@ pub fn handle_wants_live_notifications(global: &GlobalRef, send_notifications: b
     global.set_devtools_wants_updates(send_notifications);
 }
 
+pub fn handle_get_timers(global: &GlobalRef, reply: IpcSender<Vec<TimerInfo>>) {
+    reply.send(global.describe_timers()).unwrap();
+}
+
+pub fn handle_set_timer_breakpoint(global: &GlobalRef, handle: Option<i32>, enabled: bool) {
+    global.set_timer_breakpoint(handle, enabled);
+}
+
+pub fn handle_pause_before_next_timer(global: &GlobalRef) {
+    global.pause_before_next_timer();
+}
+
+pub fn handle_set_timer_delay_multiplier(global: &GlobalRef, multiplier: u64) {
+    global.set_timer_delay_multiplier(multiplier);
+}
+
+pub fn handle_cancel_timer(global: &GlobalRef, handle: i32, reply: IpcSender<bool>) {
+    reply.send(global.cancel_timer(handle)).unwrap();
+}
+
 pub fn handle_set_timeline_markers(context: &BrowsingContext,
                                    marker_types: Vec<TimelineMarkerType>,
                                    reply: IpcSender<TimelineMarker>) {
@@ -9,9 +9,10 @@ use dom::bindings::js::JS;
 use dom::document::Document;
 use msg::constellation_msg::PipelineId;
 use net_traits::{PendingAsyncLoad, CoreResourceThread, LoadContext};
-use net_traits::{RequestSource, AsyncResponseTarget};
+use net_traits::RequestSource;
 use std::sync::Arc;
 use std::thread;
+use time;
 use url::Url;
 
 #[derive(JSTraceable, PartialEq, Clone, Debug, HeapSizeOf)]
@@ -56,30 +57,30 @@ pub struct LoadBlocker {
     /// The document whose load event is blocked by this object existing.
     doc: JS<Document>,
     /// The load that is blocking the document's load event.
-    load: Option<LoadType>,
+    load: Option<(LoadId, LoadType)>,
 }
 
 impl LoadBlocker {
     /// Mark the document's load event as blocked on this new load.
     pub fn new(doc: &Document, load: LoadType) -> LoadBlocker {
-        doc.add_blocking_load(load.clone());
+        let id = doc.add_blocking_load(load.clone());
         LoadBlocker {
             doc: JS::from_ref(doc),
-            load: Some(load),
+            load: Some((id, load)),
         }
     }
 
     /// Remove this load from the associated document's list of blocking loads.
     pub fn terminate(blocker: &mut Option<LoadBlocker>) {
         if let Some(this) = blocker.as_mut() {
-            this.doc.finish_load(this.load.take().unwrap());
+            this.doc.finish_load(this.load.take().unwrap().0);
         }
         *blocker = None;
     }
 
     /// Return the url associated with this load.
     pub fn url(&self) -> Option<&Url> {
-        self.load.as_ref().map(LoadType::url)
+        self.load.as_ref().map(|&(_, ref load)| load.url())
     }
 }
 
@@ -91,6 +92,17 @@ impl Drop for LoadBlocker {
     }
 }
 
+/// A load that delays the load event of the document.
+#[derive(JSTraceable, HeapSizeOf)]
+struct BlockingLoad {
+    id: LoadId,
+    load: LoadType,
+    /// When the load started delaying the load event, in ns.
+    since: u64,
+    /// Whether the load was already reported for delaying the load event too long.
+    overdue: bool,
+}
+
 #[derive(JSTraceable, HeapSizeOf)]
 pub struct DocumentLoader {
     /// We use an `Arc<CoreResourceThread>` here in order to avoid file descriptor exhaustion when there
@@ -98,7 +110,13 @@ pub struct DocumentLoader {
     #[ignore_heap_size_of = "channels are hard"]
     pub resource_thread: Arc<CoreResourceThread>,
     pipeline: Option<PipelineId>,
-    blocking_loads: Vec<LoadType>,
+    next_load_id: u64,
+    /// The load of the document itself, if it is loaded from the network.
+    page_source_load: Option<LoadId>,
+    blocking_loads: Vec<BlockingLoad>,
+    /// The loads that were released because they delayed the load event too
+    /// long, but didn't finish yet.
+    released_loads: Vec<LoadId>,
     events_inhibited: bool,
 }
 
@@ -113,52 +131,75 @@ impl DocumentLoader {
                          pipeline: Option<PipelineId>,
                          initial_load: Option<Url>)
                          -> DocumentLoader {
-        let initial_loads = initial_load.into_iter().map(LoadType::PageSource).collect();
-
-        DocumentLoader {
+        let mut loader = DocumentLoader {
             resource_thread: resource_thread,
             pipeline: pipeline,
-            blocking_loads: initial_loads,
+            next_load_id: 0,
+            page_source_load: None,
+            blocking_loads: vec![],
+            released_loads: vec![],
             events_inhibited: false,
+        };
+        if let Some(url) = initial_load {
+            loader.page_source_load = Some(loader.add_blocking_load(LoadType::PageSource(url)));
         }
+        loader
     }
 
-    /// Add a load to the list of blocking loads.
-    pub fn add_blocking_load(&mut self, load: LoadType) {
-        self.blocking_loads.push(load);
+    /// Add a load to the list of blocking loads. The returned id finishes it.
+    pub fn add_blocking_load(&mut self, load: LoadType) -> LoadId {
+        let id = LoadId(self.next_load_id);
+        self.next_load_id += 1;
+        self.blocking_loads.push(BlockingLoad {
+            id: id,
+            load: load,
+            since: time::precise_time_ns(),
+            overdue: false,
+        });
+        id
     }
 
     /// Create a new pending network request, which can be initiated at some point in
-    /// the future.
+    /// the future. The returned id finishes the load.
     pub fn prepare_async_load(&mut self,
                               load: LoadType,
-                              referrer: &Document) -> PendingAsyncLoad {
+                              referrer: &Document) -> (LoadId, PendingAsyncLoad) {
         let context = load.to_load_context();
         let url = load.url().clone();
-        self.add_blocking_load(load);
+        let id = self.add_blocking_load(load);
         let client_chan = referrer.window().custom_message_chan();
-        PendingAsyncLoad::new(context,
-                              (*self.resource_thread).clone(),
-                              url,
-                              self.pipeline,
-                              referrer.get_referrer_policy(),
-                              Some(referrer.url().clone()),
-                              RequestSource::Window(client_chan))
+        (id, PendingAsyncLoad::new(context,
+                                   (*self.resource_thread).clone(),
+                                   url,
+                                   self.pipeline,
+                                   referrer.get_referrer_policy(),
+                                   Some(referrer.url().clone()),
+                                   RequestSource::Window(client_chan)))
     }
 
-    /// Create and initiate a new network request.
-    pub fn load_async(&mut self,
-                      load: LoadType,
-                      listener: AsyncResponseTarget,
-                      referrer: &Document) {
-        let pending = self.prepare_async_load(load, referrer);
-        pending.load_async(listener)
+    /// The load of the document itself, if it is loaded from the network.
+    pub fn page_source_load(&self) -> Option<LoadId> {
+        self.page_source_load
     }
 
-    /// Mark an in-progress network request complete.
-    pub fn finish_load(&mut self, load: &LoadType) {
-        let idx = self.blocking_loads.iter().position(|unfinished| *unfinished == *load);
-        self.blocking_loads.remove(idx.expect(&format!("unknown completed load {:?}", load)));
+    /// Mark an in-progress network request complete. Returns the load unless
+    /// it had already been released, so its completion doesn't matter anymore.
+    pub fn finish_load(&mut self, id: LoadId) -> Option<LoadType> {
+        if let Some(idx) = self.released_loads.iter().position(|&released| released == id) {
+            self.released_loads.remove(idx);
+            return None;
+        }
+        let idx = self.blocking_loads.iter().position(|unfinished| unfinished.id == id);
+        Some(self.blocking_loads.remove(idx.expect(&format!("unknown completed load {:?}", id))).load)
+    }
+
+    /// Stops a load from delaying the load event, although it didn't finish.
+    /// Finishing it later is ignored.
+    pub fn release_load(&mut self, id: LoadId) {
+        if let Some(idx) = self.blocking_loads.iter().position(|unfinished| unfinished.id == id) {
+            self.blocking_loads.remove(idx);
+            self.released_loads.push(id);
+        }
     }
 
     pub fn is_blocked(&self) -> bool {
@@ -166,6 +207,35 @@ impl DocumentLoader {
         !self.blocking_loads.is_empty()
     }
 
+    /// How many loads delay the load event.
+    pub fn blocking_load_count(&self) -> usize {
+        self.blocking_loads.len()
+    }
+
+    /// Returns the loads that delayed the load event for `limit_ns` or more
+    /// by `now`, with how long they did, in ns. A load is only returned the
+    /// first time it is found overdue.
+    pub fn take_overdue_loads(&mut self, now: u64, limit_ns: u64) -> Vec<(LoadId, LoadType, u64)> {
+        let mut overdue = vec![];
+        for blocking_load in &mut self.blocking_loads {
+            let delay = now.saturating_sub(blocking_load.since);
+            if !blocking_load.overdue && delay >= limit_ns {
+                blocking_load.overdue = true;
+                overdue.push((blocking_load.id, blocking_load.load.clone(), delay));
+            }
+        }
+        overdue
+    }
+
+    /// How long after `now` the next load will have delayed the load event
+    /// for `limit_ns`, in ns, if any load that wasn't reported yet is left.
+    pub fn next_overdue_in(&self, now: u64, limit_ns: u64) -> Option<u64> {
+        self.blocking_loads.iter()
+            .filter(|blocking_load| !blocking_load.overdue)
+            .map(|blocking_load| limit_ns.saturating_sub(now.saturating_sub(blocking_load.since)))
+            .min()
+    }
+
     pub fn inhibit_events(&mut self) {
         self.events_inhibited = true;
     }
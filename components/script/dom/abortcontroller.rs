@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::abortsignal::AbortSignal;
+use dom::bindings::codegen::Bindings::AbortControllerBinding;
+use dom::bindings::codegen::Bindings::AbortControllerBinding::AbortControllerMethods;
+use dom::bindings::error::Fallible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::domexception::DOMErrorName;
+use js::jsapi::{HandleValue, JSContext};
+
+#[dom_struct]
+pub struct AbortController {
+    reflector_: Reflector,
+    signal: JS<AbortSignal>,
+}
+
+impl AbortController {
+    fn new_inherited(signal: &AbortSignal) -> AbortController {
+        AbortController {
+            reflector_: Reflector::new(),
+            signal: JS::from_ref(signal),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<AbortController> {
+        let signal = AbortSignal::new(global);
+        reflect_dom_object(box AbortController::new_inherited(&signal),
+                           global,
+                           AbortControllerBinding::Wrap)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-abortcontroller
+    pub fn Constructor(global: GlobalRef) -> Fallible<Root<AbortController>> {
+        Ok(AbortController::new(global))
+    }
+}
+
+impl AbortControllerMethods for AbortController {
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-signal
+    fn Signal(&self) -> Root<AbortSignal> {
+        Root::from_ref(&*self.signal)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortcontroller-abort
+    fn Abort(&self, _cx: *mut JSContext, reason: HandleValue) {
+        if reason.get().is_undefined() {
+            self.signal.signal_abort_with_exception(DOMErrorName::AbortError);
+        } else {
+            self.signal.signal_abort(reason);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::AbortSignalBinding;
+use dom::bindings::codegen::Bindings::AbortSignalBinding::AbortSignalMethods;
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::js::{MutHeapJSVal, Root};
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflectable, reflect_dom_object};
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::eventtarget::EventTarget;
+use euclid::length::Length;
+use js::jsapi::{HandleValue, JSAutoCompartment, JSContext, RootedValue};
+use js::jsval::{JSVal, UndefinedValue};
+use std::cell::Cell;
+use timers::OneshotTimerCallback;
+
+#[dom_struct]
+pub struct AbortSignal {
+    eventtarget: EventTarget,
+    aborted: Cell<bool>,
+    /// Why the signal was aborted, once it is.
+    reason: MutHeapJSVal,
+}
+
+impl AbortSignal {
+    fn new_inherited() -> AbortSignal {
+        AbortSignal {
+            eventtarget: EventTarget::new_inherited(),
+            aborted: Cell::new(false),
+            reason: MutHeapJSVal::new(),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<AbortSignal> {
+        reflect_dom_object(box AbortSignal::new_inherited(), global, AbortSignalBinding::Wrap)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-timeout
+    pub fn Timeout(global: GlobalRef, milliseconds: u64) -> Root<AbortSignal> {
+        let signal = AbortSignal::new(global);
+        // Like the timers of the page, the timeout is held while the document
+        // is suspended or frozen.
+        let callback = OneshotTimerCallback::AbortSignalTimeout(AbortSignalTimeout {
+            signal: Trusted::new(signal.r()),
+        });
+        global.schedule_callback(callback, Length::new(milliseconds));
+        signal
+    }
+
+    // https://dom.spec.whatwg.org/#abortsignal-signal-abort
+    pub fn signal_abort(&self, reason: HandleValue) {
+        if self.aborted.get() {
+            return;
+        }
+        self.aborted.set(true);
+        self.reason.set(reason.get());
+        self.upcast::<EventTarget>().fire_simple_event("abort");
+    }
+
+    /// Signals abort with a new DOMException of the given name as the reason.
+    #[allow(unsafe_code)]
+    pub fn signal_abort_with_exception(&self, name: DOMErrorName) {
+        let global = self.global();
+        let cx = global.r().get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        let mut reason = RootedValue::new(cx, UndefinedValue());
+        unsafe { DOMException::new(global.r(), name).to_jsval(cx, reason.handle_mut()) };
+        self.signal_abort(reason.handle());
+    }
+}
+
+impl AbortSignalMethods for AbortSignal {
+    // https://dom.spec.whatwg.org/#dom-abortsignal-aborted
+    fn Aborted(&self) -> bool {
+        self.aborted.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-reason
+    fn Reason(&self, _cx: *mut JSContext) -> JSVal {
+        self.reason.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+}
+
+/// Aborts a signal made by `AbortSignal.timeout()` with a TimeoutError once
+/// its time is up.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct AbortSignalTimeout {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    signal: Trusted<AbortSignal>,
+}
+
+impl AbortSignalTimeout {
+    pub fn invoke(self) {
+        self.signal.root().signal_abort_with_exception(DOMErrorName::TimeoutError);
+    }
+}
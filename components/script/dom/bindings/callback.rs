@@ -5,7 +5,7 @@
 //! Base classes to work with IDL callbacks.
 
 use dom::bindings::error::{Error, Fallible};
-use dom::bindings::global::global_root_from_object;
+use dom::bindings::global::{GlobalRef, global_root_from_object};
 use dom::bindings::reflector::Reflectable;
 use js::jsapi::GetGlobalForObjectCrossCompartment;
 use js::jsapi::JSAutoCompartment;
@@ -14,6 +14,8 @@ use js::jsapi::{IsCallable, JSContext, JSObject, JS_WrapObject};
 use js::jsapi::{JSCompartment, JS_EnterCompartment, JS_LeaveCompartment};
 use js::jsapi::{JS_GetProperty, JS_IsExceptionPending, JS_ReportPendingException};
 use js::jsval::{JSVal, UndefinedValue};
+use microtask;
+use script_thread::ScriptThread;
 use std::default::Default;
 use std::ffi::CString;
 use std::intrinsics::return_address;
@@ -170,6 +172,11 @@ impl CallSetup {
         let exception_compartment = unsafe {
             GetGlobalForObjectCrossCompartment(callback.callback())
         };
+        // A long task is reported to the windows whose callbacks it ran.
+        if let GlobalRef::Window(window) = global.r() {
+            ScriptThread::script_ran(window.pipeline());
+        }
+        microtask::enter_script();
         CallSetup {
             exception_compartment: RootedObject::new_with_addr(cx,
                                                                exception_compartment,
@@ -199,5 +206,10 @@ impl Drop for CallSetup {
                 JS_ReportPendingException(self.cx);
             }
         }
+        // An exception left to the caller is still pending; the caller
+        // runs the microtasks once it has dealt with it.
+        if microtask::exit_script() && !unsafe { JS_IsExceptionPending(self.cx) } {
+            global_root_from_object(self.exception_compartment.ptr).r().perform_a_microtask_checkpoint();
+        }
     }
 }
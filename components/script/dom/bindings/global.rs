@@ -7,7 +7,7 @@
 //! This module contains smart pointers to global scopes, to simplify writing
 //! code that works in workers as well as window scopes.
 
-use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
+use devtools_traits::{ScriptToDevtoolsControlMsg, TimerInfo, WorkerId};
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::conversions::root_from_object;
 use dom::bindings::js::Root;
@@ -18,15 +18,17 @@ use ipc_channel::ipc::IpcSender;
 use js::jsapi::{CurrentGlobalOrNull, GetGlobalForObjectCrossCompartment};
 use js::jsapi::{JSContext, JSObject, JS_GetClass, MutableHandleValue};
 use js::{JSCLASS_IS_DOMJSCLASS, JSCLASS_IS_GLOBAL};
+use microtask::Microtask;
 use msg::constellation_msg::{PipelineId, PanicMsg};
 use net_traits::{CoreResourceThread, RequestSource};
 use profile_traits::{mem, time};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort};
 use script_thread::{MainThreadScriptChan, ScriptThread};
-use script_traits::{MsDuration, ScriptMsg as ConstellationMsg, TimerEventRequest};
+use script_traits::{MsDuration, ScriptMsg as ConstellationMsg, TimerSchedulerMsg};
 use task_source::TaskSource;
 use task_source::dom_manipulation::DOMManipulationTask;
-use timers::{OneshotTimerCallback, OneshotTimerHandle};
+use time_resolution::TimeResolutionPolicy;
+use timers::{OneshotTimerCallback, OneshotTimerHandle, ScopedSuspension, SuspensionReason};
 use url::Url;
 
 /// A freely-copyable reference to a rooted global object.
@@ -106,7 +108,7 @@ impl<'a> GlobalRef<'a> {
     }
 
     /// Get the scheduler channel to request timer events.
-    pub fn scheduler_chan(&self) -> &IpcSender<TimerEventRequest> {
+    pub fn scheduler_chan(&self) -> &IpcSender<TimerSchedulerMsg> {
         match *self {
             GlobalRef::Window(window) => window.scheduler_chan(),
             GlobalRef::Worker(worker) => worker.scheduler_chan(),
@@ -272,6 +274,89 @@ impl<'a> GlobalRef<'a> {
         }
     }
 
+    /// Queue a microtask to run at the next microtask checkpoint of the event
+    /// loop of this global.
+    pub fn enqueue_microtask(&self, job: Microtask) {
+        match *self {
+            GlobalRef::Window(_) => ScriptThread::enqueue_microtask(job),
+            GlobalRef::Worker(worker) => worker.enqueue_microtask(job),
+        }
+    }
+
+    /// Run the microtasks of the event loop of this global.
+    pub fn perform_a_microtask_checkpoint(&self) {
+        match *self {
+            GlobalRef::Window(_) => ScriptThread::perform_a_microtask_checkpoint(),
+            GlobalRef::Worker(worker) => worker.perform_a_microtask_checkpoint(),
+        }
+    }
+
+    /// Describe the scheduled timers, for the devtools.
+    pub fn describe_timers(&self) -> Vec<TimerInfo> {
+        match *self {
+            GlobalRef::Window(window) => window.describe_timers(),
+            GlobalRef::Worker(worker) => worker.describe_timers(),
+        }
+    }
+
+    /// Pause in the debugger before the callback of the timer with `handle` runs, or before
+    /// that of any timer if `handle` is `None`.
+    pub fn set_timer_breakpoint(&self, handle: Option<i32>, enabled: bool) {
+        match *self {
+            GlobalRef::Window(window) => window.set_timer_breakpoint(handle, enabled),
+            GlobalRef::Worker(worker) => worker.set_timer_breakpoint(handle, enabled),
+        }
+    }
+
+    /// Pause in the debugger once, before the next timer callback runs.
+    pub fn pause_before_next_timer(&self) {
+        match *self {
+            GlobalRef::Window(window) => window.pause_before_next_timer(),
+            GlobalRef::Worker(worker) => worker.pause_before_next_timer(),
+        }
+    }
+
+    /// Make the timers wait `multiplier` times as long as they ask for.
+    pub fn set_timer_delay_multiplier(&self, multiplier: u64) {
+        match *self {
+            GlobalRef::Window(window) => window.set_timer_delay_multiplier(multiplier),
+            GlobalRef::Worker(worker) => worker.set_timer_delay_multiplier(multiplier),
+        }
+    }
+
+    /// Clear the timeout or interval with `handle`, as if the page had. Returns whether
+    /// there was such a timer.
+    pub fn cancel_timer(&self, handle: i32) -> bool {
+        match *self {
+            GlobalRef::Window(window) => window.cancel_timer(handle),
+            GlobalRef::Worker(worker) => worker.cancel_timer(handle),
+        }
+    }
+
+    /// Run the timers that are due now, without waiting for the timer scheduler.
+    pub fn flush_due_timers(&self) {
+        match *self {
+            GlobalRef::Window(window) => window.flush_due_timers(),
+            GlobalRef::Worker(worker) => worker.flush_due_timers(),
+        }
+    }
+
+    /// Suspends the timers until the returned guard is dropped.
+    pub fn suspend_timers_scoped(&self, reason: SuspensionReason) -> ScopedSuspension<'a, OneshotTimerCallback> {
+        match *self {
+            GlobalRef::Window(window) => window.suspend_timers_scoped(reason),
+            GlobalRef::Worker(worker) => worker.suspend_timers_scoped(reason),
+        }
+    }
+
+    /// Move the clock of the timers `duration` ahead, running the timers that become due.
+    pub fn advance_timer_clock(&self, duration: MsDuration) {
+        match *self {
+            GlobalRef::Window(window) => window.advance_timer_clock(duration),
+            GlobalRef::Worker(worker) => worker.advance_timer_clock(duration),
+        }
+    }
+
     /// Returns the receiver's reflector.
     pub fn reflector(&self) -> &Reflector {
         match *self {
@@ -287,6 +372,14 @@ impl<'a> GlobalRef<'a> {
             GlobalRef::Worker(ref worker) => worker.panic_chan(),
         }
     }
+
+    /// How precisely script of this global may observe time.
+    pub fn time_resolution(&self) -> TimeResolutionPolicy {
+        match *self {
+            GlobalRef::Window(ref window) => window.time_resolution(),
+            GlobalRef::Worker(ref worker) => worker.time_resolution(),
+        }
+    }
 }
 
 impl GlobalRoot {
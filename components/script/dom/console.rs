@@ -9,15 +9,16 @@ use dom::bindings::codegen::Bindings::ConsoleBinding::ConsoleMethods;
 use dom::bindings::global::GlobalRef;
 use dom::bindings::js::Root;
 use dom::bindings::reflector::{Reflectable, Reflector, reflect_dom_object};
+use script_traits::{MsDuration, precise_time_ms};
 use std::collections::HashMap;
-use time::{Timespec, get_time};
 use util::str::DOMString;
 
 // https://developer.mozilla.org/en-US/docs/Web/API/Console
 #[dom_struct]
 pub struct Console {
     reflector_: Reflector,
-    timers: DOMRefCell<HashMap<DOMString, u64>>,
+    /// When the timers of `time()` were started, by label.
+    timers: DOMRefCell<HashMap<DOMString, MsDuration>>,
 }
 
 impl Console {
@@ -46,6 +47,11 @@ impl Console {
             chan.send(devtools_message).unwrap();
         }
     }
+
+    fn warn(&self, message: DOMString) {
+        println!("{}", message);
+        self.send_to_devtools(LogLevel::Warn, message);
+    }
 }
 
 impl ConsoleMethods for Console {
@@ -98,39 +104,54 @@ impl ConsoleMethods for Console {
         }
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/time
+    // https://console.spec.whatwg.org/#time
     fn Time(&self, label: DOMString) {
-        let mut timers = self.timers.borrow_mut();
-        if timers.contains_key(&label) {
-            // Timer already started
+        if self.timers.borrow().contains_key(&label) {
+            self.warn(DOMString::from(format!("Timer \"{}\" already exists", label)));
             return;
         }
-        if timers.len() >= 10000 {
-            // Too many timers on page
+        if self.timers.borrow().len() >= 10000 {
+            self.warn(DOMString::from(format!("Too many timers to start \"{}\"", label)));
             return;
         }
 
-        timers.insert(label.clone(), timestamp_in_ms(get_time()));
+        // The same clock as the timers of the page, which doesn't go back.
+        self.timers.borrow_mut().insert(label.clone(), precise_time_ms());
         let message = DOMString::from(format!("{}: timer started", label));
         println!("{}", message);
         self.send_to_devtools(LogLevel::Log, message);
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/timeEnd
-    fn TimeEnd(&self, label: DOMString) {
-        let mut timers = self.timers.borrow_mut();
-        if let Some(start) = timers.remove(&label) {
-            let message = DOMString::from(
-                format!("{}: {}ms", label, timestamp_in_ms(get_time()) - start)
-            );
-            println!("{}", message);
-            self.send_to_devtools(LogLevel::Log, message);
-        };
+    // https://console.spec.whatwg.org/#timelog
+    fn TimeLog(&self, label: DOMString, data: Vec<DOMString>) {
+        let start = self.timers.borrow().get(&label).cloned();
+        match start {
+            Some(start) => {
+                let mut message = format!("{}: {}ms", label, (precise_time_ms() - start).get());
+                for item in data {
+                    message.push(' ');
+                    message.push_str(&item);
+                }
+                let message = DOMString::from(message);
+                println!("{}", message);
+                self.send_to_devtools(LogLevel::Log, message);
+            },
+            None => self.warn(DOMString::from(format!("Timer \"{}\" doesn't exist", label))),
+        }
     }
-}
 
-fn timestamp_in_ms(time: Timespec) -> u64 {
-    (time.sec * 1000 + (time.nsec / 1000000) as i64) as u64
+    // https://console.spec.whatwg.org/#timeend
+    fn TimeEnd(&self, label: DOMString) {
+        let start = self.timers.borrow_mut().remove(&label);
+        match start {
+            Some(start) => {
+                let message = DOMString::from(format!("{}: {}ms", label, (precise_time_ms() - start).get()));
+                println!("{}", message);
+                self.send_to_devtools(LogLevel::Log, message);
+            },
+            None => self.warn(DOMString::from(format!("Timer \"{}\" doesn't exist", label))),
+        }
+    }
 }
 
 fn prepare_message(logLevel: LogLevel, message: DOMString) -> ConsoleMessage {
@@ -385,17 +385,27 @@ impl DedicatedWorkerGlobalScope {
                         devtools::handle_get_cached_messages(pipe_id, message_types, sender),
                     DevtoolScriptControlMsg::WantsLiveNotifications(_pipe_id, bool_val) =>
                         devtools::handle_wants_live_notifications(&global_ref, bool_val),
+                    DevtoolScriptControlMsg::GetTimers(_pipe_id, sender) =>
+                        devtools::handle_get_timers(&global_ref, sender),
+                    DevtoolScriptControlMsg::SetTimerBreakpoint(_pipe_id, handle, enabled) =>
+                        devtools::handle_set_timer_breakpoint(&global_ref, handle, enabled),
+                    DevtoolScriptControlMsg::PauseBeforeNextTimer(_pipe_id) =>
+                        devtools::handle_pause_before_next_timer(&global_ref),
+                    DevtoolScriptControlMsg::SetTimerDelayMultiplier(_pipe_id, multiplier) =>
+                        devtools::handle_set_timer_delay_multiplier(&global_ref, multiplier),
+                    DevtoolScriptControlMsg::CancelTimer(_pipe_id, handle, reply) =>
+                        devtools::handle_cancel_timer(&global_ref, handle, reply),
                     _ => debug!("got an unusable devtools control message inside the worker!"),
                 }
             },
             MixedMessage::FromScheduler((linked_worker, timer_event)) => {
                 match timer_event {
-                    TimerEvent(TimerSource::FromWorker, id) => {
+                    TimerEvent(TimerSource::FromWorker, id, _) => {
                         let _ar = AutoWorkerReset::new(self, linked_worker);
                         let scope = self.upcast::<WorkerGlobalScope>();
                         scope.handle_fire_timer(id);
                     },
-                    TimerEvent(_, _) => {
+                    TimerEvent(_, _, _) => {
                         panic!("A worker received a TimerEvent from a window.")
                     }
                 }
@@ -3,13 +3,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use devtools_traits::CSSError;
-use document_loader::{DocumentLoader, LoadType};
+use document_loader::{DocumentLoader, LoadId, LoadType};
 use dom::activation::{ActivationSource, synthetic_click_activation};
 use dom::attr::{Attr, AttrValue};
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding;
-use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
+use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState, VisibilityState};
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
@@ -36,6 +36,7 @@ use dom::closeevent::CloseEvent;
 use dom::comment::Comment;
 use dom::customevent::CustomEvent;
 use dom::documentfragment::DocumentFragment;
+use dom::documenttimeline::DocumentTimeline;
 use dom::documenttype::DocumentType;
 use dom::domimplementation::DOMImplementation;
 use dom::element::{Element, ElementCreator};
@@ -87,7 +88,9 @@ use dom::webglcontextevent::WebGLContextEvent;
 use dom::window::{ReflowReason, Window};
 use encoding::EncodingRef;
 use encoding::all::UTF_8;
+use euclid::length::Length;
 use euclid::point::Point2D;
+use frame_scheduler::FrameScheduler;
 use html5ever::tree_builder::{LimitedQuirks, NoQuirks, Quirks, QuirksMode};
 use ipc_channel::ipc::{self, IpcSender};
 use js::jsapi::JS_GetRuntime;
@@ -99,13 +102,13 @@ use msg::constellation_msg::{PipelineId, ReferrerPolicy, SubpageId};
 use net_traits::CookieSource::NonHTTP;
 use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
 use net_traits::response::HttpsState;
-use net_traits::{AsyncResponseTarget, PendingAsyncLoad, IpcSend};
+use net_traits::{PendingAsyncLoad, IpcSend};
 use num_traits::{ToPrimitive};
 use origin::Origin;
 use parse::{ParserRoot, ParserRef, MutNullableParserField};
 use script_thread::{MainThreadScriptMsg, Runnable};
 use script_traits::UntrustedNodeAddress;
-use script_traits::{AnimationState, MouseButton, MouseEventType, MozBrowserEvent};
+use script_traits::{MouseButton, MouseEventType, MozBrowserEvent};
 use script_traits::{ScriptMsg as ConstellationMsg, ScriptToCompositorMsg};
 use script_traits::{TouchpadPressurePhase, TouchEventType, TouchId};
 use std::ascii::AsciiExt;
@@ -125,9 +128,12 @@ use style::restyle_hints::ElementSnapshot;
 use style::servo::Stylesheet;
 use task_source::dom_manipulation::DOMManipulationTask;
 use time;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
 use url::Url;
 use url::percent_encoding::percent_decode;
-use util::str::{DOMString, split_html_space_chars, str_join};
+use util::opts;
+use util::prefs;
+use util::str::{DOMString, HTML_SPACE_CHARACTERS, split_html_space_chars, str_join};
 
 #[derive(JSTraceable, PartialEq, HeapSizeOf)]
 pub enum IsHTMLDocument {
@@ -135,6 +141,14 @@ pub enum IsHTMLDocument {
     NonHTMLDocument,
 }
 
+/// Something a document holds that needs its timers to run on time even
+/// while it is hidden.
+#[derive(JSTraceable, HeapSizeOf, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ThrottlingExemption {
+    /// The document is playing audio that can be heard.
+    Audio,
+}
+
 #[derive(PartialEq)]
 enum ParserBlockedByScript {
     Blocked,
@@ -173,6 +187,9 @@ pub struct Document {
     /// Whether the list of stylesheets has changed since the last reflow was triggered.
     stylesheets_changed_since_reflow: Cell<bool>,
     ready_state: Cell<DocumentReadyState>,
+    visibility_state: Cell<VisibilityState>,
+    /// How many of each throttling exemption the document currently holds.
+    throttling_exemptions: DOMRefCell<HashMap<ThrottlingExemption, usize>>,
     /// Whether the DOMContentLoaded event has already been dispatched.
     domcontentloaded_dispatched: Cell<bool>,
     /// The element that has most recently requested focus for itself.
@@ -201,11 +218,10 @@ pub struct Document {
     /// List of animation frame callbacks
     #[ignore_heap_size_of = "closures are hard"]
     animation_frame_list: DOMRefCell<BTreeMap<u32, Box<FnBox(f64)>>>,
-    /// Whether we're in the process of running animation callbacks.
-    ///
-    /// Tracking this is not necessary for correctness. Instead, it is an optimization to avoid
-    /// sending needless `ChangeRunningAnimationsState` messages to the compositor.
-    running_animation_callbacks: Cell<bool>,
+    /// Decides when the animation frame callbacks run.
+    frame_scheduler: FrameScheduler,
+    /// https://drafts.csswg.org/web-animations/#the-documents-default-timeline
+    timeline: MutNullableHeap<JS<DocumentTimeline>>,
     /// Tracks all outstanding loads related to this document.
     loader: DOMRefCell<DocumentLoader>,
     /// The current active HTML parser, to allow resuming after interruptions.
@@ -240,6 +256,14 @@ pub struct Document {
     origin: Origin,
     ///  https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states
     referrer_policy: Option<ReferrerPolicy>,
+    /// https://html.spec.whatwg.org/multipage/#will-declaratively-refresh
+    will_declaratively_refresh: Cell<bool>,
+    /// The timer of a pending declarative refresh, if any.
+    declarative_refresh_timer: DOMRefCell<Option<OneshotTimerHandle>>,
+    /// The timer that reports a stalled load to the embedder, if `--page-load-timeout` is set.
+    page_load_watchdog: DOMRefCell<Option<OneshotTimerHandle>>,
+    /// The timer that reports the loads that delay the load event for too long.
+    load_delay_watchdog: DOMRefCell<Option<OneshotTimerHandle>>,
 }
 
 #[derive(JSTraceable, HeapSizeOf)]
@@ -331,6 +355,119 @@ impl Document {
         self.trigger_mozbrowser_event(MozBrowserEvent::SecurityChange(https_state));
     }
 
+    // https://html.spec.whatwg.org/multipage/#shared-declarative-refresh-steps
+    // Used for both `<meta http-equiv=refresh>` and the `Refresh` response header.
+    pub fn shared_declarative_refresh_steps(&self, input: &str) {
+        // Step 1
+        if self.will_declaratively_refresh.get() || self.browsing_context.is_none() {
+            return;
+        }
+
+        // Steps 2-11
+        let (time, url) = match parse_declarative_refresh(input, self.url(), &self.base_url()) {
+            Some(refresh) => refresh,
+            None => return,
+        };
+
+        // Step 12
+        self.will_declaratively_refresh.set(true);
+
+        // Step 13
+        let window = self.window();
+        let callback = OneshotTimerCallback::RefreshRedirectDue(RefreshRedirectDue {
+            window: Trusted::new(window),
+            url: url,
+        });
+        let duration = Length::new(time.saturating_mul(1000));
+        *self.declarative_refresh_timer.borrow_mut() = Some(window.schedule_callback(callback, duration));
+    }
+
+    /// Abandons a pending declarative refresh, e.g. because another navigation started first.
+    pub fn cancel_declarative_refresh(&self) {
+        if let Some(handle) = self.declarative_refresh_timer.borrow_mut().take() {
+            self.window().unschedule_callback(handle);
+        }
+    }
+
+    /// Starts the page-load watchdog, if the embedder configured a page load timeout.
+    pub fn arm_page_load_watchdog(&self) {
+        let timeout = match opts::get().page_load_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        self.disarm_page_load_watchdog();
+        let window = self.window();
+        let callback = OneshotTimerCallback::PageLoadWatchdog(PageLoadWatchdog {
+            document: Trusted::new(self),
+        });
+        let duration = Length::new(timeout.saturating_mul(1000));
+        *self.page_load_watchdog.borrow_mut() = Some(window.schedule_callback(callback, duration));
+    }
+
+    pub fn disarm_page_load_watchdog(&self) {
+        if let Some(handle) = self.page_load_watchdog.borrow_mut().take() {
+            self.window().unschedule_callback(handle);
+        }
+    }
+
+    /// Schedules the watchdog for when the next load will have delayed the
+    /// load event too long, unless it is scheduled already.
+    pub fn arm_load_delay_watchdog(&self) {
+        if self.load_delay_watchdog.borrow().is_some() {
+            return;
+        }
+        let limit_ms = match load_event_delay_limit_ms() {
+            Some(limit_ms) => limit_ms,
+            None => return,
+        };
+        let next_overdue_in = match self.loader.borrow().next_overdue_in(time::precise_time_ns(), limit_ms * 1000000) {
+            Some(next_overdue_in) => next_overdue_in,
+            None => return,
+        };
+        let window = self.window();
+        let callback = OneshotTimerCallback::LoadDelayWatchdog(LoadDelayWatchdog {
+            document: Trusted::new(self),
+        });
+        let duration = Length::new((next_overdue_in + 999999) / 1000000);
+        *self.load_delay_watchdog.borrow_mut() = Some(window.schedule_callback(callback, duration));
+    }
+
+    fn disarm_load_delay_watchdog(&self) {
+        if let Some(handle) = self.load_delay_watchdog.borrow_mut().take() {
+            self.window().unschedule_callback(handle);
+        }
+    }
+
+    /// Reports the loads that delayed the load event too long, and releases
+    /// them if the `dom.load_event_delay.force_release` pref says so.
+    fn check_load_delays(&self) {
+        let limit_ms = match load_event_delay_limit_ms() {
+            Some(limit_ms) => limit_ms,
+            None => return,
+        };
+        let overdue = self.loader.borrow_mut().take_overdue_loads(time::precise_time_ns(), limit_ms * 1000000);
+        let force_release = prefs::get_pref("dom.load_event_delay.force_release").as_boolean().unwrap_or(false);
+        for (id, load, delay) in overdue {
+            // The document itself stops delaying the load event once it is
+            // parsed, and scripts have to run once they load, so they are
+            // never released.
+            let release = force_release && match load {
+                LoadType::PageSource(_) | LoadType::Script(_) => false,
+                _ => true,
+            };
+            warn!("{:?} delayed the load event of {} for {} ms{}.",
+                  load,
+                  self.url(),
+                  delay / 1000000,
+                  if release { ", releasing it" } else { "" });
+            if release {
+                self.loader.borrow_mut().release_load(id);
+                self.load_finished(load);
+            }
+        }
+        self.arm_load_delay_watchdog();
+    }
+
     pub fn report_css_error(&self, css_error: CSSError) {
         self.css_errors_store.borrow_mut().push(css_error);
     }
@@ -355,6 +492,10 @@ impl Document {
         &self.url
     }
 
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
     // https://html.spec.whatwg.org/multipage/#fallback-base-url
     pub fn fallback_base_url(&self) -> Url {
         // Step 1: iframe srcdoc (#4767).
@@ -578,6 +719,75 @@ impl Document {
         self.upcast::<EventTarget>().fire_simple_event("readystatechange");
     }
 
+    // https://w3c.github.io/page-visibility/#dfn-now-visible-algorithm
+    // https://w3c.github.io/page-visibility/#dfn-now-hidden-algorithm
+    pub fn set_visibility_state(&self, state: VisibilityState) {
+        if self.visibility_state.get() == state {
+            return;
+        }
+        self.visibility_state.set(state);
+
+        // Timers of hidden documents fire at most once per second.
+        self.window.set_hidden(state == VisibilityState::Hidden);
+
+        self.upcast::<EventTarget>().fire_event("visibilitychange",
+                                                EventBubbles::Bubbles,
+                                                EventCancelable::NotCancelable);
+    }
+
+    /// Fires the `freeze` event, letting the page save its state before its
+    /// timers and tasks are stopped.
+    ///
+    /// https://wicg.github.io/page-lifecycle/#freeze-steps
+    pub fn fire_freeze_event(&self) {
+        self.upcast::<EventTarget>().fire_event("freeze",
+                                                EventBubbles::Bubbles,
+                                                EventCancelable::NotCancelable);
+    }
+
+    /// Fires the `resume` event once the timers of a frozen document run again.
+    ///
+    /// https://wicg.github.io/page-lifecycle/#resume-steps
+    pub fn fire_resume_event(&self) {
+        self.upcast::<EventTarget>().fire_event("resume",
+                                                EventBubbles::Bubbles,
+                                                EventCancelable::NotCancelable);
+    }
+
+    /// Records that the document holds something that keeps its timers from
+    /// being throttled while it is hidden. Every call has to be balanced by a
+    /// call to `remove_throttling_exemption` once the document lets go of it.
+    pub fn add_throttling_exemption(&self, exemption: ThrottlingExemption) {
+        let was_exempt = self.is_throttling_exempt();
+        *self.throttling_exemptions.borrow_mut().entry(exemption).or_insert(0) += 1;
+        if !was_exempt {
+            self.window.set_throttling_exempt(true);
+        }
+    }
+
+    pub fn remove_throttling_exemption(&self, exemption: ThrottlingExemption) {
+        match self.throttling_exemptions.borrow_mut().entry(exemption) {
+            Occupied(mut entry) => {
+                *entry.get_mut() -= 1;
+                if *entry.get() == 0 {
+                    entry.remove();
+                }
+            },
+            Vacant(_) => {
+                warn!("Removing throttling exemption {:?} that wasn't added.", exemption);
+                return;
+            },
+        }
+        if !self.is_throttling_exempt() {
+            self.window.set_throttling_exempt(false);
+        }
+    }
+
+    /// Whether the timers of the document are exempt from being throttled.
+    pub fn is_throttling_exempt(&self) -> bool {
+        !self.throttling_exemptions.borrow().is_empty()
+    }
+
     /// Return whether scripting is enabled or not
     pub fn is_scripting_enabled(&self) -> bool {
         self.scripting_enabled.get()
@@ -1284,19 +1494,8 @@ impl Document {
         self.animation_frame_ident.set(ident);
         self.animation_frame_list.borrow_mut().insert(ident, callback);
 
-        // No need to send a `ChangeRunningAnimationsState` if we're running animation callbacks:
-        // we're guaranteed to already be in the "animation callbacks present" state.
-        //
-        // This reduces CPU usage by avoiding needless thread wakeups in the common case of
-        // repeated rAF.
-        //
         // TODO: Should tick animation only when document is visible
-        if !self.running_animation_callbacks.get() {
-            let event = ConstellationMsg::ChangeRunningAnimationsState(
-                self.window.pipeline(),
-                AnimationState::AnimationCallbacksPresent);
-            self.window.constellation_chan().send(event).unwrap();
-        }
+        self.frame_scheduler.request_frame(self);
 
         ident
     }
@@ -1305,17 +1504,51 @@ impl Document {
     pub fn cancel_animation_frame(&self, ident: u32) {
         self.animation_frame_list.borrow_mut().remove(&ident);
         if self.animation_frame_list.borrow().is_empty() {
-            let event = ConstellationMsg::ChangeRunningAnimationsState(self.window.pipeline(),
-                                                                       AnimationState::NoAnimationCallbacksPresent);
-            self.window.constellation_chan().send(event).unwrap();
+            self.stop_frames();
+        }
+    }
+
+    /// Stops ticking the frames, and with them the timeline.
+    fn stop_frames(&self) {
+        self.frame_scheduler.stop(&self.window);
+        if let Some(timeline) = self.timeline.get() {
+            timeline.stop_ticking();
+        }
+    }
+
+    pub fn frame_scheduler(&self) -> &FrameScheduler {
+        &self.frame_scheduler
+    }
+
+    /// Stops or restarts the animation frames of this document as the timers
+    /// of its window get suspended or resumed.
+    pub fn update_animation_frames_for_suspension(&self) {
+        if self.window.timers_suspended() {
+            self.stop_frames();
+        } else if !self.animation_frame_list.borrow().is_empty() {
+            self.frame_scheduler.request_frame(self);
+        }
+    }
+
+    /// https://drafts.csswg.org/web-animations/#update-animations-and-send-events
+    ///
+    /// Only the timeline is updated, as there are no animations to update or
+    /// send events for yet.
+    fn update_animations_and_send_events(&self) {
+        if let Some(timeline) = self.timeline.get() {
+            timeline.update_current_time();
         }
     }
 
     /// https://html.spec.whatwg.org/multipage/#run-the-animation-frame-callbacks
     pub fn run_the_animation_frame_callbacks(&self) {
+        if !self.frame_scheduler.begin_frame(&self.window) {
+            return;
+        }
+        self.update_animations_and_send_events();
+
         let animation_frame_list =
             mem::replace(&mut *self.animation_frame_list.borrow_mut(), BTreeMap::new());
-        self.running_animation_callbacks.set(true);
         let performance = self.window.Performance();
         let performance = performance.r();
         let timing = performance.Now();
@@ -1324,44 +1557,47 @@ impl Document {
             callback(*timing);
         }
 
-        // Only send the animation change state message after running any callbacks.
-        // This means that if the animation callback adds a new callback for
-        // the next frame (which is the common case), we won't send a NoAnimationCallbacksPresent
-        // message quickly followed by an AnimationCallbacksPresent message.
+        // The callbacks requested for the next frame keep the compositor
+        // ticking, rather than stopping and restarting it.
         if self.animation_frame_list.borrow().is_empty() {
-            let event = ConstellationMsg::ChangeRunningAnimationsState(self.window.pipeline(),
-                                                                       AnimationState::NoAnimationCallbacksPresent);
-            self.window.constellation_chan().send(event).unwrap();
+            self.stop_frames();
+        } else {
+            self.frame_scheduler.request_frame(self);
         }
 
-        self.running_animation_callbacks.set(false);
-
         self.window.reflow(ReflowGoal::ForDisplay,
                            ReflowQueryType::NoQuery,
                            ReflowReason::RequestAnimationFrame);
     }
 
     /// Add a load to the list of loads blocking this document's load.
-    pub fn add_blocking_load(&self, load: LoadType) {
-        let mut loader = self.loader.borrow_mut();
-        loader.add_blocking_load(load)
+    pub fn add_blocking_load(&self, load: LoadType) -> LoadId {
+        let id = self.loader.borrow_mut().add_blocking_load(load);
+        self.arm_load_delay_watchdog();
+        id
     }
 
-    pub fn prepare_async_load(&self, load: LoadType) -> PendingAsyncLoad {
-        let mut loader = self.loader.borrow_mut();
-        loader.prepare_async_load(load, self)
+    pub fn prepare_async_load(&self, load: LoadType) -> (LoadId, PendingAsyncLoad) {
+        let pending = self.loader.borrow_mut().prepare_async_load(load, self);
+        self.arm_load_delay_watchdog();
+        pending
     }
 
-    pub fn load_async(&self, load: LoadType, listener: AsyncResponseTarget) {
-        let mut loader = self.loader.borrow_mut();
-        loader.load_async(load, listener, self);
-    }
-
-    pub fn finish_load(&self, load: LoadType) {
+    pub fn finish_load(&self, id: LoadId) {
         // The parser might need the loader, so restrict the lifetime of the borrow.
-        {
+        let load = {
             let mut loader = self.loader.borrow_mut();
-            loader.finish_load(&load);
+            loader.finish_load(id)
+        };
+        if let Some(load) = load {
+            self.load_finished(load);
+        }
+    }
+
+    /// Continues whatever waited for a load that no longer delays the load event.
+    fn load_finished(&self, load: LoadType) {
+        if !self.loader.borrow().is_blocked() {
+            self.disarm_load_delay_watchdog();
         }
 
         if let LoadType::Script(_) = load {
@@ -1503,6 +1739,15 @@ impl Document {
         self.current_parser.get()
     }
 
+    /// Lets the cross-origin iframes of this document know whether they can
+    /// be seen, so that the timers of those that can't are throttled.
+    pub fn update_iframe_visibility(&self) {
+        let clip_rect = self.window.page_clip_rect();
+        for iframe in self.upcast::<Node>().traverse_preorder().filter_map(Root::downcast::<HTMLIFrameElement>) {
+            iframe.update_visibility(clip_rect);
+        }
+    }
+
     /// Find an iframe element in the document.
     pub fn find_iframe(&self, subpage_id: SubpageId) -> Option<Root<HTMLIFrameElement>> {
         self.upcast::<Node>()
@@ -1675,6 +1920,8 @@ impl Document {
             stylesheets: DOMRefCell::new(None),
             stylesheets_changed_since_reflow: Cell::new(false),
             ready_state: Cell::new(ready_state),
+            visibility_state: Cell::new(VisibilityState::Visible),
+            throttling_exemptions: DOMRefCell::new(HashMap::new()),
             domcontentloaded_dispatched: Cell::new(domcontentloaded_dispatched),
             possibly_focused: Default::default(),
             focused: Default::default(),
@@ -1687,7 +1934,8 @@ impl Document {
             scripting_enabled: Cell::new(browsing_context.is_some()),
             animation_frame_ident: Cell::new(0),
             animation_frame_list: DOMRefCell::new(BTreeMap::new()),
-            running_animation_callbacks: Cell::new(false),
+            frame_scheduler: FrameScheduler::new(),
+            timeline: Default::default(),
             loader: DOMRefCell::new(doc_loader),
             current_parser: Default::default(),
             reflow_timeout: Cell::new(None),
@@ -1708,6 +1956,10 @@ impl Document {
             origin: origin,
             //TODO - setting this for now so no Referer header set
             referrer_policy: Some(ReferrerPolicy::NoReferrer),
+            will_declaratively_refresh: Cell::new(false),
+            declarative_refresh_timer: DOMRefCell::new(None),
+            page_load_watchdog: DOMRefCell::new(None),
+            load_delay_watchdog: DOMRefCell::new(None),
         }
     }
 
@@ -1866,6 +2118,11 @@ impl DocumentMethods for Document {
         StyleSheetList::new(&self.window, JS::from_ref(&self))
     }
 
+    // https://drafts.csswg.org/web-animations/#dom-document-timeline
+    fn Timeline(&self) -> Root<DocumentTimeline> {
+        self.timeline.or_init(|| DocumentTimeline::new(self, 0.))
+    }
+
     // https://dom.spec.whatwg.org/#dom-document-implementation
     fn Implementation(&self) -> Root<DOMImplementation> {
         self.implementation.or_init(|| DOMImplementation::new(self))
@@ -2553,6 +2810,16 @@ impl DocumentMethods for Document {
         self.ready_state.get()
     }
 
+    // https://w3c.github.io/page-visibility/#dom-document-hidden
+    fn Hidden(&self) -> bool {
+        self.visibility_state.get() == VisibilityState::Hidden
+    }
+
+    // https://w3c.github.io/page-visibility/#dom-document-visibilitystate
+    fn VisibilityState(&self) -> VisibilityState {
+        self.visibility_state.get()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-document-defaultview
     fn DefaultView(&self) -> Root<Window> {
         Root::from_ref(&*self.window)
@@ -2725,6 +2992,15 @@ impl DocumentMethods for Document {
     // https://html.spec.whatwg.org/multipage/#handler-onreadystatechange
     event_handler!(readystatechange, GetOnreadystatechange, SetOnreadystatechange);
 
+    // https://w3c.github.io/page-visibility/#dom-document-onvisibilitychange
+    event_handler!(visibilitychange, GetOnvisibilitychange, SetOnvisibilitychange);
+
+    // https://wicg.github.io/page-lifecycle/#dom-document-onfreeze
+    event_handler!(freeze, GetOnfreeze, SetOnfreeze);
+
+    // https://wicg.github.io/page-lifecycle/#dom-document-onresume
+    event_handler!(resume, GetOnresume, SetOnresume);
+
     #[allow(unsafe_code)]
     // https://drafts.csswg.org/cssom-view/#dom-document-elementfrompoint
     fn ElementFromPoint(&self, x: Finite<f64>, y: Finite<f64>) -> Option<Root<Element>> {
@@ -2842,6 +3118,7 @@ impl DocumentProgressHandler {
         // http://w3c.github.io/navigation-timing/#widl-PerformanceNavigationTiming-loadEventEnd
         update_with_current_time_ms(&document.load_event_end);
 
+        document.disarm_page_load_watchdog();
         document.notify_constellation_load();
 
         window.reflow(ReflowGoal::ForDisplay,
@@ -2861,6 +3138,148 @@ impl Runnable for DocumentProgressHandler {
     }
 }
 
+// https://html.spec.whatwg.org/multipage/#shared-declarative-refresh-steps
+// Steps 2-11: returns the delay in seconds and the URL to navigate to.
+fn parse_declarative_refresh(input: &str, document_url: &Url, base_url: &Url) -> Option<(u64, Url)> {
+    // Step 3
+    let input = input.trim_left_matches(HTML_SPACE_CHARACTERS);
+
+    // Steps 4-6
+    let digits_end = input.find(|c: char| !c.is_digit(10)).unwrap_or(input.len());
+    let (digits, rest) = input.split_at(digits_end);
+    let time = if digits.is_empty() {
+        if !rest.starts_with('.') {
+            return None;
+        }
+        0
+    } else {
+        match digits.parse() {
+            Ok(time) => time,
+            Err(_) => return None,
+        }
+    };
+
+    // Step 7
+    let rest = rest.trim_left_matches(|c: char| c.is_digit(10) || c == '.');
+
+    // Steps 8-9
+    let first = match rest.chars().next() {
+        None => return Some((time, document_url.clone())),
+        Some(first) => first,
+    };
+
+    // Step 10
+    if first != ';' && first != ',' && !HTML_SPACE_CHARACTERS.contains(&first) {
+        return None;
+    }
+
+    // Steps 11.1-11.3
+    let rest = rest.trim_left_matches(HTML_SPACE_CHARACTERS);
+    let rest = if rest.starts_with(';') || rest.starts_with(',') {
+        &rest[1..]
+    } else {
+        rest
+    };
+    let mut rest = rest.trim_left_matches(HTML_SPACE_CHARACTERS);
+
+    // Step 11.4
+    if rest.is_empty() {
+        return Some((time, document_url.clone()));
+    }
+
+    // Steps 11.5-11.7
+    if rest.len() >= 3 && rest.as_bytes()[..3].eq_ignore_ascii_case(b"url") {
+        let after_url = rest[3..].trim_left_matches(HTML_SPACE_CHARACTERS);
+        if after_url.starts_with('=') {
+            rest = after_url[1..].trim_left_matches(HTML_SPACE_CHARACTERS);
+        }
+    }
+
+    // Steps 11.8-11.10
+    let url = match rest.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => {
+            let unquoted = &rest[1..];
+            match unquoted.find(quote) {
+                Some(end) => &unquoted[..end],
+                None => unquoted,
+            }
+        },
+        _ => rest,
+    };
+
+    // Step 11.11
+    base_url.join(url.trim_right_matches(HTML_SPACE_CHARACTERS)).ok().map(|url| (time, url))
+}
+
+/// Navigates a window once its declarative refresh timeout has elapsed.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct RefreshRedirectDue {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    window: Trusted<Window>,
+    url: Url,
+}
+
+impl RefreshRedirectDue {
+    pub fn invoke(self) {
+        let window = self.window.root();
+        if !window.is_alive() {
+            return;
+        }
+        *window.Document().declarative_refresh_timer.borrow_mut() = None;
+        window.load_url(self.url);
+    }
+}
+
+const DEFAULT_LOAD_EVENT_DELAY_LIMIT_MS: u64 = 30 * 1000;
+
+/// How long a load may delay the load event before it is reported, unless the
+/// `dom.load_event_delay.limit_seconds` pref turns the reports off.
+fn load_event_delay_limit_ms() -> Option<u64> {
+    match prefs::get_pref("dom.load_event_delay.limit_seconds").as_i64() {
+        Some(seconds) if seconds > 0 => Some(seconds as u64 * 1000),
+        Some(_) => None,
+        None => Some(DEFAULT_LOAD_EVENT_DELAY_LIMIT_MS),
+    }
+}
+
+/// Reports the loads that delay the load event of a document for too long.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct LoadDelayWatchdog {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    document: Trusted<Document>,
+}
+
+impl LoadDelayWatchdog {
+    pub fn invoke(self) {
+        let document = self.document.root();
+        *document.load_delay_watchdog.borrow_mut() = None;
+        if !document.window().is_alive() {
+            return;
+        }
+        document.check_load_delays();
+    }
+}
+
+/// Tells the constellation that a document failed to fire its load event in time.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct PageLoadWatchdog {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    document: Trusted<Document>,
+}
+
+impl PageLoadWatchdog {
+    pub fn invoke(self) {
+        let document = self.document.root();
+        *document.page_load_watchdog.borrow_mut() = None;
+        let window = document.window();
+        if !window.is_alive() || document.ReadyState() == DocumentReadyState::Complete {
+            return;
+        }
+        let event = ConstellationMsg::LoadTimedOut(window.pipeline());
+        window.constellation_chan().send(event).unwrap();
+    }
+}
+
 /// Specifies the type of focus event that is sent to a pipeline
 #[derive(Copy, Clone, PartialEq)]
 pub enum FocusType {
@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::DocumentTimelineBinding;
+use dom::bindings::codegen::Bindings::DocumentTimelineBinding::{DocumentTimelineMethods, DocumentTimelineOptions};
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::error::Fallible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::document::Document;
+use std::cell::Cell;
+
+/// A timeline that follows the time of a document. It is driven by the same
+/// clock as the timers of the document's window, so it stands still while
+/// they are suspended, and leaves out the time they spent suspended.
+///
+/// While the document's frames are ticking, the time only moves on from one
+/// frame to the next, so that script sees the same time throughout a frame.
+#[dom_struct]
+pub struct DocumentTimeline {
+    reflector_: Reflector,
+    document: JS<Document>,
+    /// https://drafts.csswg.org/web-animations/#dom-documenttimelineoptions-origintime
+    origin_time: f64,
+    /// The time of the current frame, if the frames are ticking.
+    frame_time: Cell<Option<f64>>,
+}
+
+impl DocumentTimeline {
+    fn new_inherited(document: &Document, origin_time: f64) -> DocumentTimeline {
+        DocumentTimeline {
+            reflector_: Reflector::new(),
+            document: JS::from_ref(document),
+            origin_time: origin_time,
+            frame_time: Cell::new(None),
+        }
+    }
+
+    pub fn new(document: &Document, origin_time: f64) -> Root<DocumentTimeline> {
+        let window = document.window();
+        reflect_dom_object(box DocumentTimeline::new_inherited(document, origin_time),
+                           GlobalRef::Window(window),
+                           DocumentTimelineBinding::Wrap)
+    }
+
+    // https://drafts.csswg.org/web-animations/#dom-documenttimeline-documenttimeline
+    pub fn Constructor(global: GlobalRef, options: &DocumentTimelineOptions) -> Fallible<Root<DocumentTimeline>> {
+        Ok(DocumentTimeline::new(&global.as_window().Document(), *options.originTime))
+    }
+
+    /// The current time of the timeline in ms, unless the timeline is inactive.
+    pub fn current_time(&self) -> Option<f64> {
+        // https://drafts.csswg.org/web-animations/#document-timelines
+        // A document timeline is inactive while its document isn't fully active.
+        if !self.document.is_fully_active() {
+            return None;
+        }
+        Some(self.frame_time.get().unwrap_or_else(|| self.sample_time()))
+    }
+
+    fn sample_time(&self) -> f64 {
+        let now = self.document.window().timer_time_since_navigation_start();
+        now.get() as f64 - self.origin_time
+    }
+
+    /// Moves the timeline on to the time of a new frame.
+    pub fn update_current_time(&self) {
+        self.frame_time.set(Some(self.sample_time()));
+    }
+
+    /// Lets the timeline follow the clock again, as the frames stop ticking.
+    pub fn stop_ticking(&self) {
+        self.frame_time.set(None);
+    }
+}
+
+impl DocumentTimelineMethods for DocumentTimeline {
+    // https://drafts.csswg.org/web-animations/#dom-animationtimeline-currenttime
+    fn GetCurrentTime(&self) -> Option<Finite<f64>> {
+        self.current_time().map(Finite::wrap)
+    }
+}
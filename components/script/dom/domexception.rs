@@ -36,6 +36,7 @@ pub enum DOMErrorName {
     InvalidNodeTypeError = DOMExceptionConstants::INVALID_NODE_TYPE_ERR,
     DataCloneError = DOMExceptionConstants::DATA_CLONE_ERR,
     EncodingError,
+    NotAllowedError,
 }
 
 #[dom_struct]
@@ -64,7 +65,7 @@ impl DOMExceptionMethods for DOMException {
     fn Code(&self) -> u16 {
         match self.code {
             // https://heycam.github.io/webidl/#dfn-throw
-            DOMErrorName::EncodingError => 0,
+            DOMErrorName::EncodingError | DOMErrorName::NotAllowedError => 0,
             code => code as u16,
         }
     }
@@ -100,7 +101,9 @@ impl DOMExceptionMethods for DOMException {
             DOMErrorName::InvalidNodeTypeError =>
                 "The supplied node is incorrect or has an incorrect ancestor for this operation.",
             DOMErrorName::DataCloneError => "The object can not be cloned.",
-            DOMErrorName::EncodingError => "The encoding operation (either encoded or decoding) failed."
+            DOMErrorName::EncodingError => "The encoding operation (either encoded or decoding) failed.",
+            DOMErrorName::NotAllowedError =>
+                "The request is not allowed by the user agent or the platform in the current context."
         };
 
         DOMString::from(message)
@@ -2,22 +2,67 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use cors::{CORSRequest, RequestMode, allow_cross_origin_request};
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
 use dom::bindings::codegen::Bindings::EventSourceBinding::{EventSourceInit, EventSourceMethods, Wrap};
+use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::{Error, Fallible};
 use dom::bindings::global::GlobalRef;
+use dom::bindings::inheritance::Castable;
 use dom::bindings::js::Root;
-use dom::bindings::reflector::reflect_dom_object;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflectable, reflect_dom_object};
+use dom::event::Event;
 use dom::eventtarget::EventTarget;
+use dom::messageevent::MessageEvent;
+use hyper::header::{Accept, ContentType, qitem};
+use hyper::mime::{Mime, SubLevel, TopLevel};
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::jsapi::{JSAutoCompartment, RootedValue};
+use js::jsval::UndefinedValue;
+use msg::constellation_msg::{PipelineId, ReferrerPolicy};
+use net_traits::CoreResourceMsg::Load;
+use net_traits::{AsyncResponseListener, AsyncResponseTarget, Metadata, NetworkError, RequestSource};
+use net_traits::{LoadConsumer, LoadContext, LoadData, LoadOrigin, ResourceCORSData};
+use network_listener::{NetworkListener, PreInvoke};
+use retry_timer::{RetryPolicy, RetryTimer};
+use script_runtime::ScriptThreadEventCategory::NetworkEvent;
+use script_runtime::{CommonScriptMsg, ScriptChan};
+use script_thread::Runnable;
 use std::cell::Cell;
+use std::cmp;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use string_cache::Atom;
+use timers::OneshotTimerCallback;
 use url::Url;
 use util::str::DOMString;
 
+/// The reconnection time used until the server sends a `retry` field.
+const DEFAULT_RECONNECTION_TIME: u64 = 5000;
+
+/// The longest, in ms, failed reconnections make the next one wait, unless
+/// the server asks for a longer reconnection time itself.
+const MAX_RECONNECTION_BACKOFF: u64 = 5 * 60 * 1000;
+
+/// Doubles the reconnection time for every reconnection in a row that failed.
+// https://html.spec.whatwg.org/multipage/#reestablish-the-connection Step 3
+const RECONNECTION_POLICY: RetryPolicy = RetryPolicy {
+    initial_delay_ms: DEFAULT_RECONNECTION_TIME,
+    multiplier: 2,
+    max_delay_ms: MAX_RECONNECTION_BACKOFF,
+    jitter: 0.,
+    max_attempts: None,
+};
+
+#[derive(JSTraceable, PartialEq, Copy, Clone, Debug, HeapSizeOf)]
+struct GenerationId(u32);
+
 #[derive(JSTraceable, PartialEq, Copy, Clone, Debug, HeapSizeOf)]
 enum EventSourceReadyState {
     Connecting = 0,
-    #[allow(dead_code)]
     Open = 1,
     Closed = 2
 }
@@ -28,7 +73,13 @@ pub struct EventSource {
     url: Url,
     ready_state: Cell<EventSourceReadyState>,
     with_credentials: bool,
-    last_event_id: DOMRefCell<DOMString>
+    last_event_id: DOMRefCell<DOMString>,
+    /// Schedules the reconnections, the first one after the reconnection time.
+    /// https://html.spec.whatwg.org/multipage/#concept-event-stream-reconnection-time
+    reconnection: RetryTimer,
+    /// Incremented whenever a reconnection is scheduled or the object is closed,
+    /// so that stale reconnection timers and responses can be recognized.
+    generation_id: Cell<GenerationId>,
 }
 
 impl EventSource {
@@ -38,7 +89,9 @@ impl EventSource {
             url: url,
             ready_state: Cell::new(EventSourceReadyState::Connecting),
             with_credentials: with_credentials,
-            last_event_id: DOMRefCell::new(DOMString::from(""))
+            last_event_id: DOMRefCell::new(DOMString::from("")),
+            reconnection: RetryTimer::new(RECONNECTION_POLICY),
+            generation_id: Cell::new(GenerationId(0)),
         }
     }
 
@@ -55,18 +108,184 @@ impl EventSource {
             Ok(u) => u,
             Err(_) => return Err(Error::Syntax)
         };
-        // Step 3
+        // Steps 3-6
         let event_source = EventSource::new(global, url, event_source_init.withCredentials);
-        // Step 4
-        // Step 5
-        // Step 6
-        // Step 7
-        // Step 8
-        // Step 9
-        // Step 10
-        // Step 11
-        Ok(event_source)
+        // An open connection or a pending reconnection keeps the object
+        // alive until it is closed, including when its document goes away.
+        if let GlobalRef::Window(window) = global {
+            window.add_event_source(&event_source);
+        }
+        // Steps 7-11
+        event_source.fetch();
         // Step 12
+        Ok(event_source)
+    }
+
+    /// Requests the event stream, from the event with the last event ID on
+    /// if the server sent one.
+    // https://html.spec.whatwg.org/multipage/#dom-eventsource
+    fn fetch(&self) {
+        let global = self.global();
+        let mut load_data = LoadData::new(LoadContext::Browsing, self.url.clone(), self);
+        load_data.credentials_flag = self.with_credentials;
+        // Step 8
+        let mime = Mime(TopLevel::Text, SubLevel::EventStream, vec![]);
+        load_data.preserved_headers.set(Accept(vec![qitem(mime)]));
+
+        // The request is a simple one, so it never needs a preflight.
+        let cors_request = CORSRequest::maybe_new(global.r().get_url(),
+                                                  self.url.clone(),
+                                                  RequestMode::CORS,
+                                                  load_data.method.clone(),
+                                                  load_data.preserved_headers.clone(),
+                                                  true);
+        let cors_request = match cors_request {
+            Ok(cors_request) => cors_request,
+            Err(_) => {
+                // The constructor may be what is running, so the page gets
+                // to add its listeners before the connection fails.
+                let task = box FailTheConnectionTask {
+                    event_source: Trusted::new(self),
+                };
+                let _ = global.r().networking_task_source().send(CommonScriptMsg::RunnableMsg(NetworkEvent, task));
+                return;
+            },
+        };
+        if let Some(ref req) = cors_request {
+            load_data.headers.set_raw("Origin".to_owned(), vec![req.origin.to_string().into_bytes()]);
+            load_data.cors = Some(ResourceCORSData {
+                preflight: false,
+                origin: req.origin.clone(),
+            });
+        }
+
+        // https://html.spec.whatwg.org/multipage/#reestablish-the-connection Step 5.4
+        if !self.last_event_id.borrow().is_empty() {
+            let last_event_id = self.last_event_id.borrow().as_bytes().to_vec();
+            load_data.headers.set_raw("Last-Event-ID".to_owned(), vec![last_event_id]);
+        }
+
+        let context = Arc::new(Mutex::new(EventSourceContext {
+            event_source: Trusted::new(self),
+            gen_id: self.generation_id.get(),
+            cors_request: cors_request,
+            parser: EventStreamParser::new(),
+        }));
+        let listener = NetworkListener {
+            context: context,
+            script_chan: global.r().networking_task_source(),
+        };
+        let (action_sender, action_receiver) = ipc::channel().unwrap();
+        let response_target = AsyncResponseTarget {
+            sender: action_sender,
+        };
+        ROUTER.add_route(action_receiver.to_opaque(), box move |message| {
+            listener.notify(message.to().unwrap());
+        });
+        let msg = Load(load_data, LoadConsumer::Listener(response_target), None);
+        if let Err(e) = global.r().core_resource_thread().send(msg) {
+            warn!("Requesting the event stream failed ({}).", e);
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#announce-the-connection
+    fn announce_the_connection(&self) {
+        if self.ready_state.get() == EventSourceReadyState::Closed {
+            return;
+        }
+        self.ready_state.set(EventSourceReadyState::Open);
+        self.reconnection.reset();
+        self.upcast::<EventTarget>().fire_simple_event("open");
+    }
+
+    // https://html.spec.whatwg.org/multipage/#fail-the-connection
+    fn fail_the_connection(&self) {
+        if self.ready_state.get() == EventSourceReadyState::Closed {
+            return;
+        }
+        self.close();
+        self.upcast::<EventTarget>().fire_simple_event("error");
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dispatchMessage
+    #[allow(unsafe_code)]
+    fn dispatch_message(&self, event: StreamEvent) {
+        if self.ready_state.get() == EventSourceReadyState::Closed {
+            return;
+        }
+        let global = self.global();
+        let cx = global.r().get_cx();
+        let _ac = JSAutoCompartment::new(cx, self.reflector().get_jsobject().get());
+        let mut data = RootedValue::new(cx, UndefinedValue());
+        unsafe { DOMString::from(event.data).to_jsval(cx, data.handle_mut()) };
+        let message = MessageEvent::new(global.r(),
+                                        Atom::from(event.type_),
+                                        false,
+                                        false,
+                                        data.handle(),
+                                        DOMString::from(self.url.origin().unicode_serialization()),
+                                        self.last_event_id.borrow().clone());
+        message.upcast::<Event>().fire(self.upcast());
+    }
+
+    // https://html.spec.whatwg.org/multipage/#reestablish-the-connection
+    pub fn reestablish_the_connection(&self) {
+        // Step 1.1
+        if self.ready_state.get() == EventSourceReadyState::Closed {
+            return;
+        }
+
+        // Step 1.2
+        self.ready_state.set(EventSourceReadyState::Connecting);
+
+        // Step 1.3
+        self.upcast::<EventTarget>().fire_simple_event("error");
+
+        // Step 2
+        let GenerationId(generation_id) = self.generation_id.get();
+        self.generation_id.set(GenerationId(generation_id + 1));
+        let callback = OneshotTimerCallback::EventSourceTimeout(EventSourceTimeoutCallback {
+            event_source: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+        });
+        // Reconnections never run out.
+        let global = self.global();
+        self.reconnection.schedule(global.r(), callback);
+    }
+
+    /// Closes the connection without firing any events, as when the document
+    /// of the object goes away.
+    // https://html.spec.whatwg.org/multipage/#concept-eventsource-forcibly-close
+    pub fn forcibly_close(&self) {
+        self.close();
+    }
+
+    fn close(&self) {
+        // Stale responses and reconnection timers are ignored from now on.
+        let GenerationId(generation_id) = self.generation_id.get();
+        self.generation_id.set(GenerationId(generation_id + 1));
+        let global = self.global();
+        self.reconnection.cancel(global.r());
+        self.ready_state.set(EventSourceReadyState::Closed);
+        if let GlobalRef::Window(window) = global.r() {
+            window.remove_event_source(self);
+        }
+    }
+}
+
+impl LoadOrigin for EventSource {
+    fn referrer_url(&self) -> Option<Url> {
+        None
+    }
+    fn referrer_policy(&self) -> Option<ReferrerPolicy> {
+        None
+    }
+    fn request_source(&self) -> RequestSource {
+        self.global().r().request_source()
+    }
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        let global = self.global();
+        Some(global.r().pipeline())
     }
 }
 
@@ -97,7 +316,273 @@ impl EventSourceMethods for EventSource {
 
     // https://html.spec.whatwg.org/multipage/#dom-eventsource-close
     fn Close(&self) {
-        self.ready_state.set(EventSourceReadyState::Closed);
-        // TODO: Terminate ongoing fetch
+        self.close();
+    }
+}
+
+/// The state of a single request for the event stream.
+struct EventSourceContext {
+    event_source: Trusted<EventSource>,
+    gen_id: GenerationId,
+    cors_request: Option<CORSRequest>,
+    parser: EventStreamParser,
+}
+
+impl EventSourceContext {
+    fn process_items(&self, event_source: &EventSource, items: Vec<EventStreamItem>) {
+        for item in items {
+            // An event handler may have closed the object.
+            if event_source.generation_id.get() != self.gen_id {
+                return;
+            }
+            match item {
+                EventStreamItem::Retry(time) => event_source.reconnection.set_initial_delay(time),
+                EventStreamItem::Dispatch { last_event_id, event } => {
+                    *event_source.last_event_id.borrow_mut() = DOMString::from(last_event_id);
+                    if let Some(event) = event {
+                        event_source.dispatch_message(event);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl AsyncResponseListener for EventSourceContext {
+    // https://html.spec.whatwg.org/multipage/#dom-eventsource Step 11
+    fn headers_available(&mut self, metadata: Result<Metadata, NetworkError>) {
+        let event_source = self.event_source.root();
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(_) => return event_source.reestablish_the_connection(),
+        };
+        if let Some(ref req) = self.cors_request {
+            match metadata.headers {
+                Some(ref headers) if allow_cross_origin_request(req, headers) => {},
+                _ => return event_source.fail_the_connection(),
+            }
+        }
+        let is_ok = metadata.status.as_ref().map_or(false, |status| status.0 == 200);
+        let is_event_stream = match metadata.content_type {
+            Some(ContentType(Mime(TopLevel::Text, SubLevel::EventStream, _))) => true,
+            _ => false,
+        };
+        if is_ok && is_event_stream {
+            event_source.announce_the_connection();
+        } else {
+            event_source.fail_the_connection();
+        }
+    }
+
+    fn data_available(&mut self, payload: Vec<u8>) {
+        let event_source = self.event_source.root();
+        let items = self.parser.feed(&payload);
+        self.process_items(&event_source, items);
+    }
+
+    fn response_complete(&mut self, status: Result<(), NetworkError>) {
+        let event_source = self.event_source.root();
+        match status {
+            Err(NetworkError::LoadCancelled) => event_source.fail_the_connection(),
+            // Whatever else made the connection end, the event stream goes on
+            // from the last event ID.
+            _ => event_source.reestablish_the_connection(),
+        }
+    }
+}
+
+impl PreInvoke for EventSourceContext {
+    fn should_invoke(&self) -> bool {
+        self.event_source.root().generation_id.get() == self.gen_id
+    }
+}
+
+/// An event, as parsed from an event stream.
+#[derive(Debug, PartialEq)]
+pub struct StreamEvent {
+    pub type_: String,
+    pub data: String,
+}
+
+/// What an event stream asks its `EventSource` to do.
+#[derive(Debug, PartialEq)]
+pub enum EventStreamItem {
+    /// Set the reconnection time, in ms.
+    Retry(u64),
+    /// Set the last event ID string, then dispatch the event, unless the
+    /// event had no data.
+    Dispatch {
+        last_event_id: String,
+        event: Option<StreamEvent>,
+    },
+}
+
+/// Parses an event stream as it arrives.
+// https://html.spec.whatwg.org/multipage/#event-stream-interpretation
+pub struct EventStreamParser {
+    /// The bytes at the end of the stream so far that only start a UTF-8
+    /// sequence.
+    undecoded: Vec<u8>,
+    /// Whether anything has been decoded yet, so that a leading BOM is skipped.
+    started: bool,
+    /// Whether the last character was a CR, so that an LF right after it
+    /// doesn't end another line.
+    after_cr: bool,
+    line: String,
+    data: String,
+    event_type: String,
+    last_event_id: String,
+}
+
+impl EventStreamParser {
+    pub fn new() -> EventStreamParser {
+        EventStreamParser {
+            undecoded: vec![],
+            started: false,
+            after_cr: false,
+            line: String::new(),
+            data: String::new(),
+            event_type: String::new(),
+            last_event_id: String::new(),
+        }
+    }
+
+    /// Parses the next part of the stream. An event is only complete once
+    /// the blank line after it arrived.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<EventStreamItem> {
+        self.undecoded.extend_from_slice(bytes);
+        let complete = self.undecoded.len() - incomplete_utf8_suffix(&self.undecoded);
+        let rest = self.undecoded.split_off(complete);
+        let bytes = mem::replace(&mut self.undecoded, rest);
+        let text = String::from_utf8_lossy(&bytes);
+
+        let mut items = vec![];
+        for c in text.chars() {
+            if !self.started {
+                self.started = true;
+                if c == '\u{FEFF}' {
+                    continue;
+                }
+            }
+            match c {
+                '\n' if self.after_cr => {},
+                '\r' | '\n' => {
+                    let line = mem::replace(&mut self.line, String::new());
+                    self.process_line(&line, &mut items);
+                },
+                c => self.line.push(c),
+            }
+            self.after_cr = c == '\r';
+        }
+        items
+    }
+
+    fn process_line(&mut self, line: &str, items: &mut Vec<EventStreamItem>) {
+        if line.is_empty() {
+            return self.dispatch(items);
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = match line.find(':') {
+            Some(colon) => {
+                let value = &line[colon + 1..];
+                (&line[..colon], if value.starts_with(' ') { &value[1..] } else { value })
+            },
+            None => (line, ""),
+        };
+        // https://html.spec.whatwg.org/multipage/#processField
+        match field {
+            "event" => self.event_type = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            },
+            "id" if !value.contains('\0') => self.last_event_id = value.to_owned(),
+            "retry" if !value.is_empty() && value.chars().all(|c| c.is_digit(10)) => {
+                if let Ok(time) = value.parse() {
+                    items.push(EventStreamItem::Retry(time));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dispatchMessage
+    fn dispatch(&mut self, items: &mut Vec<EventStreamItem>) {
+        let mut data = mem::replace(&mut self.data, String::new());
+        let event_type = mem::replace(&mut self.event_type, String::new());
+        let event = if data.is_empty() {
+            None
+        } else {
+            data.pop();
+            Some(StreamEvent {
+                type_: if event_type.is_empty() { "message".to_owned() } else { event_type },
+                data: data,
+            })
+        };
+        items.push(EventStreamItem::Dispatch {
+            last_event_id: self.last_event_id.clone(),
+            event: event,
+        });
+    }
+}
+
+/// How many bytes at the end of `bytes` start a UTF-8 sequence that isn't
+/// complete yet.
+fn incomplete_utf8_suffix(bytes: &[u8]) -> usize {
+    for len in 1..cmp::min(4, bytes.len() + 1) {
+        let byte = bytes[bytes.len() - len];
+        if byte & 0xC0 != 0x80 {
+            let sequence_len = if byte >= 0xF0 {
+                4
+            } else if byte >= 0xE0 {
+                3
+            } else if byte >= 0xC0 {
+                2
+            } else {
+                1
+            };
+            return if sequence_len > len { len } else { 0 };
+        }
+    }
+    0
+}
+
+/// Task queued to fail the connection when the event stream can't even be
+/// requested.
+// https://html.spec.whatwg.org/multipage/#fail-the-connection
+struct FailTheConnectionTask {
+    event_source: Trusted<EventSource>,
+}
+
+impl Runnable for FailTheConnectionTask {
+    fn handler(self: Box<Self>) {
+        self.event_source.root().fail_the_connection();
+    }
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct EventSourceTimeoutCallback {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    event_source: Trusted<EventSource>,
+    generation_id: GenerationId,
+}
+
+impl EventSourceTimeoutCallback {
+    // https://html.spec.whatwg.org/multipage/#reestablish-the-connection
+    pub fn invoke(self) {
+        let event_source = self.event_source.root();
+        // Step 5.1
+        if event_source.generation_id.get() != self.generation_id {
+            return;
+        }
+        event_source.reconnection.fired();
+        // Step 5.2
+        if event_source.ready_state.get() != EventSourceReadyState::Connecting {
+            return;
+        }
+        // Steps 5.3-5.5
+        event_source.fetch();
     }
 }
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use app_units::Au;
 use document_loader::{LoadType, LoadBlocker};
 use dom::attr::{Attr, AttrValue};
 use dom::bindings::cell::DOMRefCell;
@@ -29,6 +30,7 @@ use dom::node::{Node, NodeDamage, UnbindContext, window_from_node, document_from
 use dom::urlhelper::UrlHelper;
 use dom::virtualmethods::VirtualMethods;
 use dom::window::{ReflowReason, Window};
+use euclid::rect::Rect;
 use ipc_channel::ipc;
 use js::jsapi::{JSAutoCompartment, RootedValue, JSContext, MutableHandleValue};
 use js::jsval::{UndefinedValue, NullValue};
@@ -66,6 +68,9 @@ pub struct HTMLIFrameElement {
     subpage_id: Cell<Option<SubpageId>>,
     sandbox: Cell<Option<u8>>,
     load_blocker: DOMRefCell<Option<LoadBlocker>>,
+    /// Whether the constellation was told that the document in this frame
+    /// can't be seen.
+    hidden_from_view: Cell<bool>,
 }
 
 impl HTMLIFrameElement {
@@ -87,8 +92,39 @@ impl HTMLIFrameElement {
         }).unwrap_or_else(|| Url::parse("about:blank").unwrap())
     }
 
+    /// Throttles the timers of a cross-origin document in this frame while the
+    /// frame isn't rendered, or is far outside of `clip_rect`, the part of the
+    /// page that is laid out for display.
+    pub fn update_visibility(&self, clip_rect: Rect<Au>) {
+        let pipeline_id = match self.pipeline_id.get() {
+            Some(pipeline_id) => pipeline_id,
+            None => return,
+        };
+        let document = document_from_node(self);
+        let cross_origin = match self.GetContentWindow() {
+            Some(content_window) => !content_window.Document().origin().same_origin(document.origin()),
+            // Frames that aren't run by this thread are for another host, or sandboxed.
+            None => true,
+        };
+        let hidden = cross_origin && {
+            let content_box = self.upcast::<Node>().bounding_content_box();
+            content_box.is_empty() || !content_box.intersects(&clip_rect)
+        };
+        if hidden == self.hidden_from_view.get() {
+            return;
+        }
+        let msg = ConstellationMsg::ChangeFrameVisibility(pipeline_id, !hidden);
+        match document.window().constellation_chan().send(msg) {
+            Ok(()) => self.hidden_from_view.set(hidden),
+            // The next check tries again.
+            Err(e) => warn!("Sending frame visibility change message failed ({}).", e),
+        }
+    }
+
     pub fn generate_new_subpage_id(&self) -> (SubpageId, Option<SubpageId>) {
         self.pipeline_id.set(Some(PipelineId::new()));
+        // The new document starts out unthrottled.
+        self.hidden_from_view.set(false);
 
         let old_subpage_id = self.subpage_id.get();
         let win = window_from_node(self);
@@ -193,6 +229,7 @@ impl HTMLIFrameElement {
             subpage_id: Cell::new(None),
             sandbox: Cell::new(None),
             load_blocker: DOMRefCell::new(None),
+            hidden_from_view: Cell::new(false),
         }
     }
 
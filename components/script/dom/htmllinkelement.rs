@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use cssparser::Parser as CssParser;
-use document_loader::LoadType;
+use document_loader::{LoadId, LoadType};
 use dom::attr::{Attr, AttrValue};
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::HTMLLinkElementBinding;
@@ -19,14 +19,19 @@ use dom::eventtarget::EventTarget;
 use dom::htmlelement::HTMLElement;
 use dom::node::{Node, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
+use dom::window::Window;
 use encoding::EncodingRef;
 use encoding::all::UTF_8;
 use hyper::header::ContentType;
+use hyper::header::Headers;
+use hyper::method::Method;
 use hyper::mime::{Mime, TopLevel, SubLevel};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use layout_interface::{LayoutChan, Msg};
 use net_traits::{AsyncResponseListener, AsyncResponseTarget, Metadata, NetworkError};
+use net_traits::{CoreResourceMsg, IpcSend, LoadConsumer, LoadContext, LoadData, RequestSource};
+use net_traits::ResourceCORSData;
 use network_listener::{NetworkListener, PreInvoke};
 use script_traits::{MozBrowserEvent, ScriptMsg as ConstellationMsg};
 use std::ascii::AsciiExt;
@@ -119,6 +124,95 @@ fn is_favicon(value: &Option<String>) -> bool {
     }
 }
 
+/// Whether the `rel` attribute lists the given link type.
+fn has_link_type(value: &Option<String>, link_type: &str) -> bool {
+    match *value {
+        Some(ref value) => value.split(HTML_SPACE_CHARACTERS).any(|s| s.eq_ignore_ascii_case(link_type)),
+        None => false,
+    }
+}
+
+fn is_speculative(value: &Option<String>) -> bool {
+    has_link_type(value, "prefetch") || has_link_type(value, "preconnect")
+}
+
+/// The state of the `crossorigin` attribute of a `<link>`, when present.
+/// https://html.spec.whatwg.org/multipage/#cors-settings-attribute
+#[derive(JSTraceable, HeapSizeOf, Clone, Copy, PartialEq)]
+pub enum CorsSettings {
+    /// Credentials are only sent to the same origin; also the state of
+    /// invalid values.
+    Anonymous,
+    UseCredentials,
+}
+
+fn cors_settings(value: &Option<String>) -> Option<CorsSettings> {
+    match *value {
+        Some(ref value) if value.eq_ignore_ascii_case("use-credentials") => Some(CorsSettings::UseCredentials),
+        Some(_) => Some(CorsSettings::Anonymous),
+        None => None,
+    }
+}
+
+/// Speculative network activity a `<link>` asked for, which waits for an
+/// idle period of its window so that it doesn't compete with the page.
+#[derive(JSTraceable, HeapSizeOf, Clone, PartialEq)]
+pub enum SpeculativeLoad {
+    /// https://html.spec.whatwg.org/multipage/#link-type-prefetch
+    /// A prefetch without a `crossorigin` attribute is a no-cors request.
+    Prefetch(Url, Option<CorsSettings>),
+    /// https://html.spec.whatwg.org/multipage/#link-type-preconnect
+    Preconnect(Url),
+}
+
+impl SpeculativeLoad {
+    pub fn start(self, window: &Window) {
+        let msg = match self {
+            SpeculativeLoad::Prefetch(url, cors_settings) => {
+                // FIXME: there is no HTTP cache in the tree yet, so the
+                // response is thrown away and the prefetch only gets the
+                // connection ready. It is worth little until one exists.
+                let origin = window.get_url();
+                let same_origin = url.origin() == origin.origin();
+                let mut headers = Headers::new();
+                let cors = match cors_settings {
+                    Some(_) if !same_origin => {
+                        let serialized = origin.origin().unicode_serialization();
+                        headers.set_raw("Origin".to_owned(), vec![serialized.into_bytes()]);
+                        Some(ResourceCORSData {
+                            preflight: false,
+                            origin: origin,
+                        })
+                    }
+                    _ => None,
+                };
+                let load_data = LoadData {
+                    context: LoadContext::Browsing,
+                    url: url,
+                    method: Method::Get,
+                    headers: headers,
+                    preserved_headers: Headers::new(),
+                    data: None,
+                    cors: cors,
+                    pipeline_id: Some(window.pipeline()),
+                    // https://fetch.spec.whatwg.org/#concept-request-credentials-mode
+                    credentials_flag: cors_settings != Some(CorsSettings::Anonymous) || same_origin,
+                    referrer_policy: None,
+                    referrer_url: None,
+                    source: RequestSource::None,
+                };
+                let (sender, receiver) = ipc::channel().unwrap();
+                ROUTER.add_route(receiver.to_opaque(), box |_| {});
+                CoreResourceMsg::Load(load_data, LoadConsumer::Channel(sender), None)
+            },
+            SpeculativeLoad::Preconnect(url) => CoreResourceMsg::Preconnect(url),
+        };
+        if let Err(e) = window.resource_threads().send(msg) {
+            warn!("Starting a speculative load failed ({}).", e);
+        }
+    }
+}
+
 impl VirtualMethods for HTMLLinkElement {
     fn super_type(&self) -> Option<&VirtualMethods> {
         Some(self.upcast::<HTMLElement>() as &VirtualMethods)
@@ -138,6 +232,8 @@ impl VirtualMethods for HTMLLinkElement {
                 } else if is_favicon(&rel) {
                     let sizes = get_attr(self.upcast(), &atom!("sizes"));
                     self.handle_favicon_url(rel.as_ref().unwrap(), &attr.value(), &sizes);
+                } else if is_speculative(&rel) {
+                    self.handle_speculative_url(&rel, &attr.value());
                 }
             },
             &atom!("sizes") => {
@@ -182,6 +278,9 @@ impl VirtualMethods for HTMLLinkElement {
                 Some(ref href) if is_favicon(&rel) => {
                     self.handle_favicon_url(rel.as_ref().unwrap(), href, &sizes);
                 }
+                Some(ref href) if is_speculative(&rel) => {
+                    self.handle_speculative_url(&rel, href);
+                }
                 _ => {}
             }
         }
@@ -207,13 +306,14 @@ impl HTMLLinkElement {
 
                 // TODO: #8085 - Don't load external stylesheets if the node's mq doesn't match.
                 let elem = Trusted::new(self);
+                let (load, pending) = document.prepare_async_load(LoadType::Stylesheet(url));
 
                 let context = Arc::new(Mutex::new(StylesheetContext {
                     elem: elem,
                     media: Some(media),
                     data: vec!(),
                     metadata: None,
-                    url: url.clone(),
+                    load: load,
                 }));
 
                 let (action_sender, action_receiver) = ipc::channel().unwrap();
@@ -231,7 +331,7 @@ impl HTMLLinkElement {
                 if self.parser_inserted.get() {
                     document.increment_script_blocking_stylesheet_count();
                 }
-                document.load_async(LoadType::Stylesheet(url), response_target);
+                pending.load_async(response_target);
             }
             Err(e) => debug!("Parsing url {} failed: {}", href, e)
         }
@@ -253,6 +353,23 @@ impl HTMLLinkElement {
             Err(e) => debug!("Parsing url {} failed: {}", href, e)
         }
     }
+
+    fn handle_speculative_url(&self, rel: &Option<String>, href: &str) {
+        let document = document_from_node(self);
+        match document.base_url().join(href) {
+            Ok(url) => {
+                let window = document.window();
+                if has_link_type(rel, "preconnect") {
+                    window.queue_speculative_load(SpeculativeLoad::Preconnect(url.clone()));
+                }
+                if has_link_type(rel, "prefetch") {
+                    let cors_settings = cors_settings(&get_attr(self.upcast(), &atom!("crossorigin")));
+                    window.queue_speculative_load(SpeculativeLoad::Prefetch(url, cors_settings));
+                }
+            }
+            Err(e) => debug!("Parsing url {} failed: {}", href, e)
+        }
+    }
 }
 
 /// The context required for asynchronously loading an external stylesheet.
@@ -264,8 +381,9 @@ struct StylesheetContext {
     data: Vec<u8>,
     /// The response metadata received to date.
     metadata: Option<Metadata>,
-    /// The initial URL requested.
-    url: Url,
+    /// The load that delays the load event of the document until the
+    /// stylesheet is fetched.
+    load: LoadId,
 }
 
 impl PreInvoke for StylesheetContext {}
@@ -324,7 +442,7 @@ impl AsyncResponseListener for StylesheetContext {
         if elem.parser_inserted.get() {
             document.decrement_script_blocking_stylesheet_count();
         }
-        document.finish_load(LoadType::Stylesheet(self.url.clone()));
+        document.finish_load(self.load);
     }
 }
 
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use document_loader::LoadType;
+use document_loader::{LoadId, LoadType};
 use dom::attr::Attr;
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
@@ -15,7 +15,7 @@ use dom::bindings::global::GlobalRef;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::js::{Root, MutNullableHeap, JS};
 use dom::bindings::refcounted::Trusted;
-use dom::document::Document;
+use dom::document::{Document, ThrottlingExemption};
 use dom::element::{Element, AttributeMutation};
 use dom::event::{Event, EventBubbles, EventCancelable};
 use dom::htmlelement::HTMLElement;
@@ -23,6 +23,7 @@ use dom::htmlsourceelement::HTMLSourceElement;
 use dom::mediaerror::MediaError;
 use dom::node::{window_from_node, document_from_node, Node, UnbindContext};
 use dom::virtualmethods::VirtualMethods;
+use euclid::length::Length;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use net_traits::{AsyncResponseListener, AsyncResponseTarget, Metadata, NetworkError};
@@ -32,7 +33,7 @@ use std::cell::Cell;
 use std::sync::{Arc, Mutex};
 use string_cache::Atom;
 use task_source::dom_manipulation::DOMManipulationTask;
-use time::{self, Timespec, Duration};
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
 use url::Url;
 use util::str::DOMString;
 
@@ -45,10 +46,9 @@ struct HTMLMediaElementContext {
     metadata: Option<Metadata>,
     /// The generation of the media element when this fetch started.
     generation_id: u32,
-    /// Time of last progress notification.
-    next_progress_event: Timespec,
-    /// Url of resource requested.
-    url: Url,
+    /// The load that delays the load event of the document until the
+    /// resource is fetched.
+    load: LoadId,
     /// Whether the media metadata has been completely received.
     have_metadata: bool,
     /// True if this response is invalid and should be ignored.
@@ -98,17 +98,17 @@ impl AsyncResponseListener for HTMLMediaElementContext {
         }
 
         // https://html.spec.whatwg.org/multipage/#concept-media-load-resource step 4,
-        // => "If mode is remote" step 2
-        if time::get_time() > self.next_progress_event {
-            elem.queue_fire_simple_event("progress");
-            self.next_progress_event = time::get_time() + Duration::milliseconds(350);
-        }
+        // => "If mode is remote" steps 2 and 3
+        elem.media_data_received();
     }
 
     // https://html.spec.whatwg.org/multipage/#media-data-processing-steps-list
     fn response_complete(&mut self, status: Result<(), NetworkError>) {
         let elem = self.elem.root();
 
+        elem.cancel_media_timer(MediaTimer::Progress);
+        elem.cancel_media_timer(MediaTimer::Stalled);
+
         // => "Once the entire media resource has been fetched..."
         if status.is_ok() {
             elem.change_ready_state(HAVE_ENOUGH_DATA);
@@ -139,7 +139,7 @@ impl AsyncResponseListener for HTMLMediaElementContext {
         }
 
         let document = document_from_node(&*elem);
-        document.finish_load(LoadType::Media(self.url.clone()));
+        document.finish_load(self.load);
     }
 }
 
@@ -151,14 +151,13 @@ impl PreInvoke for HTMLMediaElementContext {
 }
 
 impl HTMLMediaElementContext {
-    fn new(elem: &HTMLMediaElement, url: Url) -> HTMLMediaElementContext {
+    fn new(elem: &HTMLMediaElement, load: LoadId) -> HTMLMediaElementContext {
         HTMLMediaElementContext {
             elem: Trusted::new(elem),
             data: vec![],
             metadata: None,
             generation_id: elem.generation_id.get(),
-            next_progress_event: time::get_time() + Duration::milliseconds(350),
-            url: url,
+            load: load,
             have_metadata: false,
             ignore_response: false,
         }
@@ -176,6 +175,12 @@ pub struct HTMLMediaElement {
     error: MutNullableHeap<JS<MediaError>>,
     paused: Cell<bool>,
     autoplaying: Cell<bool>,
+    /// Pending timers for the periodic `timeupdate`, `progress` and `stalled` events.
+    timeupdate_timer: DOMRefCell<Option<OneshotTimerHandle>>,
+    progress_timer: DOMRefCell<Option<OneshotTimerHandle>>,
+    stalled_timer: DOMRefCell<Option<OneshotTimerHandle>>,
+    /// Whether the element holds the audio throttling exemption of its document.
+    audible: Cell<bool>,
 }
 
 impl HTMLMediaElement {
@@ -193,6 +198,10 @@ impl HTMLMediaElement {
             error: Default::default(),
             paused: Cell::new(true),
             autoplaying: Cell::new(true),
+            timeupdate_timer: DOMRefCell::new(None),
+            progress_timer: DOMRefCell::new(None),
+            stalled_timer: DOMRefCell::new(None),
+            audible: Cell::new(false),
         }
     }
 
@@ -201,6 +210,34 @@ impl HTMLMediaElement {
         &self.htmlelement
     }
 
+    /// Starts or stops playback.
+    fn set_paused(&self, paused: bool) {
+        self.paused.set(paused);
+        self.update_audible();
+    }
+
+    /// Whether the playback can be heard. There is no volume yet, so only
+    /// the `muted` attribute silences it.
+    fn is_audible(&self) -> bool {
+        !self.paused.get() && !self.upcast::<Element>().has_attribute(&atom!("muted"))
+    }
+
+    /// While the element can be heard, the timers of its document keep
+    /// running on time even if the document is hidden.
+    fn update_audible(&self) {
+        let audible = self.is_audible();
+        if self.audible.get() == audible {
+            return;
+        }
+        self.audible.set(audible);
+        let document = document_from_node(self);
+        if audible {
+            document.add_throttling_exemption(ThrottlingExemption::Audio);
+        } else {
+            document.remove_throttling_exemption(ThrottlingExemption::Audio);
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/#internal-pause-steps
     fn internal_pause_steps(&self) {
         // Step 1
@@ -209,7 +246,8 @@ impl HTMLMediaElement {
         // Step 2
         if !self.Paused() {
             // 2.1
-            self.paused.set(true);
+            self.set_paused(true);
+            self.cancel_media_timer(MediaTimer::TimeUpdate);
 
             // 2.2
             self.queue_internal_pause_steps_task();
@@ -225,6 +263,80 @@ impl HTMLMediaElement {
         // Step 1
         self.fire_simple_event("playing");
         // TODO Step 2
+
+        // https://html.spec.whatwg.org/multipage/#playing-the-media-resource
+        // "...the user agent must queue a task to fire a simple event named timeupdate
+        // at the element" periodically while the current playback position changes.
+        if !self.Paused() {
+            self.schedule_media_timer(MediaTimer::TimeUpdate);
+        }
+    }
+
+    fn media_timer_slot(&self, timer: MediaTimer) -> &DOMRefCell<Option<OneshotTimerHandle>> {
+        match timer {
+            MediaTimer::TimeUpdate => &self.timeupdate_timer,
+            MediaTimer::Progress => &self.progress_timer,
+            MediaTimer::Stalled => &self.stalled_timer,
+        }
+    }
+
+    fn schedule_media_timer(&self, timer: MediaTimer) {
+        self.cancel_media_timer(timer);
+        let callback = OneshotTimerCallback::MediaTimer(MediaTimerCallback {
+            elem: Trusted::new(self),
+            timer: timer,
+            generation_id: self.generation_id.get(),
+        });
+        let window = window_from_node(self);
+        let handle = window.schedule_callback(callback, Length::new(timer.interval_ms()));
+        *self.media_timer_slot(timer).borrow_mut() = Some(handle);
+    }
+
+    fn cancel_media_timer(&self, timer: MediaTimer) {
+        if let Some(handle) = self.media_timer_slot(timer).borrow_mut().take() {
+            window_from_node(self).unschedule_callback(handle);
+        }
+    }
+
+    fn cancel_media_timers(&self) {
+        self.cancel_media_timer(MediaTimer::TimeUpdate);
+        self.cancel_media_timer(MediaTimer::Progress);
+        self.cancel_media_timer(MediaTimer::Stalled);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-media-load-resource step 4,
+    // => "If mode is remote" steps 2 and 3
+    fn media_data_received(&self) {
+        // Progress is reported at most every 350ms, so only arm the timer when
+        // it isn't already pending.
+        if self.progress_timer.borrow().is_none() {
+            self.schedule_media_timer(MediaTimer::Progress);
+        }
+        // Any received data restarts the stall detection.
+        self.schedule_media_timer(MediaTimer::Stalled);
+    }
+
+    fn handle_media_timer(&self, timer: MediaTimer) {
+        *self.media_timer_slot(timer).borrow_mut() = None;
+        match timer {
+            MediaTimer::TimeUpdate => {
+                if self.Paused() {
+                    return;
+                }
+                self.fire_simple_event("timeupdate");
+                self.schedule_media_timer(MediaTimer::TimeUpdate);
+            }
+            MediaTimer::Progress => {
+                if self.network_state.get() == NETWORK_LOADING {
+                    self.fire_simple_event("progress");
+                }
+            }
+            MediaTimer::Stalled => {
+                if self.network_state.get() == NETWORK_LOADING {
+                    self.fire_simple_event("stalled");
+                }
+            }
+        }
     }
 
     fn queue_notify_about_playing(&self) {
@@ -357,7 +469,7 @@ impl HTMLMediaElement {
                    self.Paused() &&
                    self.Autoplay() {
                     // Step 1
-                    self.paused.set(false);
+                    self.set_paused(false);
                     // TODO step 2: show poster
                     // Step 3
                     self.queue_fire_simple_event("play");
@@ -471,7 +583,10 @@ impl HTMLMediaElement {
             }
 
             // 4.2
-            let context = Arc::new(Mutex::new(HTMLMediaElementContext::new(self, url.clone())));
+            // FIXME: we're supposed to block the load event much earlier than now
+            let doc = document_from_node(self);
+            let (load, pending) = doc.prepare_async_load(LoadType::Media(url));
+            let context = Arc::new(Mutex::new(HTMLMediaElementContext::new(self, load)));
             let (action_sender, action_receiver) = ipc::channel().unwrap();
             let script_chan = window_from_node(self).networking_task_source();
             let listener = box NetworkListener {
@@ -486,9 +601,7 @@ impl HTMLMediaElement {
                 listener.notify(message.to().unwrap());
             });
 
-            // FIXME: we're supposed to block the load event much earlier than now
-            let doc = document_from_node(self);
-            doc.load_async(LoadType::Media(url), response_target);
+            pending.load_async(response_target);
         } else {
             // TODO local resource fetch
             self.queue_dedicated_media_source_failure_steps();
@@ -528,6 +641,7 @@ impl HTMLMediaElement {
 
         // Step 2
         self.generation_id.set(self.generation_id.get() + 1);
+        self.cancel_media_timers();
         // TODO reject pending play promises
 
         // Step 3
@@ -553,7 +667,7 @@ impl HTMLMediaElement {
 
             // 4.6
             if !self.Paused() {
-                self.paused.set(true);
+                self.set_paused(true);
             }
             // TODO 4.7 (seeking)
             // TODO 4.8 (playback position)
@@ -647,7 +761,7 @@ impl HTMLMediaElementMethods for HTMLMediaElement {
         // Step 7
         if self.Paused() {
             // 7.1
-            self.paused.set(false);
+            self.set_paused(false);
 
             // TODO 7.2 (show poster)
 
@@ -707,10 +821,21 @@ impl VirtualMethods for HTMLMediaElement {
                     self.media_element_load_algorithm();
                 }
             }
+            &atom!("muted") => self.update_audible(),
             _ => (),
         };
     }
 
+    fn adopting_steps(&self, old_doc: &Document) {
+        self.super_type().unwrap().adopting_steps(old_doc);
+
+        // The new document keeps its timers running on time for the playback instead.
+        if self.audible.get() {
+            old_doc.remove_throttling_exemption(ThrottlingExemption::Audio);
+            document_from_node(self).add_throttling_exemption(ThrottlingExemption::Audio);
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/#playing-the-media-resource:remove-an-element-from-a-document
     fn unbind_from_tree(&self, context: &UnbindContext) {
         self.super_type().unwrap().unbind_from_tree(context);
@@ -801,6 +926,47 @@ impl Runnable for PauseIfNotInDocumentTask {
     }
 }
 
+/// The periodic events a media element schedules through the window's timers.
+#[derive(JSTraceable, PartialEq, Copy, Clone, Debug, HeapSizeOf)]
+enum MediaTimer {
+    TimeUpdate,
+    Progress,
+    Stalled,
+}
+
+impl MediaTimer {
+    fn interval_ms(&self) -> u64 {
+        match *self {
+            // https://html.spec.whatwg.org/multipage/#playing-the-media-resource
+            // "...at least every 15ms and no more than every 250ms."
+            MediaTimer::TimeUpdate => 250,
+            // https://html.spec.whatwg.org/multipage/#concept-media-load-resource
+            // "...every 350ms (±200ms) or for every byte received, whichever is least frequent."
+            MediaTimer::Progress => 350,
+            // "...for about three seconds without receiving any further data."
+            MediaTimer::Stalled => 3000,
+        }
+    }
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct MediaTimerCallback {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    elem: Trusted<HTMLMediaElement>,
+    timer: MediaTimer,
+    generation_id: u32,
+}
+
+impl MediaTimerCallback {
+    pub fn invoke(self) {
+        let elem = self.elem.root();
+        if elem.generation_id.get() != self.generation_id {
+            return;
+        }
+        elem.handle_media_timer(self.timer);
+    }
+}
+
 enum ResourceSelectionMode {
     Object,
     Attribute(String),
@@ -59,6 +59,25 @@ impl HTMLMetaElement {
                 self.apply_viewport();
             }
         }
+
+        if let Some(http_equiv) = element.get_attribute(&ns!(), &atom!("http-equiv")).r() {
+            let http_equiv = http_equiv.value().to_ascii_lowercase();
+            if http_equiv.trim_matches(HTML_SPACE_CHARACTERS) == "refresh" {
+                self.declarative_refresh();
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#attr-meta-http-equiv-refresh
+    fn declarative_refresh(&self) {
+        // Step 1
+        let element = self.upcast::<Element>();
+        if let Some(content) = element.get_attribute(&ns!(), &atom!("content")).r() {
+            // Step 2
+            let content = content.value();
+            // Step 3
+            document_from_node(self).shared_declarative_refresh_steps(&**content);
+        }
     }
 
     fn apply_viewport(&self) {
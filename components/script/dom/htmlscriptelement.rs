@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use document_loader::LoadType;
+use document_loader::{LoadId, LoadType};
 use dom::attr::Attr;
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
@@ -132,8 +132,9 @@ struct ScriptContext {
     data: Vec<u8>,
     /// The response metadata received to date.
     metadata: Option<Metadata>,
-    /// The initial URL requested.
-    url: Url,
+    /// The load that delays the load event of the document until the script
+    /// is fetched.
+    load: LoadId,
     /// Indicates whether the request failed, and why
     status: Result<(), NetworkError>
 }
@@ -175,7 +176,7 @@ impl AsyncResponseListener for ScriptContext {
         elem.ready_to_be_parser_executed.set(true);
 
         let document = document_from_node(elem.r());
-        document.finish_load(LoadType::Script(self.url.clone()));
+        document.finish_load(self.load);
     }
 }
 
@@ -297,12 +298,13 @@ impl HTMLScriptElement {
                 // Step 16.6.
                 // TODO(#9186): use the fetch infrastructure.
                 let elem = Trusted::new(self);
+                let (load, pending) = doc.prepare_async_load(LoadType::Script(url));
 
                 let context = Arc::new(Mutex::new(ScriptContext {
                     elem: elem,
                     data: vec!(),
                     metadata: None,
-                    url: url.clone(),
+                    load: load,
                     status: Ok(())
                 }));
 
@@ -318,7 +320,7 @@ impl HTMLScriptElement {
                     listener.notify(message.to().unwrap());
                 });
 
-                doc.load_async(LoadType::Script(url), response_target);
+                pending.load_async(response_target);
                 true
             },
             None => false,
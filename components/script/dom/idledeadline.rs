@@ -0,0 +1,55 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IdleDeadlineBinding;
+use dom::bindings::codegen::Bindings::IdleDeadlineBinding::IdleDeadlineMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::performance::DOMHighResTimeStamp;
+use dom::window::Window;
+use time;
+use time_resolution::TimeResolutionPolicy;
+
+#[dom_struct]
+pub struct IdleDeadline {
+    reflector_: Reflector,
+    /// When the idle period ends, in milliseconds on the `precise_time_ns` clock.
+    deadline: f64,
+    did_timeout: bool,
+    /// How precisely the time remaining may be told, like `performance.now()`.
+    time_resolution: TimeResolutionPolicy,
+}
+
+impl IdleDeadline {
+    fn new_inherited(deadline: f64, did_timeout: bool, time_resolution: TimeResolutionPolicy) -> IdleDeadline {
+        IdleDeadline {
+            reflector_: Reflector::new(),
+            deadline: deadline,
+            did_timeout: did_timeout,
+            time_resolution: time_resolution,
+        }
+    }
+
+    pub fn new(window: &Window, deadline: f64, did_timeout: bool) -> Root<IdleDeadline> {
+        reflect_dom_object(box IdleDeadline::new_inherited(deadline, did_timeout, window.time_resolution()),
+                           GlobalRef::Window(window),
+                           IdleDeadlineBinding::Wrap)
+    }
+}
+
+impl IdleDeadlineMethods for IdleDeadline {
+    // https://w3c.github.io/requestidlecallback/#dom-idledeadline-timeremaining
+    fn TimeRemaining(&self) -> DOMHighResTimeStamp {
+        let now = time::precise_time_ns() as f64 / 1000000 as f64;
+        let remaining_ns = ((self.deadline - now).max(0.) * 1000000 as f64) as u64;
+        Finite::wrap(self.time_resolution.coarsen_ns(remaining_ns) as f64 / 1000000 as f64)
+    }
+
+    // https://w3c.github.io/requestidlecallback/#dom-idledeadline-didtimeout
+    fn DidTimeout(&self) -> bool {
+        self.did_timeout
+    }
+}
@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::IdleDetectorBinding;
+use dom::bindings::codegen::Bindings::IdleDetectorBinding::{IdleDetectorMethods, IdleOptions};
+use dom::bindings::codegen::Bindings::IdleDetectorBinding::{PermissionState, ScreenIdleState, UserIdleState};
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::js::Root;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflectable, reflect_dom_object};
+use dom::eventtarget::EventTarget;
+use euclid::length::Length;
+use script_thread::ScriptThread;
+use script_traits::MsDuration;
+use std::cell::Cell;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
+use util::prefs;
+
+/// The shortest threshold a page may ask for, so that it can't tell apart the
+/// moments the user interacts with the system.
+const MIN_THRESHOLD_MS: u64 = 60 * 1000;
+
+/// How often the state of the user is evaluated once a detector started.
+const STATE_POLL_INTERVAL_MS: u64 = 1000;
+
+#[dom_struct]
+pub struct IdleDetector {
+    eventtarget: EventTarget,
+    user_state: Cell<Option<UserIdleState>>,
+    screen_state: Cell<Option<ScreenIdleState>>,
+    /// How long the user has to be inactive to be idle, in ms, once started.
+    threshold: Cell<Option<u64>>,
+    /// The timer that evaluates the state of the user again and again, once started.
+    poll: Cell<Option<OneshotTimerHandle>>,
+}
+
+impl IdleDetector {
+    fn new_inherited() -> IdleDetector {
+        IdleDetector {
+            eventtarget: EventTarget::new_inherited(),
+            user_state: Cell::new(None),
+            screen_state: Cell::new(None),
+            threshold: Cell::new(None),
+            poll: Cell::new(None),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<IdleDetector> {
+        reflect_dom_object(box IdleDetector::new_inherited(), global, IdleDetectorBinding::Wrap)
+    }
+
+    // https://wicg.github.io/idle-detection/#dom-idledetector-constructor
+    pub fn Constructor(global: GlobalRef) -> Fallible<Root<IdleDetector>> {
+        Ok(IdleDetector::new(global))
+    }
+
+    // https://wicg.github.io/idle-detection/#dom-idledetector-requestpermission
+    pub fn RequestPermission(_: GlobalRef) -> PermissionState {
+        // There is no way to ask the user yet, so the embedder decides up front.
+        match prefs::get_pref("dom.idledetection.permission").as_string() {
+            Some("granted") => PermissionState::Granted,
+            _ => PermissionState::Denied,
+        }
+    }
+
+    /// Evaluates the state of the user, firing `change` if it changed since
+    /// the last evaluation.
+    fn update_state(&self) {
+        let threshold = match self.threshold.get() {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let user_state = if ScriptThread::time_since_user_input().get() >= threshold {
+            UserIdleState::Idle
+        } else {
+            UserIdleState::Active
+        };
+        // Whether the screen is locked isn't known to Servo.
+        let screen_state = ScreenIdleState::Unlocked;
+        if self.user_state.get() == Some(user_state) && self.screen_state.get() == Some(screen_state) {
+            return;
+        }
+        self.user_state.set(Some(user_state));
+        self.screen_state.set(Some(screen_state));
+        self.upcast::<EventTarget>().fire_simple_event("change");
+    }
+
+    fn schedule_poll(&self) {
+        let callback = OneshotTimerCallback::IdleDetectorPoll(IdleDetectorPoll {
+            detector: Trusted::new(self),
+        });
+        let global = self.global();
+        self.poll.set(Some(global.r().schedule_callback(callback, Length::new(STATE_POLL_INTERVAL_MS))));
+    }
+
+    /// Stops evaluating the state of the user, once the window of the
+    /// detector is unloaded.
+    pub fn stop(&self) {
+        let global = self.global();
+        if let Some(handle) = self.poll.get() {
+            global.r().unschedule_callback(handle);
+            self.poll.set(None);
+        }
+        if let GlobalRef::Window(window) = global.r() {
+            window.remove_idle_detector(self);
+        }
+        self.threshold.set(None);
+    }
+}
+
+impl IdleDetectorMethods for IdleDetector {
+    // https://wicg.github.io/idle-detection/#dom-idledetector-userstate
+    fn GetUserState(&self) -> Option<UserIdleState> {
+        self.user_state.get()
+    }
+
+    // https://wicg.github.io/idle-detection/#dom-idledetector-screenstate
+    fn GetScreenState(&self) -> Option<ScreenIdleState> {
+        self.screen_state.get()
+    }
+
+    // https://wicg.github.io/idle-detection/#dom-idledetector-onchange
+    event_handler!(change, GetOnchange, SetOnchange);
+
+    // https://wicg.github.io/idle-detection/#dom-idledetector-start
+    fn Start(&self, options: &IdleOptions) -> ErrorResult {
+        if IdleDetector::RequestPermission(self.global().r()) != PermissionState::Granted {
+            return Err(Error::NotAllowed);
+        }
+        if self.threshold.get().is_some() {
+            return Err(Error::InvalidState);
+        }
+        let threshold = match options.threshold {
+            Some(threshold) if threshold >= MIN_THRESHOLD_MS => threshold,
+            Some(_) => return Err(Error::Type(format!("The threshold is less than {} ms", MIN_THRESHOLD_MS))),
+            None => return Err(Error::Type("No threshold was given".to_owned())),
+        };
+        self.threshold.set(Some(threshold));
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.add_idle_detector(self);
+        }
+        self.update_state();
+        self.schedule_poll();
+        Ok(())
+    }
+}
+
+/// Evaluates the state of the user for an idle detector, again and again
+/// until the detector is stopped. It is an ordinary timer, so it gets
+/// coalesced with the other timers of the page while the page is hidden.
+#[derive(JSTraceable, HeapSizeOf, Clone)]
+pub struct IdleDetectorPoll {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    detector: Trusted<IdleDetector>,
+}
+
+impl IdleDetectorPoll {
+    /// How long to wait between evaluations.
+    pub fn period(&self) -> MsDuration {
+        Length::new(STATE_POLL_INTERVAL_MS)
+    }
+
+    pub fn invoke(self) {
+        self.detector.root().update_state();
+    }
+}
@@ -0,0 +1,176 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::callback::ExceptionHandling;
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::InternalsBinding;
+use dom::bindings::codegen::Bindings::InternalsBinding::{InternalsMethods, TimerDiagnosticsCallback};
+use dom::bindings::error::{Error, ErrorResult};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::js::Root;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflectable, Reflector, reflect_dom_object};
+use dom::reportbody::ReportBody;
+use dom::testreportbody::TestReportBody;
+use euclid::length::Length;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use rustc_serialize::json::{Json, ToJson};
+use script_runtime::CommonScriptMsg;
+use script_runtime::ScriptThreadEventCategory::ScriptEvent;
+use script_thread::Runnable;
+use script_traits::{PipelineTimerStats, ScriptMsg as ConstellationMsg, TimerDiagnostics};
+use script_traits::{TimerLatencyStats, TimerSchedulerStats};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use util::str::DOMString;
+
+// Hooks for tests and automation, only exposed if dom.internals.enabled is set.
+#[dom_struct]
+pub struct Internals {
+    reflector_: Reflector,
+    /// The callbacks waiting for timer diagnostics, by the id of their request.
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    diagnostics_callbacks: DOMRefCell<HashMap<u32, Rc<TimerDiagnosticsCallback>>>,
+    next_diagnostics_request: Cell<u32>,
+}
+
+impl Internals {
+    fn new_inherited() -> Internals {
+        Internals {
+            reflector_: Reflector::new(),
+            diagnostics_callbacks: DOMRefCell::new(HashMap::new()),
+            next_diagnostics_request: Cell::new(0),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<Internals> {
+        reflect_dom_object(box Internals::new_inherited(), global, InternalsBinding::Wrap)
+    }
+}
+
+impl InternalsMethods for Internals {
+    fn FlushDueTimers(&self) {
+        self.global().r().flush_due_timers();
+    }
+
+    fn AdvanceClock(&self, ms: u32) {
+        self.global().r().advance_timer_clock(Length::new(ms as u64));
+    }
+
+    fn SetViewportEventInterval(&self, ms: u32) {
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.set_viewport_event_interval(ms as u64);
+        }
+    }
+
+    // https://w3c.github.io/reporting/#generate-test-report-command
+    fn GenerateTestReport(&self, message: DOMString, group: DOMString) {
+        // Workers don't keep reports yet.
+        if let GlobalRef::Window(window) = self.global().r() {
+            let body = TestReportBody::new(GlobalRef::Window(window), message);
+            window.reporting().generate_report(window, "test", Some(body.upcast::<ReportBody>()), &group);
+        }
+    }
+
+    fn CollectTimerDiagnostics(&self, callback: Rc<TimerDiagnosticsCallback>) -> ErrorResult {
+        // The statistics cover the pages of every origin.
+        let global = self.global();
+        match global.r() {
+            GlobalRef::Window(window) if window.get_url().scheme() == "about" => {},
+            _ => return Err(Error::Security),
+        }
+        let request = self.next_diagnostics_request.get();
+        self.next_diagnostics_request.set(request.wrapping_add(1));
+        self.diagnostics_callbacks.borrow_mut().insert(request, callback);
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let internals = Trusted::new(self);
+        let script_chan = global.r().script_chan();
+        ROUTER.add_route(receiver.to_opaque(), box move |message| {
+            let runnable = box TimerDiagnosticsRunnable {
+                internals: internals.clone(),
+                request: request,
+                diagnostics: message.to().unwrap(),
+            };
+            let _ = script_chan.send(CommonScriptMsg::RunnableMsg(ScriptEvent, runnable));
+        });
+        if let Err(e) = global.r().constellation_chan().send(ConstellationMsg::GetTimerDiagnostics(sender)) {
+            warn!("Requesting timer diagnostics failed ({}).", e);
+        }
+        Ok(())
+    }
+}
+
+/// Hands the timer diagnostics to the callback that asked for them, as JSON.
+struct TimerDiagnosticsRunnable {
+    internals: Trusted<Internals>,
+    request: u32,
+    diagnostics: TimerDiagnostics,
+}
+
+impl Runnable for TimerDiagnosticsRunnable {
+    fn handler(self: Box<TimerDiagnosticsRunnable>) {
+        let internals = self.internals.root();
+        let callback = internals.diagnostics_callbacks.borrow_mut().remove(&self.request);
+        if let Some(callback) = callback {
+            let json = DOMString::from(diagnostics_to_json(&self.diagnostics).to_string());
+            let _ = callback.Call__(json, ExceptionHandling::Report);
+        }
+    }
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(name, value)| (name.to_owned(), value)).collect())
+}
+
+fn diagnostics_to_json(diagnostics: &TimerDiagnostics) -> Json {
+    object(vec![
+        ("pipelines", Json::Array(diagnostics.pipelines.iter().map(pipeline_stats_to_json).collect())),
+        ("scheduler", diagnostics.scheduler.as_ref().map_or(Json::Null, scheduler_stats_to_json)),
+    ])
+}
+
+fn pipeline_stats_to_json(stats: &PipelineTimerStats) -> Json {
+    object(vec![
+        ("pipeline", stats.pipeline_id.to_string().to_json()),
+        ("url", stats.url.to_json()),
+        ("timers", stats.timers.to_json()),
+        ("scriptTimers", stats.script_timers.to_json()),
+        ("nextDue", stats.next_due.to_json()),
+        ("throttled", stats.throttled.to_json()),
+        ("throttlingExempt", stats.throttling_exempt.to_json()),
+        ("suspensions", Json::Array(stats.suspensions.iter().map(|&(ref reason, count)| {
+            object(vec![("reason", reason.to_json()), ("count", count.to_json())])
+        }).collect())),
+        ("frozen", stats.frozen.to_json()),
+        ("delayMultiplier", stats.delay_multiplier.to_json()),
+    ])
+}
+
+fn scheduler_stats_to_json(stats: &TimerSchedulerStats) -> Json {
+    let pending = stats.pending.iter().map(|&(pipeline_id, count)| {
+        object(vec![("pipeline", pipeline_id.to_string().to_json()), ("count", count.to_json())])
+    });
+    object(vec![
+        ("pending", Json::Array(pending.collect())),
+        ("wakeupsPerSecond", stats.wakeups_per_second.to_json()),
+        ("normalLatency", latency_stats_to_json(&stats.normal_latency)),
+        ("userBlockingLatency", latency_stats_to_json(&stats.user_blocking_latency)),
+        ("dropped", stats.dropped.to_json()),
+        ("stale", stats.stale.to_json()),
+        ("queued", stats.queued.to_json()),
+        ("maxQueued", stats.max_queued.to_json()),
+    ])
+}
+
+fn latency_stats_to_json(stats: &TimerLatencyStats) -> Json {
+    object(vec![
+        ("events", stats.events.to_json()),
+        ("meanMs", stats.mean_ms.to_json()),
+        ("maxMs", stats.max_ms.to_json()),
+    ])
+}
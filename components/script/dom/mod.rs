@@ -209,6 +209,8 @@ pub mod types {
     include!(concat!(env!("OUT_DIR"), "/InterfaceTypes.rs"));
 }
 
+pub mod abortcontroller;
+pub mod abortsignal;
 pub mod activation;
 pub mod attr;
 pub mod beforeunloadevent;
@@ -241,6 +243,7 @@ pub mod customevent;
 pub mod dedicatedworkerglobalscope;
 pub mod document;
 pub mod documentfragment;
+pub mod documenttimeline;
 pub mod documenttype;
 pub mod domexception;
 pub mod domimplementation;
@@ -338,7 +341,10 @@ pub mod htmltrackelement;
 pub mod htmlulistelement;
 pub mod htmlunknownelement;
 pub mod htmlvideoelement;
+pub mod idledeadline;
+pub mod idledetector;
 pub mod imagedata;
+pub mod internals;
 pub mod keyboardevent;
 pub mod location;
 pub mod mediaerror;
@@ -354,6 +360,10 @@ pub mod nodeiterator;
 pub mod nodelist;
 pub mod pagetransitionevent;
 pub mod performance;
+pub mod performanceentry;
+pub mod performancelongtasktiming;
+pub mod performanceobserver;
+pub mod performanceobserverentrylist;
 pub mod performancetiming;
 pub mod plugin;
 pub mod pluginarray;
@@ -362,6 +372,10 @@ pub mod processinginstruction;
 pub mod progressevent;
 pub mod radionodelist;
 pub mod range;
+pub mod report;
+pub mod reportbody;
+pub mod reportingobserver;
+pub mod scheduling;
 pub mod screen;
 pub mod servohtmlparser;
 pub mod servoxmlparser;
@@ -369,8 +383,10 @@ pub mod storage;
 pub mod storageevent;
 pub mod stylesheet;
 pub mod stylesheetlist;
+pub mod taskattributiontiming;
 pub mod testbinding;
 pub mod testbindingproxy;
+pub mod testreportbody;
 pub mod text;
 pub mod textdecoder;
 pub mod textencoder;
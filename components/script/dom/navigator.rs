@@ -11,6 +11,7 @@ use dom::bluetooth::Bluetooth;
 use dom::mimetypearray::MimeTypeArray;
 use dom::navigatorinfo;
 use dom::pluginarray::PluginArray;
+use dom::scheduling::Scheduling;
 use dom::window::Window;
 use util::str::DOMString;
 
@@ -20,6 +21,7 @@ pub struct Navigator {
     bluetooth: MutNullableHeap<JS<Bluetooth>>,
     plugins: MutNullableHeap<JS<PluginArray>>,
     mime_types: MutNullableHeap<JS<MimeTypeArray>>,
+    scheduling: MutNullableHeap<JS<Scheduling>>,
 }
 
 impl Navigator {
@@ -29,6 +31,7 @@ impl Navigator {
             bluetooth: Default::default(),
             plugins: Default::default(),
             mime_types: Default::default(),
+            scheduling: Default::default(),
         }
     }
 
@@ -99,4 +102,9 @@ impl NavigatorMethods for Navigator {
     fn JavaEnabled(&self) -> bool {
         false
     }
+
+    // https://wicg.github.io/is-input-pending/#dom-navigator-scheduling
+    fn Scheduling(&self) -> Root<Scheduling> {
+        self.scheduling.or_init(|| Scheduling::new(self.global().r()))
+    }
 }
@@ -2,15 +2,25 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::PerformanceBinding;
 use dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
 use dom::bindings::global::GlobalRef;
 use dom::bindings::js::{JS, Root};
 use dom::bindings::num::Finite;
-use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflectable, Reflector, reflect_dom_object};
+use dom::performanceentry::PerformanceEntry;
+use dom::performanceobserver::PerformanceObserver;
 use dom::performancetiming::PerformanceTiming;
 use dom::window::Window;
+use script_runtime::CommonScriptMsg;
+use script_runtime::ScriptThreadEventCategory::ScriptEvent;
+use script_thread::Runnable;
+use std::cell::Cell;
 use time;
+use time_resolution::TimeResolutionPolicy;
+use util::str::DOMString;
 
 pub type DOMHighResTimeStamp = Finite<f64>;
 
@@ -18,6 +28,12 @@ pub type DOMHighResTimeStamp = Finite<f64>;
 pub struct Performance {
     reflector_: Reflector,
     timing: JS<PerformanceTiming>,
+    /// The observers registered through `PerformanceObserver.observe`.
+    observers: DOMRefCell<Vec<JS<PerformanceObserver>>>,
+    /// Whether a task to notify the observers has been queued.
+    pending_notification_observers_task: Cell<bool>,
+    /// How precisely the timestamps may tell time.
+    time_resolution: TimeResolutionPolicy,
 }
 
 impl Performance {
@@ -29,6 +45,9 @@ impl Performance {
             timing: JS::from_rooted(&PerformanceTiming::new(window,
                                                             navigation_start,
                                                             navigation_start_precise)),
+            observers: DOMRefCell::new(vec![]),
+            pending_notification_observers_task: Cell::new(false),
+            time_resolution: window.time_resolution(),
         }
     }
 
@@ -41,6 +60,71 @@ impl Performance {
                            GlobalRef::Window(window),
                            PerformanceBinding::Wrap)
     }
+
+    pub fn add_observer(&self, observer: &PerformanceObserver) {
+        let mut observers = self.observers.borrow_mut();
+        if !observers.iter().any(|registered| &**registered == observer) {
+            observers.push(JS::from_ref(observer));
+        }
+    }
+
+    pub fn remove_observer(&self, observer: &PerformanceObserver) {
+        self.observers.borrow_mut().retain(|registered| &**registered != observer);
+    }
+
+    /// Whether any registered observer is interested in `entry_type` entries.
+    pub fn has_observers_for(&self, entry_type: &str) -> bool {
+        let entry_type = DOMString::from(entry_type);
+        self.observers.borrow().iter().any(|observer| observer.observes(&entry_type))
+    }
+
+    /// Converts a time from `time::precise_time_ns` to milliseconds since the
+    /// start of the navigation, coarsened to the time resolution.
+    pub fn to_dom_high_res_time_stamp(&self, precise_time_ns: u64) -> f64 {
+        let time = self.time_resolution.coarsen_ns(precise_time_ns) as f64;
+        let navigation_start = self.time_resolution.coarsen_ns(self.timing.NavigationStartPrecise() as u64) as f64;
+        (time - navigation_start) / 1000000 as f64
+    }
+
+    // https://w3c.github.io/performance-timeline/#queue-a-performanceentry
+    pub fn queue_entry(&self, entry: &PerformanceEntry) {
+        let mut queued = false;
+        for observer in self.observers.borrow().iter() {
+            if observer.observes(entry.entry_type()) {
+                observer.queue_entry(entry);
+                queued = true;
+            }
+        }
+        if !queued || self.pending_notification_observers_task.get() {
+            return;
+        }
+        self.pending_notification_observers_task.set(true);
+        let runnable = box NotifyPerformanceObserversRunnable {
+            performance: Trusted::new(self),
+        };
+        let global = self.global();
+        let _ = global.r().script_chan().send(CommonScriptMsg::RunnableMsg(ScriptEvent, runnable));
+    }
+
+    // https://w3c.github.io/performance-timeline/#notify-performance-observers
+    fn notify_observers(&self) {
+        self.pending_notification_observers_task.set(false);
+        let observers: Vec<Root<PerformanceObserver>> =
+            self.observers.borrow().iter().map(|observer| Root::from_ref(&**observer)).collect();
+        for observer in observers {
+            observer.notify();
+        }
+    }
+}
+
+struct NotifyPerformanceObserversRunnable {
+    performance: Trusted<Performance>,
+}
+
+impl Runnable for NotifyPerformanceObserversRunnable {
+    fn handler(self: Box<NotifyPerformanceObserversRunnable>) {
+        self.performance.root().notify_observers();
+    }
 }
 
 impl PerformanceMethods for Performance {
@@ -51,8 +135,12 @@ impl PerformanceMethods for Performance {
 
     // https://dvcs.w3.org/hg/webperf/raw-file/tip/specs/HighResolutionTime/Overview.html#dom-performance-now
     fn Now(&self) -> DOMHighResTimeStamp {
-        let navStart = self.timing.NavigationStartPrecise();
-        let now = (time::precise_time_ns() as f64 - navStart) / 1000000 as f64;
-        Finite::wrap(now)
+        // The time skipped ahead by the clock of the timers has passed too. Only
+        // performance.now() sees it; Date reads the system clock in the JS engine.
+        let skipped_ns = match self.global().r() {
+            GlobalRef::Window(window) => window.skipped_time().get() * 1000000,
+            GlobalRef::Worker(worker) => worker.skipped_time().get() * 1000000,
+        };
+        Finite::wrap(self.to_dom_high_res_time_stamp(time::precise_time_ns() + skipped_ns))
     }
 }
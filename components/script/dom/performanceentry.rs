@@ -0,0 +1,76 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::PerformanceEntryBinding;
+use dom::bindings::codegen::Bindings::PerformanceEntryBinding::PerformanceEntryMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::performance::DOMHighResTimeStamp;
+use util::str::DOMString;
+
+#[dom_struct]
+pub struct PerformanceEntry {
+    reflector_: Reflector,
+    name: DOMString,
+    entry_type: DOMString,
+    start_time: f64,
+    duration: f64,
+}
+
+impl PerformanceEntry {
+    pub fn new_inherited(name: DOMString,
+                         entry_type: DOMString,
+                         start_time: f64,
+                         duration: f64) -> PerformanceEntry {
+        PerformanceEntry {
+            reflector_: Reflector::new(),
+            name: name,
+            entry_type: entry_type,
+            start_time: start_time,
+            duration: duration,
+        }
+    }
+
+    pub fn new(global: GlobalRef,
+               name: DOMString,
+               entry_type: DOMString,
+               start_time: f64,
+               duration: f64) -> Root<PerformanceEntry> {
+        reflect_dom_object(box PerformanceEntry::new_inherited(name, entry_type, start_time, duration),
+                           global,
+                           PerformanceEntryBinding::Wrap)
+    }
+
+    pub fn name(&self) -> &DOMString {
+        &self.name
+    }
+
+    pub fn entry_type(&self) -> &DOMString {
+        &self.entry_type
+    }
+}
+
+impl PerformanceEntryMethods for PerformanceEntry {
+    // https://w3c.github.io/performance-timeline/#dom-performanceentry-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceentry-entrytype
+    fn EntryType(&self) -> DOMString {
+        self.entry_type.clone()
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceentry-starttime
+    fn StartTime(&self) -> DOMHighResTimeStamp {
+        Finite::wrap(self.start_time)
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceentry-duration
+    fn Duration(&self) -> DOMHighResTimeStamp {
+        Finite::wrap(self.duration)
+    }
+}
@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::PerformanceLongTaskTimingBinding;
+use dom::bindings::codegen::Bindings::PerformanceLongTaskTimingBinding::PerformanceLongTaskTimingMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::reflect_dom_object;
+use dom::performanceentry::PerformanceEntry;
+use dom::taskattributiontiming::TaskAttributionTiming;
+use util::str::DOMString;
+
+#[dom_struct]
+pub struct PerformanceLongTaskTiming {
+    entry: PerformanceEntry,
+    attribution: JS<TaskAttributionTiming>,
+}
+
+impl PerformanceLongTaskTiming {
+    fn new_inherited(name: DOMString,
+                     start_time: f64,
+                     duration: f64,
+                     attribution: &TaskAttributionTiming) -> PerformanceLongTaskTiming {
+        PerformanceLongTaskTiming {
+            entry: PerformanceEntry::new_inherited(name, DOMString::from("longtask"), start_time, duration),
+            attribution: JS::from_ref(attribution),
+        }
+    }
+
+    pub fn new(global: GlobalRef,
+               name: DOMString,
+               start_time: f64,
+               duration: f64,
+               attribution: &TaskAttributionTiming) -> Root<PerformanceLongTaskTiming> {
+        reflect_dom_object(box PerformanceLongTaskTiming::new_inherited(name, start_time, duration, attribution),
+                           global,
+                           PerformanceLongTaskTimingBinding::Wrap)
+    }
+}
+
+impl PerformanceLongTaskTimingMethods for PerformanceLongTaskTiming {
+    // https://w3c.github.io/longtasks/#dom-performancelongtasktiming-attribution
+    fn Attribution(&self) -> Root<TaskAttributionTiming> {
+        Root::from_ref(&*self.attribution)
+    }
+}
@@ -0,0 +1,107 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::callback::ExceptionHandling;
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::PerformanceObserverBinding;
+use dom::bindings::codegen::Bindings::PerformanceObserverBinding::PerformanceObserverCallback;
+use dom::bindings::codegen::Bindings::PerformanceObserverBinding::{PerformanceObserverInit, PerformanceObserverMethods};
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::{Reflectable, Reflector, reflect_dom_object};
+use dom::performanceentry::PerformanceEntry;
+use dom::performanceobserverentrylist::PerformanceObserverEntryList;
+use std::rc::Rc;
+use util::str::DOMString;
+
+/// The entry types we are able to report.
+const SUPPORTED_ENTRY_TYPES: &'static [&'static str] = &["longtask"];
+
+#[dom_struct]
+pub struct PerformanceObserver {
+    reflector_: Reflector,
+    #[ignore_heap_size_of = "can't measure Rc values"]
+    callback: Rc<PerformanceObserverCallback>,
+    /// The entry types passed to the last call to `observe`.
+    entry_types: DOMRefCell<Vec<DOMString>>,
+    /// The entries that are waiting to be delivered to the callback.
+    entries: DOMRefCell<Vec<JS<PerformanceEntry>>>,
+}
+
+impl PerformanceObserver {
+    fn new_inherited(callback: Rc<PerformanceObserverCallback>) -> PerformanceObserver {
+        PerformanceObserver {
+            reflector_: Reflector::new(),
+            callback: callback,
+            entry_types: DOMRefCell::new(vec![]),
+            entries: DOMRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: GlobalRef, callback: Rc<PerformanceObserverCallback>) -> Root<PerformanceObserver> {
+        reflect_dom_object(box PerformanceObserver::new_inherited(callback),
+                           global,
+                           PerformanceObserverBinding::Wrap)
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserver-performanceobserver
+    pub fn Constructor(global: GlobalRef,
+                       callback: Rc<PerformanceObserverCallback>)
+                       -> Fallible<Root<PerformanceObserver>> {
+        Ok(PerformanceObserver::new(global, callback))
+    }
+
+    /// Whether entries of type `entry_type` should be queued for this observer.
+    pub fn observes(&self, entry_type: &DOMString) -> bool {
+        self.entry_types.borrow().iter().any(|observed| observed == entry_type)
+    }
+
+    pub fn queue_entry(&self, entry: &PerformanceEntry) {
+        self.entries.borrow_mut().push(JS::from_ref(entry));
+    }
+
+    /// Invokes the callback with the queued entries, if there are any.
+    pub fn notify(&self) {
+        let entries: Vec<Root<PerformanceEntry>> =
+            self.entries.borrow_mut().drain(..).map(|entry| Root::from_ref(&*entry)).collect();
+        if entries.is_empty() {
+            return;
+        }
+        let global = self.global();
+        let list = PerformanceObserverEntryList::new(global.r(), entries);
+        let _ = self.callback.Call__(&list, self, ExceptionHandling::Report);
+    }
+}
+
+impl PerformanceObserverMethods for PerformanceObserver {
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserver-observe
+    fn Observe(&self, options: &PerformanceObserverInit) -> Fallible<()> {
+        let entry_types: Vec<DOMString> = options.entryTypes.iter()
+            .filter(|entry_type| {
+                let entry_type: &str = entry_type;
+                SUPPORTED_ENTRY_TYPES.contains(&entry_type)
+            })
+            .cloned()
+            .collect();
+        if entry_types.is_empty() {
+            return Err(Error::Type("None of the entry types can be observed".to_owned()));
+        }
+        *self.entry_types.borrow_mut() = entry_types;
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.Performance().add_observer(self);
+        }
+        Ok(())
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserver-disconnect
+    fn Disconnect(&self) {
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.Performance().remove_observer(self);
+        }
+        self.entry_types.borrow_mut().clear();
+        self.entries.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::PerformanceObserverEntryListBinding;
+use dom::bindings::codegen::Bindings::PerformanceObserverEntryListBinding::PerformanceObserverEntryListMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::performanceentry::PerformanceEntry;
+use util::str::DOMString;
+
+#[dom_struct]
+pub struct PerformanceObserverEntryList {
+    reflector_: Reflector,
+    entries: Vec<JS<PerformanceEntry>>,
+}
+
+impl PerformanceObserverEntryList {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(entries: Vec<JS<PerformanceEntry>>) -> PerformanceObserverEntryList {
+        PerformanceObserverEntryList {
+            reflector_: Reflector::new(),
+            entries: entries,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(global: GlobalRef, entries: Vec<Root<PerformanceEntry>>) -> Root<PerformanceObserverEntryList> {
+        let entries = entries.iter().map(|entry| JS::from_rooted(entry)).collect();
+        reflect_dom_object(box PerformanceObserverEntryList::new_inherited(entries),
+                           global,
+                           PerformanceObserverEntryListBinding::Wrap)
+    }
+
+    fn filter<F: Fn(&PerformanceEntry) -> bool>(&self, predicate: F) -> Vec<Root<PerformanceEntry>> {
+        self.entries.iter().filter(|entry| predicate(entry)).map(|entry| Root::from_ref(&**entry)).collect()
+    }
+}
+
+impl PerformanceObserverEntryListMethods for PerformanceObserverEntryList {
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserverentrylist-getentries
+    fn GetEntries(&self) -> Vec<Root<PerformanceEntry>> {
+        self.filter(|_| true)
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserverentrylist-getentriesbytype
+    fn GetEntriesByType(&self, type_: DOMString) -> Vec<Root<PerformanceEntry>> {
+        self.filter(|entry| *entry.entry_type() == type_)
+    }
+
+    // https://w3c.github.io/performance-timeline/#dom-performanceobserverentrylist-getentriesbyname
+    fn GetEntriesByName(&self, name: DOMString, type_: Option<DOMString>) -> Vec<Root<PerformanceEntry>> {
+        self.filter(|entry| {
+            *entry.name() == name && type_.as_ref().map_or(true, |type_| entry.entry_type() == type_)
+        })
+    }
+}
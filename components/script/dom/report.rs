@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::ReportBinding;
+use dom::bindings::codegen::Bindings::ReportBinding::ReportMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::reportbody::ReportBody;
+use util::str::DOMString;
+
+// https://w3c.github.io/reporting/#report
+#[dom_struct]
+pub struct Report {
+    reflector_: Reflector,
+    type_: DOMString,
+    url: DOMString,
+    body: Option<JS<ReportBody>>,
+}
+
+impl Report {
+    fn new_inherited(type_: DOMString, url: DOMString, body: Option<&ReportBody>) -> Report {
+        Report {
+            reflector_: Reflector::new(),
+            type_: type_,
+            url: url,
+            body: body.map(JS::from_ref),
+        }
+    }
+
+    pub fn new(global: GlobalRef, type_: DOMString, url: DOMString, body: Option<&ReportBody>) -> Root<Report> {
+        reflect_dom_object(box Report::new_inherited(type_, url, body), global, ReportBinding::Wrap)
+    }
+
+    pub fn type_(&self) -> &DOMString {
+        &self.type_
+    }
+}
+
+impl ReportMethods for Report {
+    // https://w3c.github.io/reporting/#dom-report-type
+    fn Type(&self) -> DOMString {
+        self.type_.clone()
+    }
+
+    // https://w3c.github.io/reporting/#dom-report-url
+    fn Url(&self) -> DOMString {
+        self.url.clone()
+    }
+
+    // https://w3c.github.io/reporting/#dom-report-body
+    fn GetBody(&self) -> Option<Root<ReportBody>> {
+        self.body.as_ref().map(|body| Root::from_ref(&**body))
+    }
+}
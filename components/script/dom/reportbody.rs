@@ -0,0 +1,26 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::ReportBodyBinding;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+
+// https://w3c.github.io/reporting/#reportbody
+#[dom_struct]
+pub struct ReportBody {
+    reflector_: Reflector,
+}
+
+impl ReportBody {
+    pub fn new_inherited() -> ReportBody {
+        ReportBody {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<ReportBody> {
+        reflect_dom_object(box ReportBody::new_inherited(), global, ReportBodyBinding::Wrap)
+    }
+}
@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::callback::ExceptionHandling;
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::ReportingObserverBinding;
+use dom::bindings::codegen::Bindings::ReportingObserverBinding::{ReportingObserverCallback, ReportingObserverMethods};
+use dom::bindings::codegen::Bindings::ReportingObserverBinding::ReportingObserverOptions;
+use dom::bindings::error::Fallible;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::reflector::{Reflectable, Reflector, reflect_dom_object};
+use dom::report::Report;
+use std::cell::Cell;
+use std::rc::Rc;
+use util::str::DOMString;
+
+// https://w3c.github.io/reporting/#interface-reporting-observer
+#[dom_struct]
+pub struct ReportingObserver {
+    reflector_: Reflector,
+    #[ignore_heap_size_of = "can't measure Rc values"]
+    callback: Rc<ReportingObserverCallback>,
+    /// The types of reports to observe; every type if there are none.
+    types: Option<Vec<DOMString>>,
+    /// Whether the reports generated before `observe` was called are observed too.
+    buffered: Cell<bool>,
+    /// https://w3c.github.io/reporting/#reportingobserver-report-queue
+    report_queue: DOMRefCell<Vec<JS<Report>>>,
+}
+
+impl ReportingObserver {
+    fn new_inherited(callback: Rc<ReportingObserverCallback>,
+                     options: &ReportingObserverOptions) -> ReportingObserver {
+        ReportingObserver {
+            reflector_: Reflector::new(),
+            callback: callback,
+            types: options.types.clone(),
+            buffered: Cell::new(options.buffered),
+            report_queue: DOMRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: GlobalRef,
+               callback: Rc<ReportingObserverCallback>,
+               options: &ReportingObserverOptions) -> Root<ReportingObserver> {
+        reflect_dom_object(box ReportingObserver::new_inherited(callback, options),
+                           global,
+                           ReportingObserverBinding::Wrap)
+    }
+
+    // https://w3c.github.io/reporting/#dom-reportingobserver-reportingobserver
+    pub fn Constructor(global: GlobalRef,
+                       callback: Rc<ReportingObserverCallback>,
+                       options: &ReportingObserverOptions)
+                       -> Fallible<Root<ReportingObserver>> {
+        Ok(ReportingObserver::new(global, callback, options))
+    }
+
+    /// Whether reports of type `type_` are observed.
+    pub fn observes(&self, type_: &DOMString) -> bool {
+        self.types.as_ref().map_or(true, |types| types.is_empty() || types.contains(type_))
+    }
+
+    pub fn queue_report(&self, report: &Report) {
+        self.report_queue.borrow_mut().push(JS::from_ref(report));
+    }
+
+    fn take_reports(&self) -> Vec<Root<Report>> {
+        self.report_queue.borrow_mut().drain(..).map(|report| Root::from_ref(&*report)).collect()
+    }
+
+    // https://w3c.github.io/reporting/#invoke-observers
+    pub fn notify(&self) {
+        let reports = self.take_reports();
+        if reports.is_empty() {
+            return;
+        }
+        let _ = self.callback.Call__(reports, self, ExceptionHandling::Report);
+    }
+}
+
+impl ReportingObserverMethods for ReportingObserver {
+    // https://w3c.github.io/reporting/#dom-reportingobserver-observe
+    fn Observe(&self) {
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.reporting().add_observer(window, self, self.buffered.get());
+        }
+        // Only the reports generated before the first call are buffered ones.
+        self.buffered.set(false);
+    }
+
+    // https://w3c.github.io/reporting/#dom-reportingobserver-disconnect
+    fn Disconnect(&self) {
+        if let GlobalRef::Window(window) = self.global().r() {
+            window.reporting().remove_observer(self);
+        }
+    }
+
+    // https://w3c.github.io/reporting/#dom-reportingobserver-takerecords
+    fn TakeRecords(&self) -> Vec<Root<Report>> {
+        self.take_reports()
+    }
+}
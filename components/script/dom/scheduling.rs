@@ -0,0 +1,36 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::SchedulingBinding;
+use dom::bindings::codegen::Bindings::SchedulingBinding::{IsInputPendingOptions, SchedulingMethods};
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use script_thread::ScriptThread;
+
+#[dom_struct]
+pub struct Scheduling {
+    reflector_: Reflector,
+}
+
+impl Scheduling {
+    fn new_inherited() -> Scheduling {
+        Scheduling {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: GlobalRef) -> Root<Scheduling> {
+        reflect_dom_object(box Scheduling::new_inherited(),
+                           global,
+                           SchedulingBinding::Wrap)
+    }
+}
+
+impl SchedulingMethods for Scheduling {
+    // https://wicg.github.io/is-input-pending/#dom-scheduling-isinputpending
+    fn IsInputPending(&self, options: &IsInputPendingOptions) -> bool {
+        ScriptThread::is_input_pending(options.includeContinuous)
+    }
+}
@@ -5,7 +5,6 @@
 //! The bulk of the HTML parser integration is in `script::parse::html`.
 //! This module is mostly about its interaction with DOM memory management.
 
-use document_loader::LoadType;
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::ServoHTMLParserBinding;
 use dom::bindings::global::GlobalRef;
@@ -170,7 +169,11 @@ impl AsyncResponseListener for ParserContext {
             Some(parser) => parser.root(),
             None => return,
         };
-        parser.r().document().finish_load(LoadType::PageSource(self.url.clone()));
+        let document = parser.r().document();
+        let load = document.loader().page_source_load();
+        if let Some(load) = load {
+            document.finish_load(load);
+        }
 
         if let Err(err) = status {
             debug!("Failed to load page URL {}, error: {:?}", self.url, err);
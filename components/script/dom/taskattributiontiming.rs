@@ -0,0 +1,75 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::TaskAttributionTimingBinding;
+use dom::bindings::codegen::Bindings::TaskAttributionTimingBinding::TaskAttributionTimingMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::performanceentry::PerformanceEntry;
+use util::str::DOMString;
+
+/// The container a long task is attributed to: the frame element of the
+/// window whose script ran, or the window itself if it isn't nested.
+#[dom_struct]
+pub struct TaskAttributionTiming {
+    entry: PerformanceEntry,
+    container_type: DOMString,
+    container_src: DOMString,
+    container_id: DOMString,
+    container_name: DOMString,
+}
+
+impl TaskAttributionTiming {
+    fn new_inherited(container_type: DOMString,
+                     container_src: DOMString,
+                     container_id: DOMString,
+                     container_name: DOMString) -> TaskAttributionTiming {
+        TaskAttributionTiming {
+            entry: PerformanceEntry::new_inherited(DOMString::from("unknown"),
+                                                   DOMString::from("taskattribution"),
+                                                   0.,
+                                                   0.),
+            container_type: container_type,
+            container_src: container_src,
+            container_id: container_id,
+            container_name: container_name,
+        }
+    }
+
+    pub fn new(global: GlobalRef,
+               container_type: DOMString,
+               container_src: DOMString,
+               container_id: DOMString,
+               container_name: DOMString) -> Root<TaskAttributionTiming> {
+        reflect_dom_object(box TaskAttributionTiming::new_inherited(container_type,
+                                                                    container_src,
+                                                                    container_id,
+                                                                    container_name),
+                           global,
+                           TaskAttributionTimingBinding::Wrap)
+    }
+}
+
+impl TaskAttributionTimingMethods for TaskAttributionTiming {
+    // https://w3c.github.io/longtasks/#dom-taskattributiontiming-containertype
+    fn ContainerType(&self) -> DOMString {
+        self.container_type.clone()
+    }
+
+    // https://w3c.github.io/longtasks/#dom-taskattributiontiming-containersrc
+    fn ContainerSrc(&self) -> DOMString {
+        self.container_src.clone()
+    }
+
+    // https://w3c.github.io/longtasks/#dom-taskattributiontiming-containerid
+    fn ContainerId(&self) -> DOMString {
+        self.container_id.clone()
+    }
+
+    // https://w3c.github.io/longtasks/#dom-taskattributiontiming-containername
+    fn ContainerName(&self) -> DOMString {
+        self.container_name.clone()
+    }
+}
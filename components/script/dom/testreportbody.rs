@@ -0,0 +1,38 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::TestReportBodyBinding;
+use dom::bindings::codegen::Bindings::TestReportBodyBinding::TestReportBodyMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::Root;
+use dom::bindings::reflector::reflect_dom_object;
+use dom::reportbody::ReportBody;
+use util::str::DOMString;
+
+// https://w3c.github.io/reporting/#testreportbody
+#[dom_struct]
+pub struct TestReportBody {
+    body: ReportBody,
+    message: DOMString,
+}
+
+impl TestReportBody {
+    fn new_inherited(message: DOMString) -> TestReportBody {
+        TestReportBody {
+            body: ReportBody::new_inherited(),
+            message: message,
+        }
+    }
+
+    pub fn new(global: GlobalRef, message: DOMString) -> Root<TestReportBody> {
+        reflect_dom_object(box TestReportBody::new_inherited(message), global, TestReportBodyBinding::Wrap)
+    }
+}
+
+impl TestReportBodyMethods for TestReportBody {
+    // https://w3c.github.io/reporting/#dom-testreportbody-message
+    fn Message(&self) -> DOMString {
+        self.message.clone()
+    }
+}
@@ -22,6 +22,7 @@ use dom::event::{Event, EventBubbles, EventCancelable};
 use dom::eventtarget::EventTarget;
 use dom::messageevent::MessageEvent;
 use dom::urlhelper::UrlHelper;
+use euclid::length::Length;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use js::jsapi::{JSAutoCompartment, RootedValue};
 use js::jsapi::{JS_GetArrayBufferData, JS_NewArrayBuffer};
@@ -36,12 +37,15 @@ use net_traits::{WebSocketCommunicate, WebSocketConnectData, WebSocketDomAction,
 use script_runtime::ScriptThreadEventCategory::WebSocketEvent;
 use script_runtime::{CommonScriptMsg, ScriptChan};
 use script_thread::Runnable;
+use script_traits::precise_time_ns;
 use std::ascii::AsciiExt;
 use std::borrow::ToOwned;
 use std::cell::Cell;
 use std::ptr;
 use std::sync::Arc;
 use std::thread;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
+use util::prefs;
 use util::str::DOMString;
 use websocket::client::request::Url;
 use websocket::header::{Headers, WebSocketProtocol};
@@ -142,6 +146,17 @@ mod close_code {
     pub const TLS_FAILED: u16 = 1015;
 }
 
+/// How long to wait for the opening handshake to complete, in milliseconds.
+const DEFAULT_CONNECT_TIMEOUT: u64 = 30000;
+/// How long to wait for the server to complete the closing handshake, in milliseconds.
+const DEFAULT_CLOSE_TIMEOUT: u64 = 5000;
+/// How long to wait for the server to answer a keepalive ping, in milliseconds.
+const DEFAULT_PONG_TIMEOUT: u64 = 10000;
+
+fn timeout_pref(name: &str, default: u64) -> u64 {
+    prefs::get_pref(name).as_i64().map(|ms| ms as u64).unwrap_or(default)
+}
+
 pub fn close_the_websocket_connection(address: Trusted<WebSocket>,
                                       sender: Box<ScriptChan>,
                                       code: Option<u16>,
@@ -176,6 +191,9 @@ pub struct WebSocket {
     sender: DOMRefCell<Option<IpcSender<WebSocketDomAction>>>,
     binary_type: Cell<BinaryType>,
     protocol: DOMRefCell<String>, //Subprotocol selected by server
+    timeout_cancel: DOMRefCell<Option<OneshotTimerHandle>>,
+    /// When the server last sent anything, in ns.
+    last_activity: Cell<u64>,
 }
 
 impl WebSocket {
@@ -189,6 +207,8 @@ impl WebSocket {
             sender: DOMRefCell::new(None),
             binary_type: Cell::new(BinaryType::Blob),
             protocol: DOMRefCell::new("".to_owned()),
+            timeout_cancel: DOMRefCell::new(None),
+            last_activity: Cell::new(0),
         }
     }
 
@@ -269,6 +289,10 @@ impl WebSocket {
 
         *ws.sender.borrow_mut() = Some(dom_action_sender);
 
+        // Don't wait forever for the opening handshake.
+        ws.set_timeout(WebSocketTimeout::OpeningHandshake,
+                       timeout_pref("dom.websocket.connect_timeout_ms", DEFAULT_CONNECT_TIMEOUT));
+
         let moved_address = address.clone();
         let sender = global.networking_task_source();
         thread::spawn(move || {
@@ -289,6 +313,12 @@ impl WebSocket {
                         };
                         sender.send(CommonScriptMsg::RunnableMsg(WebSocketEvent, message_thread)).unwrap();
                     },
+                    WebSocketNetworkEvent::Pong => {
+                        let pong_task = box PongReceivedTask {
+                            address: moved_address.clone(),
+                        };
+                        sender.send(CommonScriptMsg::RunnableMsg(WebSocketEvent, pong_task)).unwrap();
+                    },
                     WebSocketNetworkEvent::Fail => {
                         fail_the_websocket_connection(moved_address.clone(), sender.clone());
                     },
@@ -336,6 +366,44 @@ impl WebSocket {
 
         Ok(true)
     }
+
+    fn set_timeout(&self, kind: WebSocketTimeout, duration_ms: u64) {
+        // Only one handshake or keepalive check can be pending at a time, so
+        // this replaces any earlier timeout.
+        self.cancel_timeout();
+        let callback = OneshotTimerCallback::WebSocketTimeout(WebSocketTimeoutCallback {
+            ws: Trusted::new(self),
+            kind: kind,
+        });
+        let global = self.global();
+        let duration = Length::new(duration_ms);
+        *self.timeout_cancel.borrow_mut() = Some(global.r().schedule_callback(callback, duration));
+    }
+
+    fn cancel_timeout(&self) {
+        if let Some(handle) = self.timeout_cancel.borrow_mut().take() {
+            let global = self.global();
+            global.r().unschedule_callback(handle);
+        }
+    }
+
+    /// Pings the server once the connection has been idle for the interval
+    /// of the `dom.websocket.keepalive_interval_ms` pref, if it sets one.
+    /// Anything the server sends in the meantime only moves the ping later
+    /// when the timer fires, so busy connections don't reschedule it all the time.
+    fn schedule_keepalive(&self) {
+        self.last_activity.set(precise_time_ns());
+        if let Some(interval) = keepalive_interval() {
+            self.set_timeout(WebSocketTimeout::Keepalive, interval);
+        }
+    }
+}
+
+fn keepalive_interval() -> Option<u64> {
+    match prefs::get_pref("dom.websocket.keepalive_interval_ms").as_i64() {
+        Some(ms) if ms > 0 => Some(ms as u64),
+        _ => None,
+    }
 }
 
 impl WebSocketMethods for WebSocket {
@@ -450,6 +518,11 @@ impl WebSocketMethods for WebSocket {
                 let mut other_sender = self.sender.borrow_mut();
                 let my_sender = other_sender.as_mut().unwrap();
                 let _ = my_sender.send(WebSocketDomAction::Close(code, reason));
+
+                // https://html.spec.whatwg.org/multipage/#closeWebSocket
+                // Wait a user-agent-determined time for the closing handshake.
+                self.set_timeout(WebSocketTimeout::ClosingHandshake,
+                                 timeout_pref("dom.websocket.close_timeout_ms", DEFAULT_CLOSE_TIMEOUT));
             }
         }
         Ok(()) //Return Ok
@@ -469,6 +542,8 @@ impl Runnable for ConnectionEstablishedTask {
         let ws = self.address.root();
         let global = ws.r().global();
 
+        ws.cancel_timeout();
+
         // Step 1: Protocols.
         if !self.protocols.is_empty() && self.headers.get::<WebSocketProtocol>().is_none() {
             let sender = global.r().networking_task_source();
@@ -501,6 +576,25 @@ impl Runnable for ConnectionEstablishedTask {
 
         // Step 6.
         ws.upcast().fire_simple_event("open");
+
+        if ws.ready_state.get() == WebSocketRequestState::Open {
+            ws.schedule_keepalive();
+        }
+    }
+}
+
+/// Task queued when the server answers a keepalive ping.
+struct PongReceivedTask {
+    address: Trusted<WebSocket>,
+}
+
+impl Runnable for PongReceivedTask {
+    fn handler(self: Box<Self>) {
+        let ws = self.address.root();
+        // A pong while closing must not replace the closing handshake timeout.
+        if ws.ready_state.get() == WebSocketRequestState::Open {
+            ws.schedule_keepalive();
+        }
     }
 }
 
@@ -540,6 +634,8 @@ impl Runnable for CloseTask {
             return;
         }
 
+        ws.cancel_timeout();
+
         // Perform _the WebSocket connection is closed_ steps.
         // https://html.spec.whatwg.org/multipage/#closeWebSocket
 
@@ -582,6 +678,7 @@ impl Runnable for MessageReceivedTask {
         if ws.ready_state.get() != WebSocketRequestState::Open {
             return;
         }
+        ws.last_activity.set(precise_time_ns());
 
         // Step 2-5.
         let global = ws.r().global();
@@ -616,3 +713,60 @@ impl Runnable for MessageReceivedTask {
         }
     }
 }
+
+#[derive(JSTraceable, PartialEq, Copy, Clone, Debug, HeapSizeOf)]
+enum WebSocketTimeout {
+    OpeningHandshake,
+    ClosingHandshake,
+    /// Time to ping the server, unless it sent something recently.
+    Keepalive,
+    /// The server didn't answer the ping in time.
+    Pong,
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct WebSocketTimeoutCallback {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    ws: Trusted<WebSocket>,
+    kind: WebSocketTimeout,
+}
+
+impl WebSocketTimeoutCallback {
+    pub fn invoke(self) {
+        let ws = self.ws.root();
+        *ws.timeout_cancel.borrow_mut() = None;
+
+        let expected_state = match self.kind {
+            WebSocketTimeout::OpeningHandshake => WebSocketRequestState::Connecting,
+            WebSocketTimeout::ClosingHandshake => WebSocketRequestState::Closing,
+            WebSocketTimeout::Keepalive | WebSocketTimeout::Pong => WebSocketRequestState::Open,
+        };
+        if ws.ready_state.get() != expected_state {
+            return;
+        }
+
+        if self.kind == WebSocketTimeout::Keepalive {
+            let interval = match keepalive_interval() {
+                Some(interval) => interval,
+                None => return,
+            };
+            let idle_ms = (precise_time_ns() - ws.last_activity.get()) / 1000000;
+            if idle_ms < interval {
+                ws.set_timeout(WebSocketTimeout::Keepalive, interval - idle_ms);
+                return;
+            }
+            if let Some(ref sender) = *ws.sender.borrow() {
+                let _ = sender.send(WebSocketDomAction::Ping);
+            }
+            ws.set_timeout(WebSocketTimeout::Pong,
+                           timeout_pref("dom.websocket.pong_timeout_ms", DEFAULT_PONG_TIMEOUT));
+            return;
+        }
+
+        debug!("WebSocket {:?} timed out for {}", self.kind, ws.url);
+        ws.ready_state.set(WebSocketRequestState::Closing);
+        let global = ws.global();
+        let sender = global.r().networking_task_source();
+        fail_the_websocket_connection(self.ws, sender);
+    }
+}
@@ -3,14 +3,15 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
-use devtools_traits::{ScriptToDevtoolsControlMsg, TimelineMarker, TimelineMarkerType, WorkerId};
+use devtools_traits::{ScriptToDevtoolsControlMsg, TimelineMarker, TimelineMarkerType, TimerInfo, WorkerId};
 use dom::bindings::callback::ExceptionHandling;
 use dom::bindings::cell::DOMRefCell;
 use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
 use dom::bindings::codegen::Bindings::EventHandlerBinding::OnBeforeUnloadEventHandlerNonNull;
 use dom::bindings::codegen::Bindings::EventHandlerBinding::OnErrorEventHandlerNonNull;
-use dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use dom::bindings::codegen::Bindings::FunctionBinding::{Function, VoidFunction};
+use dom::bindings::codegen::Bindings::WindowBinding::{IdleRequestCallback, IdleRequestOptions};
 use dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, ScrollToOptions};
 use dom::bindings::codegen::Bindings::WindowBinding::{self, FrameRequestCallback, WindowMethods};
 use dom::bindings::error::{Error, Fallible, report_pending_exception};
@@ -18,6 +19,7 @@ use dom::bindings::global::GlobalRef;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::js::{JS, MutNullableHeap, Root};
 use dom::bindings::num::Finite;
+use dom::bindings::refcounted::Trusted;
 use dom::bindings::reflector::Reflectable;
 use dom::bindings::utils::{GlobalStaticData, WindowProxyHandler};
 use dom::browsingcontext::BrowsingContext;
@@ -26,13 +28,23 @@ use dom::crypto::Crypto;
 use dom::cssstyledeclaration::{CSSModificationAccess, CSSStyleDeclaration};
 use dom::document::Document;
 use dom::element::Element;
+use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::eventsource::EventSource;
 use dom::eventtarget::EventTarget;
+use dom::htmllinkelement::SpeculativeLoad;
+use dom::idledeadline::IdleDeadline;
+use dom::idledetector::IdleDetector;
+use dom::internals::Internals;
 use dom::location::Location;
 use dom::navigator::Navigator;
 use dom::node::{Node, TrustedNodeAddress, from_untrusted_node_address, window_from_node};
 use dom::performance::Performance;
+use dom::performancelongtasktiming::PerformanceLongTaskTiming;
 use dom::screen::Screen;
 use dom::storage::Storage;
+use dom::taskattributiontiming::TaskAttributionTiming;
+use dom::uievent::UIEvent;
+use euclid::length::Length;
 use euclid::{Point2D, Rect, Size2D};
 use gfx_traits::LayerId;
 use ipc_channel::ipc::{self, IpcSender};
@@ -44,6 +56,8 @@ use js::rust::Runtime;
 use layout_interface::{ContentBoxResponse, ContentBoxesResponse, ResolvedStyleResponse, ScriptReflow};
 use layout_interface::{LayoutChan, LayoutRPC, Msg, Reflow, ReflowQueryType, MarginStyleResponse};
 use libc;
+use long_tasks::is_long_task;
+use microtask::{self, Microtask, UserMicrotask};
 use msg::constellation_msg::{LoadData, PanicMsg, PipelineId, SubpageId};
 use msg::constellation_msg::{WindowSizeData, WindowSizeType};
 use msg::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
@@ -56,13 +70,14 @@ use profile_traits::mem;
 use profile_traits::time::{ProfilerCategory, TimerMetadata, TimerMetadataFrameType};
 use profile_traits::time::{ProfilerChan, TimerMetadataReflowType, profile};
 use reporter::CSSErrorReporter;
+use reporting::ReportingContext;
 use rustc_serialize::base64::{FromBase64, STANDARD, ToBase64};
 use script_runtime::{ScriptChan, ScriptPort};
 use script_thread::SendableMainThreadScriptChan;
-use script_thread::{MainThreadScriptChan, MainThreadScriptMsg, RunnableWrapper};
+use script_thread::{MainThreadScriptChan, MainThreadScriptMsg, RunnableWrapper, ScriptThread};
 use script_traits::{ConstellationControlMsg, UntrustedNodeAddress};
-use script_traits::{DocumentState, MsDuration, ScriptToCompositorMsg, TimerEvent, TimerEventId};
-use script_traits::{ScriptMsg as ConstellationMsg, TimerEventRequest, TimerSource};
+use script_traits::{DocumentState, MsDuration, PipelineTimerStats, ScriptToCompositorMsg, TimerEvent, TimerEventId};
+use script_traits::{ScriptMsg as ConstellationMsg, TimerSchedulerMsg, TimerSource};
 use std::ascii::AsciiExt;
 use std::borrow::ToOwned;
 use std::cell::Cell;
@@ -70,6 +85,7 @@ use std::collections::HashSet;
 use std::default::Default;
 use std::ffi::CString;
 use std::io::{Write, stderr, stdout};
+use std::mem;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::TryRecvError::{Disconnected, Empty};
@@ -87,13 +103,17 @@ use task_source::history_traversal::HistoryTraversalTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::user_interaction::UserInteractionTaskSource;
 use time;
-use timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle, OneshotTimers, TimerCallback};
+use time_resolution::TimeResolutionPolicy;
+use timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle, OneshotTimers, PreciseTimerClock};
+use timers::{ScopedSuspension, ScriptThreadInputMonitor, SuspensionReason, TimerCallback};
+use timers::{read_timer_event_log, timer_event_log_path, write_timer_event_log};
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 use tinyfiledialogs::{self, MessageBoxIcon};
 use url::Url;
 use util::geometry::{self, MAX_RECT};
 use util::str::{DOMString, HTML_SPACE_CHARACTERS};
 use util::{breakpoint, opts};
+use viewport_events::{PendingViewportEvents, ViewportEvent, ViewportEventsDue};
 use webdriver_handlers::jsval_to_webdriver;
 
 /// Current state of the window object
@@ -128,6 +148,64 @@ pub enum ReflowReason {
 
 pub type ScrollPoint = Point2D<Au>;
 
+/// The longest an idle period lasts, so that the page stays responsive to
+/// what comes in while an idle callback runs.
+const MAX_IDLE_PERIOD_MS: u64 = 50;
+
+/// How long after a reflow for display the cross-origin iframes are checked
+/// for whether they can be seen, so that reflows in quick succession share one
+/// check. Throttling only aligns timers to whole seconds, so this is soon enough.
+const IFRAME_VISIBILITY_CHECK_DELAY_MS: u64 = 100;
+
+/// A callback waiting for an idle period, with the timer that runs it anyway
+/// once its timeout has elapsed.
+#[derive(JSTraceable, HeapSizeOf)]
+struct IdleRequest {
+    handle: u32,
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    callback: Rc<IdleRequestCallback>,
+    timeout: Option<OneshotTimerHandle>,
+}
+
+/// Runs an idle callback whose timeout elapsed before an idle period came.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct IdleCallbackTimeout {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    window: Trusted<Window>,
+    handle: u32,
+}
+
+impl IdleCallbackTimeout {
+    pub fn invoke(self) {
+        let window = self.window.root();
+        if window.is_alive() {
+            window.run_timed_out_idle_callback(self.handle);
+        }
+    }
+}
+
+/// Checks whether the cross-origin iframes of the document can be seen, once
+/// the reflows that came before have settled.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct IframeVisibilityCheck {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    window: Trusted<Window>,
+}
+
+impl IframeVisibilityCheck {
+    pub fn invoke(self) {
+        let window = self.window.root();
+        window.iframe_visibility_check_pending.set(false);
+        if window.is_alive() {
+            window.Document().update_iframe_visibility();
+        }
+    }
+}
+
+fn precise_time_ms() -> f64 {
+    time::precise_time_ns() as f64 / 1000000 as f64
+}
+
 #[dom_struct]
 pub struct Window {
     eventtarget: EventTarget,
@@ -145,6 +223,7 @@ pub struct Window {
     file_reading_task_source: FileReadingTaskSource,
     console: MutNullableHeap<JS<Console>>,
     crypto: MutNullableHeap<JS<Crypto>>,
+    internals: MutNullableHeap<JS<Internals>>,
     navigator: MutNullableHeap<JS<Navigator>>,
     #[ignore_heap_size_of = "channels are hard"]
     image_cache_thread: ImageCacheThread,
@@ -162,9 +241,13 @@ pub struct Window {
     session_storage: MutNullableHeap<JS<Storage>>,
     local_storage: MutNullableHeap<JS<Storage>>,
     #[ignore_heap_size_of = "channels are hard"]
-    scheduler_chan: IpcSender<TimerEventRequest>,
+    scheduler_chan: IpcSender<TimerSchedulerMsg>,
     timers: OneshotTimers,
 
+    /// The callbacks waiting for an idle period, in the order they were requested.
+    idle_request_callbacks: DOMRefCell<Vec<IdleRequest>>,
+    next_idle_request_handle: Cell<u32>,
+
     next_worker_id: Cell<WorkerId>,
 
     /// For sending messages to the memory profiler.
@@ -254,6 +337,12 @@ pub struct Window {
 
     current_viewport: Cell<Rect<Au>>,
 
+    /// The viewport events waiting to be merged with the ones that come after them.
+    viewport_events: PendingViewportEvents,
+
+    /// Whether the cross-origin iframes are about to be checked for whether they can be seen.
+    iframe_visibility_check_pending: Cell<bool>,
+
     /// A flag to prevent async events from attempting to interact with this window.
     #[ignore_heap_size_of = "defined in std"]
     ignore_further_async_events: Arc<AtomicBool>,
@@ -262,6 +351,21 @@ pub struct Window {
 
     #[ignore_heap_size_of = "Defined in ipc-channel"]
     panic_chan: IpcSender<PanicMsg>,
+
+    /// How precisely `performance.now()` and the timers may observe time.
+    time_resolution: TimeResolutionPolicy,
+
+    /// The reports generated for this window, and where they go.
+    reporting: ReportingContext,
+
+    /// The event sources that haven't been closed yet.
+    event_sources: DOMRefCell<Vec<JS<EventSource>>>,
+
+    /// The idle detectors that were started and haven't been stopped yet.
+    idle_detectors: DOMRefCell<Vec<JS<IdleDetector>>>,
+
+    /// The prefetches and preconnects waiting for an idle period.
+    speculative_loads: DOMRefCell<Vec<SpeculativeLoad>>,
 }
 
 impl Window {
@@ -492,6 +596,10 @@ impl WindowMethods for Window {
         self.crypto.or_init(|| Crypto::new(GlobalRef::Window(self)))
     }
 
+    fn Internals(&self) -> Root<Internals> {
+        self.internals.or_init(|| Internals::new(GlobalRef::Window(self)))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<Root<Element>> {
         self.browsing_context().frame_element().map(Root::from_ref)
@@ -552,6 +660,23 @@ impl WindowMethods for Window {
         self.ClearTimeout(handle);
     }
 
+    // https://w3c.github.io/setImmediate/#si-setImmediate
+    fn SetImmediate(&self, _cx: *mut JSContext, callback: Rc<Function>, args: Vec<HandleValue>) -> i32 {
+        self.timers.set_immediate(GlobalRef::Window(self), callback, args)
+    }
+
+    // https://w3c.github.io/setImmediate/#si-clearImmediate
+    fn ClearImmediate(&self, handle: i32) {
+        self.timers.clear_immediate(handle);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-queuemicrotask
+    fn QueueMicrotask(&self, callback: Rc<VoidFunction>) {
+        GlobalRef::Window(self).enqueue_microtask(Microtask::User(UserMicrotask {
+            callback: callback,
+        }));
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-window
     fn Window(&self) -> Root<Window> {
         Root::from_ref(self)
@@ -630,6 +755,36 @@ impl WindowMethods for Window {
         doc.cancel_animation_frame(ident);
     }
 
+    // https://w3c.github.io/requestidlecallback/#dom-window-requestidlecallback
+    fn RequestIdleCallback(&self, callback: Rc<IdleRequestCallback>, options: &IdleRequestOptions) -> u32 {
+        let handle = self.next_idle_request_handle.get() + 1;
+        self.next_idle_request_handle.set(handle);
+        let timeout = if options.timeout > 0 {
+            let callback = OneshotTimerCallback::IdleCallbackTimeout(IdleCallbackTimeout {
+                window: Trusted::new(self),
+                handle: handle,
+            });
+            Some(self.schedule_callback(callback, Length::new(options.timeout as u64)))
+        } else {
+            None
+        };
+        self.idle_request_callbacks.borrow_mut().push(IdleRequest {
+            handle: handle,
+            callback: callback,
+            timeout: timeout,
+        });
+        handle
+    }
+
+    // https://w3c.github.io/requestidlecallback/#dom-window-cancelidlecallback
+    fn CancelIdleCallback(&self, handle: u32) {
+        if let Some(request) = self.take_idle_request(handle) {
+            if let Some(timeout) = request.timeout {
+                self.unschedule_callback(timeout);
+            }
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-window-captureevents
     fn CaptureEvents(&self) {
         // This method intentionally does nothing
@@ -865,17 +1020,22 @@ impl<'a, T: Reflectable> ScriptHelpers for &'a T {
                 let globalhandle = global.r().reflector().get_jsobject();
                 let code: Vec<u16> = code.encode_utf16().collect();
                 let filename = CString::new(filename).unwrap();
+                if let GlobalRef::Window(window) = global.r() {
+                    ScriptThread::script_ran(window.pipeline());
+                }
 
-                let _ac = JSAutoCompartment::new(cx, globalhandle.get());
-                let options = CompileOptionsWrapper::new(cx, filename.as_ptr(), 0);
-                unsafe {
-                    if !Evaluate2(cx, options.ptr, code.as_ptr(),
-                                  code.len() as libc::size_t,
-                                  rval) {
-                        debug!("error evaluating JS string");
-                        report_pending_exception(cx, globalhandle.get());
+                microtask::run_script(|| {
+                    let _ac = JSAutoCompartment::new(cx, globalhandle.get());
+                    let options = CompileOptionsWrapper::new(cx, filename.as_ptr(), 0);
+                    unsafe {
+                        if !Evaluate2(cx, options.ptr, code.as_ptr(),
+                                      code.len() as libc::size_t,
+                                      rval) {
+                            debug!("error evaluating JS string");
+                            report_pending_exception(cx, globalhandle.get());
+                        }
                     }
-                }
+                }, || global.r().perform_a_microtask_checkpoint())
             }
         )
     }
@@ -889,6 +1049,24 @@ impl Window {
     }
 
     pub fn clear_js_runtime(&self) {
+        self.reporting.deliver_reports(self);
+        self.speculative_loads.borrow_mut().clear();
+        let event_sources: Vec<Root<EventSource>> =
+            self.event_sources.borrow().iter().map(|source| Root::from_ref(&**source)).collect();
+        for event_source in event_sources {
+            event_source.forcibly_close();
+        }
+        let idle_detectors: Vec<Root<IdleDetector>> =
+            self.idle_detectors.borrow().iter().map(|detector| Root::from_ref(&**detector)).collect();
+        for idle_detector in idle_detectors {
+            idle_detector.stop();
+        }
+        if let Some(ref dir) = opts::get().record_timer_events {
+            let records = self.timers.take_recorded_events();
+            if let Err(e) = write_timer_event_log(&timer_event_log_path(dir, self.id), &records) {
+                warn!("Couldn't write the timer events of pipeline {} ({}).", self.id, e);
+            }
+        }
         self.Document().upcast::<Node>().teardown();
 
         // The above code may not catch all DOM objects
@@ -974,6 +1152,7 @@ impl Window {
         // TODO (farodin91): Raise an event to stop the current_viewport
         let size = self.current_viewport.get().size;
         self.current_viewport.set(Rect::new(Point2D::new(Au::from_f32_px(x), Au::from_f32_px(y)), size));
+        self.queue_viewport_event(ViewportEvent::Scroll);
 
         self.compositor.send(ScriptToCompositorMsg::ScrollFragmentPoint(
                                                          self.pipeline(), layer_id, point, smooth)).unwrap()
@@ -1086,6 +1265,11 @@ impl Window {
         if !for_display || self.Document().needs_reflow() {
             self.force_reflow(goal, query_type, reason);
 
+            if for_display {
+                // The layout of the frames in this document may have changed.
+                self.queue_iframe_visibility_check();
+            }
+
             // If window_size is `None`, we don't reflow, so the document stays dirty.
             // Otherwise, we shouldn't need a reflow immediately after a reflow.
             assert!(!self.Document().needs_reflow() ||
@@ -1243,14 +1427,192 @@ impl Window {
     /// Commence a new URL load which will either replace this window or scroll to a fragment.
     pub fn load_url(&self, url: Url) {
         let doc = self.Document();
+        doc.cancel_declarative_refresh();
         self.main_thread_script_chan().send(
             MainThreadScriptMsg::Navigate(self.id,
                 LoadData::new(url, doc.get_referrer_policy(), Some(doc.url().clone())))).unwrap();
     }
 
+    pub fn reporting(&self) -> &ReportingContext {
+        &self.reporting
+    }
+
+    pub fn add_event_source(&self, event_source: &EventSource) {
+        self.event_sources.borrow_mut().push(JS::from_ref(event_source));
+    }
+
+    pub fn remove_event_source(&self, event_source: &EventSource) {
+        self.event_sources.borrow_mut().retain(|source| &**source != event_source);
+    }
+
+    pub fn add_idle_detector(&self, detector: &IdleDetector) {
+        self.idle_detectors.borrow_mut().push(JS::from_ref(detector));
+    }
+
+    pub fn remove_idle_detector(&self, detector: &IdleDetector) {
+        self.idle_detectors.borrow_mut().retain(|started| &**started != detector);
+    }
+
+    pub fn time_resolution(&self) -> TimeResolutionPolicy {
+        self.time_resolution
+    }
+
     pub fn handle_fire_timer(&self, timer_id: TimerEventId) {
+        let start = time::precise_time_ns();
         self.timers.fire_timer(timer_id, self);
         self.reflow(ReflowGoal::ForDisplay, ReflowQueryType::NoQuery, ReflowReason::Timer);
+        let end = time::precise_time_ns();
+        if is_long_task(start, end) {
+            // Naming the timer formats its stack, so it is only done for long tasks.
+            if let Some(label) = self.timers.last_run_label() {
+                debug!("{} of pipeline {} ran for {}ms.", label, self.id, (end - start) / 1000000);
+            }
+            self.report_long_task(start, end);
+        }
+    }
+
+    /// Reports the long task that ran from `start` to `end`, in which script
+    /// of this window ran, to its performance observers.
+    // https://w3c.github.io/longtasks/#report-long-tasks
+    pub fn report_long_task(&self, start: u64, end: u64) {
+        let performance = self.Performance();
+        if !performance.has_observers_for("longtask") {
+            return;
+        }
+        // The task is attributed to the frame element the window is nested in, if any.
+        let global = GlobalRef::Window(self);
+        let attribution = match self.GetFrameElement() {
+            Some(frame) => TaskAttributionTiming::new(global,
+                                                      DOMString::from("iframe"),
+                                                      frame.get_string_attribute(&atom!("src")),
+                                                      frame.get_string_attribute(&atom!("id")),
+                                                      frame.get_string_attribute(&atom!("name"))),
+            None => TaskAttributionTiming::new(global,
+                                               DOMString::from("window"),
+                                               DOMString::from(self.get_url().as_str()),
+                                               DOMString::new(),
+                                               DOMString::new()),
+        };
+        let entry = PerformanceLongTaskTiming::new(global,
+                                                   DOMString::from("self"),
+                                                   performance.to_dom_high_res_time_stamp(start),
+                                                   (end - start) as f64 / 1000000 as f64,
+                                                   &attribution);
+        performance.queue_entry(entry.upcast());
+    }
+
+    pub fn suspend_timers_scoped(&self, reason: SuspensionReason) -> ScopedSuspension<OneshotTimerCallback> {
+        self.timers.suspend_scoped(reason)
+    }
+
+    pub fn flush_due_timers(&self) {
+        self.timers.flush_due_timers(self);
+        self.reflow(ReflowGoal::ForDisplay, ReflowQueryType::NoQuery, ReflowReason::Timer);
+    }
+
+    pub fn advance_timer_clock(&self, duration: MsDuration) {
+        self.timers.advance_clock(duration, self);
+        self.reflow(ReflowGoal::ForDisplay, ReflowQueryType::NoQuery, ReflowReason::Timer);
+    }
+
+    /// How far the clock of the timers was moved ahead of the real one.
+    pub fn skipped_time(&self) -> MsDuration {
+        self.timers.skipped_time()
+    }
+
+    /// The time since the navigation started by the clock of the timers.
+    pub fn timer_time_since_navigation_start(&self) -> MsDuration {
+        let navigation_start = (self.navigation_start_precise / 1000000 as f64) as u64;
+        Length::new(self.timers.current_time().get().saturating_sub(navigation_start))
+    }
+
+    pub fn timers_suspended(&self) -> bool {
+        self.timers.is_suspended()
+    }
+
+    pub fn timers_quiescent(&self, window: MsDuration) -> bool {
+        self.timers.is_quiescent(window)
+    }
+
+    pub fn timer_stats(&self) -> PipelineTimerStats {
+        self.timers.stats(self.get_url().into_string())
+    }
+
+    /// Whether idle callbacks or speculative loads are waiting for an idle
+    /// period that can begin, which it can't while the timers are suspended
+    /// or a timer is due. The timer task that runs it gets to look again.
+    pub fn has_runnable_idle_callbacks(&self) -> bool {
+        !self.timers.is_suspended() && self.timers.idle_period(Length::new(MAX_IDLE_PERIOD_MS)).is_some() &&
+        (!self.idle_request_callbacks.borrow().is_empty() || !self.speculative_loads.borrow().is_empty())
+    }
+
+    /// Queues a prefetch or preconnect for the next idle period, which ends
+    /// before the next timer is due. Hidden documents don't get any.
+    pub fn queue_speculative_load(&self, load: SpeculativeLoad) {
+        if self.Document().Hidden() {
+            return;
+        }
+        let mut loads = self.speculative_loads.borrow_mut();
+        if !loads.contains(&load) {
+            loads.push(load);
+        }
+    }
+
+    /// https://w3c.github.io/requestidlecallback/#start-an-idle-period-algorithm
+    pub fn run_idle_callbacks(&self) {
+        if !self.has_runnable_idle_callbacks() {
+            return;
+        }
+        // The idle period ends when the next timer is due, so as not to delay it.
+        let idle_period = match self.timers.idle_period(Length::new(MAX_IDLE_PERIOD_MS)) {
+            Some(idle_period) => idle_period,
+            None => return,
+        };
+        let deadline = precise_time_ms() + idle_period.get() as f64;
+        // Callbacks requested during the idle period wait for the next one.
+        let pending = self.idle_request_callbacks.borrow().len();
+        for _ in 0..pending {
+            if precise_time_ms() >= deadline {
+                break;
+            }
+            let request = {
+                let mut requests = self.idle_request_callbacks.borrow_mut();
+                if requests.is_empty() {
+                    break;
+                }
+                requests.remove(0)
+            };
+            self.invoke_idle_callback(request, deadline, false);
+        }
+        // Speculative loads go last, if the idle period isn't over by then.
+        if precise_time_ms() < deadline {
+            let loads = mem::replace(&mut *self.speculative_loads.borrow_mut(), vec![]);
+            for load in loads {
+                load.start(self);
+            }
+        }
+    }
+
+    /// https://w3c.github.io/requestidlecallback/#invoke-idle-callback-timeout-algorithm
+    fn run_timed_out_idle_callback(&self, handle: u32) {
+        if let Some(mut request) = self.take_idle_request(handle) {
+            // The timer that got here has fired already.
+            request.timeout = None;
+            self.invoke_idle_callback(request, precise_time_ms(), true);
+        }
+    }
+
+    fn take_idle_request(&self, handle: u32) -> Option<IdleRequest> {
+        let mut requests = self.idle_request_callbacks.borrow_mut();
+        requests.iter().position(|request| request.handle == handle).map(|index| requests.remove(index))
+    }
+
+    fn invoke_idle_callback(&self, request: IdleRequest, deadline: f64, did_timeout: bool) {
+        if let Some(timeout) = request.timeout {
+            self.unschedule_callback(timeout);
+        }
+        let deadline = IdleDeadline::new(self, deadline, did_timeout);
+        let _ = request.callback.Call__(&deadline, ExceptionHandling::Report);
     }
 
     pub fn set_fragment_name(&self, fragment: Option<String>) {
@@ -1297,7 +1659,7 @@ impl Window {
         &self.constellation_chan
     }
 
-    pub fn scheduler_chan(&self) -> &IpcSender<TimerEventRequest> {
+    pub fn scheduler_chan(&self) -> &IpcSender<TimerSchedulerMsg> {
         &self.scheduler_chan
     }
 
@@ -1306,15 +1668,41 @@ impl Window {
     }
 
     pub fn schedule_callback(&self, callback: OneshotTimerCallback, duration: MsDuration) -> OneshotTimerHandle {
-        self.timers.schedule_callback(callback,
-                                      duration,
-                                      TimerSource::FromWindow(self.id.clone()))
+        profile(ProfilerCategory::ScriptTimerSchedule, None, self.time_profiler_chan.clone(), || {
+            self.timers.schedule_callback(callback,
+                                          duration,
+                                          TimerSource::FromWindow(self.id.clone()))
+        })
     }
 
     pub fn unschedule_callback(&self, handle: OneshotTimerHandle) {
         self.timers.unschedule_callback(handle);
     }
 
+    pub fn describe_timers(&self) -> Vec<TimerInfo> {
+        self.timers.describe_timers()
+    }
+
+    pub fn set_timer_breakpoint(&self, handle: Option<i32>, enabled: bool) {
+        self.timers.set_timer_breakpoint(handle, enabled)
+    }
+
+    pub fn pause_before_next_timer(&self) {
+        self.timers.pause_before_next_timer()
+    }
+
+    pub fn set_timer_delay_multiplier(&self, multiplier: u64) {
+        self.timers.set_delay_multiplier(multiplier)
+    }
+
+    pub fn run_immediate(&self, handle: i32) {
+        self.timers.run_immediate(self, handle, TimerSource::FromWindow(self.id.clone()))
+    }
+
+    pub fn cancel_timer(&self, handle: i32) -> bool {
+        self.timers.clear_timeout_or_interval(GlobalRef::Window(self), handle)
+    }
+
     pub fn windowproxy_handler(&self) -> WindowProxyHandler {
         WindowProxyHandler(self.dom_static.windowproxy_handler.0)
     }
@@ -1344,8 +1732,66 @@ impl Window {
         event
     }
 
+    /// Fires a viewport event once the viewport event timer fires, together
+    /// with the other events of its kind until then.
+    pub fn queue_viewport_event(&self, event: ViewportEvent) {
+        if self.viewport_events.queue(event) {
+            let callback = OneshotTimerCallback::ViewportEvents(ViewportEventsDue::new(self));
+            self.schedule_callback(callback, Length::new(self.viewport_events.interval_ms()));
+        }
+    }
+
+    /// Checks a while from now whether the cross-origin iframes of the document
+    /// can be seen, rather than after every single reflow.
+    fn queue_iframe_visibility_check(&self) {
+        if self.iframe_visibility_check_pending.get() {
+            return;
+        }
+        self.iframe_visibility_check_pending.set(true);
+        let check = IframeVisibilityCheck {
+            window: Trusted::new(self),
+        };
+        self.schedule_callback(OneshotTimerCallback::IframeVisibilityCheck(check),
+                               Length::new(IFRAME_VISIBILITY_CHECK_DELAY_MS));
+    }
+
+    /// Sets how long the resize and scroll events of this window wait to be
+    /// merged with the ones that come after them.
+    pub fn set_viewport_event_interval(&self, interval_ms: u64) {
+        self.viewport_events.set_interval_ms(interval_ms);
+    }
+
+    pub fn fire_viewport_events(&self) {
+        for event in self.viewport_events.take() {
+            match event {
+                ViewportEvent::Resize => {
+                    let uievent = UIEvent::new(self,
+                                               DOMString::from("resize"), EventBubbles::DoesNotBubble,
+                                               EventCancelable::NotCancelable, Some(self),
+                                               0i32);
+                    uievent.upcast::<Event>().fire(self.upcast());
+                },
+                ViewportEvent::Scroll => {
+                    let event = Event::new(GlobalRef::Window(self),
+                                           Atom::from("scroll"),
+                                           EventBubbles::Bubbles,
+                                           EventCancelable::NotCancelable);
+                    event.fire(self.Document().upcast());
+                },
+            }
+        }
+    }
+
+    /// The part of the page that is laid out for display, around the viewport.
+    pub fn page_clip_rect(&self) -> Rect<Au> {
+        self.page_clip_rect.get()
+    }
+
     pub fn set_page_clip_rect_with_new_viewport(&self, viewport: Rect<f32>) -> bool {
         let rect = geometry::f32_rect_to_au_rect(viewport.clone());
+        if rect.origin != self.current_viewport.get().origin {
+            self.queue_viewport_event(ViewportEvent::Scroll);
+        }
         self.current_viewport.set(rect);
         // We use a clipping rectangle that is five times the size of the of the viewport,
         // so that we don't collect display list items for areas too far outside the viewport,
@@ -1381,7 +1827,20 @@ impl Window {
     }
 
     pub fn thaw(&self) {
-        self.timers.resume();
+        // The pending timers are only restored if the window is still around to run them.
+        let alive = self.is_alive();
+        if !alive {
+            self.timers.discard_frozen();
+        }
+        self.timers.resume(SuspensionReason::Frozen);
+
+        // Fired once the timers run again, so that handlers can re-arm them.
+        if alive && !self.timers.is_suspended_for(SuspensionReason::Frozen) {
+            self.Document().fire_resume_event();
+        }
+        if alive {
+            self.Document().update_animation_frames_for_suspension();
+        }
 
         // Push the document title to the compositor since we are
         // activating this document due to a navigation.
@@ -1389,7 +1848,46 @@ impl Window {
     }
 
     pub fn freeze(&self) {
-        self.timers.suspend();
+        // Fired while the timers are still running, so that the page can save its state.
+        if !self.timers.is_suspended_for(SuspensionReason::Frozen) {
+            self.Document().fire_freeze_event();
+        }
+        self.timers.suspend(SuspensionReason::Frozen);
+        self.Document().update_animation_frames_for_suspension();
+    }
+
+    /// Suspends or resumes the timers of this window on behalf of the embedder.
+    pub fn set_timers_suspended_by_embedder(&self, suspended: bool) {
+        if suspended == self.timers.is_suspended_for(SuspensionReason::Embedder) {
+            return;
+        }
+        if suspended {
+            self.timers.suspend(SuspensionReason::Embedder);
+        } else {
+            self.timers.resume(SuspensionReason::Embedder);
+        }
+        self.Document().update_animation_frames_for_suspension();
+    }
+
+    /// Throttles or unthrottles the timers of this window as the cross-origin
+    /// frame its document is shown in stops or starts being seen.
+    pub fn set_frame_hidden(&self, hidden: bool) {
+        self.timers.set_frame_hidden(hidden);
+    }
+
+    /// Throttles or unthrottles the timers of this window as its document gets
+    /// hidden or visible.
+    pub fn set_hidden(&self, hidden: bool) {
+        self.timers.set_hidden(hidden);
+        if hidden {
+            self.speculative_loads.borrow_mut().clear();
+        }
+    }
+
+    /// Keeps the timers of this window from being throttled while its
+    /// document is hidden.
+    pub fn set_throttling_exempt(&self, exempt: bool) {
+        self.timers.set_throttling_exempt(exempt);
     }
 
     pub fn need_emit_timeline_marker(&self, timeline_type: TimelineMarkerType) -> bool {
@@ -1458,13 +1956,14 @@ impl Window {
                devtools_chan: Option<IpcSender<ScriptToDevtoolsControlMsg>>,
                constellation_chan: IpcSender<ConstellationMsg>,
                control_chan: IpcSender<ConstellationControlMsg>,
-               scheduler_chan: IpcSender<TimerEventRequest>,
+               scheduler_chan: IpcSender<TimerSchedulerMsg>,
                panic_chan: IpcSender<PanicMsg>,
                timer_event_chan: IpcSender<TimerEvent>,
                layout_chan: LayoutChan,
                id: PipelineId,
                parent_info: Option<(PipelineId, SubpageId)>,
-               window_size: Option<WindowSizeData>)
+               window_size: Option<WindowSizeData>,
+               time_resolution: TimeResolutionPolicy)
                -> Root<Window> {
         let layout_rpc: Box<LayoutRPC> = {
             let (rpc_send, rpc_recv) = channel();
@@ -1489,6 +1988,7 @@ impl Window {
             custom_message_chan: custom_message_chan,
             console: Default::default(),
             crypto: Default::default(),
+            internals: Default::default(),
             compositor: compositor,
             navigator: Default::default(),
             image_cache_thread: image_cache_thread,
@@ -1503,7 +2003,9 @@ impl Window {
             session_storage: Default::default(),
             local_storage: Default::default(),
             scheduler_chan: scheduler_chan.clone(),
-            timers: OneshotTimers::new(timer_event_chan, scheduler_chan),
+            timers: OneshotTimers::new(id, timer_event_chan, box scheduler_chan, box PreciseTimerClock),
+            idle_request_callbacks: DOMRefCell::new(vec![]),
+            next_idle_request_handle: Cell::new(0),
             next_worker_id: Cell::new(WorkerId(0)),
             id: id,
             parent_info: parent_info,
@@ -1520,6 +2022,8 @@ impl Window {
             layout_rpc: layout_rpc,
             window_size: Cell::new(window_size),
             current_viewport: Cell::new(Rect::zero()),
+            viewport_events: PendingViewportEvents::new(),
+            iframe_visibility_check_pending: Cell::new(false),
             suppress_reflow: Cell::new(true),
             pending_reflow_count: Cell::new(0),
             current_state: Cell::new(WindowState::Alive),
@@ -1531,10 +2035,34 @@ impl Window {
             ignore_further_async_events: Arc::new(AtomicBool::new(false)),
             error_reporter: error_reporter,
             panic_chan: panic_chan,
+            time_resolution: time_resolution,
+            reporting: ReportingContext::new(),
+            event_sources: DOMRefCell::new(vec![]),
+            idle_detectors: DOMRefCell::new(vec![]),
+            speculative_loads: DOMRefCell::new(vec![]),
         };
 
-        WindowBinding::Wrap(runtime.cx(), win)
+        let win = WindowBinding::Wrap(runtime.cx(), win);
+        win.init_timer_event_log();
+        win.timers.set_input_monitor(Some(box ScriptThreadInputMonitor));
+        win.timers.set_time_resolution(win.time_resolution);
+        win
+    }
+
+    /// Starts recording or replaying the timer events of this window, if the
+    /// command line asks for it.
+    fn init_timer_event_log(&self) {
+        if opts::get().record_timer_events.is_some() {
+            self.timers.record_events();
+        }
+        if let Some(ref dir) = opts::get().replay_timer_events {
+            match read_timer_event_log(&timer_event_log_path(dir, self.id)) {
+                Ok(records) => self.timers.replay_events(records),
+                Err(e) => warn!("Couldn't read the timer events of pipeline {} ({}).", self.id, e),
+            }
+        }
     }
+
     pub fn live_devtools_updates(&self) -> bool {
         return self.devtools_wants_updates.get();
     }
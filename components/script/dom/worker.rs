@@ -141,8 +141,10 @@ impl Worker {
             constellation_chan: constellation_chan,
             scheduler_chan: scheduler_chan,
             panic_chan: global.panic_chan().clone(),
+            pipeline_id: global.pipeline(),
             worker_id: worker_id,
             closing: closing,
+            time_resolution: global.time_resolution(),
         };
 
         DedicatedWorkerGlobalScope::run_worker_scope(
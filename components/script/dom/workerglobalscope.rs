@@ -2,8 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use devtools_traits::{DevtoolScriptControlMsg, ScriptToDevtoolsControlMsg, WorkerId};
-use dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use devtools_traits::{DevtoolScriptControlMsg, ScriptToDevtoolsControlMsg, TimerInfo, WorkerId};
+use dom::bindings::codegen::Bindings::FunctionBinding::{Function, VoidFunction};
 use dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlobalScopeMethods;
 use dom::bindings::error::{Error, ErrorResult, Fallible, report_pending_exception};
 use dom::bindings::global::GlobalRef;
@@ -14,6 +14,7 @@ use dom::console::Console;
 use dom::crypto::Crypto;
 use dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use dom::eventtarget::EventTarget;
+use dom::internals::Internals;
 use dom::window::{base64_atob, base64_btoa};
 use dom::workerlocation::WorkerLocation;
 use dom::workernavigator::WorkerNavigator;
@@ -22,19 +23,22 @@ use ipc_channel::router::ROUTER;
 use js::jsapi::{HandleValue, JSContext, JSRuntime, RootedValue};
 use js::jsval::UndefinedValue;
 use js::rust::Runtime;
+use microtask::{self, Microtask, MicrotaskQueue, UserMicrotask};
 use msg::constellation_msg::{PipelineId, ReferrerPolicy, PanicMsg};
 use net_traits::{LoadContext, CoreResourceThread, load_whole_resource, RequestSource, LoadOrigin, CustomResponseSender};
 use profile_traits::{mem, time};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort};
 use script_traits::ScriptMsg as ConstellationMsg;
-use script_traits::{MsDuration, TimerEvent, TimerEventId, TimerEventRequest, TimerSource};
+use script_traits::{MsDuration, TimerEvent, TimerEventId, TimerSchedulerMsg, TimerSource};
 use std::cell::Cell;
 use std::default::Default;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Receiver;
-use timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle, OneshotTimers, TimerCallback};
+use time_resolution::TimeResolutionPolicy;
+use timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle, OneshotTimers, PreciseTimerClock, TimerCallback};
+use timers::{ScopedSuspension, SuspensionReason};
 use url::Url;
 use util::str::DOMString;
 
@@ -50,10 +54,13 @@ pub struct WorkerGlobalScopeInit {
     pub to_devtools_sender: Option<IpcSender<ScriptToDevtoolsControlMsg>>,
     pub from_devtools_sender: Option<IpcSender<DevtoolScriptControlMsg>>,
     pub constellation_chan: IpcSender<ConstellationMsg>,
-    pub scheduler_chan: IpcSender<TimerEventRequest>,
+    pub scheduler_chan: IpcSender<TimerSchedulerMsg>,
     pub panic_chan: IpcSender<PanicMsg>,
+    pub pipeline_id: PipelineId,
     pub worker_id: WorkerId,
     pub closing: Arc<AtomicBool>,
+    /// How precisely the worker may observe time, which is as precisely as its owner may.
+    pub time_resolution: TimeResolutionPolicy,
 }
 
 // https://html.spec.whatwg.org/multipage/#the-workerglobalscope-common-interface
@@ -72,7 +79,11 @@ pub struct WorkerGlobalScope {
     navigator: MutNullableHeap<JS<WorkerNavigator>>,
     console: MutNullableHeap<JS<Console>>,
     crypto: MutNullableHeap<JS<Crypto>>,
+    internals: MutNullableHeap<JS<Internals>>,
     timers: OneshotTimers,
+    /// How precisely script of the worker may observe time.
+    time_resolution: TimeResolutionPolicy,
+    microtask_queue: MicrotaskQueue,
     #[ignore_heap_size_of = "Defined in std"]
     mem_profiler_chan: mem::ProfilerChan,
     #[ignore_heap_size_of = "Defined in std"]
@@ -98,7 +109,7 @@ pub struct WorkerGlobalScope {
     constellation_chan: IpcSender<ConstellationMsg>,
 
     #[ignore_heap_size_of = "Defined in std"]
-    scheduler_chan: IpcSender<TimerEventRequest>,
+    scheduler_chan: IpcSender<TimerSchedulerMsg>,
 
     #[ignore_heap_size_of = "Defined in ipc-channel"]
     panic_chan: IpcSender<PanicMsg>,
@@ -119,6 +130,11 @@ impl WorkerGlobalScope {
                          -> WorkerGlobalScope {
         let (msg_chan, msg_port) = ipc::channel().unwrap();
         let custom_msg_port = ROUTER.route_ipc_receiver_to_new_mpsc_receiver(msg_port);
+        let timers = OneshotTimers::new(init.pipeline_id,
+                                        timer_event_chan,
+                                        box init.scheduler_chan.clone(),
+                                        box PreciseTimerClock);
+        timers.set_time_resolution(init.time_resolution);
         WorkerGlobalScope {
             eventtarget: EventTarget::new_inherited(),
             next_worker_id: Cell::new(WorkerId(0)),
@@ -131,7 +147,10 @@ impl WorkerGlobalScope {
             navigator: Default::default(),
             console: Default::default(),
             crypto: Default::default(),
-            timers: OneshotTimers::new(timer_event_chan, init.scheduler_chan.clone()),
+            internals: Default::default(),
+            timers: timers,
+            time_resolution: init.time_resolution,
+            microtask_queue: MicrotaskQueue::new(),
             mem_profiler_chan: init.mem_profiler_chan,
             time_profiler_chan: init.time_profiler_chan,
             to_devtools_sender: init.to_devtools_sender,
@@ -170,20 +189,54 @@ impl WorkerGlobalScope {
         &self.constellation_chan
     }
 
-    pub fn scheduler_chan(&self) -> &IpcSender<TimerEventRequest> {
+    pub fn scheduler_chan(&self) -> &IpcSender<TimerSchedulerMsg> {
         &self.scheduler_chan
     }
 
     pub fn schedule_callback(&self, callback: OneshotTimerCallback, duration: MsDuration) -> OneshotTimerHandle {
-        self.timers.schedule_callback(callback,
-                                      duration,
-                                      TimerSource::FromWorker)
+        time::profile(time::ProfilerCategory::ScriptTimerSchedule, None, self.time_profiler_chan.clone(), || {
+            self.timers.schedule_callback(callback,
+                                          duration,
+                                          TimerSource::FromWorker)
+        })
     }
 
     pub fn unschedule_callback(&self, handle: OneshotTimerHandle) {
         self.timers.unschedule_callback(handle);
     }
 
+    pub fn enqueue_microtask(&self, job: Microtask) {
+        self.microtask_queue.enqueue(job);
+    }
+
+    pub fn perform_a_microtask_checkpoint(&self) {
+        self.microtask_queue.checkpoint();
+    }
+
+    pub fn describe_timers(&self) -> Vec<TimerInfo> {
+        self.timers.describe_timers()
+    }
+
+    pub fn set_timer_breakpoint(&self, handle: Option<i32>, enabled: bool) {
+        self.timers.set_timer_breakpoint(handle, enabled)
+    }
+
+    pub fn pause_before_next_timer(&self) {
+        self.timers.pause_before_next_timer()
+    }
+
+    pub fn set_timer_delay_multiplier(&self, multiplier: u64) {
+        self.timers.set_delay_multiplier(multiplier)
+    }
+
+    pub fn run_immediate(&self, handle: i32) {
+        self.timers.run_immediate(self, handle, TimerSource::FromWorker)
+    }
+
+    pub fn cancel_timer(&self, handle: i32) -> bool {
+        self.timers.clear_timeout_or_interval(GlobalRef::Worker(self), handle)
+    }
+
     pub fn runtime(&self) -> *mut JSRuntime {
         self.runtime.rt()
     }
@@ -226,6 +279,10 @@ impl WorkerGlobalScope {
     pub fn panic_chan(&self) -> &IpcSender<PanicMsg> {
         &self.panic_chan
     }
+
+    pub fn time_resolution(&self) -> TimeResolutionPolicy {
+        self.time_resolution
+    }
 }
 
 impl LoadOrigin for WorkerGlobalScope {
@@ -304,6 +361,10 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.crypto.or_init(|| Crypto::new(GlobalRef::Worker(self)))
     }
 
+    fn Internals(&self) -> Root<Internals> {
+        self.internals.or_init(|| Internals::new(GlobalRef::Worker(self)))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -363,26 +424,45 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
     fn ClearInterval(&self, handle: i32) {
         self.ClearTimeout(handle);
     }
+
+    // https://w3c.github.io/setImmediate/#si-setImmediate
+    fn SetImmediate(&self, _cx: *mut JSContext, callback: Rc<Function>, args: Vec<HandleValue>) -> i32 {
+        self.timers.set_immediate(GlobalRef::Worker(self), callback, args)
+    }
+
+    // https://w3c.github.io/setImmediate/#si-clearImmediate
+    fn ClearImmediate(&self, handle: i32) {
+        self.timers.clear_immediate(handle);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-queuemicrotask
+    fn QueueMicrotask(&self, callback: Rc<VoidFunction>) {
+        self.enqueue_microtask(Microtask::User(UserMicrotask {
+            callback: callback,
+        }));
+    }
 }
 
 
 impl WorkerGlobalScope {
     pub fn execute_script(&self, source: DOMString) {
         let mut rval = RootedValue::new(self.runtime.cx(), UndefinedValue());
-        match self.runtime.evaluate_script(
-            self.reflector().get_jsobject(), &source, self.worker_url.as_str(), 1, rval.handle_mut()) {
-            Ok(_) => (),
-            Err(_) => {
-                if self.is_closing() {
-                    println!("evaluate_script failed (terminated)");
-                } else {
-                    // TODO: An error needs to be dispatched to the parent.
-                    // https://github.com/servo/servo/issues/6422
-                    println!("evaluate_script failed");
-                    report_pending_exception(self.runtime.cx(), self.reflector().get_jsobject().get());
+        microtask::run_script(|| {
+            match self.runtime.evaluate_script(
+                self.reflector().get_jsobject(), &source, self.worker_url.as_str(), 1, rval.handle_mut()) {
+                Ok(_) => (),
+                Err(_) => {
+                    if self.is_closing() {
+                        println!("evaluate_script failed (terminated)");
+                    } else {
+                        // TODO: An error needs to be dispatched to the parent.
+                        // https://github.com/servo/servo/issues/6422
+                        println!("evaluate_script failed");
+                        report_pending_exception(self.runtime.cx(), self.reflector().get_jsobject().get());
+                    }
                 }
             }
-        }
+        }, || self.perform_a_microtask_checkpoint())
     }
 
     pub fn script_chan(&self) -> Box<ScriptChan + Send> {
@@ -425,6 +505,23 @@ impl WorkerGlobalScope {
         self.timers.fire_timer(timer_id, self);
     }
 
+    pub fn flush_due_timers(&self) {
+        self.timers.flush_due_timers(self);
+    }
+
+    pub fn suspend_timers_scoped(&self, reason: SuspensionReason) -> ScopedSuspension<OneshotTimerCallback> {
+        self.timers.suspend_scoped(reason)
+    }
+
+    pub fn advance_timer_clock(&self, duration: MsDuration) {
+        self.timers.advance_clock(duration, self);
+    }
+
+    /// How far the clock of the timers was moved ahead of the real one.
+    pub fn skipped_time(&self) -> MsDuration {
+        self.timers.skipped_time()
+    }
+
     pub fn set_devtools_wants_updates(&self, value: bool) {
         self.devtools_wants_updates.set(value);
     }
@@ -60,7 +60,7 @@ use std::str;
 use std::sync::{Arc, Mutex};
 use string_cache::Atom;
 use time;
-use timers::{OneshotTimerCallback, OneshotTimerHandle};
+use timers::{OneshotTimerCallback, OneshotTimerHandle, SuspensionReason};
 use url::{Url, Position};
 use util::prefs;
 use util::str::DOMString;
@@ -146,7 +146,11 @@ pub struct XMLHttpRequest {
     send_flag: Cell<bool>,
 
     timeout_cancel: DOMRefCell<Option<OneshotTimerHandle>>,
-    fetch_time: Cell<i64>,
+    /// When the request was sent, in ns.
+    fetch_time: Cell<u64>,
+    stall_timer: DOMRefCell<Option<OneshotTimerHandle>>,
+    /// When the last part of the response arrived, or the request was sent, in ns.
+    last_progress: Cell<u64>,
     generation_id: Cell<GenerationId>,
     response_status: Cell<Result<(), ()>>,
 }
@@ -182,6 +186,8 @@ impl XMLHttpRequest {
 
             timeout_cancel: DOMRefCell::new(None),
             fetch_time: Cell::new(0),
+            stall_timer: DOMRefCell::new(None),
+            last_progress: Cell::new(0),
             generation_id: Cell::new(GenerationId(0)),
             response_status: Cell::new(Ok(())),
         }
@@ -496,18 +502,16 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         // Step 2
         self.timeout.set(timeout);
 
+        // The deadline of a request in progress is counted from when it was
+        // sent, and no timeout disables it.
         if self.send_flag.get() {
             if timeout == 0 {
                 self.cancel_timeout();
                 return Ok(());
             }
-            let progress = time::now().to_timespec().sec - self.fetch_time.get();
-            if timeout > (progress * 1000) as u32 {
-                self.set_timeout(timeout - (progress * 1000) as u32);
-            } else {
-                // Immediately execute the timeout steps
-                self.set_timeout(0);
-            }
+            let elapsed_ms = (time::precise_time_ns() - self.fetch_time.get()) / 1000000;
+            // If the deadline has passed already, the timeout steps run right away.
+            self.set_timeout((timeout as u64).saturating_sub(elapsed_ms) as u32);
         }
         Ok(())
     }
@@ -650,7 +654,8 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
 
         debug!("request_headers = {:?}", *self.request_headers.borrow());
 
-        self.fetch_time.set(time::now().to_timespec().sec);
+        self.fetch_time.set(time::precise_time_ns());
+        self.last_progress.set(self.fetch_time.get());
         let rv = self.fetch(load_data, cors_request, global.r());
         // Step 10
         if self.sync.get() {
@@ -661,6 +666,9 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         if timeout > 0 {
             self.set_timeout(timeout);
         }
+        if let Some(stall_timeout) = stall_timeout_ms() {
+            self.set_stall_timer(stall_timeout);
+        }
         Ok(())
     }
 
@@ -972,6 +980,7 @@ impl XMLHttpRequest {
                 }
                 // Part of step 13, send() (processing response)
                 // XXXManishearth handle errors, if any (substep 1)
+                self.last_progress.set(time::precise_time_ns());
                 // Substep 2
                 status.map(|RawStatus(code, reason)| {
                     self.status.set(code);
@@ -989,6 +998,7 @@ impl XMLHttpRequest {
                 // Part of step 11, send() (processing response body)
                 // XXXManishearth handle errors, if any (substep 2)
 
+                self.last_progress.set(time::precise_time_ns());
                 *self.response.borrow_mut() = partial_response;
                 if !self.sync.get() {
                     if self.ready_state.get() == XMLHttpRequestState::HeadersReceived {
@@ -1004,6 +1014,7 @@ impl XMLHttpRequest {
                         self.sync.get());
 
                 self.cancel_timeout();
+                self.cancel_stall_timer();
 
                 // Part of step 11, send() (processing response end of file)
                 // XXXManishearth handle errors, if any (substep 2)
@@ -1022,6 +1033,7 @@ impl XMLHttpRequest {
             },
             XHRProgress::Errored(_, e) => {
                 self.cancel_timeout();
+                self.cancel_stall_timer();
 
                 self.discard_subsequent_responses();
                 self.send_flag.set(false);
@@ -1097,6 +1109,7 @@ impl XMLHttpRequest {
     fn set_timeout(&self, duration_ms: u32) {
         // Sets up the object to timeout in a given number of milliseconds
         // This will cancel all previous timeouts
+        self.cancel_timeout();
         let callback = OneshotTimerCallback::XhrTimeout(XHRTimeoutCallback {
             xhr: Trusted::new(self),
             generation_id: self.generation_id.get(),
@@ -1113,6 +1126,27 @@ impl XMLHttpRequest {
         }
     }
 
+    /// Checks in `duration_ms` whether the response made progress. Rather than
+    /// rescheduling the timer for every part of the response, the timer only
+    /// looks at when the last part arrived and checks again later if it did
+    /// recently, so a busy response doesn't keep the timer scheduler busy too.
+    fn set_stall_timer(&self, duration_ms: u64) {
+        self.cancel_stall_timer();
+        let callback = OneshotTimerCallback::XhrStall(XHRStallCallback {
+            xhr: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+        });
+        let global = self.global();
+        *self.stall_timer.borrow_mut() = Some(global.r().schedule_callback(callback, Length::new(duration_ms)));
+    }
+
+    fn cancel_stall_timer(&self) {
+        if let Some(handle) = self.stall_timer.borrow_mut().take() {
+            let global = self.global();
+            global.r().unschedule_callback(handle);
+        }
+    }
+
     // https://xhr.spec.whatwg.org/#text-response
     fn text_response(&self) -> String {
         // Step 3, 5
@@ -1346,6 +1380,8 @@ impl XMLHttpRequest {
         }
 
         if let Some(script_port) = script_port {
+            // Only the events of this request are handled until it returns.
+            let _suspension = global.suspend_timers_scoped(SuspensionReason::SyncXhr);
             loop {
                 global.process_event(script_port.recv().unwrap());
                 let context = context.lock().unwrap();
@@ -1396,12 +1432,58 @@ pub struct XHRTimeoutCallback {
 impl XHRTimeoutCallback {
     pub fn invoke(self) {
         let xhr = self.xhr.root();
+        if xhr.generation_id.get() == self.generation_id {
+            *xhr.timeout_cancel.borrow_mut() = None;
+        }
         if xhr.ready_state.get() != XMLHttpRequestState::Done {
             xhr.process_partial_response(XHRProgress::Errored(self.generation_id, Error::Timeout));
         }
     }
 }
 
+/// How long, in ms, an asynchronous request may go without receiving any part
+/// of its response before it fails, if the `dom.xhr.stall_timeout_seconds`
+/// pref sets a limit.
+fn stall_timeout_ms() -> Option<u64> {
+    match prefs::get_pref("dom.xhr.stall_timeout_seconds").as_i64() {
+        Some(seconds) if seconds > 0 => Some(seconds as u64 * 1000),
+        _ => None,
+    }
+}
+
+/// Fails a request whose response stalled, unlike the timeout of the request,
+/// which limits how long the whole response may take.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct XHRStallCallback {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    xhr: Trusted<XMLHttpRequest>,
+    generation_id: GenerationId,
+}
+
+impl XHRStallCallback {
+    pub fn invoke(self) {
+        let xhr = self.xhr.root();
+        if xhr.generation_id.get() != self.generation_id {
+            return;
+        }
+        *xhr.stall_timer.borrow_mut() = None;
+        let stall_timeout = match stall_timeout_ms() {
+            Some(stall_timeout) => stall_timeout,
+            None => return,
+        };
+        if xhr.ready_state.get() == XMLHttpRequestState::Done {
+            return;
+        }
+        let idle_ms = (time::precise_time_ns() - xhr.last_progress.get()) / 1000000;
+        if idle_ms < stall_timeout {
+            xhr.set_stall_timer(stall_timeout - idle_ms);
+            return;
+        }
+        warn!("XMLHttpRequest to {:?} stalled for {} ms.", *xhr.request_url.borrow(), idle_ms);
+        xhr.process_partial_response(XHRProgress::Errored(self.generation_id, Error::Network));
+    }
+}
+
 trait Extractable {
     fn extract(&self) -> (Vec<u8>, Option<DOMString>);
 }
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Decides when the [animation frame callbacks][rafs] of a document run.
+//!
+//! The compositor ticks the frames of the documents that have callbacks as it
+//! composites. A timer ticks them instead if it doesn't for a while, e.g. for
+//! a document that isn't being composited. No frames are ticked while the
+//! timers of the window are suspended; they pick up again as the timers resume.
+//!
+//! [rafs]: https://html.spec.whatwg.org/multipage/#run-the-animation-frame-callbacks
+
+use dom::bindings::refcounted::Trusted;
+use dom::document::Document;
+use dom::window::Window;
+use euclid::length::Length;
+use script_traits::{AnimationState, ScriptMsg as ConstellationMsg};
+use std::cell::Cell;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
+
+/// How long to wait for the compositor to tick a frame before a timer does.
+/// Two frames at 60Hz, so that the timer doesn't fire between ticks that
+/// come in time.
+const FALLBACK_TICK_DELAY_MS: u64 = 33;
+
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct FrameScheduler {
+    /// Whether the compositor was told to tick the frames of the document.
+    compositor_ticking: Cell<bool>,
+    /// The timer that ticks the next frame if the compositor doesn't.
+    fallback_tick: Cell<Option<OneshotTimerHandle>>,
+}
+
+impl FrameScheduler {
+    pub fn new() -> FrameScheduler {
+        FrameScheduler {
+            compositor_ticking: Cell::new(false),
+            fallback_tick: Cell::new(None),
+        }
+    }
+
+    /// Makes sure that a frame is ticked for the callbacks of `document`,
+    /// once its timers run, if they are suspended.
+    pub fn request_frame(&self, document: &Document) {
+        let window = document.window();
+        if window.timers_suspended() {
+            return;
+        }
+        if !self.compositor_ticking.get() {
+            self.set_compositor_ticking(window, true);
+        }
+        if self.fallback_tick.get().is_none() {
+            let callback = OneshotTimerCallback::FrameTick(FrameTick {
+                document: Trusted::new(document),
+            });
+            let handle = window.schedule_callback(callback, Length::new(FALLBACK_TICK_DELAY_MS));
+            self.fallback_tick.set(Some(handle));
+        }
+    }
+
+    /// Stops ticking frames, because there are no callbacks left or the
+    /// timers got suspended.
+    pub fn stop(&self, window: &Window) {
+        self.cancel_fallback_tick(window);
+        if self.compositor_ticking.get() {
+            self.set_compositor_ticking(window, false);
+        }
+    }
+
+    /// Called as a frame is ticked, by the compositor or by the timer.
+    /// Returns whether its callbacks may run.
+    pub fn begin_frame(&self, window: &Window) -> bool {
+        self.cancel_fallback_tick(window);
+        if window.timers_suspended() {
+            self.stop(window);
+            return false;
+        }
+        true
+    }
+
+    fn cancel_fallback_tick(&self, window: &Window) {
+        if let Some(handle) = self.fallback_tick.get() {
+            window.unschedule_callback(handle);
+            self.fallback_tick.set(None);
+        }
+    }
+
+    fn set_compositor_ticking(&self, window: &Window, ticking: bool) {
+        let state = if ticking {
+            AnimationState::AnimationCallbacksPresent
+        } else {
+            AnimationState::NoAnimationCallbacksPresent
+        };
+        let event = ConstellationMsg::ChangeRunningAnimationsState(window.pipeline(), state);
+        window.constellation_chan().send(event).unwrap();
+        self.compositor_ticking.set(ticking);
+    }
+}
+
+/// Ticks a frame that the compositor didn't tick in time.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct FrameTick {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    document: Trusted<Document>,
+}
+
+impl FrameTick {
+    pub fn invoke(self) {
+        let document = self.document.root();
+        // The timer that got here has fired already.
+        document.frame_scheduler().fallback_tick.set(None);
+        if document.window().is_alive() {
+            document.run_the_animation_frame_callbacks();
+        }
+    }
+}
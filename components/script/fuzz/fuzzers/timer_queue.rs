@@ -0,0 +1,298 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Drives `OneshotTimers` with arbitrary sequences of calls, playing the part
+//! of the timer scheduler, and checks that the timers never get into a state
+//! the scheduler and the script thread couldn't agree on.
+//!
+//! Run with `cargo fuzz run timer_queue` in `components/script`.
+
+#![no_main]
+
+extern crate euclid;
+extern crate ipc_channel;
+extern crate libfuzzer_sys;
+extern crate msg;
+extern crate script;
+extern crate script_traits;
+extern crate util;
+
+use euclid::length::Length;
+use ipc_channel::ipc;
+use msg::constellation_msg::PipelineId;
+use script::test::timers::{OneshotTimerHandle, OneshotTimers, ScheduledCallback, SuspensionReason};
+use script::test::timers::{TimerClock, TimerSchedulerChan};
+use script_traits::{MsDuration, NsDuration, TimerEventId, TimerEventRequest, TimerSchedulerMsg, TimerSource};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use util::thread_state;
+
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+thread_local!(static THREAD_STATE_INITIALIZED: Cell<bool> = Cell::new(false));
+
+/// Keeps the messages sent to the timer scheduler, for the harness to play
+/// the part of the scheduler with.
+#[derive(Clone, Default)]
+struct SchedulerChan {
+    sent: Rc<RefCell<Vec<TimerSchedulerMsg>>>,
+}
+
+impl SchedulerChan {
+    fn take_sent(&self) -> Vec<TimerSchedulerMsg> {
+        self.sent.borrow_mut().drain(..).collect()
+    }
+}
+
+impl TimerSchedulerChan for SchedulerChan {
+    fn send(&self, msg: TimerSchedulerMsg) -> Result<(), ()> {
+        self.sent.borrow_mut().push(msg);
+        Ok(())
+    }
+}
+
+/// A clock that only moves when the harness advances it.
+#[derive(Clone, Default)]
+struct Clock {
+    now: Rc<Cell<u64>>,
+}
+
+impl TimerClock for Clock {
+    fn now(&self) -> NsDuration {
+        Length::new(self.now.get())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FuzzCallback {
+    id: u32,
+    period: Option<MsDuration>,
+    throttleable: bool,
+    exempt: bool,
+}
+
+impl ScheduledCallback for FuzzCallback {
+    fn repeat_period(&self) -> Option<MsDuration> {
+        self.period
+    }
+
+    fn next_occurrence(&self) -> Option<FuzzCallback> {
+        self.period.map(|_| *self)
+    }
+
+    fn throttleable(&self) -> bool {
+        self.throttleable
+    }
+
+    fn exempt_from_suspension(&self) -> bool {
+        self.exempt
+    }
+
+    fn is_script_timer(&self) -> bool {
+        !self.exempt
+    }
+}
+
+/// Reads the operations out of the fuzzer's input.
+struct Input<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        self.data.split_first().map(|(&byte, rest)| {
+            self.data = rest;
+            byte
+        })
+    }
+
+    fn short(&mut self) -> Option<u64> {
+        let high = try_opt!(self.byte()) as u64;
+        let low = try_opt!(self.byte()) as u64;
+        Some(high << 8 | low)
+    }
+}
+
+/// The event the timers last asked the scheduler for, as the scheduler sees it.
+struct RequestedEvent {
+    id: TimerEventId,
+    due: u64,
+    period: Option<u64>,
+}
+
+struct Harness {
+    timers: OneshotTimers<FuzzCallback>,
+    scheduler: SchedulerChan,
+    clock: Clock,
+    now: u64,
+    next_id: u32,
+    /// The callbacks that are scheduled and haven't been cancelled, by id.
+    scheduled: HashMap<u32, (OneshotTimerHandle, bool)>,
+    requested: Option<RequestedEvent>,
+    cancelled: Vec<TimerEventId>,
+}
+
+impl Harness {
+    fn new() -> Harness {
+        THREAD_STATE_INITIALIZED.with(|initialized| {
+            if !initialized.get() {
+                thread_state::initialize(thread_state::SCRIPT);
+                initialized.set(true);
+            }
+        });
+        let (timer_event_chan, _) = ipc::channel().unwrap();
+        let scheduler = SchedulerChan::default();
+        let clock = Clock::default();
+        Harness {
+            timers: OneshotTimers::new(PipelineId::fake_root_pipeline_id(),
+                                       timer_event_chan,
+                                       Box::new(scheduler.clone()),
+                                       Box::new(clock.clone())),
+            scheduler: scheduler,
+            clock: clock,
+            now: 0,
+            next_id: 0,
+            scheduled: HashMap::new(),
+            requested: None,
+            cancelled: vec![],
+        }
+    }
+
+    fn advance(&mut self, ms: u64) {
+        self.clock.now.set(self.clock.now.get() + ms * 1000 * 1000);
+        self.now += ms;
+    }
+
+    /// Handles what the timers sent to the scheduler, checking that there is
+    /// never more than one event requested at a time.
+    fn receive_sent(&mut self) {
+        for msg in self.scheduler.take_sent() {
+            match msg {
+                TimerSchedulerMsg::Request(TimerEventRequest(_, _, id, delay, _, _, period)) => {
+                    assert!(self.requested.is_none(), "requested {:?} before the last event was cancelled", id);
+                    self.requested = Some(RequestedEvent {
+                        id: id,
+                        due: self.now + delay.get(),
+                        period: period.map(|period| period.get()),
+                    });
+                }
+                TimerSchedulerMsg::Cancel(_, id) => {
+                    let requested = self.requested.take().expect("cancelled an event that wasn't requested");
+                    assert_eq!(requested.id, id);
+                    self.cancelled.push(id);
+                }
+                _ => panic!("unexpected message to the scheduler"),
+            }
+        }
+    }
+
+    /// Checks a callback that is run, and forgets about it unless it repeats.
+    fn ran(scheduled: &mut HashMap<u32, (OneshotTimerHandle, bool)>, callback: FuzzCallback) {
+        let repeats = match scheduled.get(&callback.id) {
+            Some(&(_, repeats)) => repeats,
+            None => panic!("callback {} ran although it was cancelled or has run", callback.id),
+        };
+        if !repeats {
+            scheduled.remove(&callback.id);
+        }
+    }
+
+    /// Delivers the requested event when it is due, as the scheduler would.
+    fn deliver_requested(&mut self) {
+        let (id, due, period) = match self.requested {
+            Some(ref requested) => (requested.id, requested.due, requested.period),
+            None => return,
+        };
+        if due > self.now {
+            let delay = due - self.now;
+            self.advance(delay);
+        }
+        self.requested = period.map(|period| RequestedEvent { id: id, due: due + period, period: Some(period) });
+        let scheduled = &mut self.scheduled;
+        self.timers.fire_timer_with(id, |callback| Harness::ran(scheduled, callback));
+    }
+
+    fn run(&mut self, input: &mut Input) -> Option<()> {
+        loop {
+            let op = try_opt!(input.byte()) % 11;
+            match op {
+                0 => {
+                    let duration = try_opt!(input.short());
+                    let flags = try_opt!(input.byte());
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    let period = if flags & 1 != 0 { Some(Length::new(try_opt!(input.short()))) } else { None };
+                    let callback = FuzzCallback {
+                        id: id,
+                        period: period,
+                        throttleable: flags & 2 != 0,
+                        exempt: flags & 4 != 0,
+                    };
+                    let source = TimerSource::FromWindow(PipelineId::fake_root_pipeline_id());
+                    let handle = self.timers.schedule_callback(callback, Length::new(duration), source);
+                    self.scheduled.insert(id, (handle, period.is_some()));
+                }
+                1 => {
+                    let index = try_opt!(input.byte()) as usize;
+                    let mut ids: Vec<u32> = self.scheduled.keys().cloned().collect();
+                    ids.sort();
+                    if !ids.is_empty() {
+                        let (handle, _) = self.scheduled.remove(&ids[index % ids.len()]).unwrap();
+                        self.timers.unschedule_callback(handle);
+                    }
+                }
+                2 | 3 => {
+                    let reason = match try_opt!(input.byte()) % 5 {
+                        0 => SuspensionReason::Frozen,
+                        1 => SuspensionReason::ModalDialog,
+                        2 => SuspensionReason::Debugger,
+                        3 => SuspensionReason::Embedder,
+                        _ => SuspensionReason::SyncXhr,
+                    };
+                    if op == 2 {
+                        self.timers.suspend(reason);
+                    } else {
+                        self.timers.resume(reason);
+                    }
+                }
+                4 => self.timers.set_hidden(try_opt!(input.byte()) & 1 != 0),
+                5 => self.timers.set_throttling_exempt(try_opt!(input.byte()) & 1 != 0),
+                6 => {
+                    let ms = try_opt!(input.short());
+                    self.advance(ms);
+                }
+                7 => self.deliver_requested(),
+                8 => {
+                    // The scheduler may still deliver events that were cancelled;
+                    // they have to be ignored.
+                    let index = try_opt!(input.byte()) as usize;
+                    if !self.cancelled.is_empty() {
+                        let id = self.cancelled[index % self.cancelled.len()];
+                        let scheduled = &mut self.scheduled;
+                        self.timers.fire_timer_with(id, |callback| Harness::ran(scheduled, callback));
+                    }
+                }
+                9 => {
+                    let scheduled = &mut self.scheduled;
+                    self.timers.flush_due_timers_with(|callback| Harness::ran(scheduled, callback));
+                }
+                _ => {
+                    let ms = try_opt!(input.short());
+                    let scheduled = &mut self.scheduled;
+                    self.timers.advance_clock_with(Length::new(ms), |callback| Harness::ran(scheduled, callback));
+                }
+            }
+            self.receive_sent();
+        }
+    }
+}
+
+#[export_name="rust_fuzzer_test_input"]
+pub extern fn go(data: &[u8]) {
+    let mut harness = Harness::new();
+    harness.run(&mut Input { data: data });
+}
@@ -30,6 +30,7 @@
 
 extern crate angle;
 extern crate app_units;
+extern crate backtrace;
 #[allow(unused_extern_crates)]
 #[macro_use]
 extern crate bitflags;
@@ -89,21 +90,29 @@ pub mod clipboard_provider;
 pub mod cors;
 mod devtools;
 pub mod document_loader;
+mod frame_scheduler;
 #[macro_use]
 pub mod dom;
 pub mod layout_interface;
+pub mod long_tasks;
 mod mem;
+pub mod microtask;
 mod network_listener;
 pub mod origin;
 pub mod parse;
 pub mod reporter;
+mod reporting;
+pub mod retry_timer;
 pub mod script_runtime;
 #[allow(unsafe_code)]
 pub mod script_thread;
 mod task_source;
+pub mod test;
 pub mod textinput;
+pub mod time_resolution;
 mod timers;
 mod unpremultiplytable;
+pub mod viewport_events;
 mod webdriver_handlers;
 
 use dom::bindings::codegen::RegisterBindings;
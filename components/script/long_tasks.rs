@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Which windows a long task of the script thread is reported to.
+//!
+//! A task is attributed to the windows whose script ran in it, rather than
+//! to every window of the thread. See https://w3c.github.io/longtasks/.
+
+use dom::bindings::cell::DOMRefCell;
+use msg::constellation_msg::PipelineId;
+
+/// Tasks that run longer than this are reported to the performance observers, in ns.
+pub const LONG_TASK_THRESHOLD_NS: u64 = 50 * 1000000;
+
+/// Whether a task that ran from `start` to `end` is a long one.
+pub fn is_long_task(start: u64, end: u64) -> bool {
+    end - start > LONG_TASK_THRESHOLD_NS
+}
+
+/// The windows whose script ran during the task being handled.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct LongTaskCulprits {
+    pipelines: DOMRefCell<Vec<PipelineId>>,
+}
+
+impl LongTaskCulprits {
+    pub fn new() -> LongTaskCulprits {
+        LongTaskCulprits {
+            pipelines: DOMRefCell::new(vec![]),
+        }
+    }
+
+    /// Notes that script of the window of `pipeline` ran in the current task.
+    pub fn script_ran(&self, pipeline: PipelineId) {
+        let mut pipelines = self.pipelines.borrow_mut();
+        if !pipelines.contains(&pipeline) {
+            pipelines.push(pipeline);
+        }
+    }
+
+    /// Ends the task that ran from `start` to `end`, and returns the windows
+    /// to report it to, which are none unless it was a long one.
+    pub fn end_task(&self, start: u64, end: u64) -> Vec<PipelineId> {
+        let pipelines = self.pipelines.borrow_mut().drain(..).collect();
+        if is_long_task(start, end) { pipelines } else { vec![] }
+    }
+}
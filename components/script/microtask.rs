@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Implementation of [microtasks](https://html.spec.whatwg.org/multipage/#microtask) and
+//! [microtask checkpoints](https://html.spec.whatwg.org/multipage/#perform-a-microtask-checkpoint).
+//!
+//! The script thread and each worker have a queue of their own, which they
+//! check whenever a script or callback returns with no other running below it.
+
+use dom::bindings::callback::ExceptionHandling;
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::FunctionBinding::VoidFunction;
+use std::cell::Cell;
+use std::mem;
+use std::rc::Rc;
+
+/// How many scripts and callbacks are running on this thread, one inside
+/// the other. The JavaScript execution context stack is empty at zero.
+thread_local!(static SCRIPT_DEPTH: Cell<usize> = Cell::new(0));
+
+/// The microtasks of an event loop, which run to completion at each checkpoint.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct MicrotaskQueue {
+    /// The microtasks waiting for the next checkpoint, in the order they were queued.
+    microtask_queue: DOMRefCell<Vec<Microtask>>,
+    /// https://html.spec.whatwg.org/multipage/#performing-a-microtask-checkpoint
+    performing_a_microtask_checkpoint: Cell<bool>,
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+pub enum Microtask {
+    User(UserMicrotask),
+}
+
+/// A callback that page script queued with `queueMicrotask`.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct UserMicrotask {
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    pub callback: Rc<VoidFunction>,
+}
+
+impl Microtask {
+    fn run(self) {
+        match self {
+            // https://html.spec.whatwg.org/multipage/#dom-queuemicrotask
+            Microtask::User(task) => {
+                // The exception is reported to the global of the callback, and
+                // doesn't keep the other microtasks from running.
+                let _ = task.callback.Call__(ExceptionHandling::Report);
+            },
+        }
+    }
+}
+
+/// Notes that a script or callback starts running on this thread.
+pub fn enter_script() {
+    SCRIPT_DEPTH.with(|depth| depth.set(depth.get() + 1));
+}
+
+/// Notes that a script or callback returned, and tells whether it was the
+/// outermost one, after which the microtasks run.
+pub fn exit_script() -> bool {
+    SCRIPT_DEPTH.with(|depth| {
+        depth.set(depth.get() - 1);
+        depth.get() == 0
+    })
+}
+
+/// Runs `script`, then the microtask `checkpoint` if no other script is left
+/// running on this thread.
+/// https://html.spec.whatwg.org/multipage/#clean-up-after-running-script
+pub fn run_script<R, S, C>(script: S, checkpoint: C) -> R
+    where S: FnOnce() -> R, C: FnOnce() {
+    enter_script();
+    let result = script();
+    if exit_script() {
+        checkpoint();
+    }
+    result
+}
+
+impl MicrotaskQueue {
+    pub fn new() -> MicrotaskQueue {
+        MicrotaskQueue {
+            microtask_queue: DOMRefCell::new(vec![]),
+            performing_a_microtask_checkpoint: Cell::new(false),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/#queue-a-microtask
+    pub fn enqueue(&self, job: Microtask) {
+        self.microtask_queue.borrow_mut().push(job);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/#perform-a-microtask-checkpoint
+    pub fn checkpoint(&self) {
+        // A checkpoint reached from a microtask, e.g. through a nested event
+        // loop, leaves the microtasks to the checkpoint that is running them.
+        if self.performing_a_microtask_checkpoint.get() {
+            return;
+        }
+        self.performing_a_microtask_checkpoint.set(true);
+
+        // Microtasks queued by microtasks run in the same checkpoint, after
+        // those that were queued before them.
+        loop {
+            let pending = mem::replace(&mut *self.microtask_queue.borrow_mut(), vec![]);
+            if pending.is_empty() {
+                break;
+            }
+            for job in pending {
+                job.run();
+            }
+        }
+
+        self.performing_a_microtask_checkpoint.set(false);
+    }
+}
@@ -0,0 +1,291 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Delivery of the reports of the [Reporting API](https://w3c.github.io/reporting/)
+//! to the observers of a window and to the endpoints its document named in
+//! its `Reporting-Endpoints` header.
+//!
+//! The reports for endpoints are sent in batches by an ordinary timer of the
+//! window, so the uploads get coalesced with the other timers of a hidden page
+//! and held back while the page is suspended. Whatever is still waiting gets
+//! sent when the page goes away.
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::codegen::Bindings::ReportBinding::ReportMethods;
+use dom::bindings::codegen::Bindings::TestReportBodyBinding::TestReportBodyMethods;
+use dom::bindings::global::GlobalRef;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::js::{JS, Root};
+use dom::bindings::refcounted::Trusted;
+use dom::report::Report;
+use dom::reportbody::ReportBody;
+use dom::reportingobserver::ReportingObserver;
+use dom::testreportbody::TestReportBody;
+use dom::window::Window;
+use euclid::length::Length;
+use hyper::header::Headers;
+use hyper::method::Method;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use net_traits::{CoreResourceMsg, IpcSend, LoadConsumer, LoadContext, LoadData, RequestSource};
+use rustc_serialize::json::Json;
+use script_runtime::CommonScriptMsg;
+use script_runtime::ScriptThreadEventCategory::ScriptEvent;
+use script_thread::Runnable;
+use script_traits::precise_time_ns;
+use std::cell::Cell;
+use std::collections::HashMap;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
+use url::Url;
+use util::opts;
+use util::str::DOMString;
+
+/// How many reports are kept for observers that ask for the earlier ones.
+const MAX_BUFFERED_REPORTS: usize = 100;
+
+/// How long the reports for endpoints wait to be sent, so that they can be
+/// sent together.
+const DELIVERY_DELAY_MS: u64 = 60 * 1000;
+
+/// The reporting state of a window.
+#[must_root]
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct ReportingContext {
+    /// https://w3c.github.io/reporting/#registered-reporting-observer-list
+    observers: DOMRefCell<Vec<JS<ReportingObserver>>>,
+    /// https://w3c.github.io/reporting/#report-buffer
+    buffer: DOMRefCell<Vec<JS<Report>>>,
+    /// Whether a task to notify the observers is queued.
+    notify_pending: Cell<bool>,
+    /// The endpoints named in the `Reporting-Endpoints` header, by name.
+    endpoints: DOMRefCell<HashMap<String, Url>>,
+    /// The reports waiting to be sent to endpoints.
+    pending_deliveries: DOMRefCell<Vec<PendingDelivery>>,
+    delivery_timer: Cell<Option<OneshotTimerHandle>>,
+}
+
+#[must_root]
+#[derive(JSTraceable, HeapSizeOf)]
+struct PendingDelivery {
+    endpoint: Url,
+    report: JS<Report>,
+    /// When the report was generated, in ns.
+    generated_at: u64,
+}
+
+impl ReportingContext {
+    pub fn new() -> ReportingContext {
+        ReportingContext {
+            observers: DOMRefCell::new(vec![]),
+            buffer: DOMRefCell::new(vec![]),
+            notify_pending: Cell::new(false),
+            endpoints: DOMRefCell::new(HashMap::new()),
+            pending_deliveries: DOMRefCell::new(vec![]),
+            delivery_timer: Cell::new(None),
+        }
+    }
+
+    /// Takes the endpoints from the value of a `Reporting-Endpoints` header,
+    /// such as `default="https://example.com/reports", csp="/csp"`.
+    pub fn set_endpoints(&self, base_url: &Url, header: &str) {
+        let mut endpoints = self.endpoints.borrow_mut();
+        endpoints.clear();
+        for member in header.split(',') {
+            let mut parts = member.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            if name.is_empty() || value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+                continue;
+            }
+            match base_url.join(&value[1..value.len() - 1]) {
+                Ok(url) => {
+                    endpoints.insert(name.to_owned(), url);
+                },
+                Err(_) => warn!("Ignoring the invalid reporting endpoint {}.", value),
+            }
+        }
+    }
+
+    // https://w3c.github.io/reporting/#generate-a-report
+    pub fn generate_report(&self, window: &Window, type_: &str, body: Option<&ReportBody>, destination: &str) {
+        let report = Report::new(GlobalRef::Window(window),
+                                 DOMString::from(type_),
+                                 DOMString::from(window.get_url().as_str()),
+                                 body);
+
+        // https://w3c.github.io/reporting/#notify-observers
+        let mut queued = false;
+        for observer in self.observers.borrow().iter() {
+            if observer.observes(report.type_()) {
+                observer.queue_report(&report);
+                queued = true;
+            }
+        }
+        if queued {
+            self.queue_notification(window);
+        }
+        {
+            let mut buffer = self.buffer.borrow_mut();
+            if buffer.len() == MAX_BUFFERED_REPORTS {
+                buffer.remove(0);
+            }
+            buffer.push(JS::from_rooted(&report));
+        }
+
+        let endpoint = match self.endpoints.borrow().get(destination) {
+            Some(endpoint) => endpoint.clone(),
+            None => return,
+        };
+        self.pending_deliveries.borrow_mut().push(PendingDelivery {
+            endpoint: endpoint,
+            report: JS::from_rooted(&report),
+            generated_at: precise_time_ns(),
+        });
+        if self.delivery_timer.get().is_none() {
+            let callback = OneshotTimerCallback::ReportDelivery(ReportDelivery {
+                window: Trusted::new(window),
+            });
+            self.delivery_timer.set(Some(window.schedule_callback(callback, Length::new(DELIVERY_DELAY_MS))));
+        }
+    }
+
+    pub fn add_observer(&self, window: &Window, observer: &ReportingObserver, buffered: bool) {
+        {
+            let mut observers = self.observers.borrow_mut();
+            if observers.iter().any(|registered| &**registered == observer) {
+                return;
+            }
+            observers.push(JS::from_ref(observer));
+        }
+        if !buffered {
+            return;
+        }
+        let mut queued = false;
+        for report in self.buffer.borrow().iter() {
+            if observer.observes(report.type_()) {
+                observer.queue_report(report);
+                queued = true;
+            }
+        }
+        if queued {
+            self.queue_notification(window);
+        }
+    }
+
+    pub fn remove_observer(&self, observer: &ReportingObserver) {
+        self.observers.borrow_mut().retain(|registered| &**registered != observer);
+    }
+
+    fn queue_notification(&self, window: &Window) {
+        if self.notify_pending.get() {
+            return;
+        }
+        self.notify_pending.set(true);
+        let runnable = box NotifyReportingObserversRunnable {
+            window: Trusted::new(window),
+        };
+        let _ = GlobalRef::Window(window).script_chan().send(CommonScriptMsg::RunnableMsg(ScriptEvent, runnable));
+    }
+
+    fn notify_observers(&self) {
+        self.notify_pending.set(false);
+        let observers: Vec<Root<ReportingObserver>> =
+            self.observers.borrow().iter().map(|observer| Root::from_ref(&**observer)).collect();
+        for observer in observers {
+            observer.notify();
+        }
+    }
+
+    /// Sends the reports that are waiting for their endpoints right away.
+    // https://w3c.github.io/reporting/#send-reports
+    #[allow(unrooted_must_root)]
+    pub fn deliver_reports(&self, window: &Window) {
+        if let Some(handle) = self.delivery_timer.get() {
+            window.unschedule_callback(handle);
+            self.delivery_timer.set(None);
+        }
+        let now = precise_time_ns();
+        let mut batches: HashMap<Url, Vec<Json>> = HashMap::new();
+        for delivery in self.pending_deliveries.borrow_mut().drain(..) {
+            let report = report_to_json(&delivery.report, now - delivery.generated_at);
+            batches.entry(delivery.endpoint).or_insert_with(Vec::new).push(report);
+        }
+        for (endpoint, reports) in batches {
+            upload(window, endpoint, Json::Array(reports));
+        }
+    }
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(name, value)| (name.to_owned(), value)).collect())
+}
+
+// https://w3c.github.io/reporting/#serialize-a-list-of-reports-to-json
+fn report_to_json(report: &Report, age_ns: u64) -> Json {
+    let body = match report.GetBody() {
+        Some(ref body) => match body.downcast::<TestReportBody>() {
+            Some(body) => object(vec![("message", Json::String(String::from(body.Message())))]),
+            None => Json::Null,
+        },
+        None => Json::Null,
+    };
+    object(vec![
+        ("age", Json::U64(age_ns / 1000000)),
+        ("type", Json::String(String::from(report.Type()))),
+        ("url", Json::String(String::from(report.Url()))),
+        ("user_agent", Json::String(opts::get().user_agent.clone())),
+        ("body", body),
+    ])
+}
+
+/// Posts a batch of reports to an endpoint. Nothing is done with the response.
+fn upload(window: &Window, endpoint: Url, reports: Json) {
+    let mut headers = Headers::new();
+    headers.set_raw("Content-Type".to_owned(), vec![b"application/reports+json".to_vec()]);
+    let load_data = LoadData {
+        context: LoadContext::Browsing,
+        url: endpoint,
+        method: Method::Post,
+        headers: headers,
+        preserved_headers: Headers::new(),
+        data: Some(reports.to_string().into_bytes()),
+        cors: None,
+        pipeline_id: Some(window.pipeline()),
+        credentials_flag: false,
+        referrer_policy: None,
+        referrer_url: None,
+        source: RequestSource::None,
+    };
+    let (sender, receiver) = ipc::channel().unwrap();
+    ROUTER.add_route(receiver.to_opaque(), box |_| {});
+    let msg = CoreResourceMsg::Load(load_data, LoadConsumer::Channel(sender), None);
+    if let Err(e) = window.resource_threads().send(msg) {
+        warn!("Sending reports failed ({}).", e);
+    }
+}
+
+struct NotifyReportingObserversRunnable {
+    window: Trusted<Window>,
+}
+
+impl Runnable for NotifyReportingObserversRunnable {
+    fn handler(self: Box<NotifyReportingObserversRunnable>) {
+        self.window.root().reporting().notify_observers();
+    }
+}
+
+/// Sends the reports of a window once they waited long enough to be batched.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct ReportDelivery {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    window: Trusted<Window>,
+}
+
+impl ReportDelivery {
+    pub fn invoke(self) {
+        let window = self.window.root();
+        window.reporting().delivery_timer.set(None);
+        window.reporting().deliver_reports(&window);
+    }
+}
@@ -0,0 +1,109 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Retries with exponential backoff, for the features that try something
+//! over the network again when it fails.
+//!
+//! The attempts are scheduled as ordinary timers of the global, so they are
+//! suspended with the page and show up in the timer diagnostics. The feature
+//! still supplies the callback, which knows what to retry.
+
+use dom::bindings::global::GlobalRef;
+use euclid::length::Length;
+use rand::random;
+use std::cell::Cell;
+use std::cmp;
+use timers::{OneshotTimerCallback, OneshotTimerHandle};
+
+/// How long to wait between attempts.
+#[derive(JSTraceable, HeapSizeOf, Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The delay before the first retry, in ms.
+    pub initial_delay_ms: u64,
+    /// What the delay is multiplied by after every retry.
+    pub multiplier: u32,
+    /// The longest delay, in ms, unless the initial delay is longer.
+    pub max_delay_ms: u64,
+    /// Up to which fraction of the delay is taken off at random, so that
+    /// the clients of a server that went down don't all retry at once.
+    pub jitter: f64,
+    /// How many retries there are before giving up, if there is a limit.
+    pub max_attempts: Option<u32>,
+}
+
+impl RetryPolicy {
+    /// The delay before retry number `attempt`, counting from 0, or `None` if
+    /// there are no more retries. `random` is between 0 and 1.
+    pub fn delay(&self, attempt: u32, random: f64) -> Option<u64> {
+        if self.max_attempts.map_or(false, |max_attempts| attempt >= max_attempts) {
+            return None;
+        }
+        let mut delay = self.initial_delay_ms;
+        for _ in 0..attempt {
+            if delay >= self.max_delay_ms {
+                break;
+            }
+            delay = delay.saturating_mul(self.multiplier as u64);
+        }
+        let delay = cmp::max(cmp::min(delay, self.max_delay_ms), self.initial_delay_ms);
+        Some(delay - (delay as f64 * self.jitter * random) as u64)
+    }
+}
+
+/// The retries of one thing, with at most one of them scheduled at a time.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct RetryTimer {
+    policy: Cell<RetryPolicy>,
+    /// How many retries were scheduled since the last success.
+    attempts: Cell<u32>,
+    handle: Cell<Option<OneshotTimerHandle>>,
+}
+
+impl RetryTimer {
+    pub fn new(policy: RetryPolicy) -> RetryTimer {
+        RetryTimer {
+            policy: Cell::new(policy),
+            attempts: Cell::new(0),
+            handle: Cell::new(None),
+        }
+    }
+
+    /// Changes the delay before the first retry, such as when a server asks
+    /// for a different one.
+    pub fn set_initial_delay(&self, delay_ms: u64) {
+        let mut policy = self.policy.get();
+        policy.initial_delay_ms = delay_ms;
+        self.policy.set(policy);
+    }
+
+    /// Schedules the next retry in place of a scheduled one, unless there
+    /// are no retries left, in which case it returns false.
+    pub fn schedule(&self, global: GlobalRef, callback: OneshotTimerCallback) -> bool {
+        self.cancel(global);
+        let delay = match self.policy.get().delay(self.attempts.get(), random()) {
+            Some(delay) => delay,
+            None => return false,
+        };
+        self.attempts.set(self.attempts.get() + 1);
+        self.handle.set(Some(global.schedule_callback(callback, Length::new(delay))));
+        true
+    }
+
+    /// To be called by the callback when it runs.
+    pub fn fired(&self) {
+        self.handle.set(None);
+    }
+
+    /// Starts over from the initial delay, once the thing succeeded.
+    pub fn reset(&self) {
+        self.attempts.set(0);
+    }
+
+    pub fn cancel(&self, global: GlobalRef) {
+        if let Some(handle) = self.handle.get() {
+            global.unschedule_callback(handle);
+            self.handle.set(None);
+        }
+    }
+}
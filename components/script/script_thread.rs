@@ -23,7 +23,7 @@ use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo};
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
 use document_loader::DocumentLoader;
 use dom::bindings::cell::DOMRefCell;
-use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
+use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState, VisibilityState};
 use dom::bindings::conversions::{FromJSValConvertible, StringificationBehavior};
 use dom::bindings::global::GlobalRef;
 use dom::bindings::inheritance::Castable;
@@ -35,14 +35,13 @@ use dom::bindings::utils::WRAP_CALLBACKS;
 use dom::browsingcontext::{BrowsingContext, IterableContext};
 use dom::document::{Document, DocumentProgressHandler, DocumentSource, FocusType, IsHTMLDocument};
 use dom::element::Element;
-use dom::event::{Event, EventBubbles, EventCancelable};
 use dom::htmlanchorelement::HTMLAnchorElement;
 use dom::node::{Node, NodeDamage, window_from_node};
 use dom::servohtmlparser::ParserContext;
-use dom::uievent::UIEvent;
 use dom::window::{ReflowReason, ScriptHelpers, Window};
 use dom::worker::TrustedWorkerAddress;
 use euclid::Rect;
+use euclid::length::Length;
 use euclid::point::Point2D;
 use gfx_traits::LayerId;
 use hyper::header::{ContentType, HttpDate};
@@ -58,7 +57,9 @@ use js::jsval::UndefinedValue;
 use js::rust::Runtime;
 use layout_interface::{ReflowQueryType};
 use layout_interface::{self, LayoutChan, NewLayoutThreadInfo, ScriptLayoutChan};
+use long_tasks::LongTaskCulprits;
 use mem::heap_size_of_self_and_children;
+use microtask::{Microtask, MicrotaskQueue};
 use msg::constellation_msg::{LoadData, PanicMsg, PipelineId, PipelineNamespace};
 use msg::constellation_msg::{SubpageId, WindowSizeData, WindowSizeType};
 use msg::webdriver_msg::WebDriverScriptCommand;
@@ -79,9 +80,9 @@ use script_traits::CompositorEvent::{KeyEvent, MouseButtonEvent, MouseMoveEvent,
 use script_traits::CompositorEvent::{TouchEvent, TouchpadPressureEvent};
 use script_traits::{CompositorEvent, ConstellationControlMsg, EventResult};
 use script_traits::{InitialScriptState, MouseButton, MouseEventType, MozBrowserEvent, NewLayoutInfo};
-use script_traits::{LayoutMsg, OpaqueScriptLayoutChannel, ScriptMsg as ConstellationMsg};
-use script_traits::{ScriptThreadFactory, ScriptToCompositorMsg, TimerEvent, TimerEventRequest, TimerSource};
-use script_traits::{TouchEventType, TouchId};
+use script_traits::{LayoutMsg, MsDuration, OpaqueScriptLayoutChannel, ScriptMsg as ConstellationMsg};
+use script_traits::{ScriptThreadFactory, ScriptToCompositorMsg, TimerEvent, TimerPriority, TimerSchedulerMsg};
+use script_traits::{PipelineTimerStats, TimerSource, TouchEventType, TouchId};
 use std::any::Any;
 use std::borrow::ToOwned;
 use std::cell::{Cell, RefCell};
@@ -99,12 +100,14 @@ use task_source::file_reading::FileReadingTaskSource;
 use task_source::history_traversal::HistoryTraversalTaskSource;
 use task_source::networking::NetworkingTaskSource;
 use task_source::user_interaction::{UserInteractionTaskSource, UserInteractionTask};
-use time::Tm;
+use time::{Tm, precise_time_ns};
+use time_resolution::{TimeResolutionPolicy, is_cross_origin_isolated};
 use url::{Url, Position};
 use util::opts;
 use util::str::DOMString;
 use util::thread;
 use util::thread_state;
+use viewport_events::ViewportEvent;
 use webdriver_handlers;
 
 thread_local!(pub static STACK_ROOTS: Cell<Option<RootCollectionPtr>> = Cell::new(None));
@@ -137,6 +140,12 @@ struct InProgressLoad {
     clip_rect: Option<Rect<f32>>,
     /// Window is frozen (navigated away while loading for example).
     is_frozen: bool,
+    /// Window is hidden (minimized while loading for example).
+    is_hidden: bool,
+    /// The cross-origin frame of the window can't be seen (display: none for example).
+    is_frame_hidden: bool,
+    /// The embedder suspended the timers of the window while it was loading.
+    timers_suspended: bool,
     /// The requested URL of the load.
     url: Url,
 }
@@ -155,6 +164,9 @@ impl InProgressLoad {
             window_size: window_size,
             clip_rect: None,
             is_frozen: false,
+            is_hidden: false,
+            is_frame_hidden: false,
+            timers_suspended: false,
             url: url,
         }
     }
@@ -208,6 +220,27 @@ enum MixedMessage {
     FromNetwork(IpcSender<Option<CustomResponse>>),
 }
 
+enum InputEventKind {
+    /// Such as a click or a key press.
+    Discrete,
+    /// Such as a mouse move, which comes in a stream.
+    Continuous,
+}
+
+/// What kind of input event `msg` is, if it is one.
+fn input_event_kind(msg: &MixedMessage) -> Option<InputEventKind> {
+    match *msg {
+        MixedMessage::FromConstellation(ConstellationControlMsg::SendEvent(_, ref event)) => match *event {
+            MouseButtonEvent(..) | KeyEvent(..) => Some(InputEventKind::Discrete),
+            TouchEvent(TouchEventType::Move, _, _) => Some(InputEventKind::Continuous),
+            TouchEvent(..) => Some(InputEventKind::Discrete),
+            MouseMoveEvent(..) | TouchpadPressureEvent(..) => Some(InputEventKind::Continuous),
+            ResizeEvent(..) => None,
+        },
+        _ => None,
+    }
+}
+
 /// Messages used to control the script event loop
 pub enum MainThreadScriptMsg {
     /// Common variants associated with the script messages
@@ -224,6 +257,12 @@ pub enum MainThreadScriptMsg {
     DOMManipulation(DOMManipulationTask),
     /// Tasks that originate from the user interaction task source
     UserInteraction(UserInteractionTask),
+    /// Tells the subscribers of pipelines that have settled, if nothing else
+    /// was waiting to be handled (only dispatched to ScriptThread).
+    CheckQuiescence,
+    /// Runs the idle callbacks of the windows, if nothing else was waiting to
+    /// be handled (only dispatched to ScriptThread).
+    StartIdlePeriod,
 }
 
 impl OpaqueSender<CommonScriptMsg> for Box<ScriptChan + Send> {
@@ -382,13 +421,35 @@ pub struct ScriptThread {
     /// List of pipelines that have been owned and closed by this script thread.
     closed_pipelines: DOMRefCell<HashSet<PipelineId>>,
 
-    scheduler_chan: IpcSender<TimerEventRequest>,
-    timer_event_chan: Sender<TimerEvent>,
+    scheduler_chan: IpcSender<TimerSchedulerMsg>,
+    /// Shared by the windows of this thread, to receive timer events from the scheduler.
+    timer_event_chan: IpcSender<TimerEvent>,
     timer_event_port: Receiver<TimerEvent>,
 
     content_process_shutdown_chan: IpcSender<()>,
 
     panic_chan: IpcSender<PanicMsg>,
+
+    /// Who to tell once a pipeline has settled, with how long no timer may be
+    /// due for that.
+    quiescence_subscribers: DOMRefCell<Vec<(PipelineId, MsDuration, IpcSender<()>)>>,
+    /// Whether a `CheckQuiescence` message is waiting to be handled.
+    quiescence_check_queued: Cell<bool>,
+    /// Whether a `StartIdlePeriod` message is waiting to be handled.
+    idle_period_queued: Cell<bool>,
+
+    /// The microtasks of the windows of this thread, which share its event loop.
+    microtask_queue: MicrotaskQueue,
+
+    /// How many discrete input events, such as clicks and key presses, are
+    /// waiting to be handled in the batch of messages being handled.
+    pending_input_events: Cell<u32>,
+    /// How many continuous input events, such as mouse moves, are.
+    pending_continuous_input_events: Cell<u32>,
+    /// When the last input event was handled, in ns.
+    last_user_input: Cell<u64>,
+    /// The windows whose script ran in the task being handled.
+    long_task_culprits: LongTaskCulprits,
 }
 
 /// In the event of thread panic, all data on the stack runs its destructor. However, there
@@ -513,6 +574,61 @@ impl ScriptThread {
         });
     }
 
+    pub fn enqueue_microtask(job: Microtask) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = *root.borrow() {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.microtask_queue.enqueue(job);
+            }
+        });
+    }
+
+    pub fn perform_a_microtask_checkpoint() {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = *root.borrow() {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.microtask_queue.checkpoint();
+            }
+        });
+    }
+
+    /// Notes that script of the window of `pipeline` ran in the task being
+    /// handled, which makes it one the task is reported to if it runs long.
+    pub fn script_ran(pipeline: PipelineId) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = *root.borrow() {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.long_task_culprits.script_ran(pipeline);
+            }
+        })
+    }
+
+    /// Whether input events are waiting to be handled after the current one,
+    /// counting continuous events such as mouse moves if asked to. Only the
+    /// events that had arrived when the current batch of messages was
+    /// gathered are known about.
+    pub fn is_input_pending(include_continuous: bool) -> bool {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            root.borrow().map_or(false, |script_thread| {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.pending_input_events.get() > 0 ||
+                    (include_continuous && script_thread.pending_continuous_input_events.get() > 0)
+            })
+        })
+    }
+
+    /// How long ago the user last interacted with a page of this thread.
+    /// Servo doesn't know about input to other applications, so this stands
+    /// in for how long the user has been idle on the whole system.
+    pub fn time_since_user_input() -> MsDuration {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            root.borrow().map_or(Length::new(0), |script_thread| {
+                let script_thread = unsafe { &*script_thread };
+                Length::new((precise_time_ns() - script_thread.last_user_input.get()) / 1000000)
+            })
+        })
+    }
+
     // https://html.spec.whatwg.org/multipage/#await-a-stable-state
     pub fn await_stable_state<T: Runnable + Send + 'static>(task: T) {
         //TODO use microtasks when they exist
@@ -551,7 +667,17 @@ impl ScriptThread {
         let image_cache_port =
             ROUTER.route_ipc_receiver_to_new_mpsc_receiver(ipc_image_cache_port);
 
-        let (timer_event_chan, timer_event_port) = channel();
+        // Timer events get a route of their own rather than going through the router,
+        // so that they aren't held up behind the traffic of other channels.
+        let (timer_event_chan, ipc_timer_event_port) = ipc::channel().unwrap();
+        let (timer_event_sender, timer_event_port) = channel();
+        thread::spawn_named(format!("ScriptTimerEvents {:?}", state.id), move || {
+            while let Ok(event) = ipc_timer_event_port.recv() {
+                if timer_event_sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
 
         // Ask the router to proxy IPC messages from the control port to us.
         let control_port = ROUTER.route_ipc_receiver_to_new_mpsc_receiver(state.control_port);
@@ -600,6 +726,15 @@ impl ScriptThread {
             timer_event_port: timer_event_port,
 
             content_process_shutdown_chan: state.content_process_shutdown_chan,
+
+            quiescence_subscribers: DOMRefCell::new(vec![]),
+            quiescence_check_queued: Cell::new(false),
+            idle_period_queued: Cell::new(false),
+            microtask_queue: MicrotaskQueue::new(),
+            pending_input_events: Cell::new(0),
+            pending_continuous_input_events: Cell::new(0),
+            last_user_input: Cell::new(precise_time_ns()),
+            long_task_culprits: LongTaskCulprits::new(),
         }
     }
 
@@ -699,6 +834,8 @@ impl ScriptThread {
         // Squash any pending resize, reflow, animation tick, and mouse-move events in the queue.
         let mut mouse_move_event_index = None;
         let mut animation_ticks = HashSet::new();
+        let mut animation_tick_events = vec![];
+        let mut user_blocking_timer_events = 0;
         loop {
             match event {
                 // This has to be handled before the ResizeMsg below,
@@ -724,7 +861,7 @@ impl ScriptThread {
                         pipeline_id)) => {
                     if !animation_ticks.contains(&pipeline_id) {
                         animation_ticks.insert(pipeline_id);
-                        sequential.push(event);
+                        animation_tick_events.push(event);
                     }
                 }
                 FromConstellation(ConstellationControlMsg::SendEvent(
@@ -740,6 +877,14 @@ impl ScriptThread {
                         }
                     }
                 }
+                // User-blocking timer events go ahead of everything else that is waiting.
+                FromScheduler(TimerEvent(_, _, TimerPriority::UserBlocking)) => {
+                    sequential.insert(user_blocking_timer_events, event);
+                    user_blocking_timer_events += 1;
+                    if let Some(ref mut index) = mouse_move_event_index {
+                        *index += 1;
+                    }
+                }
                 _ => {
                     sequential.push(event);
                 }
@@ -769,9 +914,47 @@ impl ScriptThread {
             }
         }
 
+        // Updating the rendering comes after the tasks that were queued before
+        // it, such as those of the timers that were due.
+        sequential.extend(animation_tick_events);
+
+        // Count the input events, to tell the tasks before them that they are waiting.
+        let (mut discrete, mut continuous) = (0, 0);
+        for msg in &sequential {
+            match input_event_kind(msg) {
+                Some(InputEventKind::Discrete) => discrete += 1,
+                Some(InputEventKind::Continuous) => continuous += 1,
+                None => {},
+            }
+        }
+        self.pending_input_events.set(discrete);
+        self.pending_continuous_input_events.set(continuous);
+
+        // A quiescence check or idle period that was handled with nothing but
+        // each other found the queues empty.
+        let idle = sequential.iter().all(|msg| match *msg {
+            FromScript(MainThreadScriptMsg::CheckQuiescence) |
+            FromScript(MainThreadScriptMsg::StartIdlePeriod) => true,
+            _ => false,
+        });
+
         // Process the gathered events.
         for msg in sequential {
             let category = self.categorize_msg(&msg);
+            match input_event_kind(&msg) {
+                Some(InputEventKind::Discrete) =>
+                    self.pending_input_events.set(self.pending_input_events.get() - 1),
+                Some(InputEventKind::Continuous) =>
+                    self.pending_continuous_input_events.set(self.pending_continuous_input_events.get() - 1),
+                None => {},
+            }
+
+            // Timer tasks are reported by the window that ran them, which names the timer.
+            let reported_by_window = match msg {
+                FromScheduler(_) => true,
+                _ => false,
+            };
+            let start = precise_time_ns();
 
             let result = self.profile_event(category, move || {
                 match msg {
@@ -781,6 +964,8 @@ impl ScriptThread {
                         }
                     },
                     FromConstellation(inner_msg) => self.handle_msg_from_constellation(inner_msg),
+                    FromScript(MainThreadScriptMsg::CheckQuiescence) => self.handle_check_quiescence(idle),
+                    FromScript(MainThreadScriptMsg::StartIdlePeriod) => self.handle_start_idle_period(idle),
                     FromScript(inner_msg) => self.handle_msg_from_script(inner_msg),
                     FromNetwork(inner_msg) => self.handle_msg_from_network(inner_msg),
                     FromScheduler(inner_msg) => self.handle_timer_event(inner_msg),
@@ -791,6 +976,12 @@ impl ScriptThread {
                 None
             });
 
+            // Timer tasks are still ended here, so that they aren't blamed on what comes next.
+            let culprits = self.long_task_culprits.end_task(start, precise_time_ns());
+            if !reported_by_window {
+                self.report_long_task(start, culprits);
+            }
+
             if let Some(retval) = result {
                 return retval
             }
@@ -820,6 +1011,9 @@ impl ScriptThread {
             }
         }
 
+        self.queue_quiescence_check();
+        self.queue_idle_period();
+
         true
     }
 
@@ -900,6 +1094,18 @@ impl ScriptThread {
                 self.handle_freeze_msg(pipeline_id),
             ConstellationControlMsg::Thaw(pipeline_id) =>
                 self.handle_thaw_msg(pipeline_id),
+            ConstellationControlMsg::ChangeVisibility(pipeline_id, visible) =>
+                self.handle_visibility_change_msg(pipeline_id, visible),
+            ConstellationControlMsg::ChangeFrameVisibility(pipeline_id, visible) =>
+                self.handle_frame_visibility_change_msg(pipeline_id, visible),
+            ConstellationControlMsg::SuspendTimers(pipeline_id, suspended) =>
+                self.handle_suspend_timers_msg(pipeline_id, suspended),
+            ConstellationControlMsg::NotifyWhenQuiescent(pipeline_id, window_ms, sender) =>
+                self.handle_notify_when_quiescent_msg(pipeline_id, Length::new(window_ms), sender),
+            ConstellationControlMsg::IsQuiescent(pipeline_id, window_ms, sender) =>
+                self.handle_is_quiescent_msg(pipeline_id, Length::new(window_ms), sender),
+            ConstellationControlMsg::GetTimerStats(pipeline_id, sender) =>
+                self.handle_get_timer_stats_msg(pipeline_id, sender),
             ConstellationControlMsg::MozBrowserEvent(parent_pipeline_id,
                                                      subpage_id,
                                                      event) =>
@@ -955,11 +1161,26 @@ impl ScriptThread {
                 task.handle_task(self),
             MainThreadScriptMsg::UserInteraction(task) =>
                 task.handle_task(),
+            MainThreadScriptMsg::CheckQuiescence =>
+                self.handle_check_quiescence(false),
+            MainThreadScriptMsg::StartIdlePeriod =>
+                self.handle_start_idle_period(false),
+        }
+    }
+
+    /// Reports the long task that started at `start` to the windows of
+    /// `culprits`, whose script ran in it.
+    fn report_long_task(&self, start: u64, culprits: Vec<PipelineId>) {
+        let end = precise_time_ns();
+        for pipeline_id in culprits {
+            if let Some(context) = self.find_child_context(pipeline_id) {
+                context.active_window().report_long_task(start, end);
+            }
         }
     }
 
     fn handle_timer_event(&self, timer_event: TimerEvent) {
-        let TimerEvent(source, id) = timer_event;
+        let TimerEvent(source, id, _) = timer_event;
 
         let pipeline_id = match source {
             TimerSource::FromWindow(pipeline_id) => pipeline_id,
@@ -1005,6 +1226,31 @@ impl ScriptThread {
                 devtools::handle_drop_timeline_markers(&context, marker_types),
             DevtoolScriptControlMsg::RequestAnimationFrame(pipeline_id, name) =>
                 devtools::handle_request_animation_frame(&context, pipeline_id, name),
+            DevtoolScriptControlMsg::GetTimers(id, reply) => {
+                let window = get_browsing_context(&context, id).active_window();
+                let global_ref = GlobalRef::Window(window.r());
+                devtools::handle_get_timers(&global_ref, reply)
+            },
+            DevtoolScriptControlMsg::SetTimerBreakpoint(id, handle, enabled) => {
+                let window = get_browsing_context(&context, id).active_window();
+                let global_ref = GlobalRef::Window(window.r());
+                devtools::handle_set_timer_breakpoint(&global_ref, handle, enabled)
+            },
+            DevtoolScriptControlMsg::PauseBeforeNextTimer(id) => {
+                let window = get_browsing_context(&context, id).active_window();
+                let global_ref = GlobalRef::Window(window.r());
+                devtools::handle_pause_before_next_timer(&global_ref)
+            },
+            DevtoolScriptControlMsg::SetTimerDelayMultiplier(id, multiplier) => {
+                let window = get_browsing_context(&context, id).active_window();
+                let global_ref = GlobalRef::Window(window.r());
+                devtools::handle_set_timer_delay_multiplier(&global_ref, multiplier)
+            },
+            DevtoolScriptControlMsg::CancelTimer(id, handle, reply) => {
+                let window = get_browsing_context(&context, id).active_window();
+                let global_ref = GlobalRef::Window(window.r());
+                devtools::handle_cancel_timer(&global_ref, handle, reply)
+            },
         }
     }
 
@@ -1053,6 +1299,8 @@ impl ScriptThread {
                 webdriver_handlers::handle_get_title(&context, pipeline_id, reply),
             WebDriverScriptCommand::ExecuteAsyncScript(script, reply) =>
                 webdriver_handlers::handle_execute_async_script(&context, pipeline_id, script, reply),
+            WebDriverScriptCommand::AdvanceTimers(ms) =>
+                webdriver_handlers::handle_advance_timers(&context, pipeline_id, ms),
         }
     }
 
@@ -1222,6 +1470,160 @@ impl ScriptThread {
         panic!("thaw sent to nonexistent pipeline");
     }
 
+    /// Handles visibility change message
+    fn handle_visibility_change_msg(&self, id: PipelineId, visible: bool) {
+        if let Some(inner_context) = self.root_browsing_context().find(id) {
+            let state = if visible {
+                VisibilityState::Visible
+            } else {
+                VisibilityState::Hidden
+            };
+            inner_context.active_document().set_visibility_state(state);
+            return;
+        }
+        let mut loads = self.incomplete_loads.borrow_mut();
+        if let Some(ref mut load) = loads.iter_mut().find(|load| load.pipeline_id == id) {
+            load.is_hidden = !visible;
+            return;
+        }
+        warn!("visibility change sent to nonexistent pipeline {:?}", id);
+    }
+
+    /// Handles a change of the visibility of the cross-origin frame of a pipeline
+    fn handle_frame_visibility_change_msg(&self, id: PipelineId, visible: bool) {
+        if let Some(inner_context) = self.root_browsing_context().find(id) {
+            inner_context.active_window().set_frame_hidden(!visible);
+            return;
+        }
+        let mut loads = self.incomplete_loads.borrow_mut();
+        if let Some(ref mut load) = loads.iter_mut().find(|load| load.pipeline_id == id) {
+            load.is_frame_hidden = !visible;
+            return;
+        }
+        warn!("frame visibility change sent to nonexistent pipeline {:?}", id);
+    }
+
+    /// Handles a request of the embedder to suspend or resume the timers of a pipeline
+    fn handle_suspend_timers_msg(&self, id: PipelineId, suspended: bool) {
+        if let Some(inner_context) = self.root_browsing_context().find(id) {
+            inner_context.active_window().set_timers_suspended_by_embedder(suspended);
+            return;
+        }
+        let mut loads = self.incomplete_loads.borrow_mut();
+        if let Some(ref mut load) = loads.iter_mut().find(|load| load.pipeline_id == id) {
+            load.timers_suspended = suspended;
+            return;
+        }
+        warn!("timer suspension sent to nonexistent pipeline {:?}", id);
+    }
+
+    fn handle_notify_when_quiescent_msg(&self, id: PipelineId, window: MsDuration, sender: IpcSender<()>) {
+        if self.closed_pipelines.borrow().contains(&id) {
+            return warn!("quiescence subscription sent to closed pipeline {:?}", id);
+        }
+        // Pipelines that are still loading are checked once they have loaded.
+        self.quiescence_subscribers.borrow_mut().push((id, window, sender));
+    }
+
+    /// Replies whether a pipeline has settled, leaving out the tasks that are waiting.
+    fn handle_is_quiescent_msg(&self, id: PipelineId, window: MsDuration, sender: IpcSender<bool>) {
+        if let Err(e) = sender.send(self.is_settled(id, window)) {
+            warn!("Replying to quiescence query failed ({:?}).", e);
+        }
+    }
+
+    /// Replies with the statistics of the timers of a pipeline. Pipelines that
+    /// are still loading have no timers yet, and don't reply.
+    fn handle_get_timer_stats_msg(&self, id: PipelineId, sender: IpcSender<PipelineTimerStats>) {
+        let context = match self.find_child_context(id) {
+            Some(context) => context,
+            None => return,
+        };
+        if let Err(e) = sender.send(context.active_window().timer_stats()) {
+            warn!("Replying with timer statistics failed ({:?}).", e);
+        }
+    }
+
+    /// Whether a pipeline has loaded and no timer is due within `window`.
+    fn is_settled(&self, id: PipelineId, window: MsDuration) -> bool {
+        let context = match self.find_child_context(id) {
+            Some(context) => context,
+            None => return false,
+        };
+        let window_object = context.active_window();
+        window_object.Document().ReadyState() == DocumentReadyState::Complete &&
+            window_object.timers_quiescent(window)
+    }
+
+    /// Queues a `CheckQuiescence` message behind whatever else is waiting if a
+    /// subscriber's pipeline might have settled, to find out whether the
+    /// queues have run empty by the time it is handled.
+    fn queue_quiescence_check(&self) {
+        if self.quiescence_check_queued.get() {
+            return;
+        }
+        let might_be_settled = self.quiescence_subscribers.borrow().iter()
+                                   .any(|&(id, window, _)| self.is_settled(id, window));
+        if might_be_settled {
+            let MainThreadScriptChan(ref sender) = self.chan;
+            sender.send(MainThreadScriptMsg::CheckQuiescence).unwrap();
+            self.quiescence_check_queued.set(true);
+        }
+    }
+
+    /// Tells the subscribers of the pipelines that have settled, if the check
+    /// was the only message to be handled. Otherwise the check is queued again
+    /// once the other messages have been handled.
+    fn handle_check_quiescence(&self, idle: bool) {
+        self.quiescence_check_queued.set(false);
+        let closed_pipelines = self.closed_pipelines.borrow();
+        self.quiescence_subscribers.borrow_mut().retain(|&(id, window, ref sender)| {
+            if closed_pipelines.contains(&id) {
+                // Dropping the sender tells the subscriber the pipeline went away.
+                return false;
+            }
+            if !idle || !self.is_settled(id, window) {
+                return true;
+            }
+            if let Err(e) = sender.send(()) {
+                warn!("Notifying quiescence subscriber failed ({:?}).", e);
+            }
+            false
+        });
+    }
+
+    /// Queues a `StartIdlePeriod` message behind whatever else is waiting if a
+    /// window has idle callbacks to run.
+    fn queue_idle_period(&self) {
+        if self.idle_period_queued.get() {
+            return;
+        }
+        let context = match self.browsing_context.get() {
+            Some(context) => context,
+            None => return,
+        };
+        if context.iter().any(|context| context.active_window().has_runnable_idle_callbacks()) {
+            let MainThreadScriptChan(ref sender) = self.chan;
+            sender.send(MainThreadScriptMsg::StartIdlePeriod).unwrap();
+            self.idle_period_queued.set(true);
+        }
+    }
+
+    /// Runs the idle callbacks of the windows if the idle period was the only
+    /// message to be handled. Otherwise it is queued again once the other
+    /// messages have been handled.
+    fn handle_start_idle_period(&self, idle: bool) {
+        self.idle_period_queued.set(false);
+        if !idle {
+            return;
+        }
+        if let Some(context) = self.browsing_context.get() {
+            for context in context.iter() {
+                context.active_window().run_idle_callbacks();
+            }
+        }
+    }
+
     fn handle_focus_iframe_msg(&self,
                                parent_pipeline_id: PipelineId,
                                subpage_id: SubpageId) {
@@ -1389,6 +1791,10 @@ impl ScriptThread {
     fn handle_tick_all_animations(&self, id: PipelineId) {
         let context = get_browsing_context(&self.root_browsing_context(), id);
         let document = context.active_document();
+        // The timers that are due already had their tasks queued before this
+        // rendering opportunity, so they run first. A frame ticked by a timer
+        // comes after them anyway.
+        document.window().flush_due_timers();
         document.run_the_animation_frame_callbacks();
     }
 
@@ -1451,9 +1857,16 @@ impl ScriptThread {
         let HistoryTraversalTaskSource(ref history_sender) = self.history_traversal_task_source;
         let FileReadingTaskSource(ref file_sender) = self.file_reading_task_source;
 
-        let (ipc_timer_event_chan, ipc_timer_event_port) = ipc::channel().unwrap();
-        ROUTER.route_ipc_receiver_to_mpsc_sender(ipc_timer_event_port,
-                                                 self.timer_event_chan.clone());
+        // Script of a cross-origin isolated document may observe time more precisely.
+        let header = |name| {
+            metadata.headers.as_ref().and_then(|headers| headers.get_raw(name)).and_then(|values| values.first())
+                            .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+        let (opener_policy, embedder_policy) = (header("cross-origin-opener-policy"),
+                                                header("cross-origin-embedder-policy"));
+        let cross_origin_isolated = is_cross_origin_isolated(&final_url,
+                                                             opener_policy.as_ref().map(|value| &**value),
+                                                             embedder_policy.as_ref().map(|value| &**value));
 
         // Create the window and document objects.
         let window = Window::new(self.js_runtime.clone(),
@@ -1476,11 +1889,12 @@ impl ScriptThread {
                                  self.control_chan.clone(),
                                  self.scheduler_chan.clone(),
                                  self.panic_chan.clone(),
-                                 ipc_timer_event_chan,
+                                 self.timer_event_chan.clone(),
                                  incomplete.layout_chan,
                                  incomplete.pipeline_id,
                                  incomplete.parent_info,
-                                 incomplete.window_size);
+                                 incomplete.window_size,
+                                 TimeResolutionPolicy::for_origin(cross_origin_isolated));
         let frame_element = frame_element.r().map(Castable::upcast);
 
         enum ContextToRemove {
@@ -1628,6 +2042,22 @@ impl ScriptThread {
 
         document.set_https_state(metadata.https_state);
 
+        // https://html.spec.whatwg.org/multipage/#navigating-across-documents
+        // The `Refresh` header is processed like `<meta http-equiv=refresh>`.
+        let refresh = metadata.headers.as_ref().and_then(|headers| headers.get_raw("refresh"));
+        if let Some(value) = refresh.and_then(|values| values.first()) {
+            document.shared_declarative_refresh_steps(&String::from_utf8_lossy(value));
+        }
+
+        // https://w3c.github.io/reporting/#header
+        let endpoints = metadata.headers.as_ref().and_then(|headers| headers.get_raw("reporting-endpoints"));
+        if let Some(value) = endpoints.and_then(|values| values.first()) {
+            window.reporting().set_endpoints(&final_url, &String::from_utf8_lossy(value));
+        }
+
+        document.arm_page_load_watchdog();
+        document.arm_load_delay_watchdog();
+
         let is_xml = match metadata.content_type {
             Some(ContentType(Mime(TopLevel::Application, SubLevel::Ext(ref sub_level), _)))
                 if sub_level.ends_with("+xml") => true,
@@ -1653,6 +2083,15 @@ impl ScriptThread {
         if incomplete.is_frozen {
             window.freeze();
         }
+        if incomplete.is_hidden {
+            document.set_visibility_state(VisibilityState::Hidden);
+        }
+        if incomplete.is_frame_hidden {
+            window.set_frame_hidden(true);
+        }
+        if incomplete.timers_suspended {
+            window.set_timers_suspended_by_embedder(true);
+        }
 
         context_remover.neuter();
 
@@ -1710,6 +2149,11 @@ impl ScriptThread {
             return;
         }
 
+        match event {
+            ResizeEvent(..) => {},
+            _ => self.last_user_input.set(precise_time_ns()),
+        }
+
         match event {
             ResizeEvent(new_size, size_type) => {
                 self.handle_resize_event(pipeline_id, new_size, size_type);
@@ -1890,11 +2334,7 @@ impl ScriptThread {
         // http://dev.w3.org/csswg/cssom-view/#resizing-viewports
         // https://dvcs.w3.org/hg/dom3events/raw-file/tip/html/DOM3-Events.html#event-type-resize
         if size_type == WindowSizeType::Resize {
-            let uievent = UIEvent::new(window.r(),
-                                       DOMString::from("resize"), EventBubbles::DoesNotBubble,
-                                       EventCancelable::NotCancelable, Some(window.r()),
-                                       0i32);
-            uievent.upcast::<Event>().fire(window.upcast());
+            window.queue_viewport_event(ViewportEvent::Resize);
         }
     }
 
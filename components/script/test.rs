@@ -0,0 +1,13 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! What the unit tests and fuzzers need from modules that are otherwise
+//! private to the script crate.
+
+pub mod timers {
+    pub use timers::{InputMonitor, IntervalCatchUp, IsInterval, OneshotTimerHandle, OneshotTimers};
+    pub use timers::{TimerLogRecord, TimerLogSink, chain_async_stack};
+    pub use timers::{ScheduledCallback, SuspensionReason, TimerBudget, TimerEventRecord, TimerSchedulerChan};
+    pub use timers::TimerClock;
+}
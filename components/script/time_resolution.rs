@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! How precisely script may observe time.
+//!
+//! `performance.now()` and the timers coarsen the clock the same way, so that
+//! neither can be used to measure time more precisely than the other allows.
+//! See https://w3c.github.io/hr-time/#clock-resolution.
+
+use url::Url;
+use util::prefs;
+
+/// The resolution of an origin that is cross-origin isolated, in ns.
+const ISOLATED_RESOLUTION_NS: u64 = 5 * 1000;
+
+/// The resolution of an origin that is not cross-origin isolated, in ns.
+const DEFAULT_RESOLUTION_NS: u64 = 100 * 1000;
+
+/// The resolution used when resisting fingerprinting, in ns, unless the
+/// `privacy.reduce_timer_precision.microseconds` pref says otherwise.
+const REDUCED_RESOLUTION_NS: u64 = 1000 * 1000;
+
+/// How precisely a global may observe time.
+#[derive(JSTraceable, HeapSizeOf, Clone, Copy, Debug, PartialEq)]
+pub struct TimeResolutionPolicy {
+    /// What times are rounded down to a multiple of, in ns.
+    resolution_ns: u64,
+}
+
+impl TimeResolutionPolicy {
+    pub fn new(resolution_ns: u64) -> TimeResolutionPolicy {
+        assert!(resolution_ns > 0);
+        TimeResolutionPolicy {
+            resolution_ns: resolution_ns,
+        }
+    }
+
+    /// A policy that doesn't coarsen time at all.
+    pub fn precise() -> TimeResolutionPolicy {
+        TimeResolutionPolicy::new(1)
+    }
+
+    /// The policy of an origin, which may observe time more precisely if it
+    /// is cross-origin isolated, unless the `privacy.resist_fingerprinting`
+    /// pref coarsens time for every origin.
+    pub fn for_origin(cross_origin_isolated: bool) -> TimeResolutionPolicy {
+        if prefs::get_pref("privacy.resist_fingerprinting").as_boolean().unwrap_or(false) {
+            let resolution_ns = match prefs::get_pref("privacy.reduce_timer_precision.microseconds").as_i64() {
+                Some(us) if us > 0 => us as u64 * 1000,
+                _ => REDUCED_RESOLUTION_NS,
+            };
+            return TimeResolutionPolicy::new(resolution_ns);
+        }
+        if cross_origin_isolated {
+            TimeResolutionPolicy::new(ISOLATED_RESOLUTION_NS)
+        } else {
+            TimeResolutionPolicy::new(DEFAULT_RESOLUTION_NS)
+        }
+    }
+
+    pub fn resolution_ns(&self) -> u64 {
+        self.resolution_ns
+    }
+
+    /// Rounds a time in ns down to the resolution.
+    pub fn coarsen_ns(&self, ns: u64) -> u64 {
+        ns - ns % self.resolution_ns
+    }
+}
+
+/// Whether a document loaded from `url` is cross-origin isolated, given the
+/// values of the `Cross-Origin-Opener-Policy` and
+/// `Cross-Origin-Embedder-Policy` headers of its response. Only a secure
+/// context that opts in with both headers is.
+/// https://html.spec.whatwg.org/multipage/#concept-settings-object-cross-origin-isolated-capability
+pub fn is_cross_origin_isolated(url: &Url, opener_policy: Option<&str>, embedder_policy: Option<&str>) -> bool {
+    // The parameters of a policy, such as where to report to, don't matter.
+    let policy = |value: Option<&str>| value.and_then(|value| value.split(';').next()).map(str::trim);
+    let secure = match url.scheme() {
+        "https" | "wss" | "file" => true,
+        _ => match url.host_str() {
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]") => true,
+            _ => false,
+        },
+    };
+    secure && policy(opener_policy) == Some("same-origin") && policy(embedder_policy) == Some("require-corp")
+}
@@ -2,46 +2,528 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use dom::bindings::callback::ExceptionHandling::Report;
+use backtrace::Backtrace;
+use devtools_traits::{ConsoleMessage, LogLevel, ScriptToDevtoolsControlMsg, StartedTimelineMarker};
+use devtools_traits::{TimelineMarker, TimelineMarkerType, TimerInfo, TimerKind, TimerMarkerDetail};
+use dom::bindings::callback::ExceptionHandling::{Report, Rethrow};
 use dom::bindings::cell::DOMRefCell;
+use dom::abortsignal::AbortSignalTimeout;
 use dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use dom::bindings::conversions::jsstring_to_str;
+use dom::bindings::error::report_pending_exception;
 use dom::bindings::global::GlobalRef;
+use dom::bindings::refcounted::Trusted;
 use dom::bindings::reflector::Reflectable;
-use dom::window::ScriptHelpers;
-use dom::xmlhttprequest::XHRTimeoutCallback;
+use dom::document::{LoadDelayWatchdog, PageLoadWatchdog, RefreshRedirectDue};
+use dom::eventsource::EventSourceTimeoutCallback;
+use dom::htmlmediaelement::MediaTimerCallback;
+use dom::idledetector::IdleDetectorPoll;
+use dom::websocket::WebSocketTimeoutCallback;
+use dom::window::{IdleCallbackTimeout, IframeVisibilityCheck, ScriptHelpers, Window};
+use dom::workerglobalscope::WorkerGlobalScope;
+use dom::xmlhttprequest::{XHRStallCallback, XHRTimeoutCallback};
 use euclid::length::Length;
+use frame_scheduler::FrameTick;
 use heapsize::HeapSizeOf;
-use ipc_channel::ipc::IpcSender;
-use js::jsapi::{HandleValue, Heap, RootedValue};
+use ipc_channel::ipc::{self, IpcSender};
+use js::jsapi::{BuildStackString, CaptureCurrentStack, HandleValue, Heap, JSContext};
+use js::jsapi::{RootedObject, RootedString, RootedValue};
 use js::jsval::{JSVal, UndefinedValue};
-use script_traits::{MsDuration, precise_time_ms};
-use script_traits::{TimerEvent, TimerEventId, TimerEventRequest, TimerSource};
-use std::cell::Cell;
+use msg::constellation_msg::{PipelineId, PipelineIndex, PipelineNamespaceId};
+use profile_traits::time::{ProfilerCategory, profile};
+use rand::{random, Rng, SeedableRng, StdRng};
+use reporting::ReportDelivery;
+use rustc_serialize::json;
+use script_runtime::{CommonScriptMsg, ScriptThreadEventCategory};
+use script_thread::{Runnable, ScriptThread};
+use script_traits::{MsDuration, NsDuration, PipelineTimerStats, precise_time_ns};
+use script_traits::{TimerEvent, TimerEventId, TimerEventRequest, TimerPriority, TimerSchedulerMsg, TimerSource};
+use std::cell::{Cell, RefCell};
 use std::cmp::{self, Ord, Ordering};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::ptr;
 use std::rc::Rc;
+use std::usize;
+use time_resolution::TimeResolutionPolicy;
+use util::opts;
+use util::prefs;
 use util::str::DOMString;
+use viewport_events::ViewportEventsDue;
 
 #[derive(JSTraceable, PartialEq, Eq, Copy, Clone, HeapSizeOf, Hash, PartialOrd, Ord, Debug)]
 pub struct OneshotTimerHandle(i32);
 
+/// The granularity, in ms, of throttled timers in hidden documents.
+const HIDDEN_TIMER_ALIGNMENT: u64 = 1000;
+/// The granularity, in ms, of throttled timers in documents that have been
+/// hidden for a while.
+const INTENSIVE_TIMER_ALIGNMENT: u64 = 60 * 1000;
+/// How much budget, in ns, a hidden document regains per ms: 1% of the time.
+const HIDDEN_TIMER_BUDGET_RATE: i64 = 10 * 1000;
+/// The most budget, in ns, a document can save up for when it is hidden.
+const HIDDEN_TIMER_MAX_BUDGET: i64 = 1000 * 1000 * 1000;
+/// The shortest period, in ms, a repeating timer runs with.
+const MIN_REPEAT_PERIOD_MS: u64 = 1;
+/// The longest, in ms, the chaos mode delays a timer event by.
+const MAX_CHAOS_DELAY: u64 = 4;
+/// How many frames of the stack that set a timer are kept, counting those of
+/// the timers that set the timer.
+const MAX_INSTALLED_STACK_FRAMES: usize = 32;
+/// How long, in ms, a timer callback may run before it counts as slow.
+const SLOW_TIMER_CALLBACK_MS: u64 = 50;
+/// How many slow runs of the same timer make it warn on the console.
+const SLOW_RUNS_BEFORE_WARNING: u32 = 3;
+/// How many timers of a global warn about being slow, before the rest are ignored.
+const MAX_SLOW_TIMER_WARNINGS: u32 = 10;
+
+no_jsmanaged_fields!(StdRng);
+
+/// The channel `OneshotTimers` uses to talk to the timer scheduler.
+pub trait TimerSchedulerChan {
+    /// Send a message to the timer scheduler.
+    fn send(&self, msg: TimerSchedulerMsg) -> Result<(), ()>;
+}
+
+impl TimerSchedulerChan for IpcSender<TimerSchedulerMsg> {
+    fn send(&self, msg: TimerSchedulerMsg) -> Result<(), ()> {
+        IpcSender::send(self, msg).map_err(|_| ())
+    }
+}
+
+/// Where `OneshotTimers` logs the lifecycle of its timers to.
+pub trait TimerLogSink {
+    fn log(&self, record: TimerLogRecord);
+}
+
+/// Appends the records to a file, one JSON object per line, for `--timer-log`.
+/// Every pipeline appends to the same file, each line with a single write.
+pub struct TimerLogFile(RefCell<File>);
+
+impl TimerLogFile {
+    pub fn open(path: &Path) -> Result<TimerLogFile, String> {
+        let file = try!(OpenOptions::new().create(true).append(true).open(path).map_err(|e| e.to_string()));
+        Ok(TimerLogFile(RefCell::new(file)))
+    }
+}
+
+impl TimerLogSink for TimerLogFile {
+    fn log(&self, record: TimerLogRecord) {
+        let mut line = json::encode(&record).unwrap();
+        line.push('\n');
+        if let Err(e) = self.0.borrow_mut().write_all(line.as_bytes()) {
+            warn!("Writing to the timer log failed ({}).", e);
+        }
+    }
+}
+
+/// Tells `OneshotTimers` whether input is waiting to be handled, in which
+/// case the due timers let it go first.
+pub trait InputMonitor {
+    fn input_pending(&self) -> bool;
+}
+
+/// Watches the input events for the windows of the script thread.
+pub struct ScriptThreadInputMonitor;
+
+impl InputMonitor for ScriptThreadInputMonitor {
+    fn input_pending(&self) -> bool {
+        ScriptThread::is_input_pending(false)
+    }
+}
+
+/// The clock `OneshotTimers` tells the time by.
+pub trait TimerClock {
+    /// The time since an unspecified epoch.
+    fn now(&self) -> NsDuration;
+}
+
+/// The monotonic clock of the system.
+pub struct PreciseTimerClock;
+
+impl TimerClock for PreciseTimerClock {
+    fn now(&self) -> NsDuration {
+        precise_time_ns()
+    }
+}
+
+/// What `OneshotTimers` needs to know about the callbacks it keeps.
+pub trait ScheduledCallback: Sized {
+    fn priority(&self) -> TimerPriority {
+        TimerPriority::Normal
+    }
+
+    /// How long to wait between runs, if the callback repeats.
+    fn repeat_period(&self) -> Option<MsDuration> {
+        None
+    }
+
+    /// The callback for the next run, if the callback repeats.
+    fn next_occurrence(&self) -> Option<Self> {
+        None
+    }
+
+    /// Whether the callback may be delayed while its document is hidden.
+    fn throttleable(&self) -> bool {
+        false
+    }
+
+    /// Whether the callback has to fire on time even while the timers are
+    /// suspended or throttled.
+    fn exempt_from_suspension(&self) -> bool {
+        false
+    }
+
+    /// Whether the callback runs page script, rather than being an internal
+    /// callback such as a network timeout.
+    fn is_script_timer(&self) -> bool {
+        false
+    }
+
+    /// How the callback catches up on missed runs, if it repeats and doesn't
+    /// go by the default policy of its timers.
+    fn catch_up(&self) -> Option<IntervalCatchUp> {
+        None
+    }
+}
+
+/// Why the timers of a global are suspended. Suspensions for different
+/// reasons, or for the same one, may overlap; the timers stay suspended until
+/// every suspension has been lifted again.
+#[derive(JSTraceable, HeapSizeOf, RustcEncodable, RustcDecodable, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SuspensionReason {
+    /// The document was navigated away from and is kept in the session history.
+    /// Its timers are taken off the scheduler until it is shown again.
+    Frozen,
+    /// A modal dialog such as `alert()` is being shown.
+    ModalDialog,
+    /// Script execution is paused in a debugger.
+    Debugger,
+    /// The embedder asked for the timers to stop, e.g. because the document
+    /// is shown in a webview that is off-screen.
+    Embedder,
+    /// A synchronous XMLHttpRequest blocks the event loop. The timers that
+    /// come due meanwhile are held until it returns and then run right away,
+    /// since the time it took passed for the page as well.
+    SyncXhr,
+}
+
+impl SuspensionReason {
+    /// Whether internal callbacks such as network timeouts are suspended too,
+    /// rather than only the timers that run page script.
+    pub fn suspends_internal_callbacks(&self) -> bool {
+        match *self {
+            SuspensionReason::Frozen => true,
+            // The page may still be loading, and its loads should time out as usual.
+            SuspensionReason::ModalDialog |
+            SuspensionReason::Debugger |
+            SuspensionReason::Embedder |
+            SuspensionReason::SyncXhr => false,
+        }
+    }
+
+    /// Whether the time spent suspended is left out of the time of the timers,
+    /// rather than the timers only being held meanwhile.
+    pub fn stops_the_clock(&self) -> bool {
+        match *self {
+            SuspensionReason::Frozen |
+            SuspensionReason::ModalDialog |
+            SuspensionReason::Debugger |
+            SuspensionReason::Embedder => true,
+            SuspensionReason::SyncXhr => false,
+        }
+    }
+}
+
+/// How many calls to `suspend` and `resume` are kept with
+/// `-Z trace-timer-suspensions`.
+const MAX_TRACED_SUSPENSION_CALLS: usize = 32;
+
+/// A call to `suspend` or `resume`, as recorded with `-Z trace-timer-suspensions`.
+#[derive(JSTraceable, HeapSizeOf)]
+struct SuspensionCall {
+    reason: SuspensionReason,
+    /// Whether the call was to `suspend` rather than `resume`.
+    suspend: bool,
+    /// A backtrace of the caller.
+    caller: String,
+}
+
+/// A timer event as it was handled, recorded so that it can be replayed.
+#[derive(JSTraceable, HeapSizeOf, RustcEncodable, RustcDecodable, Clone, PartialEq, Debug)]
+pub struct TimerEventRecord {
+    /// The time of the timers when the event was handled, in ms since the
+    /// recording started.
+    pub time: u64,
+    /// The handles of the timers that fired, in the order they did.
+    pub fired: Vec<i32>,
+}
+
+/// A step in the life of the timers, as logged by a `TimerLogSink`.
+#[derive(RustcEncodable, Clone, PartialEq, Debug)]
+pub struct TimerLogRecord {
+    /// The time of the timers, in ms.
+    pub time: u64,
+    /// The pipeline of the window, or of the window that owns the worker.
+    pub pipeline: String,
+    /// What happened: "schedule", "fire", "cancel", "suspend", "resume" or "throttle".
+    pub event: &'static str,
+    /// The timer it happened to, if it happened to one.
+    pub handle: Option<i32>,
+    /// How long a scheduled timer waits, or how late a fired timer runs, in ms.
+    pub delay: Option<u64>,
+    /// Whether the timer runs page script or is internal, why the timers are
+    /// suspended or resumed, or why they are throttled.
+    pub detail: Option<String>,
+}
+
+/// A timer as it is kept in a `TimerStateSnapshot`.
+#[derive(RustcEncodable, RustcDecodable, Clone, PartialEq, Debug)]
+pub struct TimerSnapshot {
+    /// The handle of the timer.
+    pub handle: i32,
+    /// Whether the timer was scheduled by a window rather than by a worker.
+    pub from_window: bool,
+    /// When the timer is due, in ms, or how long it still has to wait if it is frozen.
+    pub due: u64,
+    /// Whether the timer keeps running while the others are suspended or throttled.
+    pub exempt: bool,
+}
+
+/// The state of `OneshotTimers` without the callbacks, which have to be bound
+/// anew when it is restored.
+#[derive(RustcEncodable, RustcDecodable, Clone, PartialEq, Debug)]
+pub struct TimerStateSnapshot {
+    /// The time of the timers when the snapshot was taken, in ms.
+    pub time: u64,
+    pub next_timer_handle: i32,
+    /// The scheduled timers, the next one due last.
+    pub timers: Vec<TimerSnapshot>,
+    /// The timers taken off the scheduler, if the global was in the back/forward cache.
+    pub frozen: Option<Vec<TimerSnapshot>>,
+    pub suspension_offset: u64,
+    pub suspended_since: Option<u64>,
+    pub suspensions: Vec<(SuspensionReason, u32)>,
+    pub hidden_since: Option<u64>,
+    pub frame_hidden_since: Option<u64>,
+}
+
+fn snapshot_timer(handle: OneshotTimerHandle, source: TimerSource, due: MsDuration, exempt: bool) -> TimerSnapshot {
+    let OneshotTimerHandle(handle) = handle;
+    TimerSnapshot {
+        handle: handle,
+        from_window: match source {
+            TimerSource::FromWindow(_) => true,
+            TimerSource::FromWorker => false,
+        },
+        due: due.get(),
+        exempt: exempt,
+    }
+}
+
+/// Whether the timer events are recorded or replayed.
+#[derive(JSTraceable, HeapSizeOf)]
+enum TimerEventLog {
+    Off,
+    Recording(Vec<TimerEventRecord>),
+    /// The events still to be replayed, the next one last.
+    Replaying(Vec<TimerEventRecord>),
+}
+
+/// The RNG of the chaos mode for a pipeline. Each pipeline gets different
+/// choices, but the same ones for the same seed.
+fn chaos_rng(seed: usize, pipeline_id: PipelineId) -> StdRng {
+    let PipelineNamespaceId(namespace_id) = pipeline_id.namespace_id;
+    let PipelineIndex(index) = pipeline_id.index;
+    StdRng::from_seed(&[seed, namespace_id as usize, index as usize])
+}
+
+/// Where the timer events of a pipeline are recorded to in the directory `dir`.
+pub fn timer_event_log_path(dir: &str, pipeline_id: PipelineId) -> PathBuf {
+    let PipelineNamespaceId(namespace_id) = pipeline_id.namespace_id;
+    let PipelineIndex(index) = pipeline_id.index;
+    Path::new(dir).join(format!("timer-events-{}-{}.json", namespace_id, index))
+}
+
+pub fn read_timer_event_log(path: &Path) -> Result<Vec<TimerEventRecord>, String> {
+    let mut log = String::new();
+    try!(File::open(path).and_then(|mut file| file.read_to_string(&mut log)).map_err(|e| e.to_string()));
+    json::decode(&log).map_err(|e| e.to_string())
+}
+
+pub fn write_timer_event_log(path: &Path, records: &[TimerEventRecord]) -> Result<(), String> {
+    let log = try!(json::encode(&records).map_err(|e| e.to_string()));
+    File::create(path).and_then(|mut file| file.write_all(log.as_bytes())).map_err(|e| e.to_string())
+}
+
+/// What a repeating timer does about the runs it missed while it was
+/// suspended or throttled.
+#[derive(JSTraceable, HeapSizeOf, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum IntervalCatchUp {
+    /// The missed runs are dropped, and the timer runs again a period after
+    /// it last ran.
+    Restart,
+    /// The missed runs are dropped, and the timer keeps to its original cadence.
+    Skip,
+    /// One missed run is made up for right away, after which the timer
+    /// repeats with its period from then on.
+    Once,
+}
+
+impl IntervalCatchUp {
+    fn from_pref() -> IntervalCatchUp {
+        match prefs::get_pref("dom.timers.interval_catch_up").as_string() {
+            Some("skip") => IntervalCatchUp::Skip,
+            Some("once") => IntervalCatchUp::Once,
+            _ => IntervalCatchUp::Restart,
+        }
+    }
+
+    /// When a timer that repeats every `period` and was due at `due` runs
+    /// next, given that it is run at `now`.
+    pub fn next_run(&self, due: MsDuration, period: MsDuration, now: MsDuration) -> MsDuration {
+        let next_due = due + period;
+        if next_due > now {
+            return next_due;
+        }
+        match *self {
+            IntervalCatchUp::Restart => now + period,
+            IntervalCatchUp::Skip if period.get() > 0 => {
+                let missed = (now.get() - due.get()) / period.get();
+                due + Length::new((missed + 1) * period.get())
+            },
+            // A timer without a period has no cadence to keep.
+            IntervalCatchUp::Skip | IntervalCatchUp::Once => now,
+        }
+    }
+}
+
+/// The time that throttleable timers of a hidden document may spend running.
+/// Every run is charged against the budget, which slowly regenerates; while it
+/// is negative, the timers are deferred until it has recovered.
+#[derive(JSTraceable, HeapSizeOf, Copy, Clone, Debug)]
+pub struct TimerBudget {
+    /// The budget in ns, as of `updated`.
+    level: i64,
+    /// When `level` was last brought up to date.
+    updated: MsDuration,
+}
+
+impl TimerBudget {
+    pub fn new() -> TimerBudget {
+        TimerBudget {
+            level: HIDDEN_TIMER_MAX_BUDGET,
+            updated: Length::new(0),
+        }
+    }
+
+    /// The budget in ns at `now`.
+    pub fn level(&self, now: MsDuration) -> i64 {
+        let elapsed = now.get().saturating_sub(self.updated.get()) as i64;
+        cmp::min(HIDDEN_TIMER_MAX_BUDGET, self.level + elapsed * HIDDEN_TIMER_BUDGET_RATE)
+    }
+
+    /// Takes the time a timer spent running at `now` from the budget.
+    pub fn charge(&mut self, now: MsDuration, cost: NsDuration) {
+        self.level = self.level(now) - cost.get() as i64;
+        self.updated = cmp::max(self.updated, now);
+    }
+
+    /// The earliest time from `now` on at which the budget isn't negative.
+    pub fn available_at(&self, now: MsDuration) -> MsDuration {
+        let level = self.level(now);
+        if level >= 0 {
+            return now;
+        }
+        let wait = (-level + HIDDEN_TIMER_BUDGET_RATE - 1) / HIDDEN_TIMER_BUDGET_RATE;
+        now + Length::new(wait as u64)
+    }
+}
+
 #[derive(JSTraceable, HeapSizeOf)]
 #[privatize]
-pub struct OneshotTimers {
+pub struct OneshotTimers<C = OneshotTimerCallback> {
     js_timers: JsTimers,
+    /// The pipeline of the window, or of the window that owns the worker.
+    pipeline_id: PipelineId,
     #[ignore_heap_size_of = "Defined in std"]
     timer_event_chan: IpcSender<TimerEvent>,
-    #[ignore_heap_size_of = "Defined in std"]
-    scheduler_chan: IpcSender<TimerEventRequest>,
+    #[ignore_heap_size_of = "Can't measure trait objects"]
+    scheduler_chan: Box<TimerSchedulerChan>,
+    #[ignore_heap_size_of = "Can't measure trait objects"]
+    clock: Box<TimerClock>,
+    /// Where the lifecycle of the timers is logged to, if anywhere.
+    #[ignore_heap_size_of = "Can't measure trait objects"]
+    log_sink: DOMRefCell<Option<Box<TimerLogSink>>>,
+    /// What tells whether input is pending, if anything does.
+    #[ignore_heap_size_of = "Can't measure trait objects"]
+    input_monitor: DOMRefCell<Option<Box<InputMonitor>>>,
+    /// How precisely the timers may observe the clock.
+    time_resolution: Cell<TimeResolutionPolicy>,
+    /// The latest time the clock told, in ns.
+    latest_time: Cell<NsDuration>,
     next_timer_handle: Cell<OneshotTimerHandle>,
-    timers: DOMRefCell<Vec<OneshotTimer>>,
+    timers: DOMRefCell<Vec<OneshotTimer<C>>>,
     suspended_since: Cell<Option<MsDuration>>,
+    /// Whether a suspension that stops the clock was in effect since
+    /// `suspended_since`. If not, the time spent suspended counts once the
+    /// timers resume.
+    clock_stopped: Cell<bool>,
+    /// How many suspensions for each reason are in effect.
+    suspensions: DOMRefCell<HashMap<SuspensionReason, u32>>,
+    /// Whether the timer events are recorded or replayed.
+    event_log: DOMRefCell<TimerEventLog>,
+    /// When the timer events started to be recorded or replayed, which the
+    /// times of the records are relative to.
+    event_log_start: Cell<MsDuration>,
+    /// The latest calls to `suspend` and `resume`, if they are traced.
+    suspension_calls: DOMRefCell<Option<Vec<SuspensionCall>>>,
+    /// In chaos mode, the RNG that shuffles timers due at the same time and
+    /// delays timer events.
+    #[ignore_heap_size_of = "Defined in rand"]
+    chaos: DOMRefCell<Option<StdRng>>,
+    /// The timers taken off the scheduler while the global is in the
+    /// back/forward cache, if it is.
+    frozen: DOMRefCell<Option<Vec<FrozenTimer<C>>>>,
+    /// Timers due by then were overdue when the timers last resumed or stopped
+    /// being throttled, until they have all run.
+    backlog_until: Cell<Option<MsDuration>>,
+    /// How many timers of the backlog run per event, if it is spread out.
+    overdue_batch_size: Cell<Option<usize>>,
+    /// How repeating timers catch up on missed runs, unless they say otherwise.
+    interval_catch_up: Cell<IntervalCatchUp>,
     /// Initially 0, increased whenever the associated document is reactivated
     /// by the amount of ms the document was inactive. The current time can be
     /// offset back by this amount for a coherent time across document
     /// activations.
     suspension_offset: Cell<MsDuration>,
+    /// Whether the scheduler skips ahead to due timers rather than waiting for them.
+    virtual_time: bool,
+    /// How far the clock has been moved ahead to keep up with a scheduler that
+    /// skipped ahead, in virtual time mode.
+    skipped: Cell<MsDuration>,
+    /// Since when the document is hidden, if it is. Throttleable timers of
+    /// hidden documents only fire on whole seconds, and on whole minutes once
+    /// the document has been hidden for `intensive_throttling_delay`.
+    hidden_since: Cell<Option<MsDuration>>,
+    /// Since when the document is in a cross-origin frame that can't be seen,
+    /// if it is. Its timers are throttled as if the document was hidden.
+    frame_hidden_since: Cell<Option<MsDuration>>,
+    /// How long a document has to be hidden before its timers are throttled to
+    /// one wakeup per minute.
+    intensive_throttling_delay: MsDuration,
+    /// Whether the document does something that must not be throttled while
+    /// it is hidden, such as playing audio.
+    throttling_exempt: Cell<bool>,
+    /// The time throttleable timers may spend running while the document is hidden.
+    budget: Cell<TimerBudget>,
+    /// How many times longer than they ask for the timers wait, to show how a
+    /// page would do on a slow device.
+    delay_multiplier: Cell<u64>,
     /// Calls to `fire_timer` with a different argument than this get ignored.
     /// They were previously scheduled and got invalidated when
     ///  - timers were suspended,
@@ -49,15 +531,70 @@ pub struct OneshotTimers {
     ///  - a timer was added with an earlier callback time. In this case the
     ///    original timer is rescheduled when it is the next one to get called.
     expected_event_id: Cell<TimerEventId>,
+    /// The timers `fire_timer_with` took off the queue, while it runs them.
+    /// Only kept track of when the invariants of the timers are checked.
+    firing: DOMRefCell<Vec<OneshotTimerHandle>>,
+    /// The repeating timer whose callback is running, which is rearmed once
+    /// the callback returns, and whether the callback unscheduled it.
+    running_repeat: Cell<Option<(OneshotTimerHandle, bool)>>,
+    /// Whether a timer callback is running, during which the due timers
+    /// aren't flushed, as they would run in the middle of it.
+    in_callback: Cell<bool>,
+    /// The timers a callback unscheduled while it ran. Those that were due
+    /// in the same event as it don't run either.
+    unscheduled_while_firing: DOMRefCell<Vec<OneshotTimerHandle>>,
+    /// The event last requested from the scheduler, until it is delivered.
+    requested_event: Cell<Option<(TimerSource, TimerEventId)>>,
+    /// If the requested event repeats, the timer it is for and when that timer
+    /// was last due. The scheduler delivers the event again one period later.
+    requested_repeat: Cell<Option<(OneshotTimerHandle, MsDuration)>>,
+    /// When the expected event is due.
+    requested_deadline: Cell<MsDuration>,
 }
 
 #[derive(JSTraceable, HeapSizeOf)]
 #[privatize]
-struct OneshotTimer {
+struct OneshotTimer<C> {
     handle: OneshotTimerHandle,
     source: TimerSource,
-    callback: OneshotTimerCallback,
+    callback: C,
     scheduled_for: MsDuration,
+    /// Whether the timer keeps running while the others are suspended or
+    /// throttled. While a timer runs during a suspension, its deadline is
+    /// measured on a clock that isn't stopped by suspensions.
+    exempt: bool,
+}
+
+impl<C: ScheduledCallback> OneshotTimer<C> {
+    fn throttleable(&self) -> bool {
+        self.callback.throttleable() && !self.exempt
+    }
+}
+
+/// A timer of a global in the back/forward cache. Only how long it still had
+/// to wait is kept, so that its deadline can be rebuilt on whatever the clock
+/// says when the global is restored.
+#[derive(JSTraceable, HeapSizeOf)]
+#[privatize]
+struct FrozenTimer<C> {
+    handle: OneshotTimerHandle,
+    source: TimerSource,
+    callback: C,
+    remaining: MsDuration,
+    exempt: bool,
+}
+
+/// Keeps the timers suspended for as long as it lives, even if the code that
+/// suspended them returns early.
+pub struct ScopedSuspension<'a, C: ScheduledCallback + 'a> {
+    timers: &'a OneshotTimers<C>,
+    reason: SuspensionReason,
+}
+
+impl<'a, C: ScheduledCallback> Drop for ScopedSuspension<'a, C> {
+    fn drop(&mut self) {
+        self.timers.resume(self.reason);
+    }
 }
 
 // This enum is required to work around the fact that trait objects do not support generic methods.
@@ -66,20 +603,112 @@ struct OneshotTimer {
 #[derive(JSTraceable, HeapSizeOf)]
 pub enum OneshotTimerCallback {
     XhrTimeout(XHRTimeoutCallback),
+    XhrStall(XHRStallCallback),
+    EventSourceTimeout(EventSourceTimeoutCallback),
+    WebSocketTimeout(WebSocketTimeoutCallback),
+    MediaTimer(MediaTimerCallback),
+    RefreshRedirectDue(RefreshRedirectDue),
+    PageLoadWatchdog(PageLoadWatchdog),
+    LoadDelayWatchdog(LoadDelayWatchdog),
+    IdleCallbackTimeout(IdleCallbackTimeout),
+    ViewportEvents(ViewportEventsDue),
+    IframeVisibilityCheck(IframeVisibilityCheck),
+    FrameTick(FrameTick),
+    IdleDetectorPoll(IdleDetectorPoll),
+    ReportDelivery(ReportDelivery),
+    AbortSignalTimeout(AbortSignalTimeout),
     JsTimer(JsTimerTask),
+    /// A callback queued with `setImmediate`, held back while the timers are suspended.
+    Immediate(i32),
+}
+
+impl ScheduledCallback for OneshotTimerCallback {
+    fn priority(&self) -> TimerPriority {
+        match *self {
+            // Media events keep playback controls in sync with what is being played.
+            OneshotTimerCallback::MediaTimer(_) => TimerPriority::UserBlocking,
+            _ => TimerPriority::Normal,
+        }
+    }
+
+    fn repeat_period(&self) -> Option<MsDuration> {
+        match *self {
+            OneshotTimerCallback::JsTimer(ref task) => task.repeat_period(),
+            OneshotTimerCallback::IdleDetectorPoll(ref callback) => Some(callback.period()),
+            _ => None,
+        }
+    }
+
+    fn next_occurrence(&self) -> Option<OneshotTimerCallback> {
+        match *self {
+            OneshotTimerCallback::JsTimer(ref task) => task.next_occurrence().map(OneshotTimerCallback::JsTimer),
+            OneshotTimerCallback::IdleDetectorPoll(ref callback) =>
+                Some(OneshotTimerCallback::IdleDetectorPoll(callback.clone())),
+            _ => None,
+        }
+    }
+
+    fn throttleable(&self) -> bool {
+        // Internal timeouts such as XHR's keep their deadlines. Idle detectors
+        // only poll, reports are sent in batches anyway and a stalled response
+        // doesn't need to be noticed to the ms, so they may be coalesced with
+        // the timers of the page.
+        match *self {
+            OneshotTimerCallback::JsTimer(_) |
+            OneshotTimerCallback::XhrStall(_) |
+            OneshotTimerCallback::IdleDetectorPoll(_) |
+            OneshotTimerCallback::ReportDelivery(_) => true,
+            _ => false,
+        }
+    }
+
+    fn exempt_from_suspension(&self) -> bool {
+        // The watchdog has to report a hung page load even if the page is frozen.
+        match *self {
+            OneshotTimerCallback::PageLoadWatchdog(_) => true,
+            _ => false,
+        }
+    }
+
+    fn is_script_timer(&self) -> bool {
+        // Idle callbacks that timed out and signals that abort run page
+        // script, just as its timers do.
+        match *self {
+            OneshotTimerCallback::JsTimer(_) |
+            OneshotTimerCallback::Immediate(_) |
+            OneshotTimerCallback::AbortSignalTimeout(_) |
+            OneshotTimerCallback::IdleCallbackTimeout(_) => true,
+            _ => false,
+        }
+    }
 }
 
 impl OneshotTimerCallback {
     fn invoke<T: Reflectable>(self, this: &T, js_timers: &JsTimers) {
         match self {
             OneshotTimerCallback::XhrTimeout(callback) => callback.invoke(),
+            OneshotTimerCallback::XhrStall(callback) => callback.invoke(),
+            OneshotTimerCallback::EventSourceTimeout(callback) => callback.invoke(),
+            OneshotTimerCallback::WebSocketTimeout(callback) => callback.invoke(),
+            OneshotTimerCallback::MediaTimer(callback) => callback.invoke(),
+            OneshotTimerCallback::RefreshRedirectDue(callback) => callback.invoke(),
+            OneshotTimerCallback::PageLoadWatchdog(callback) => callback.invoke(),
+            OneshotTimerCallback::LoadDelayWatchdog(callback) => callback.invoke(),
+            OneshotTimerCallback::IdleCallbackTimeout(callback) => callback.invoke(),
+            OneshotTimerCallback::ViewportEvents(callback) => callback.invoke(),
+            OneshotTimerCallback::IframeVisibilityCheck(callback) => callback.invoke(),
+            OneshotTimerCallback::FrameTick(callback) => callback.invoke(),
+            OneshotTimerCallback::IdleDetectorPoll(callback) => callback.invoke(),
+            OneshotTimerCallback::ReportDelivery(callback) => callback.invoke(),
+            OneshotTimerCallback::AbortSignalTimeout(callback) => callback.invoke(),
             OneshotTimerCallback::JsTimer(task) => task.invoke(this, js_timers),
+            OneshotTimerCallback::Immediate(handle) => js_timers.run_immediate(this, handle),
         }
     }
 }
 
-impl Ord for OneshotTimer {
-    fn cmp(&self, other: &OneshotTimer) -> Ordering {
+impl<C> Ord for OneshotTimer<C> {
+    fn cmp(&self, other: &OneshotTimer<C>) -> Ordering {
         match self.scheduled_for.cmp(&other.scheduled_for).reverse() {
             Ordering::Equal => self.handle.cmp(&other.handle).reverse(),
             res => res
@@ -87,164 +716,1357 @@ impl Ord for OneshotTimer {
     }
 }
 
-impl PartialOrd for OneshotTimer {
-    fn partial_cmp(&self, other: &OneshotTimer) -> Option<Ordering> {
+impl<C> PartialOrd for OneshotTimer<C> {
+    fn partial_cmp(&self, other: &OneshotTimer<C>) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for OneshotTimer {}
-impl PartialEq for OneshotTimer {
-    fn eq(&self, other: &OneshotTimer) -> bool {
-        self as *const OneshotTimer == other as *const OneshotTimer
+impl<C> Eq for OneshotTimer<C> {}
+impl<C> PartialEq for OneshotTimer<C> {
+    fn eq(&self, other: &OneshotTimer<C>) -> bool {
+        self as *const OneshotTimer<C> == other as *const OneshotTimer<C>
     }
 }
 
 impl OneshotTimers {
-    pub fn new(timer_event_chan: IpcSender<TimerEvent>,
-               scheduler_chan: IpcSender<TimerEventRequest>)
-               -> OneshotTimers {
+    pub fn fire_timer<T: Reflectable>(&self, id: TimerEventId, this: &T) {
+        *self.js_timers.last_run.borrow_mut() = None;
+        let time_profiler_chan = this.global().r().time_profiler_chan().clone();
+        profile(ProfilerCategory::ScriptTimerFire, None, time_profiler_chan, || {
+            self.fire_timer_with(id, |callback| callback.invoke(this, &self.js_timers))
+        });
+    }
+
+    pub fn set_immediate(&self, global: GlobalRef, function: Rc<Function>, arguments: Vec<HandleValue>) -> i32 {
+        self.js_timers.set_immediate(global, function, arguments)
+    }
+
+    pub fn clear_immediate(&self, handle: i32) {
+        self.js_timers.clear_immediate(handle)
+    }
+
+    /// Runs the callback queued with `setImmediate` under `handle`, or holds
+    /// it until the timers resume if they are suspended.
+    pub fn run_immediate<T: Reflectable>(&self, this: &T, handle: i32, source: TimerSource) {
+        if let Some(callback) = self.run_now_or_hold(OneshotTimerCallback::Immediate(handle), source) {
+            callback.invoke(this, &self.js_timers);
+        }
+    }
+
+    /// Names the last timer callback that ran in the current timer event, if any.
+    pub fn last_run_label(&self) -> Option<String> {
+        self.js_timers.last_run.borrow().as_ref().map(|&(is_interval, ref stack)| {
+            timer_label(is_interval, stack.as_ref().map(|stack| stack.as_str()))
+        })
+    }
+
+    pub fn flush_due_timers<T: Reflectable>(&self, this: &T) {
+        self.flush_due_timers_with(|callback| callback.invoke(this, &self.js_timers));
+    }
+
+    pub fn advance_clock<T: Reflectable>(&self, duration: MsDuration, this: &T) {
+        self.advance_clock_with(duration, |callback| callback.invoke(this, &self.js_timers));
+    }
+
+    /// How far the clock of the timers was moved ahead of the real one.
+    pub fn skipped_time(&self) -> MsDuration {
+        self.skipped.get()
+    }
+
+    /// Describes the scheduled timers for the devtools, the next one due first.
+    pub fn describe_timers(&self) -> Vec<TimerInfo> {
+        self.inspect_timers(|callback, remaining| match *callback {
+            OneshotTimerCallback::JsTimer(ref task) => task.describe(remaining),
+            _ => TimerInfo {
+                handle: None,
+                kind: TimerKind::Internal,
+                remaining: remaining.get(),
+                period: callback.repeat_period().map(|period| period.get()),
+                nesting_level: 0,
+                installed_from: None,
+                installed_stack: None,
+            },
+        })
+    }
+}
+
+impl<C: ScheduledCallback> OneshotTimers<C> {
+    pub fn new(pipeline_id: PipelineId,
+               timer_event_chan: IpcSender<TimerEvent>,
+               scheduler_chan: Box<TimerSchedulerChan>,
+               clock: Box<TimerClock>)
+               -> OneshotTimers<C> {
         OneshotTimers {
             js_timers: JsTimers::new(),
+            pipeline_id: pipeline_id,
             timer_event_chan: timer_event_chan,
             scheduler_chan: scheduler_chan,
+            clock: clock,
+            log_sink: DOMRefCell::new(opts::get().timer_log.as_ref().and_then(|path| {
+                match TimerLogFile::open(Path::new(path)) {
+                    Ok(file) => Some(box file as Box<TimerLogSink>),
+                    Err(e) => {
+                        warn!("Opening the timer log {} failed ({}).", path, e);
+                        None
+                    },
+                }
+            })),
+            input_monitor: DOMRefCell::new(None),
+            time_resolution: Cell::new(TimeResolutionPolicy::precise()),
+            latest_time: Cell::new(Length::new(0)),
             next_timer_handle: Cell::new(OneshotTimerHandle(1)),
             timers: DOMRefCell::new(Vec::new()),
             suspended_since: Cell::new(None),
+            clock_stopped: Cell::new(false),
+            suspensions: DOMRefCell::new(HashMap::new()),
+            event_log: DOMRefCell::new(TimerEventLog::Off),
+            event_log_start: Cell::new(Length::new(0)),
+            suspension_calls: DOMRefCell::new(if opts::get().trace_timer_suspensions { Some(vec![]) } else { None }),
+            chaos: DOMRefCell::new(if opts::get().timer_chaos {
+                let seed = opts::get().timer_chaos_seed.unwrap_or_else(random);
+                warn!("Shuffling the timers of pipeline {}.", pipeline_id);
+                info!("Using seed {} for the timer chaos mode.", seed);
+                Some(chaos_rng(seed, pipeline_id))
+            } else {
+                None
+            }),
+            frozen: DOMRefCell::new(None),
+            backlog_until: Cell::new(None),
+            overdue_batch_size: Cell::new(match prefs::get_pref("dom.timers.overdue_batch_size").as_i64() {
+                Some(batch_size) if batch_size > 0 => Some(batch_size as usize),
+                _ => None,
+            }),
+            interval_catch_up: Cell::new(IntervalCatchUp::from_pref()),
             suspension_offset: Cell::new(Length::new(0)),
+            virtual_time: opts::get().virtual_time_budget.is_some(),
+            skipped: Cell::new(Length::new(0)),
+            hidden_since: Cell::new(None),
+            frame_hidden_since: Cell::new(None),
+            intensive_throttling_delay: Length::new(
+                cmp::max(0, prefs::get_pref("dom.timers.intensive_throttling_delay_minutes")
+                                .as_i64().unwrap_or(5)) as u64 * 60 * 1000),
+            throttling_exempt: Cell::new(false),
+            budget: Cell::new(TimerBudget::new()),
+            delay_multiplier: Cell::new(1),
             expected_event_id: Cell::new(TimerEventId(0)),
+            firing: DOMRefCell::new(vec![]),
+            running_repeat: Cell::new(None),
+            in_callback: Cell::new(false),
+            unscheduled_while_firing: DOMRefCell::new(vec![]),
+            requested_event: Cell::new(None),
+            requested_repeat: Cell::new(None),
+            requested_deadline: Cell::new(Length::new(0)),
+        }
+    }
+
+    pub fn schedule_callback(&self,
+                             callback: C,
+                             duration: MsDuration,
+                             source: TimerSource)
+                             -> OneshotTimerHandle {
+        let new_handle = self.next_timer_handle.get();
+        self.next_timer_handle.set(OneshotTimerHandle(new_handle.0 + 1));
+
+        let exempt = callback.exempt_from_suspension();
+        let now = if self.is_running(exempt, &callback) { self.live_time() } else { self.base_time() };
+        self.log("schedule", Some(new_handle), Some(self.stretch(duration)), Some(timer_kind(&callback)));
+        let timer = OneshotTimer {
+            handle: new_handle,
+            source: source,
+            callback: callback,
+            scheduled_for: now + self.stretch(duration),
+            exempt: exempt,
+        };
+        // While hidden, a timer that isn't throttled may be due before the
+        // throttled ones ahead of it.
+        let unthrottled_while_hidden = self.is_throttling() && !timer.throttleable();
+
+        self.insert_timer(timer);
+
+        if self.is_requested_timer(new_handle) || unthrottled_while_hidden {
+            self.schedule_timer_call();
+        }
+        self.check_invariants();
+
+        new_handle
+    }
+
+    /// Hands `callback` back to be run right away if the timers are running,
+    /// or else holds it like a timer that is due, to run once they resume. A
+    /// callback held while the timers are frozen is frozen along with them.
+    pub fn run_now_or_hold(&self, callback: C, source: TimerSource) -> Option<C> {
+        if self.is_running(callback.exempt_from_suspension(), &callback) {
+            return Some(callback);
+        }
+
+        let new_handle = self.next_timer_handle.get();
+        self.next_timer_handle.set(OneshotTimerHandle(new_handle.0 + 1));
+        self.log("hold", Some(new_handle), None, Some(timer_kind(&callback)));
+        let timer = OneshotTimer {
+            handle: new_handle,
+            source: source,
+            callback: callback,
+            scheduled_for: self.base_time(),
+            exempt: false,
+        };
+        self.rearm(timer);
+        self.check_invariants();
+        None
+    }
+
+    fn insert_timer(&self, timer: OneshotTimer<C>) {
+        let mut timers = self.timers.borrow_mut();
+        let insertion_index = timers.binary_search(&timer).err().unwrap();
+        timers.insert(insertion_index, timer);
+    }
+
+    /// Puts a repeating timer back after its callback ran, with the frozen
+    /// timers if the callback froze them.
+    fn rearm(&self, timer: OneshotTimer<C>) {
+        if !timer.exempt && self.is_frozen() {
+            let now = self.clock_for(&timer);
+            if let Some(ref mut frozen) = *self.frozen.borrow_mut() {
+                frozen.push(FrozenTimer {
+                    handle: timer.handle,
+                    source: timer.source,
+                    remaining: Length::new(timer.scheduled_for.get().saturating_sub(now.get())),
+                    callback: timer.callback,
+                    exempt: timer.exempt,
+                });
+            }
+            return;
+        }
+        self.insert_timer(timer);
+    }
+
+    pub fn unschedule_callback(&self, handle: OneshotTimerHandle) {
+        let was_next = self.is_requested_timer(handle);
+        self.log("cancel", Some(handle), None, None);
+
+        self.timers.borrow_mut().retain(|t| t.handle != handle);
+        if let Some(ref mut frozen) = *self.frozen.borrow_mut() {
+            frozen.retain(|t| t.handle != handle);
+        }
+        if let Some((running, _)) = self.running_repeat.get() {
+            if running == handle {
+                // Clearing an interval from its own callback stops the next run.
+                self.running_repeat.set(Some((running, true)));
+            }
+        }
+        if self.in_callback.get() {
+            self.unscheduled_while_firing.borrow_mut().push(handle);
+        }
+
+        if was_next {
+            self.invalidate_expected_event_id();
+            self.schedule_timer_call();
+        }
+        self.check_invariants();
+    }
+
+    /// Whether the timer is the one the next event is requested for. While
+    /// suspended, that is the next timer that keeps running.
+    fn is_requested_timer(&self, handle: OneshotTimerHandle) -> bool {
+        let timers = self.timers.borrow();
+        let next = timers.iter().rev().find(|timer| self.is_running(timer.exempt, &timer.callback));
+        next.map_or(false, |timer| timer.handle == handle)
+    }
+
+    /// Whether a timer with the given exemption and callback runs under the
+    /// current suspensions.
+    fn is_running(&self, exempt: bool, callback: &C) -> bool {
+        !self.is_suspended() || exempt || (!self.suspends_internal_callbacks() && !callback.is_script_timer())
+    }
+
+    /// Whether the current suspensions stop internal callbacks too.
+    fn suspends_internal_callbacks(&self) -> bool {
+        self.suspensions.borrow().keys().any(|reason| reason.suspends_internal_callbacks())
+    }
+
+    /// Handles a timer event from the scheduler, passing each due callback
+    /// to `invoke` in the order the timers were scheduled for.
+    pub fn fire_timer_with<F>(&self, id: TimerEventId, mut invoke: F) where F: FnMut(C) {
+        let expected_id = self.expected_event_id.get();
+        // A repeating event stays requested until it is invalidated.
+        if self.requested_event.get().map_or(false, |(_, requested_id)| requested_id == id) &&
+           self.requested_repeat.get().is_none() {
+            self.requested_event.set(None);
+        }
+        if expected_id != id {
+            debug!("ignoring timer fire event {:?} (expected {:?})", id, expected_id);
+            return;
+        }
+
+        let suspended = self.is_suspended();
+        let replayed = self.next_replayed_event();
+        let mut base_time = self.live_time();
+        // Throttling may have changed since, so go by what was requested.
+        let next_due = self.requested_deadline.get();
+        if (self.virtual_time || replayed.is_some()) && base_time < next_due {
+            // The scheduler skipped ahead to this timer; so does our clock.
+            self.skipped.set(self.skipped.get() + (next_due - base_time));
+            base_time = next_due;
+        }
+
+        // Since the event id was the expected one, at least one timer should be due.
+        assert!(base_time >= next_due);
+
+        // select timers to run to prevent firing timers
+        // that were installed during fire of another timer
+        let mut timers_to_run = Vec::new();
+        // The backlog of overdue timers may be spread over several events.
+        let batch_size = self.overdue_batch_size.get().map_or(usize::MAX, |batch_size| cmp::max(batch_size, 1));
+        let backlog_until = self.backlog_until.get();
+
+        if let Some(ref record) = replayed {
+            // Run what the recording has, whether it is due or not.
+            let mut timers = self.timers.borrow_mut();
+            for &handle in &record.fired {
+                match timers.iter().position(|timer| timer.handle == OneshotTimerHandle(handle)) {
+                    Some(index) => timers_to_run.push(timers.remove(index)),
+                    None => warn!("Replayed timer {} isn't scheduled.", handle),
+                }
+            }
+        }
+
+        while replayed.is_none() {
+            let mut timers = self.timers.borrow_mut();
+
+            if timers.is_empty() || timers.last().unwrap().scheduled_for > base_time {
+                break;
+            }
+            let overdue = backlog_until.map_or(false, |until| timers.last().unwrap().scheduled_for <= until);
+            if overdue && timers_to_run.len() >= batch_size {
+                break;
+            }
+
+            timers_to_run.push(timers.pop().unwrap());
+        }
+
+        if let Some(ref mut rng) = *self.chaos.borrow_mut() {
+            // Nothing says in which order timers due at the same time run, so mix them up.
+            // Replayed timers keep the order they were recorded in.
+            let mut start = 0;
+            while replayed.is_none() && start < timers_to_run.len() {
+                let deadline = timers_to_run[start].scheduled_for;
+                let end = timers_to_run[start..].iter()
+                                                .position(|timer| timer.scheduled_for != deadline)
+                                                .map_or(timers_to_run.len(), |count| start + count);
+                rng.shuffle(&mut timers_to_run[start..end]);
+                start = end;
+            }
+        }
+
+        if let Some(backlog_until) = self.backlog_until.get() {
+            if self.timers.borrow().last().map_or(true, |timer| timer.scheduled_for > backlog_until) {
+                self.backlog_until.set(None);
+            }
+        }
+
+        if cfg!(feature = "debugtimers") {
+            *self.firing.borrow_mut() = timers_to_run.iter().map(|timer| timer.handle).collect();
+        }
+        let mut fired = vec![];
+        let mut timers_to_run = timers_to_run.into_iter();
+        while let Some(timer) = timers_to_run.next() {
+            if self.unscheduled_while_firing.borrow().contains(&timer.handle) {
+                // An earlier callback of this event unscheduled the timer.
+                continue;
+            }
+            if replayed.is_none() && !fired.is_empty() && self.input_pending() {
+                // The rest of the due timers are requested again, after the input.
+                self.log("yield", Some(timer.handle), None, Some("input pending"));
+                self.insert_timer(timer);
+                for timer in timers_to_run.by_ref() {
+                    self.insert_timer(timer);
+                }
+                break;
+            }
+            if replayed.is_none() && suspended && !self.is_running(timer.exempt, &timer.callback) {
+                // The timer is suspended.
+                self.insert_timer(timer);
+                continue;
+            }
+            let throttled = self.is_throttling() && timer.throttleable();
+            if replayed.is_none() && throttled && self.budget.get().level(base_time) < 0 {
+                // The timers run so far have used up the budget; wait for it to recover.
+                self.log("throttle", Some(timer.handle), None, Some("out of budget"));
+                self.insert_timer(timer);
+                continue;
+            }
+            let lateness = Length::new(self.clock_for(&timer).get().saturating_sub(timer.scheduled_for.get()));
+            self.log("fire", Some(timer.handle), Some(lateness), Some(timer_kind(&timer.callback)));
+            let OneshotTimerHandle(handle) = timer.handle;
+            fired.push(handle);
+            let next_occurrence = timer.callback.next_occurrence();
+            let catch_up = timer.callback.catch_up().unwrap_or(self.interval_catch_up.get());
+            // A callback may fire timers of its own, e.g. from a nested event loop.
+            let outer_repeat = self.running_repeat.get();
+            self.running_repeat.set(next_occurrence.as_ref().map(|_| (timer.handle, false)));
+            let outer_in_callback = self.in_callback.get();
+            self.in_callback.set(true);
+            let start = self.clock.now();
+            invoke(timer.callback);
+            self.in_callback.set(outer_in_callback);
+            if throttled {
+                let mut budget = self.budget.get();
+                budget.charge(self.base_time(), Length::new(self.clock.now().get().saturating_sub(start.get())));
+                self.budget.set(budget);
+            }
+            let cleared = self.running_repeat.get().map_or(false, |(_, cleared)| cleared);
+            self.running_repeat.set(outer_repeat);
+            if let Some(callback) = next_occurrence {
+                if !cleared {
+                    // Rearm a repeating timer once it has run, as the timer initialisation steps do.
+                    // It keeps to its original cadence unless it has fallen behind.
+                    let period = self.repeat_period_of(&callback).unwrap_or(Length::new(MIN_REPEAT_PERIOD_MS));
+                    let mut rearmed = OneshotTimer {
+                        handle: timer.handle,
+                        source: timer.source,
+                        callback: callback,
+                        scheduled_for: timer.scheduled_for,
+                        exempt: timer.exempt,
+                    };
+                    let now = cmp::max(base_time, self.clock_for(&rearmed));
+                    rearmed.scheduled_for = catch_up.next_run(timer.scheduled_for, period, now);
+                    self.rearm(rearmed);
+                }
+            }
+        }
+        self.firing.borrow_mut().clear();
+        if !self.in_callback.get() {
+            self.unscheduled_while_firing.borrow_mut().clear();
+        }
+
+        if let TimerEventLog::Recording(ref mut records) = *self.event_log.borrow_mut() {
+            records.push(TimerEventRecord {
+                time: base_time.get().saturating_sub(self.event_log_start.get().get()),
+                fired: fired,
+            });
+        }
+
+        self.schedule_timer_call();
+    }
+
+    /// Runs the timers that are due now right away, rather than when the
+    /// scheduler delivers their event, for tests and automation. Timers that
+    /// get scheduled by the timers that run wait for the next event. Returns
+    /// whether any timers were due.
+    pub fn flush_due_timers_with<F>(&self, invoke: F) -> bool where F: FnMut(C) {
+        // Replayed timers only fire when the recording says they did.
+        if self.replayed_deadline().is_some() {
+            return false;
         }
+        // The timers due with a running callback run after it, in order.
+        if self.in_callback.get() {
+            return false;
+        }
+        // The event requested from the scheduler is for the next timers due, as
+        // far as suspension and throttling let them. Handle it now instead.
+        if self.requested_event.get().is_none() || self.requested_deadline.get() > self.live_time() {
+            return false;
+        }
+        let id = self.invalidate_expected_event_id();
+        self.fire_timer_with(id, invoke);
+        true
+    }
+
+    /// Moves the clock of the timers `duration` ahead, running the timers that
+    /// become due on the way in order, as they would have run had the time
+    /// passed. Only the timers see the time that was skipped.
+    pub fn advance_clock_with<F>(&self, duration: MsDuration, mut invoke: F) where F: FnMut(C) {
+        let until = self.live_time() + duration;
+        loop {
+            let next_due = self.requested_deadline.get();
+            if self.requested_event.get().is_none() || next_due > until {
+                break;
+            }
+            self.skip_to(next_due);
+            if !self.flush_due_timers_with(&mut invoke) {
+                break;
+            }
+        }
+        self.skip_to(until);
+        // The delay of the pending request was for the clock before it moved.
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
+
+    /// Whether no timer is due within `window` from now, counting timers that
+    /// are due already but haven't run yet. Timers held by a suspension count
+    /// too, as close to due as they were when it began, since they run as soon
+    /// as it is lifted.
+    pub fn is_quiescent(&self, window: MsDuration) -> bool {
+        let frozen_due = self.frozen.borrow().as_ref().map_or(false, |frozen| {
+            frozen.iter().any(|timer| timer.remaining <= window)
+        });
+        !frozen_due && self.timers.borrow().iter().all(|timer| timer.scheduled_for > self.clock_for(timer) + window)
+    }
+
+    /// How long until the next timer is due, if any is, counting timers that
+    /// are due already but whose event hasn't been handled yet as due now.
+    pub fn time_until_next_due(&self) -> Option<MsDuration> {
+        self.requested_event.get().map(|_| {
+            Length::new(self.requested_deadline.get().get().saturating_sub(self.live_time().get()))
+        })
+    }
+
+    /// How long an idle period that begins now may last without delaying the
+    /// next timer, up to `max`. There is none while a timer is due.
+    pub fn idle_period(&self, max: MsDuration) -> Option<MsDuration> {
+        match self.time_until_next_due() {
+            Some(until) if until.get() == 0 => None,
+            Some(until) => Some(Length::new(cmp::min(until.get(), max.get()))),
+            None => Some(max),
+        }
+    }
+
+    /// Sums up what the timers of the document at `url` are up to, for about:timers.
+    pub fn stats(&self, url: String) -> PipelineTimerStats {
+        let timers = self.inspect_timers(|callback, remaining| (callback.is_script_timer(), remaining));
+        let next_due = self.timers.borrow().last().map(|timer| {
+            timer.scheduled_for.get().saturating_sub(self.clock_for(timer).get())
+        });
+        let mut suspensions: Vec<_> = self.suspensions.borrow().iter().map(|(reason, &count)| {
+            (format!("{:?}", reason), count)
+        }).collect();
+        suspensions.sort();
+        PipelineTimerStats {
+            pipeline_id: self.pipeline_id,
+            url: url,
+            timers: timers.len(),
+            script_timers: timers.iter().filter(|&&(is_script_timer, _)| is_script_timer).count(),
+            next_due: next_due,
+            throttled: self.is_throttling(),
+            throttling_exempt: self.throttling_exempt.get(),
+            suspensions: suspensions,
+            frozen: self.is_frozen(),
+            delay_multiplier: self.delay_multiplier.get(),
+        }
+    }
+
+    fn skip_to(&self, time: MsDuration) {
+        let now = self.live_time();
+        if time > now {
+            self.skipped.set(self.skipped.get() + (time - now));
+        }
+    }
+
+    /// Shuffles timers due at the same time and delays timer events by a few
+    /// ms, choosing at random from `seed`, or stops doing so if it is `None`.
+    pub fn set_chaos_seed(&self, seed: Option<usize>) {
+        *self.chaos.borrow_mut() = seed.map(|seed| chaos_rng(seed, self.pipeline_id));
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
+
+    /// Starts recording the timer events handled from now on.
+    pub fn record_events(&self) {
+        self.event_log_start.set(self.live_time());
+        *self.event_log.borrow_mut() = TimerEventLog::Recording(vec![]);
+    }
+
+    /// Returns the timer events recorded so far, oldest first.
+    pub fn take_recorded_events(&self) -> Vec<TimerEventRecord> {
+        match *self.event_log.borrow_mut() {
+            TimerEventLog::Recording(ref mut records) => mem::replace(records, vec![]),
+            _ => vec![],
+        }
+    }
+
+    /// Fires the timers as `records` say they were, rather than when they are
+    /// due, until every record has been replayed.
+    pub fn replay_events(&self, mut records: Vec<TimerEventRecord>) {
+        records.reverse();
+        self.event_log_start.set(self.live_time());
+        *self.event_log.borrow_mut() = TimerEventLog::Replaying(records);
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
+
+    /// When the next replayed event is to be handled, if events are replayed.
+    fn replayed_deadline(&self) -> Option<MsDuration> {
+        match *self.event_log.borrow() {
+            TimerEventLog::Replaying(ref records) => {
+                records.last().map(|record| self.event_log_start.get() + Length::new(record.time))
+            },
+            _ => None,
+        }
+    }
+
+    fn next_replayed_event(&self) -> Option<TimerEventRecord> {
+        let mut event_log = self.event_log.borrow_mut();
+        let (record, done) = match *event_log {
+            TimerEventLog::Replaying(ref mut records) => (records.pop(), records.is_empty()),
+            _ => return None,
+        };
+        if done {
+            // Once the recording is used up, the timers run live again.
+            *event_log = TimerEventLog::Off;
+        }
+        record
+    }
+
+    /// Calls `describe` with each scheduled timer and how long until it is
+    /// due, the next one due first, and then with the frozen timers and how
+    /// long they had left.
+    pub fn inspect_timers<F, T>(&self, mut describe: F) -> Vec<T> where F: FnMut(&C, MsDuration) -> T {
+        let mut described: Vec<T> = self.timers.borrow().iter().rev().map(|timer| {
+            let now = self.clock_for(timer);
+            describe(&timer.callback, Length::new(timer.scheduled_for.get().saturating_sub(now.get())))
+        }).collect();
+        if let Some(ref frozen) = *self.frozen.borrow() {
+            described.extend(frozen.iter().map(|timer| describe(&timer.callback, timer.remaining)));
+        }
+        described
+    }
+
+    /// Saves the state of the timers, apart from their callbacks, so that they
+    /// can be rolled back to it with `restore_with`.
+    pub fn snapshot(&self) -> TimerStateSnapshot {
+        let OneshotTimerHandle(next_timer_handle) = self.next_timer_handle.get();
+        let timers = self.timers.borrow().iter().map(|timer| {
+            snapshot_timer(timer.handle, timer.source, timer.scheduled_for, timer.exempt)
+        }).collect();
+        let frozen = self.frozen.borrow().as_ref().map(|frozen| frozen.iter().map(|timer| {
+            snapshot_timer(timer.handle, timer.source, timer.remaining, timer.exempt)
+        }).collect());
+        TimerStateSnapshot {
+            time: self.now().get(),
+            next_timer_handle: next_timer_handle,
+            timers: timers,
+            frozen: frozen,
+            suspension_offset: self.suspension_offset.get().get(),
+            suspended_since: self.suspended_since.get().map(|since| since.get()),
+            suspensions: self.suspensions.borrow().iter().map(|(&reason, &count)| (reason, count)).collect(),
+            hidden_since: self.hidden_since.get().map(|since| since.get()),
+            frame_hidden_since: self.frame_hidden_since.get().map(|since| since.get()),
+        }
+    }
+
+    /// Replaces the timers with those of `snapshot`, asking `rebind` for the
+    /// callback of each of them. Timers it has no callback for are dropped.
+    /// The timers see the time go on from when the snapshot was taken.
+    pub fn restore_with<F>(&self, snapshot: &TimerStateSnapshot, mut rebind: F)
+        where F: FnMut(&TimerSnapshot) -> Option<C> {
+        // The clock can't be turned back, so the offset is moved instead,
+        // which also covers the time between the snapshot and now.
+        let time = Length::new(snapshot.time);
+        let now = self.now();
+        if time > now {
+            self.skipped.set(self.skipped.get() + (time - now));
+        }
+        let elapsed = cmp::max(now, time) - time;
+        self.suspension_offset.set(Length::new(snapshot.suspension_offset) + elapsed);
+        self.suspended_since.set(snapshot.suspended_since.map(|since| Length::new(since) + elapsed));
+        *self.suspensions.borrow_mut() = snapshot.suspensions.iter().cloned().collect();
+        self.clock_stopped.set(snapshot.suspensions.iter().any(|&(reason, _)| reason.stops_the_clock()));
+        self.hidden_since.set(snapshot.hidden_since.map(Length::new));
+        self.frame_hidden_since.set(snapshot.frame_hidden_since.map(Length::new));
+        self.backlog_until.set(None);
+        self.next_timer_handle.set(OneshotTimerHandle(snapshot.next_timer_handle));
+
+        let pipeline_id = self.pipeline_id;
+        let mut rebind = |saved: &TimerSnapshot| {
+            let callback = rebind(saved);
+            if callback.is_none() {
+                warn!("Dropping timer {} that no callback was bound to.", saved.handle);
+            }
+            let source = if saved.from_window {
+                TimerSource::FromWindow(pipeline_id)
+            } else {
+                TimerSource::FromWorker
+            };
+            callback.map(|callback| (OneshotTimerHandle(saved.handle), source, callback))
+        };
+        let mut timers: Vec<_> = snapshot.timers.iter().filter_map(|saved| {
+            rebind(saved).map(|(handle, source, callback)| OneshotTimer {
+                handle: handle,
+                source: source,
+                callback: callback,
+                scheduled_for: Length::new(saved.due),
+                exempt: saved.exempt,
+            })
+        }).collect();
+        timers.sort();
+        *self.timers.borrow_mut() = timers;
+        let frozen = snapshot.frozen.as_ref().map(|frozen| frozen.iter().filter_map(|saved| {
+            rebind(saved).map(|(handle, source, callback)| FrozenTimer {
+                handle: handle,
+                source: source,
+                callback: callback,
+                remaining: Length::new(saved.due),
+                exempt: saved.exempt,
+            })
+        }).collect());
+        *self.frozen.borrow_mut() = frozen;
+
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
+
+    /// The time the timers go by, which stands still while they are
+    /// suspended and leaves out the time they spent suspended.
+    pub fn current_time(&self) -> MsDuration {
+        self.base_time()
+    }
+
+    fn base_time(&self) -> MsDuration {
+        let offset = self.suspension_offset.get();
+
+        match self.suspended_since.get() {
+            Some(time) => time - offset,
+            None => self.now() - offset,
+        }
+    }
+
+    fn now(&self) -> MsDuration {
+        // A clock that goes back is taken to stand still until it has caught
+        // up, so that the timers never see time go backwards.
+        let now = cmp::max(self.clock.now(), self.latest_time.get());
+        self.latest_time.set(now);
+        Length::new(self.time_resolution.get().coarsen_ns(now.get()) / (1000 * 1000)) + self.skipped.get()
+    }
+
+    /// The time timers that keep running during a suspension go by, which
+    /// unlike `base_time` isn't stopped by the suspension.
+    fn live_time(&self) -> MsDuration {
+        self.now() - self.suspension_offset.get()
+    }
+
+    /// Since when the timers are throttled, unless exempt, because either the
+    /// document or the frame it is shown in can't be seen.
+    fn throttled_since(&self) -> Option<MsDuration> {
+        match (self.hidden_since.get(), self.frame_hidden_since.get()) {
+            (Some(hidden_since), Some(frame_hidden_since)) => Some(cmp::min(hidden_since, frame_hidden_since)),
+            (hidden_since, frame_hidden_since) => hidden_since.or(frame_hidden_since),
+        }
+    }
+
+    /// Whether throttleable timers are throttled.
+    fn is_throttling(&self) -> bool {
+        self.throttled_since().is_some() && !self.throttling_exempt.get()
+    }
+
+    /// The granularity of throttleable timers, if they are throttled.
+    fn throttling_alignment(&self, base_time: MsDuration) -> Option<u64> {
+        if self.throttling_exempt.get() {
+            return None;
+        }
+        self.throttled_since().map(|hidden_since| {
+            let hidden_for = base_time.get().saturating_sub(hidden_since.get());
+            if hidden_for >= self.intensive_throttling_delay.get() {
+                INTENSIVE_TIMER_ALIGNMENT
+            } else {
+                HIDDEN_TIMER_ALIGNMENT
+            }
+        })
+    }
+
+    /// When the next timer event should be delivered. While the document is
+    /// hidden, throttleable timers are held back until the budget allows them
+    /// to run, and then to the next whole second (or minute), so that they
+    /// cause at most one wakeup per second (or minute).
+    fn next_deadline(&self) -> Option<MsDuration> {
+        let base_time = self.base_time();
+        let timers = self.timers.borrow();
+        if self.is_suspended() {
+            // Only some timers keep running, and they aren't throttled.
+            return timers.iter()
+                         .rev()
+                         .find(|timer| self.is_running(timer.exempt, &timer.callback))
+                         .map(|timer| timer.scheduled_for);
+        }
+        let head = match timers.last() {
+            Some(timer) => timer,
+            None => return None,
+        };
+        let alignment = match self.throttling_alignment(base_time) {
+            Some(alignment) if head.throttleable() => alignment,
+            _ => return Some(head.scheduled_for),
+        };
+        let budget = self.budget.get();
+        let due = if budget.level(base_time) < 0 {
+            cmp::max(head.scheduled_for, budget.available_at(base_time))
+        } else {
+            head.scheduled_for
+        };
+        let aligned = Length::new((due.get() + alignment - 1) / alignment * alignment);
+        // A timer that isn't throttled still fires on time, and takes the
+        // throttled ones that are due by then along.
+        let unthrottled = timers.iter().rev()
+                                .take_while(|timer| timer.scheduled_for < aligned)
+                                .find(|timer| !timer.throttleable())
+                                .map(|timer| timer.scheduled_for);
+        Some(unthrottled.unwrap_or(aligned))
+    }
+
+    /// Starts or stops throttling timers because the document got hidden or
+    /// visible again.
+    pub fn set_hidden(&self, hidden: bool) {
+        self.set_hidden_since(&self.hidden_since, hidden, "document");
+    }
+
+    /// Throttles or unthrottles the timers of a document in a cross-origin
+    /// frame as the frame stops or starts being seen, while the page around
+    /// it may well be visible.
+    pub fn set_frame_hidden(&self, hidden: bool) {
+        self.set_hidden_since(&self.frame_hidden_since, hidden, "frame");
+    }
+
+    fn set_hidden_since(&self, since: &Cell<Option<MsDuration>>, hidden: bool, what: &str) {
+        if since.get().is_some() == hidden {
+            return;
+        }
+        if self.log_sink.borrow().is_some() {
+            let detail = format!("{} {}", what, if hidden { "hidden" } else { "visible" });
+            self.log("throttle", None, None, Some(&detail));
+        }
+        since.set(if hidden { Some(self.base_time()) } else { None });
+        if !hidden {
+            // Throttled timers may have piled up.
+            self.backlog_until.set(Some(self.base_time()));
+        }
+        if self.suspended_since.get().is_none() {
+            // The requested event was for the old deadline of the next timer.
+            self.invalidate_expected_event_id();
+            // Coming to the foreground, don't keep the page waiting for the
+            // timers that are overdue already.
+            if hidden || self.is_throttling() || !self.fire_overdue_timers_soon() {
+                self.schedule_timer_call();
+            }
+        }
+        self.check_invariants();
+    }
+
+    /// Queues a timer event for the timers that are due already, without a
+    /// round trip through the scheduler, which may align it with other wakeups.
+    /// Returns false if no timer is due yet.
+    fn fire_overdue_timers_soon(&self) -> bool {
+        let (source, priority, due) = match self.timers.borrow().last() {
+            Some(timer) if timer.scheduled_for <= self.live_time() =>
+                (timer.source, timer.callback.priority(), timer.scheduled_for),
+            _ => return false,
+        };
+        let event = TimerEvent(source, self.expected_event_id.get(), priority);
+        if self.timer_event_chan.send(event).is_err() {
+            return false;
+        }
+        self.requested_event.set(None);
+        self.requested_repeat.set(None);
+        self.requested_deadline.set(due);
+        true
+    }
+
+    /// Makes the timers wait `multiplier` times as long as they ask for, the
+    /// timers already scheduled included, to emulate a slow device.
+    pub fn set_delay_multiplier(&self, multiplier: u64) {
+        let multiplier = cmp::max(1, multiplier);
+        let previous = self.delay_multiplier.get();
+        if multiplier == previous {
+            return;
+        }
+        self.delay_multiplier.set(multiplier);
+        let rescale = |now: u64, scheduled_for: u64| {
+            if scheduled_for <= now {
+                scheduled_for
+            } else {
+                now + (scheduled_for - now) * multiplier / previous
+            }
+        };
+        let (live_time, base_time) = (self.live_time().get(), self.base_time().get());
+        self.shift_timers(|timer| self.is_running(timer.exempt, &timer.callback),
+                          |scheduled_for| rescale(live_time, scheduled_for));
+        self.shift_timers(|timer| !self.is_running(timer.exempt, &timer.callback),
+                          |scheduled_for| rescale(base_time, scheduled_for));
+        if let Some(ref mut frozen) = *self.frozen.borrow_mut() {
+            for timer in frozen {
+                timer.remaining = Length::new(rescale(0, timer.remaining.get()));
+            }
+        }
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
+
+    fn stretch(&self, duration: MsDuration) -> MsDuration {
+        Length::new(duration.get().saturating_mul(self.delay_multiplier.get()))
+    }
+
+    /// How long a repeating callback waits between runs, which is at least a
+    /// ms, so that time passes between them.
+    fn repeat_period_of(&self, callback: &C) -> Option<MsDuration> {
+        callback.repeat_period().map(|period| cmp::max(self.stretch(period), Length::new(MIN_REPEAT_PERIOD_MS)))
+    }
+
+    /// Exempts the timers from being throttled while the document holds on to
+    /// something that needs them to run on time, such as audio being played.
+    pub fn set_throttling_exempt(&self, exempt: bool) {
+        if self.throttling_exempt.get() == exempt {
+            return;
+        }
+        self.throttling_exempt.set(exempt);
+        if self.throttled_since().is_some() && self.suspended_since.get().is_none() {
+            self.invalidate_expected_event_id();
+            self.schedule_timer_call();
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_since.get().is_some()
+    }
+
+    /// Logs what happened to `handle`, or to all the timers, if they are logged.
+    fn log(&self, event: &'static str, handle: Option<OneshotTimerHandle>, delay: Option<MsDuration>,
+           detail: Option<&str>) {
+        if let Some(ref sink) = *self.log_sink.borrow() {
+            sink.log(TimerLogRecord {
+                time: self.now().get(),
+                pipeline: self.pipeline_id.to_string(),
+                event: event,
+                handle: handle.map(|OneshotTimerHandle(handle)| handle),
+                delay: delay.map(|delay| delay.get()),
+                detail: detail.map(|detail| detail.to_owned()),
+            });
+        }
+    }
+
+    fn log_suspension(&self, event: &'static str, reason: SuspensionReason) {
+        if self.log_sink.borrow().is_some() {
+            self.log(event, None, None, Some(&format!("{:?}", reason)));
+        }
+    }
+
+    /// Logs the lifecycle of the timers to `sink` from now on, or stops logging it.
+    pub fn set_log_sink(&self, sink: Option<Box<TimerLogSink>>) {
+        *self.log_sink.borrow_mut() = sink;
+    }
+
+    /// Lets the input that `monitor` says is pending go before the rest of the
+    /// due timers, once a timer has run, or stops doing so.
+    pub fn set_input_monitor(&self, monitor: Option<Box<InputMonitor>>) {
+        *self.input_monitor.borrow_mut() = monitor;
+    }
+
+    /// Makes the timers observe the clock no more precisely than `policy`
+    /// allows, like `performance.now()` does.
+    pub fn set_time_resolution(&self, policy: TimeResolutionPolicy) {
+        self.time_resolution.set(policy);
+    }
+
+    fn input_pending(&self) -> bool {
+        self.input_monitor.borrow().as_ref().map_or(false, |monitor| monitor.input_pending())
+    }
+
+    /// Whether the timers are suspended for the given reason.
+    pub fn is_suspended_for(&self, reason: SuspensionReason) -> bool {
+        self.suspensions.borrow().contains_key(&reason)
+    }
+
+    /// Suspends the timers for the given reason until the returned guard is
+    /// dropped.
+    pub fn suspend_scoped(&self, reason: SuspensionReason) -> ScopedSuspension<C> {
+        self.suspend(reason);
+        ScopedSuspension {
+            timers: self,
+            reason: reason,
+        }
+    }
+
+    /// Suspends the timers for the given reason, until `resume` is called for
+    /// it as often as `suspend` was.
+    pub fn suspend(&self, reason: SuspensionReason) {
+        self.suspend_timers(reason);
+        // Once in the back/forward cache, the timers are taken off the scheduler.
+        if reason == SuspensionReason::Frozen && !self.is_frozen() {
+            self.freeze();
+        }
+    }
+
+    fn suspend_timers(&self, reason: SuspensionReason) {
+        self.trace_suspension_call(reason, true);
+        self.log_suspension("suspend", reason);
+        let suspended_internal_callbacks = self.suspends_internal_callbacks();
+        *self.suspensions.borrow_mut().entry(reason).or_insert(0) += 1;
+        if self.suspended_since.get().is_some() {
+            if reason.stops_the_clock() {
+                self.clock_stopped.set(true);
+            }
+            if !suspended_internal_callbacks && reason.suspends_internal_callbacks() {
+                self.stop_internal_callbacks();
+            }
+            return;
+        }
+
+        self.suspended_since.set(Some(self.now()));
+        self.clock_stopped.set(reason.stops_the_clock());
+        self.invalidate_expected_event_id();
+        // Exempt timers, and possibly internal callbacks, keep running.
+        self.schedule_timer_call();
     }
 
-    pub fn schedule_callback(&self,
-                             callback: OneshotTimerCallback,
-                             duration: MsDuration,
-                             source: TimerSource)
-                             -> OneshotTimerHandle {
-        let new_handle = self.next_timer_handle.get();
-        self.next_timer_handle.set(OneshotTimerHandle(new_handle.0 + 1));
-
-        let scheduled_for = self.base_time() + duration;
+    /// Lifts one suspension for the given reason. The timers resume once no
+    /// suspension is left.
+    pub fn resume(&self, reason: SuspensionReason) {
+        // The timers are put back before the clock moves on, so that they wait
+        // as long as they still had to when they were frozen.
+        if reason == SuspensionReason::Frozen && self.suspensions.borrow().get(&reason) == Some(&1) {
+            self.thaw();
+        }
+        self.resume_timers(reason);
+    }
 
-        let timer = OneshotTimer {
-            handle: new_handle,
-            source: source,
-            callback: callback,
-            scheduled_for: scheduled_for,
+    fn resume_timers(&self, reason: SuspensionReason) {
+        self.trace_suspension_call(reason, false);
+        let remaining = match self.suspensions.borrow_mut().get_mut(&reason) {
+            Some(count) => {
+                *count -= 1;
+                Some(*count)
+            },
+            None => None,
         };
-
-        {
-            let mut timers = self.timers.borrow_mut();
-            let insertion_index = timers.binary_search(&timer).err().unwrap();
-            timers.insert(insertion_index, timer);
+        if remaining.is_some() {
+            self.log_suspension("resume", reason);
+        }
+        match remaining {
+            Some(0) => {},
+            Some(_) => return,
+            None => {
+                // Nothing to resume; the call is ignored.
+                self.warn_mismatched_resume(reason);
+                return;
+            },
         }
 
-        if self.is_next_timer(new_handle) {
-            self.schedule_timer_call();
+        let suspended_internal_callbacks = self.suspends_internal_callbacks();
+        self.suspensions.borrow_mut().remove(&reason);
+        if !self.suspensions.borrow().is_empty() {
+            if suspended_internal_callbacks && !self.suspends_internal_callbacks() {
+                self.start_internal_callbacks();
+            }
+            return;
         }
 
-        new_handle
-    }
+        let additional_offset = match self.suspended_since.get() {
+            Some(suspended_since) if self.clock_stopped.get() => self.now() - suspended_since,
+            // The timers were only held, and those that came due meanwhile are overdue now.
+            Some(_) => Length::new(0),
+            None => {
+                warn!("Pipeline {:?} lifted its last timer suspension, but its timers weren't suspended.",
+                      self.pipeline_id);
+                return;
+            },
+        };
 
-    pub fn unschedule_callback(&self, handle: OneshotTimerHandle) {
-        let was_next = self.is_next_timer(handle);
+        // The timers that kept running move along with the clock.
+        self.shift_timers(|timer| timer.exempt || (!suspended_internal_callbacks && !timer.callback.is_script_timer()),
+                          |scheduled_for| scheduled_for.saturating_sub(additional_offset.get()));
 
-        self.timers.borrow_mut().retain(|t| t.handle != handle);
+        self.suspension_offset.set(self.suspension_offset.get() + additional_offset);
+        self.suspended_since.set(None);
+        self.clock_stopped.set(false);
+        // Timers that were due when the suspension began are overdue now.
+        self.backlog_until.set(Some(self.base_time()));
 
-        if was_next {
-            self.invalidate_expected_event_id();
-            self.schedule_timer_call();
+        self.schedule_timer_call();
+    }
+
+    fn trace_suspension_call(&self, reason: SuspensionReason, suspend: bool) {
+        if let Some(ref mut calls) = *self.suspension_calls.borrow_mut() {
+            if calls.len() == MAX_TRACED_SUSPENSION_CALLS {
+                calls.remove(0);
+            }
+            calls.push(SuspensionCall {
+                reason: reason,
+                suspend: suspend,
+                caller: format!("{:?}", Backtrace::new()),
+            });
         }
     }
 
-    fn is_next_timer(&self, handle: OneshotTimerHandle) -> bool {
-        match self.timers.borrow().last() {
-            None => false,
-            Some(ref max_timer) => max_timer.handle == handle
+    /// Logs a call to `resume` that no call to `suspend` was made for, along
+    /// with the suspensions in effect and, if traced, where the latest calls
+    /// came from.
+    fn warn_mismatched_resume(&self, reason: SuspensionReason) {
+        let mut suspensions: Vec<String> = self.suspensions.borrow().iter()
+                                               .map(|(reason, count)| format!("{:?} x{}", reason, count))
+                                               .collect();
+        suspensions.sort();
+        warn!("Pipeline {:?} resumed its timers for {:?} without suspending them for it (suspended for [{}]).",
+              self.pipeline_id, reason, suspensions.join(", "));
+        if let Some(ref calls) = *self.suspension_calls.borrow() {
+            for call in calls {
+                warn!("{} for {:?} by:\n{}",
+                      if call.suspend { "Suspended" } else { "Resumed" }, call.reason, call.caller);
+            }
         }
     }
 
-    pub fn fire_timer<T: Reflectable>(&self, id: TimerEventId, this: &T) {
+    /// Checks that the timers are in a consistent state, and panics with a dump
+    /// of it in debug builds if they aren't. Since this goes over all timers
+    /// after every change, it is only built with the `debugtimers` feature.
+    #[cfg(feature = "debugtimers")]
+    fn check_invariants(&self) {
+        use std::collections::HashSet;
+
+        let mut problems = vec![];
+        let mut handles = HashSet::new();
+        {
+            let timers = self.timers.borrow();
+            if timers.windows(2).any(|pair| pair[0] >= pair[1]) {
+                problems.push("the timers are out of order".to_owned());
+            }
+            let frozen = self.frozen.borrow();
+            let frozen = frozen.iter().flat_map(|frozen| frozen.iter().map(|timer| timer.handle));
+            for handle in timers.iter().map(|timer| timer.handle).chain(frozen) {
+                if !handles.insert(handle) {
+                    problems.push(format!("{:?} is scheduled more than once", handle));
+                }
+            }
+        }
+
         let expected_id = self.expected_event_id.get();
-        if expected_id != id {
-            debug!("ignoring timer fire event {:?} (expected {:?})", id, expected_id);
-            return;
+        match (self.requested_event.get(), self.requested_repeat.get()) {
+            (Some((_, id)), _) if id != expected_id =>
+                problems.push(format!("{:?} is requested, but {:?} is expected", id, expected_id)),
+            (None, Some((handle, _))) =>
+                problems.push(format!("{:?} is to repeat without an event being requested", handle)),
+            _ => {},
         }
 
-        assert!(self.suspended_since.get().is_none());
+        // While the JS timers are being changed, they may not agree yet. A timeout
+        // that has run stays active until it is cleared, so only intervals must be
+        // scheduled.
+        if let Some(active_timers) = self.js_timers.active_timers.try_borrow() {
+            let firing = self.firing.borrow();
+            for (&JsTimerHandle(js_handle), entry) in active_timers.iter() {
+                if entry.is_interval != IsInterval::Interval {
+                    continue;
+                }
+                if !handles.contains(&entry.oneshot_handle) && !firing.contains(&entry.oneshot_handle) {
+                    problems.push(format!("JS timer {} is active, but {:?} isn't scheduled",
+                                          js_handle, entry.oneshot_handle));
+                }
+            }
+        }
 
-        let base_time = self.base_time();
+        if problems.is_empty() {
+            return;
+        }
+        let report = format!("Pipeline {:?} has inconsistent timers:\n  {}\n{}",
+                             self.pipeline_id, problems.join("\n  "), self.dump_state());
+        if cfg!(debug_assertions) {
+            panic!("{}", report);
+        }
+        error!("{}", report);
+    }
 
-        // Since the event id was the expected one, at least one timer should be due.
-        assert!(base_time >= self.timers.borrow().last().unwrap().scheduled_for);
+    #[cfg(not(feature = "debugtimers"))]
+    fn check_invariants(&self) {}
+
+    #[cfg(feature = "debugtimers")]
+    fn dump_state(&self) -> String {
+        let mut suspensions: Vec<String> = self.suspensions.borrow().iter()
+                                               .map(|(reason, count)| format!("{:?} x{}", reason, count))
+                                               .collect();
+        suspensions.sort();
+        let mut dump = format!("  live time {} ms, base time {} ms, suspended for [{}], hidden since {:?}\n",
+                               self.live_time().get(), self.base_time().get(), suspensions.join(", "),
+                               self.throttled_since().map(|since| since.get()));
+        dump.push_str(&format!("  expecting {:?}, requested {:?} due at {} ms, repeating {:?}\n",
+                               self.expected_event_id.get(),
+                               self.requested_event.get().map(|(_, id)| id),
+                               self.requested_deadline.get().get(),
+                               self.requested_repeat.get().map(|(handle, due)| (handle, due.get()))));
+        for timer in self.timers.borrow().iter().rev() {
+            dump.push_str(&format!("  {:?} due at {} ms, exempt: {}, throttleable: {}, repeats every {:?} ms\n",
+                                   timer.handle, timer.scheduled_for.get(), timer.exempt, timer.throttleable(),
+                                   timer.callback.repeat_period().map(|period| period.get())));
+        }
+        if let Some(ref frozen) = *self.frozen.borrow() {
+            for timer in frozen {
+                dump.push_str(&format!("  {:?} frozen with {} ms to go\n", timer.handle, timer.remaining.get()));
+            }
+        }
+        dump
+    }
 
-        // select timers to run to prevent firing timers
-        // that were installed during fire of another timer
-        let mut timers_to_run = Vec::new();
+    /// Limits how many overdue timers run per event after the timers resume or
+    /// stop being throttled, so that the backlog doesn't run in one burst.
+    /// `None` runs the whole backlog at once.
+    pub fn set_overdue_batch_size(&self, batch_size: Option<usize>) {
+        self.overdue_batch_size.set(batch_size);
+    }
 
-        loop {
-            let mut timers = self.timers.borrow_mut();
+    /// Sets how repeating timers that don't have a policy of their own catch
+    /// up on the runs they missed.
+    pub fn set_interval_catch_up(&self, catch_up: IntervalCatchUp) {
+        self.interval_catch_up.set(catch_up);
+    }
 
-            if timers.is_empty() || timers.last().unwrap().scheduled_for > base_time {
-                break;
-            }
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.borrow().is_some()
+    }
 
-            timers_to_run.push(timers.pop().unwrap());
+    /// Drops the timers taken off the scheduler for a global that is gone
+    /// from the back/forward cache, so that they aren't put back.
+    pub fn discard_frozen(&self) {
+        if let Some(ref mut frozen) = *self.frozen.borrow_mut() {
+            frozen.clear();
         }
+    }
 
-        for timer in timers_to_run {
-            let callback = timer.callback;
-            callback.invoke(this, &self.js_timers);
-        }
+    /// Takes the pending timers off the scheduler while the global is kept in
+    /// the back/forward cache. Exempt timers keep running. The frozen timers
+    /// are part of `snapshot`, so that they can be saved along with the rest.
+    fn freeze(&self) {
+        let timers = mem::replace(&mut *self.timers.borrow_mut(), vec![]);
+        let (exempt, frozen): (Vec<_>, Vec<_>) = timers.into_iter().partition(|timer| timer.exempt);
+        let frozen = frozen.into_iter().map(|timer| {
+            let now = self.clock_for(&timer);
+            FrozenTimer {
+                handle: timer.handle,
+                source: timer.source,
+                remaining: Length::new(timer.scheduled_for.get().saturating_sub(now.get())),
+                callback: timer.callback,
+                exempt: timer.exempt,
+            }
+        }).collect();
+        *self.timers.borrow_mut() = exempt;
+        *self.frozen.borrow_mut() = Some(frozen);
 
+        self.invalidate_expected_event_id();
         self.schedule_timer_call();
     }
 
-    fn base_time(&self) -> MsDuration {
-        let offset = self.suspension_offset.get();
+    /// Puts the timers taken off by `freeze` back, each with the time it still
+    /// had to wait then.
+    fn thaw(&self) {
+        let frozen = match self.frozen.borrow_mut().take() {
+            Some(frozen) => frozen,
+            None => return,
+        };
 
-        match self.suspended_since.get() {
-            Some(time) => time - offset,
-            None => precise_time_ms() - offset,
+        for frozen_timer in frozen {
+            let mut timer = OneshotTimer {
+                handle: frozen_timer.handle,
+                source: frozen_timer.source,
+                callback: frozen_timer.callback,
+                scheduled_for: frozen_timer.remaining,
+                exempt: frozen_timer.exempt,
+            };
+            timer.scheduled_for = self.clock_for(&timer) + frozen_timer.remaining;
+            self.insert_timer(timer);
         }
-    }
-
-    pub fn suspend(&self) {
-        assert!(self.suspended_since.get().is_none());
 
-        self.suspended_since.set(Some(precise_time_ms()));
         self.invalidate_expected_event_id();
+        self.schedule_timer_call();
     }
 
-    pub fn resume(&self) {
-        assert!(self.suspended_since.get().is_some());
+    /// The clock the deadline of the timer is measured on.
+    fn clock_for(&self, timer: &OneshotTimer<C>) -> MsDuration {
+        if self.is_running(timer.exempt, &timer.callback) {
+            self.live_time()
+        } else {
+            self.base_time()
+        }
+    }
 
-        let additional_offset = match self.suspended_since.get() {
-            Some(suspended_since) => precise_time_ms() - suspended_since,
-            None => panic!("Timers are not suspended.")
-        };
+    /// How long the timers have been suspended.
+    fn suspended_for(&self) -> u64 {
+        self.suspended_since.get().map_or(0, |since| self.now().get() - since.get())
+    }
 
-        self.suspension_offset.set(self.suspension_offset.get() + additional_offset);
-        self.suspended_since.set(None);
+    /// Stops the internal callbacks that kept running during a suspension, as
+    /// a suspension that covers them begins.
+    fn stop_internal_callbacks(&self) {
+        // Their deadlines are taken over to the stopped clock.
+        let elapsed = self.suspended_for();
+        self.shift_timers(|timer| !timer.exempt && !timer.callback.is_script_timer(),
+                          |scheduled_for| scheduled_for.saturating_sub(elapsed));
+        self.invalidate_expected_event_id();
+        self.schedule_timer_call();
+    }
 
+    /// Restarts the internal callbacks, as the last suspension that covers
+    /// them ends while others remain.
+    fn start_internal_callbacks(&self) {
+        // Their deadlines are taken over to the running clock.
+        let elapsed = self.suspended_for();
+        self.shift_timers(|timer| !timer.exempt && !timer.callback.is_script_timer(),
+                          |scheduled_for| scheduled_for + elapsed);
+        self.invalidate_expected_event_id();
         self.schedule_timer_call();
     }
 
-    fn schedule_timer_call(&self) {
-        if self.suspended_since.get().is_some() {
-            // The timer will be scheduled when the pipeline is thawed.
-            return;
+    /// Moves the deadlines of the timers `which` selects with `shift`.
+    fn shift_timers<W, S>(&self, which: W, shift: S)
+        where W: Fn(&OneshotTimer<C>) -> bool, S: Fn(u64) -> u64 {
+        let mut timers = self.timers.borrow_mut();
+        for timer in timers.iter_mut().filter(|timer| which(timer)) {
+            timer.scheduled_for = Length::new(shift(timer.scheduled_for.get()));
         }
+        timers.sort();
+    }
 
+    fn schedule_timer_call(&self) {
+        self.request_timer_event();
+        self.check_invariants();
+    }
+
+    /// Asks the scheduler for an event when the next timer is due, unless it
+    /// is going to deliver one anyway.
+    fn request_timer_event(&self) {
+        // While replaying, timers fire when the recording says they did.
+        let replayed_deadline = self.replayed_deadline();
+        let deadline = match replayed_deadline.or_else(|| self.next_deadline()) {
+            Some(deadline) => deadline,
+            None => return,
+        };
         let timers = self.timers.borrow();
+        let suspended = self.is_suspended();
+        // While suspended, the other timers will be scheduled when the pipeline is thawed.
+        let timer = timers.iter().rev().find(|timer| self.is_running(timer.exempt, &timer.callback));
+
+        if let Some(timer) = timer {
+            // Throttled timers are not aligned by the scheduler, so don't let it repeat them.
+            // In chaos mode, every run gets a delay of its own.
+            let period = if deadline == timer.scheduled_for && !suspended && replayed_deadline.is_none() &&
+                            self.chaos.borrow().is_none() {
+                self.repeat_period_of(&timer.callback)
+            } else {
+                None
+            };
+            if let (Some((handle, last_due)), Some(period)) = (self.requested_repeat.get(), period) {
+                if handle == timer.handle && last_due + period == timer.scheduled_for {
+                    // The scheduler is going to deliver the next run anyway.
+                    self.requested_repeat.set(Some((handle, timer.scheduled_for)));
+                    self.requested_deadline.set(timer.scheduled_for);
+                    return;
+                }
+            }
 
-        if let Some(timer) = timers.last() {
             let expected_event_id = self.invalidate_expected_event_id();
 
-            let delay = Length::new(timer.scheduled_for.get().saturating_sub(precise_time_ms().get()));
+            // Only window requests can be cancelled, and so only they may repeat.
+            let repeat = match timer.source {
+                TimerSource::FromWindow(_) => period,
+                TimerSource::FromWorker => None,
+            };
+            let mut delay = Length::new(deadline.get().saturating_sub(self.live_time().get()));
+            if let Some(ref mut rng) = *self.chaos.borrow_mut() {
+                delay = delay + Length::new(rng.gen_range(0, MAX_CHAOS_DELAY + 1));
+            }
             let request = TimerEventRequest(self.timer_event_chan.clone(), timer.source,
-                                            expected_event_id, delay);
-            self.scheduler_chan.send(request).unwrap();
+                                            expected_event_id, delay, timer.callback.priority(),
+                                            self.pipeline_id, repeat);
+            if self.scheduler_chan.send(TimerSchedulerMsg::Request(request)).is_err() {
+                // The scheduler has shut down, so no timer is going to fire again.
+                warn!("Timer scheduler is gone, couldn't request a timer event.");
+            }
+            self.requested_event.set(Some((timer.source, expected_event_id)));
+            self.requested_repeat.set(repeat.map(|_| (timer.handle, timer.scheduled_for)));
+            self.requested_deadline.set(deadline);
         }
     }
 
@@ -253,6 +2075,12 @@ impl OneshotTimers {
         let next_id = TimerEventId(currently_expected + 1);
         debug!("invalidating expected timer (was {:?}, now {:?}", currently_expected, next_id);
         self.expected_event_id.set(next_id);
+        // The event requested for the old id would be ignored; let the scheduler drop it.
+        if let Some((TimerSource::FromWindow(pipeline_id), id)) = self.requested_event.get() {
+            let _ = self.scheduler_chan.send(TimerSchedulerMsg::Cancel(pipeline_id, id));
+        }
+        self.requested_event.set(None);
+        self.requested_repeat.set(None);
         next_id
     }
 
@@ -273,9 +2101,21 @@ impl OneshotTimers {
                                                source)
     }
 
-    pub fn clear_timeout_or_interval(&self, global: GlobalRef, handle: i32) {
+    /// Returns whether there was such a timer to clear.
+    pub fn clear_timeout_or_interval(&self, global: GlobalRef, handle: i32) -> bool {
         self.js_timers.clear_timeout_or_interval(global, handle)
     }
+
+    /// Makes the debugger pause before the callback of the timer with `handle`
+    /// runs, or before that of any timer if `handle` is `None`.
+    pub fn set_timer_breakpoint(&self, handle: Option<i32>, enabled: bool) {
+        self.js_timers.set_breakpoint(handle, enabled)
+    }
+
+    /// Makes the debugger pause once, before the next timer callback runs.
+    pub fn pause_before_next_timer(&self) {
+        self.js_timers.break_on_next.set(true)
+    }
 }
 
 #[derive(JSTraceable, PartialEq, Eq, Copy, Clone, HeapSizeOf, Hash, PartialOrd, Ord)]
@@ -288,18 +2128,88 @@ pub struct JsTimers {
     active_timers: DOMRefCell<HashMap<JsTimerHandle, JsTimerEntry>>,
     /// The nesting level of the currently executing timer task or 0.
     nesting_level: Cell<u32>,
+    /// The stack that set the currently executing timer task, if it was kept,
+    /// and whether the task is an interval.
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    running_stack: DOMRefCell<Option<(IsInterval, Rc<String>)>>,
+    /// Whether the debugger pauses before any timer callback runs.
+    break_on_all: Cell<bool>,
+    /// Whether the debugger pauses before the next timer callback runs, once.
+    break_on_next: Cell<bool>,
+    /// The timers the debugger pauses before the callbacks of.
+    breakpoints: DOMRefCell<HashSet<JsTimerHandle>>,
+    /// The timers whose callbacks ran slow, with how often they did.
+    slow_timers: DOMRefCell<HashMap<JsTimerHandle, SlowTimer>>,
+    /// How many timers warned about being slow so far.
+    slow_timer_warnings: Cell<u32>,
+    /// The callbacks queued with `setImmediate` that haven't run yet.
+    immediates: DOMRefCell<HashMap<JsTimerHandle, ImmediateCallback>>,
+    /// The last timer callback that ran, to name it if the task ran long.
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    last_run: DOMRefCell<Option<RunningTimer>>,
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+struct SlowTimer {
+    runs: u32,
+    warned: bool,
+}
+
+#[derive(JSTraceable, HeapSizeOf)]
+struct ImmediateCallback {
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    function: Rc<Function>,
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    arguments: Rc<Vec<Heap<JSVal>>>,
+}
+
+/// Runs a callback queued with `setImmediate` as a task of its own, without
+/// going through the timer scheduler.
+struct ImmediateRunnable {
+    global: TrustedGlobal,
+    handle: i32,
+}
+
+enum TrustedGlobal {
+    Window(Trusted<Window>),
+    Worker(Trusted<WorkerGlobalScope>),
+}
+
+impl Runnable for ImmediateRunnable {
+    fn handler(self: Box<Self>) {
+        let this = *self;
+        // A window that was navigated away from, or a worker that is closing,
+        // doesn't run its callbacks any more.
+        match this.global {
+            TrustedGlobal::Window(window) => {
+                let window = window.root();
+                if window.is_alive() {
+                    window.run_immediate(this.handle);
+                }
+            },
+            TrustedGlobal::Worker(worker) => {
+                let worker = worker.root();
+                if !worker.is_closing() {
+                    worker.run_immediate(this.handle);
+                }
+            },
+        }
+    }
 }
 
 #[derive(JSTraceable, HeapSizeOf)]
 struct JsTimerEntry {
     oneshot_handle: OneshotTimerHandle,
+    /// How long the timer was set to wait.
+    duration: MsDuration,
+    is_interval: IsInterval,
 }
 
 // Holder for the various JS values associated with setTimeout
 // (ie. function value to invoke and all arguments to pass
 //      to the function when calling it)
 // TODO: Handle rooting during invocation when movable GC is turned on
-#[derive(JSTraceable, HeapSizeOf)]
+#[derive(JSTraceable, HeapSizeOf, Clone)]
 pub struct JsTimerTask {
     #[ignore_heap_size_of = "Because it is non-owning"]
     handle: JsTimerHandle,
@@ -308,6 +2218,12 @@ pub struct JsTimerTask {
     is_interval: IsInterval,
     nesting_level: u32,
     duration: MsDuration,
+    /// The stack of the script that set the timer, innermost frame first,
+    /// followed by the stacks of the timers that led to it, if devtools were
+    /// listening. It is shared with the timer callback running, so that it
+    /// can be named without copying it.
+    #[ignore_heap_size_of = "Rc<T> is hard"]
+    installed_stack: Option<Rc<String>>,
 }
 
 // Enum allowing more descriptive values for the is_interval field
@@ -342,6 +2258,14 @@ impl JsTimers {
             next_timer_handle: Cell::new(JsTimerHandle(1)),
             active_timers: DOMRefCell::new(HashMap::new()),
             nesting_level: Cell::new(0),
+            running_stack: DOMRefCell::new(None),
+            break_on_all: Cell::new(false),
+            break_on_next: Cell::new(false),
+            breakpoints: DOMRefCell::new(HashSet::new()),
+            slow_timers: DOMRefCell::new(HashMap::new()),
+            slow_timer_warnings: Cell::new(0),
+            immediates: DOMRefCell::new(HashMap::new()),
+            last_run: DOMRefCell::new(None),
         }
     }
 
@@ -357,18 +2281,8 @@ impl JsTimers {
         let callback = match callback {
             TimerCallback::StringTimerCallback(code_str) =>
                 InternalTimerCallback::StringTimerCallback(code_str),
-            TimerCallback::FunctionTimerCallback(function) => {
-                // This is a bit complicated, but this ensures that the vector's
-                // buffer isn't reallocated (and moved) after setting the Heap values
-                let mut args = Vec::with_capacity(arguments.len());
-                for _ in 0..arguments.len() {
-                    args.push(Heap::default());
-                }
-                for (i, item) in arguments.iter().enumerate() {
-                    args.get_mut(i).unwrap().set(item.get());
-                }
-                InternalTimerCallback::FunctionTimerCallback(function, Rc::new(args))
-            }
+            TimerCallback::FunctionTimerCallback(function) =>
+                InternalTimerCallback::FunctionTimerCallback(function, heap_arguments(&arguments)),
         };
 
         // step 2
@@ -377,6 +2291,15 @@ impl JsTimers {
 
         // step 3 as part of initialize_and_schedule below
 
+        // Only worth the cost of looking at the stack when the devtools may ask.
+        let installed_stack = if global.devtools_chan().is_some() {
+            let running_stack = self.running_stack.borrow();
+            let running_stack = running_stack.as_ref().map(|&(is_interval, ref stack)| (is_interval, stack.as_str()));
+            script_stack(global.get_cx()).map(|stack| Rc::new(chain_async_stack(stack, running_stack)))
+        } else {
+            None
+        };
+
         // step 4
         let mut task = JsTimerTask {
             handle: JsTimerHandle(new_handle),
@@ -385,26 +2308,103 @@ impl JsTimers {
             is_interval: is_interval,
             nesting_level: 0,
             duration: Length::new(0),
+            installed_stack: installed_stack,
         };
 
         // step 5
         task.duration = Length::new(cmp::max(0, timeout) as u64);
 
+        let marker = start_timer_marker(global, "TimerInstall");
+        let (handle, duration) = (task.handle, task.duration);
+
         // step 3, 6-9, 11-14
         self.initialize_and_schedule(global, task);
+        end_timer_marker(global, marker, handle, duration);
 
         // step 10
         new_handle
     }
 
-    pub fn clear_timeout_or_interval(&self, global: GlobalRef, handle: i32) {
+    // https://w3c.github.io/setImmediate/#si-setImmediate
+    pub fn set_immediate(&self, global: GlobalRef, function: Rc<Function>, arguments: Vec<HandleValue>) -> i32 {
+        let JsTimerHandle(new_handle) = self.next_timer_handle.get();
+        self.next_timer_handle.set(JsTimerHandle(new_handle + 1));
+
+        self.immediates.borrow_mut().insert(JsTimerHandle(new_handle), ImmediateCallback {
+            function: function,
+            arguments: heap_arguments(&arguments),
+        });
+
+        // The callback is queued as a task right away: it is neither clamped
+        // for nesting nor scheduled through the timer scheduler.
+        let trusted_global = match global {
+            GlobalRef::Window(window) => TrustedGlobal::Window(Trusted::new(window)),
+            GlobalRef::Worker(worker) => TrustedGlobal::Worker(Trusted::new(worker)),
+        };
+        let runnable = box ImmediateRunnable {
+            global: trusted_global,
+            handle: new_handle,
+        };
+        let msg = CommonScriptMsg::RunnableMsg(ScriptThreadEventCategory::TimerEvent, runnable);
+        if global.script_chan().send(msg).is_err() {
+            warn!("Queueing the callback of setImmediate failed.");
+        }
+        new_handle
+    }
+
+    // https://w3c.github.io/setImmediate/#si-clearImmediate
+    pub fn clear_immediate(&self, handle: i32) {
+        self.immediates.borrow_mut().remove(&JsTimerHandle(handle));
+    }
+
+    /// Runs the callback queued with `setImmediate` under `handle`, unless it
+    /// was cleared since.
+    pub fn run_immediate<T: Reflectable>(&self, this: &T, handle: i32) {
+        let callback = match self.immediates.borrow_mut().remove(&JsTimerHandle(handle)) {
+            Some(callback) => callback,
+            None => return,
+        };
+        let arguments: Vec<JSVal> = callback.arguments.iter().map(|arg| arg.get()).collect();
+        let arguments = arguments.iter().by_ref().map(|arg| unsafe {
+            HandleValue::from_marked_location(arg)
+        }).collect();
+        let _ = callback.function.Call_(this, arguments, Report);
+    }
+
+    pub fn clear_timeout_or_interval(&self, global: GlobalRef, handle: i32) -> bool {
         let mut active_timers = self.active_timers.borrow_mut();
 
-        if let Some(entry) = active_timers.remove(&JsTimerHandle(handle)) {
+        let entry = active_timers.remove(&JsTimerHandle(handle));
+        if let Some(ref entry) = entry {
+            let marker = start_timer_marker(global, "TimerCancel");
             global.unschedule_callback(entry.oneshot_handle);
+            end_timer_marker(global, marker, JsTimerHandle(handle), entry.duration);
+        }
+        self.breakpoints.borrow_mut().remove(&JsTimerHandle(handle));
+        self.slow_timers.borrow_mut().remove(&JsTimerHandle(handle));
+        entry.is_some()
+    }
+
+    pub fn set_breakpoint(&self, handle: Option<i32>, enabled: bool) {
+        match handle {
+            None => self.break_on_all.set(enabled),
+            Some(handle) if enabled => {
+                self.breakpoints.borrow_mut().insert(JsTimerHandle(handle));
+            },
+            Some(handle) => {
+                self.breakpoints.borrow_mut().remove(&JsTimerHandle(handle));
+            },
         }
     }
 
+    /// Whether the debugger pauses before the callback of the timer with `handle`,
+    /// which uses up a pause before the next callback.
+    fn breaks_before(&self, handle: JsTimerHandle) -> bool {
+        let break_on_next = self.break_on_next.get();
+        self.break_on_next.set(false);
+        break_on_next || self.break_on_all.get() || self.breakpoints.borrow().contains(&handle)
+    }
+
     // see https://html.spec.whatwg.org/multipage/#timer-initialisation-steps
     fn initialize_and_schedule(&self, global: GlobalRef, mut task: JsTimerTask) {
         let handle = task.handle;
@@ -419,6 +2419,8 @@ impl JsTimers {
         // step 8, 9
         task.nesting_level = nesting_level + 1;
 
+        let is_interval = task.is_interval;
+
         // essentially step 11-14
         let callback = OneshotTimerCallback::JsTimer(task);
         let oneshot_handle = global.schedule_callback(callback, duration);
@@ -426,8 +2428,249 @@ impl JsTimers {
         // step 3
         let entry = active_timers.entry(handle).or_insert(JsTimerEntry {
             oneshot_handle: oneshot_handle,
+            duration: duration,
+            is_interval: is_interval,
         });
         entry.oneshot_handle = oneshot_handle;
+        entry.duration = duration;
+    }
+
+    /// Warns on the console, once per timer, about a timer whose callback keeps
+    /// running long, or an interval whose callback takes longer than its period.
+    fn check_run_time(&self, global: GlobalRef, task: &JsTimerTask, run_time: MsDuration) {
+        let overran = task.repeat_period().map_or(false, |period| period.get() > 0 && run_time > period);
+        if !overran && run_time.get() < SLOW_TIMER_CALLBACK_MS {
+            return;
+        }
+        // A timer cleared by its own callback has nothing more to warn about.
+        if !self.active_timers.borrow().contains_key(&task.handle) {
+            return;
+        }
+        let mut slow_timers = self.slow_timers.borrow_mut();
+        let slow_timer = slow_timers.entry(task.handle).or_insert(SlowTimer { runs: 0, warned: false });
+        slow_timer.runs += 1;
+        if slow_timer.warned || (!overran && slow_timer.runs < SLOW_RUNS_BEFORE_WARNING) {
+            return;
+        }
+        slow_timer.warned = true;
+        let warnings = self.slow_timer_warnings.get();
+        if warnings >= MAX_SLOW_TIMER_WARNINGS {
+            return;
+        }
+        self.slow_timer_warnings.set(warnings + 1);
+
+        let JsTimerHandle(handle) = task.handle;
+        let mut message = match task.repeat_period() {
+            Some(period) if overran => {
+                format!("The callback of interval {} took {} ms, longer than its period of {} ms. \
+                         Consider a longer period, or setting the next run with setTimeout once \
+                         the callback is done.", handle, run_time.get(), period.get())
+            },
+            _ => {
+                format!("The callback of timer {} took {} ms or more {} times, {} ms the last time. \
+                         Consider splitting the work up into shorter callbacks.",
+                        handle, SLOW_TIMER_CALLBACK_MS, slow_timer.runs, run_time.get())
+            },
+        };
+        if warnings + 1 == MAX_SLOW_TIMER_WARNINGS {
+            message.push_str(" Further slow timers will not be reported.");
+        }
+        report_slow_timer(global, task.installed_stack.as_ref().map(|stack| stack.as_str()), message);
+    }
+}
+
+/// Follows `stack` with the stack that set the running timer task, if any,
+/// marking the frame that set it like the debugger marks async frames.
+pub fn chain_async_stack(stack: String, running_stack: Option<(IsInterval, &str)>) -> String {
+    let mut frames: Vec<String> = stack.lines().map(|frame| frame.to_owned()).collect();
+    if let Some((is_interval, running_stack)) = running_stack {
+        frames.extend(running_stack.lines().enumerate().map(|(index, frame)| {
+            if index == 0 {
+                format!("{} handler*{}", set_by(is_interval), frame)
+            } else {
+                frame.to_owned()
+            }
+        }));
+    }
+    frames.truncate(MAX_INSTALLED_STACK_FRAMES);
+    frames.join("\n")
+}
+
+/// The function that sets timers like the ones `is_interval` describes.
+fn set_by(is_interval: IsInterval) -> &'static str {
+    match is_interval {
+        IsInterval::Interval => "setInterval",
+        IsInterval::NonInterval => "setTimeout",
+    }
+}
+
+/// Like an event listener breakpoint: waits right before the callback of the
+/// timer with `handle` until the devtools resume the script. The time spent
+/// paused doesn't count for the timers.
+fn pause_before_timer(global: GlobalRef, handle: JsTimerHandle) {
+    let chan = match global.devtools_chan() {
+        Some(chan) => chan,
+        None => return,
+    };
+    let _suspension = global.suspend_timers_scoped(SuspensionReason::Debugger);
+    let JsTimerHandle(handle) = handle;
+    let (resume_chan, resume_port) = ipc::channel().unwrap();
+    let msg = ScriptToDevtoolsControlMsg::PausedBeforeTimer(global.pipeline(), global.get_worker_id(), handle,
+                                                            resume_chan);
+    if chan.send(msg).is_err() {
+        return;
+    }
+    // The devtools drop the sender when they go away, which resumes the script too.
+    let _ = resume_port.recv();
+}
+
+/// Starts a timeline marker about a timer, if the devtools are recording them.
+fn start_timer_marker(global: GlobalRef, name: &str) -> Option<StartedTimelineMarker> {
+    match global {
+        GlobalRef::Window(window) if window.need_emit_timeline_marker(TimelineMarkerType::Timer) =>
+            Some(TimelineMarker::start(name.to_owned())),
+        _ => None,
+    }
+}
+
+fn end_timer_marker(global: GlobalRef,
+                    marker: Option<StartedTimelineMarker>,
+                    handle: JsTimerHandle,
+                    delay: MsDuration) {
+    if let (GlobalRef::Window(window), Some(marker)) = (global, marker) {
+        let JsTimerHandle(handle) = handle;
+        let mut marker = marker.end();
+        marker.timer = Some(TimerMarkerDetail {
+            handle: handle,
+            delay: delay.get(),
+        });
+        window.emit_timeline_marker(marker);
+    }
+}
+
+/// The stack of the script running, one `function@url:line:column` frame
+/// per line, innermost first.
+#[allow(unsafe_code)]
+fn script_stack(cx: *mut JSContext) -> Option<String> {
+    unsafe {
+        let mut stack = RootedObject::new(cx, ptr::null_mut());
+        if !CaptureCurrentStack(cx, stack.handle_mut(), MAX_INSTALLED_STACK_FRAMES as u32) || stack.ptr.is_null() {
+            return None;
+        }
+        let mut string = RootedString::new(cx, ptr::null_mut());
+        if !BuildStackString(cx, stack.handle(), string.handle_mut(), 0) || string.ptr.is_null() {
+            return None;
+        }
+        Some(String::from(jsstring_to_str(cx, string.ptr)).trim_right().to_owned())
+    }
+}
+
+/// The url, line and column of a `function@url:line:column` stack frame.
+fn frame_location(frame: &str) -> (String, usize, usize) {
+    let mut parts = frame.rsplitn(3, ':');
+    let column = parts.next().and_then(|column| column.parse().ok()).unwrap_or(0);
+    let line = parts.next().and_then(|line| line.parse().ok()).unwrap_or(0);
+    let url = parts.next().and_then(|rest| rest.splitn(2, '@').nth(1)).unwrap_or("");
+    (url.to_owned(), line, column)
+}
+
+/// Tells where the timer whose callback just threw was set, following the
+/// report of the exception.
+fn report_installed_stack(global: GlobalRef, stack: &str) {
+    let message = format!("The timer whose callback threw was set at:\n{}", stack);
+    debug!("{}", message);
+    send_console_message(global, LogLevel::Error, message, stack);
+}
+
+/// Warns about a slow timer, naming where it was set if that is known.
+fn report_slow_timer(global: GlobalRef, stack: Option<&str>, message: String) {
+    let message = match stack.and_then(|stack| stack.lines().next()) {
+        Some(frame) => format!("{} The timer was set at {}.", message, frame),
+        None => message,
+    };
+    warn!("{}", message);
+    send_console_message(global, LogLevel::Warn, message, stack.unwrap_or(""));
+}
+
+/// Shows `message` on the devtools console, at the innermost frame of `stack`.
+fn send_console_message(global: GlobalRef, level: LogLevel, message: String, stack: &str) {
+    if let Some(chan) = global.devtools_chan() {
+        let (filename, line, column) = frame_location(stack.lines().next().unwrap_or(""));
+        let message = ConsoleMessage {
+            message: message,
+            logLevel: level,
+            filename: filename,
+            lineNumber: line,
+            columnNumber: column,
+        };
+        let _ = chan.send(ScriptToDevtoolsControlMsg::ConsoleAPI(global.pipeline(), message, global.get_worker_id()));
+    }
+}
+
+/// Copies the arguments of a timer callback to the heap, where they wait for
+/// the callback to run.
+fn heap_arguments(arguments: &[HandleValue]) -> Rc<Vec<Heap<JSVal>>> {
+    // This is a bit complicated, but this ensures that the vector's
+    // buffer isn't reallocated (and moved) after setting the Heap values
+    let mut args = Vec::with_capacity(arguments.len());
+    for _ in 0..arguments.len() {
+        args.push(Heap::default());
+    }
+    for (i, item) in arguments.iter().enumerate() {
+        args.get_mut(i).unwrap().set(item.get());
+    }
+    Rc::new(args)
+}
+
+/// How a timer is described in the timer log.
+fn timer_kind<C: ScheduledCallback>(callback: &C) -> &'static str {
+    if callback.is_script_timer() { "script" } else { "internal" }
+}
+
+/// What set the timer callback running on this thread, and where, if that is known.
+type RunningTimer = (IsInterval, Option<Rc<String>>);
+
+thread_local!(static CURRENT_TIMER: RefCell<Option<RunningTimer>> = RefCell::new(None));
+
+/// The timer callback running on this thread, if any, as in
+/// `setInterval @ https://example.com/foo.js:123`, so that profilers can
+/// attribute the time spent to the timer rather than to the timer event.
+/// The name is only put together when asked for.
+pub fn current_timer() -> Option<String> {
+    CURRENT_TIMER.with(|current| current.borrow().as_ref().map(|&(is_interval, ref stack)| {
+        timer_label(is_interval, stack.as_ref().map(|stack| stack.as_str()))
+    }))
+}
+
+/// How a timer is called in profiles: by what set it, and where, if that is known.
+fn timer_label(is_interval: IsInterval, stack: Option<&str>) -> String {
+    let set_by = set_by(is_interval);
+    match stack.and_then(|stack| stack.lines().next()) {
+        Some(frame) => {
+            let (url, line, _) = frame_location(frame);
+            format!("{} @ {}:{}", set_by, url, line)
+        },
+        None => set_by.to_owned(),
+    }
+}
+
+/// Makes a timer callback the current one until dropped, when the one it
+/// interrupted, if any, becomes current again.
+struct CurrentTimer {
+    interrupted: Option<RunningTimer>,
+}
+
+impl CurrentTimer {
+    fn enter(timer: RunningTimer) -> CurrentTimer {
+        CurrentTimer {
+            interrupted: CURRENT_TIMER.with(|current| mem::replace(&mut *current.borrow_mut(), Some(timer))),
+        }
+    }
+}
+
+impl Drop for CurrentTimer {
+    fn drop(&mut self) {
+        CURRENT_TIMER.with(|current| *current.borrow_mut() = self.interrupted.take());
     }
 }
 
@@ -451,9 +2694,22 @@ impl JsTimerTask {
 
         // prep for step 6 in nested set_timeout_or_interval calls
         timers.nesting_level.set(self.nesting_level);
+        *timers.running_stack.borrow_mut() = self.installed_stack.clone().map(|stack| (self.is_interval, stack));
+
+        if timers.breaks_before(self.handle) {
+            pause_before_timer(this.global().r(), self.handle);
+        }
+
+        let global = this.global();
+        let marker = start_timer_marker(global.r(), "TimerFire");
+        let start = precise_time_ns();
+        let running_timer = (self.is_interval, self.installed_stack.clone());
+        *timers.last_run.borrow_mut() = Some(running_timer.clone());
+        let current_timer = CurrentTimer::enter(running_timer);
 
         // step 4.2
-        match *&self.callback {
+        let time_profiler_chan = global.r().time_profiler_chan().clone();
+        profile(ProfilerCategory::ScriptTimerCallback, None, time_profiler_chan, || match *&self.callback {
             InternalTimerCallback::StringTimerCallback(ref code_str) => {
                 let cx = this.global().r().get_cx();
                 let mut rval = RootedValue::new(cx, UndefinedValue());
@@ -466,20 +2722,71 @@ impl JsTimerTask {
                     HandleValue::from_marked_location(arg)
                 }).collect();
 
-                let _ = function.Call_(this, arguments, Report);
+                match self.installed_stack {
+                    Some(ref stack) => {
+                        // Report the exception here, to tell where the timer was set after it.
+                        if function.Call_(this, arguments, Rethrow).is_err() {
+                            report_pending_exception(global.r().get_cx(), this.reflector().get_jsobject().get());
+                            report_installed_stack(global.r(), stack);
+                            // The microtasks were left until the exception was dealt with.
+                            global.r().perform_a_microtask_checkpoint();
+                        }
+                    },
+                    None => {
+                        let _ = function.Call_(this, arguments, Report);
+                    },
+                }
             },
-        };
+        });
+
+        drop(current_timer);
+        end_timer_marker(global.r(), marker, self.handle, self.duration);
+        timers.check_run_time(global.r(), &self, Length::new((precise_time_ns() - start).get() / (1000 * 1000)));
 
         // reset nesting level (see above)
         timers.nesting_level.set(0);
+        *timers.running_stack.borrow_mut() = None;
+
+        // A timeout that has run has nothing more to break on or warn about.
+        if let IsInterval::NonInterval = self.is_interval {
+            timers.breakpoints.borrow_mut().remove(&self.handle);
+            timers.slow_timers.borrow_mut().remove(&self.handle);
+        }
+
+        // step 4.3 is taken care of by OneshotTimers, which reschedules repeating
+        // timers once they return, unless step 4.2 cleared them.
+    }
 
-        // step 4.3
-        // Since we choose proactively prevent execution (see 4.1 above), we must only
-        // reschedule repeating timers when they were not canceled as part of step 4.2.
-        if self.is_interval == IsInterval::Interval &&
-            timers.active_timers.borrow().contains_key(&self.handle) {
+    fn describe(&self, remaining: MsDuration) -> TimerInfo {
+        let JsTimerHandle(handle) = self.handle;
+        TimerInfo {
+            handle: Some(handle),
+            kind: match self.is_interval {
+                IsInterval::Interval => TimerKind::Interval,
+                IsInterval::NonInterval => TimerKind::Timeout,
+            },
+            remaining: remaining.get(),
+            period: self.repeat_period().map(|period| period.get()),
+            nesting_level: self.nesting_level,
+            installed_from: self.installed_stack.as_ref().and_then(|stack| stack.lines().next())
+                                                         .map(|frame| frame.to_owned()),
+            installed_stack: self.installed_stack.as_ref().map(|stack| (**stack).clone()),
+        }
+    }
 
-            timers.initialize_and_schedule(this.global().r(), self);
+    fn repeat_period(&self) -> Option<MsDuration> {
+        match self.is_interval {
+            IsInterval::Interval => Some(self.duration),
+            IsInterval::NonInterval => None,
         }
     }
+
+    fn next_occurrence(&self) -> Option<JsTimerTask> {
+        // Rerunning the timer initialisation steps from this task, see steps 6 to 8.
+        self.repeat_period().map(|duration| JsTimerTask {
+            duration: clamp_duration(self.nesting_level, duration),
+            nesting_level: self.nesting_level.saturating_add(1),
+            .. self.clone()
+        })
+    }
 }
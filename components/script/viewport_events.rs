@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Coalescing of the events that are fired when the viewport changes.
+//!
+//! The compositor may report many viewport changes while the window is being
+//! resized or scrolled. Rather than firing an event for each, a window fires one event of
+//! each kind when a timer fires. The events then also wait while the timers of
+//! the window are suspended.
+
+use dom::bindings::cell::DOMRefCell;
+use dom::bindings::refcounted::Trusted;
+use dom::window::Window;
+use std::cell::Cell;
+use util::prefs;
+
+/// How long the events wait to be merged with the ones that come after them,
+/// unless the `dom.viewport_events.interval_ms` pref says otherwise: a frame.
+const DEFAULT_INTERVAL_MS: u64 = 16;
+
+/// How long the events wait to be merged with the ones that come after them.
+fn default_interval_ms() -> u64 {
+    match prefs::get_pref("dom.viewport_events.interval_ms").as_i64() {
+        Some(interval) if interval >= 0 => interval as u64,
+        _ => DEFAULT_INTERVAL_MS,
+    }
+}
+
+/// An event that is fired when the viewport changes, in the order the kinds
+/// are fired in.
+#[derive(JSTraceable, HeapSizeOf, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ViewportEvent {
+    // https://drafts.csswg.org/cssom-view/#run-the-resize-steps
+    Resize,
+    // https://drafts.csswg.org/cssom-view/#run-the-scroll-steps
+    Scroll,
+}
+
+/// The events that wait for the timer of a window.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct PendingViewportEvents {
+    events: DOMRefCell<Vec<ViewportEvent>>,
+    /// How long the events of this window wait, in milliseconds.
+    interval_ms: Cell<u64>,
+}
+
+impl PendingViewportEvents {
+    pub fn new() -> PendingViewportEvents {
+        PendingViewportEvents {
+            events: DOMRefCell::new(vec![]),
+            interval_ms: Cell::new(default_interval_ms()),
+        }
+    }
+
+    pub fn interval_ms(&self) -> u64 {
+        self.interval_ms.get()
+    }
+
+    /// Changes how long the events wait from the next time the timer is
+    /// scheduled; a timer that is already running keeps its delay.
+    pub fn set_interval_ms(&self, interval_ms: u64) {
+        self.interval_ms.set(interval_ms);
+    }
+
+    /// Adds an event, unless one of its kind is waiting already. Returns
+    /// whether the timer has to be scheduled, because no event was waiting.
+    pub fn queue(&self, event: ViewportEvent) -> bool {
+        let mut events = self.events.borrow_mut();
+        let schedule = events.is_empty();
+        if !events.contains(&event) {
+            events.push(event);
+        }
+        schedule
+    }
+
+    /// Takes the events that waited for the timer, in the order they are fired.
+    pub fn take(&self) -> Vec<ViewportEvent> {
+        let mut events: Vec<ViewportEvent> = self.events.borrow_mut().drain(..).collect();
+        events.sort();
+        events
+    }
+}
+
+/// Fires the events that were merged since the timer was scheduled.
+#[derive(JSTraceable, HeapSizeOf)]
+pub struct ViewportEventsDue {
+    #[ignore_heap_size_of = "Because it is non-owning"]
+    window: Trusted<Window>,
+}
+
+impl ViewportEventsDue {
+    pub fn new(window: &Window) -> ViewportEventsDue {
+        ViewportEventsDue {
+            window: Trusted::new(window),
+        }
+    }
+
+    pub fn invoke(self) {
+        let window = self.window.root();
+        if window.is_alive() {
+            window.fire_viewport_events();
+        }
+    }
+}
@@ -23,6 +23,7 @@ use dom::htmlinputelement::HTMLInputElement;
 use dom::htmloptionelement::HTMLOptionElement;
 use dom::node::Node;
 use dom::window::ScriptHelpers;
+use euclid::length::Length;
 use euclid::point::Point2D;
 use euclid::rect::Rect;
 use euclid::size::Size2D;
@@ -178,6 +179,11 @@ pub fn handle_get_active_element(context: &BrowsingContext,
         |elem| elem.upcast::<Node>().unique_id())).unwrap();
 }
 
+pub fn handle_advance_timers(context: &BrowsingContext, pipeline: PipelineId, ms: u64) {
+    let context = get_browsing_context(&context, pipeline);
+    context.active_window().advance_timer_clock(Length::new(ms));
+}
+
 pub fn handle_get_title(context: &BrowsingContext, _pipeline: PipelineId, reply: IpcSender<String>) {
     reply.send(String::from(context.active_document().Title())).unwrap();
 }
@@ -123,6 +123,20 @@ pub enum ConstellationControlMsg {
     Freeze(PipelineId),
     /// Notifies script thread to resume all its timers
     Thaw(PipelineId),
+    /// Notifies script thread whether the document of a pipeline is visible
+    ChangeVisibility(PipelineId, bool),
+    /// Notifies script thread whether the cross-origin frame of a pipeline can be seen
+    ChangeFrameVisibility(PipelineId, bool),
+    /// Notifies script thread to suspend or resume the timers of a pipeline
+    SuspendTimers(PipelineId, bool),
+    /// Asks the script thread to reply once a pipeline has settled: its document has
+    /// loaded, no timer is due within the given number of ms, and no task is waiting.
+    NotifyWhenQuiescent(PipelineId, u64, IpcSender<()>),
+    /// Asks the script thread to reply right away whether a pipeline has loaded and no
+    /// timer is due within the given number of ms.
+    IsQuiescent(PipelineId, u64, IpcSender<bool>),
+    /// Requests the statistics of the timers of a pipeline, for about:timers.
+    GetTimerStats(PipelineId, IpcSender<PipelineTimerStats>),
     /// Notifies script thread that a url should be loaded in this iframe.
     Navigate(PipelineId, SubpageId, LoadData),
     /// Requests the script thread forward a mozbrowser event to an iframe it owns
@@ -248,17 +262,149 @@ pub enum TouchpadPressurePhase {
 pub struct OpaqueScriptLayoutChannel(pub (Box<Any + Send>, Box<Any + Send>));
 
 /// Requests a TimerEvent-Message be sent after the given duration.
+/// The sixth field is the pipeline the requesting window or worker belongs to.
+/// If the last field is set, the TimerEvent is sent again, with the same id,
+/// every time that period has passed, until the request is cancelled. Only
+/// windows may ask for that, since worker requests can't be cancelled.
+///
+/// The duration counts from when the scheduler receives the request. Deadlines
+/// are never sent as absolute times: the scheduler may run in another process,
+/// whose clock can have a different base than `precise_time_ms` in script.
 #[derive(Deserialize, Serialize)]
 pub struct TimerEventRequest(pub IpcSender<TimerEvent>,
                              pub TimerSource,
                              pub TimerEventId,
-                             pub MsDuration);
+                             pub MsDuration,
+                             pub TimerPriority,
+                             pub PipelineId,
+                             pub Option<MsDuration>);
+
+/// How precisely the scheduler has to honor the deadline of a TimerEvent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, HeapSizeOf, Deserialize, Serialize)]
+pub enum TimerPriority {
+    /// The event may arrive up to about a millisecond late.
+    Normal,
+    /// The event is delivered as close to its deadline as possible, at the cost
+    /// of some busy waiting in the scheduler. Should only be used by timers
+    /// that drive user-visible updates.
+    UserBlocking,
+}
+
+/// Messages sent to the timer scheduler.
+#[derive(Deserialize, Serialize)]
+pub enum TimerSchedulerMsg {
+    /// Schedule a TimerEvent.
+    Request(TimerEventRequest),
+    /// Drop a window's pending TimerEvent that is no longer expected, so that it
+    /// doesn't wake up the script thread for nothing. Worker events can't be
+    /// cancelled, since their ids are not unique among workers.
+    Cancel(PipelineId, TimerEventId),
+    /// Drop every pending TimerEvent requested by a pipeline or its workers,
+    /// because the pipeline has exited.
+    ExitPipeline(PipelineId),
+    /// Delay normal priority TimerEvents to the next multiple of the given number
+    /// of milliseconds, so that events of all pipelines share wakeups; or stop doing
+    /// so, if None.
+    AlignWakeups(Option<u64>),
+    /// Report what the scheduler is currently doing, for diagnosing late timers.
+    GetStats(IpcSender<TimerSchedulerStats>),
+    /// Drop every pending TimerEvent and shut the scheduler down, acknowledging
+    /// once it is done. Requests sent afterwards fail.
+    Exit(IpcSender<()>),
+}
+
+/// Delivers TimerEvents when they are due. Servo has a backend of its own, which
+/// runs on a dedicated thread; embedders may supply theirs instead, for example
+/// to make use of the timers of an event loop they already run.
+///
+/// The methods are called on a thread of their own, in the order the messages
+/// to the scheduler were received.
+pub trait TimerSchedulerBackend: Send {
+    /// Send the TimerEvent described by `request` once its duration, counted
+    /// from now, has passed, and again every repeat period after that, if the
+    /// request has one.
+    fn arm(&mut self, request: TimerEventRequest);
+    /// Don't send the given window's TimerEvent, if it is still pending.
+    fn cancel(&mut self, pipeline_id: PipelineId, id: TimerEventId);
+    /// Drop every pending TimerEvent and stop. Nothing is called afterwards.
+    fn shutdown(&mut self);
+    /// Drop every pending TimerEvent of a pipeline that has exited. By default
+    /// they are delivered anyway, and ignored.
+    fn exit_pipeline(&mut self, _pipeline_id: PipelineId) {}
+    /// See `TimerSchedulerMsg::AlignWakeups`. Ignored by default.
+    fn align_wakeups(&mut self, _alignment: Option<u64>) {}
+    /// Reply with the statistics of the backend. By default the sender is
+    /// dropped, so the query fails.
+    fn report_stats(&mut self, _sender: IpcSender<TimerSchedulerStats>) {}
+}
+
+/// A snapshot of the timer scheduler's bookkeeping.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimerSchedulerStats {
+    /// The number of TimerEvents waiting to be delivered, per pipeline.
+    pub pending: Vec<(PipelineId, usize)>,
+    /// How often the scheduler thread woke up per second, measured over the
+    /// last second or so.
+    pub wakeups_per_second: f64,
+    /// How late normal priority TimerEvents were delivered.
+    pub normal_latency: TimerLatencyStats,
+    /// How late user-blocking TimerEvents were delivered.
+    pub user_blocking_latency: TimerLatencyStats,
+    /// Requests that were cancelled, superseded or whose pipeline exited before
+    /// they were due.
+    pub dropped: u64,
+    /// TimerEvents that were due, but whose receiver had gone away.
+    pub stale: u64,
+    /// The number of messages waiting to reach the scheduler thread, and the
+    /// most there ever were.
+    pub queued: usize,
+    pub max_queued: usize,
+}
+
+/// How late TimerEvents of one priority were delivered.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimerLatencyStats {
+    /// The number of TimerEvents delivered.
+    pub events: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+/// What the timers of a pipeline are up to, as shown on about:timers.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PipelineTimerStats {
+    pub pipeline_id: PipelineId,
+    pub url: String,
+    /// The number of scheduled timers, frozen ones included, and how many of
+    /// them run page script.
+    pub timers: usize,
+    pub script_timers: usize,
+    /// How many ms until the next timer is due, if any is scheduled.
+    pub next_due: Option<u64>,
+    /// Whether the timers are throttled because the document, or the frame it
+    /// is shown in, can't be seen.
+    pub throttled: bool,
+    pub throttling_exempt: bool,
+    /// Why the timers are suspended, with how many suspensions for each reason.
+    pub suspensions: Vec<(String, u32)>,
+    pub frozen: bool,
+    pub delay_multiplier: u64,
+}
+
+/// The timer statistics of every pipeline and of the timer scheduler.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimerDiagnostics {
+    pub pipelines: Vec<PipelineTimerStats>,
+    /// None if the scheduler backend doesn't report statistics.
+    pub scheduler: Option<TimerSchedulerStats>,
+}
 
 /// Notifies the script thread to fire due timers.
 /// TimerSource must be FromWindow when dispatched to ScriptThread and
-/// must be FromWorker when dispatched to a DedicatedGlobalWorkerScope
+/// must be FromWorker when dispatched to a DedicatedGlobalWorkerScope.
+/// The priority is the one the event was requested with.
 #[derive(Deserialize, Serialize)]
-pub struct TimerEvent(pub TimerSource, pub TimerEventId);
+pub struct TimerEvent(pub TimerSource, pub TimerEventId, pub TimerPriority);
 
 /// Describes the thread that requested the TimerEvent.
 #[derive(Copy, Clone, HeapSizeOf, Deserialize, Serialize)]
@@ -270,7 +416,7 @@ pub enum TimerSource {
 }
 
 /// The id to be used for a TimerEvent is defined by the corresponding TimerEventRequest.
-#[derive(PartialEq, Eq, Copy, Clone, Debug, HeapSizeOf, Deserialize, Serialize)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash, HeapSizeOf, Deserialize, Serialize)]
 pub struct TimerEventId(pub u32);
 
 /// Unit of measurement.
@@ -317,7 +463,7 @@ pub struct InitialScriptState {
     /// A channel for sending panics to the constellation.
     pub panic_chan: IpcSender<PanicMsg>,
     /// A channel to schedule timer events.
-    pub scheduler_chan: IpcSender<TimerEventRequest>,
+    pub scheduler_chan: IpcSender<TimerSchedulerMsg>,
     /// A channel to the resource manager thread.
     pub resource_threads: ResourceThreads,
     /// A channel to the bluetooth thread.
@@ -8,6 +8,7 @@ use IFrameLoadInfo;
 use MouseButton;
 use MouseEventType;
 use MozBrowserEvent;
+use TimerDiagnostics;
 use canvas_traits::CanvasMsg;
 use euclid::point::Point2D;
 use euclid::size::Size2D;
@@ -49,6 +50,8 @@ pub enum ScriptMsg {
     DOMLoad(PipelineId),
     /// Notifies the constellation that this frame has received focus.
     Focus(PipelineId),
+    /// Notifies the constellation whether the cross-origin document of an iframe can be seen.
+    ChangeFrameVisibility(PipelineId, bool),
     /// Re-send a mouse button event that was sent to the parent window.
     ForwardMouseButtonEvent(PipelineId, MouseEventType, MouseButton, Point2D<f32>),
     /// Re-send a mouse move event that was sent to the parent window.
@@ -83,4 +86,8 @@ pub enum ScriptMsg {
     SetFinalUrl(PipelineId, Url),
     /// Check if an alert dialog box should be presented
     Alert(PipelineId, String, IpcSender<bool>),
+    /// The page-load watchdog of a pipeline expired before its load event fired.
+    LoadTimedOut(PipelineId),
+    /// Requests the statistics of the timers of every pipeline and of the timer scheduler.
+    GetTimerDiagnostics(IpcSender<TimerDiagnostics>),
 }
@@ -79,6 +79,7 @@ use profile::mem as profile_mem;
 use profile::time as profile_time;
 use profile_traits::mem;
 use profile_traits::time;
+use script_traits::TimerSchedulerBackend;
 use std::rc::Rc;
 use std::sync::mpsc::Sender;
 use util::resource_files::resources_dir_path;
@@ -116,6 +117,7 @@ impl Browser {
         let (compositor_proxy, compositor_receiver) =
             window.create_compositor_channel();
         let supports_clipboard = window.supports_clipboard();
+        let timer_scheduler_backend = window.timer_scheduler_backend();
         let time_profiler_chan = profile_time::Profiler::create(&opts.time_profiling,
                                                                 opts.time_profiler_trace_path.clone());
         let mem_profiler_chan = profile_mem::Profiler::create(opts.mem_profiler_period);
@@ -159,7 +161,8 @@ impl Browser {
                                                       mem_profiler_chan.clone(),
                                                       devtools_chan,
                                                       supports_clipboard,
-                                                      webrender_api_sender.clone());
+                                                      webrender_api_sender.clone(),
+                                                      timer_scheduler_backend);
 
         if cfg!(feature = "webdriver") {
             if let Some(port) = opts.webdriver_port {
@@ -207,7 +210,9 @@ fn create_constellation(opts: opts::Opts,
                         mem_profiler_chan: mem::ProfilerChan,
                         devtools_chan: Option<Sender<devtools_traits::DevtoolsControlMsg>>,
                         supports_clipboard: bool,
-                        webrender_api_sender: Option<webrender_traits::RenderApiSender>) -> Sender<ConstellationMsg> {
+                        webrender_api_sender: Option<webrender_traits::RenderApiSender>,
+                        timer_scheduler_backend: Option<Box<TimerSchedulerBackend>>)
+                        -> Sender<ConstellationMsg> {
     let bluetooth_thread: IpcSender<BluetoothMethodMsg> = BluetoothThreadFactory::new();
 
     let resource_threads = new_resource_threads(opts.user_agent.clone(),
@@ -229,6 +234,7 @@ fn create_constellation(opts: opts::Opts,
         mem_profiler_chan: mem_profiler_chan,
         supports_clipboard: supports_clipboard,
         webrender_api_sender: webrender_api_sender,
+        timer_scheduler_backend: timer_scheduler_backend,
     };
     let constellation_chan =
         Constellation::<layout::layout_thread::LayoutThread,
@@ -182,6 +182,32 @@ pub struct Opts {
     /// True to exit after the page load (`-x`).
     pub exit_after_load: bool,
 
+    /// If set, the number of seconds a page may take to fire its load event before
+    /// the embedder is notified that the load has stalled.
+    pub page_load_timeout: Option<u64>,
+
+    /// If set, timers don't wait for real time to pass: the timer scheduler skips ahead
+    /// to the next due timer, until it has skipped this many milliseconds in total.
+    pub virtual_time_budget: Option<u64>,
+
+    /// If set, the directory each pipeline writes the timer events it got to when it exits.
+    pub record_timer_events: Option<String>,
+
+    /// If set, the directory of timer events recorded by an earlier run, which
+    /// pipelines fire their timers by instead of the timer scheduler.
+    pub replay_timer_events: Option<String>,
+
+    /// If set, the file every pipeline appends a JSON record of each timer being
+    /// scheduled, fired, cancelled, suspended, resumed or throttled to.
+    pub timer_log: Option<String>,
+
+    /// Shuffle timers that are due at the same time and delay timers a little,
+    /// used for testing code that relies on the order timers fire in.
+    pub timer_chaos: bool,
+
+    /// The seed for the RNG used by the timer chaos mode.
+    pub timer_chaos_seed: Option<usize>,
+
     /// Do not use native titlebar
     pub no_native_titlebar: bool,
 
@@ -205,6 +231,9 @@ pub struct Opts {
 
     // don't skip any backtraces on panic
     pub full_backtraces: bool,
+
+    /// Record where timers were suspended and resumed from, to explain mismatched calls.
+    pub trace_timer_suspensions: bool,
 }
 
 fn print_usage(app: &str, opts: &Options) {
@@ -295,6 +324,8 @@ pub struct DebugOptions {
     // don't skip any backtraces on panic
     pub full_backtraces: bool,
 
+    /// Record where timers were suspended and resumed from.
+    pub trace_timer_suspensions: bool,
 }
 
 
@@ -330,6 +361,7 @@ impl DebugOptions {
                 "wr-stats" => debug_options.webrender_stats = true,
                 "msaa" => debug_options.use_msaa = true,
                 "full-backtraces" => debug_options.full_backtraces = true,
+                "trace-timer-suspensions" => debug_options.trace_timer_suspensions = true,
                 "" => {},
                 _ => return Err(option)
             };
@@ -377,6 +409,8 @@ pub fn print_debug_usage(app: &str) -> ! {
     print_option("wr-stats", "Show WebRender profiler on screen.");
     print_option("msaa", "Use multisample antialiasing in WebRender.");
     print_option("full-backtraces", "Print full backtraces for all errors");
+    print_option("trace-timer-suspensions",
+                 "Record the callers suspending and resuming timers, and log them on mismatched calls.");
 
     println!("");
 
@@ -518,6 +552,13 @@ pub fn default_opts() -> Opts {
         disable_share_style_cache: false,
         convert_mouse_to_touch: false,
         exit_after_load: false,
+        page_load_timeout: None,
+        virtual_time_budget: None,
+        record_timer_events: None,
+        replay_timer_events: None,
+        timer_log: None,
+        timer_chaos: false,
+        timer_chaos_seed: None,
         no_native_titlebar: false,
         enable_vsync: true,
         use_webrender: false,
@@ -526,6 +567,7 @@ pub fn default_opts() -> Opts {
         render_api: DEFAULT_RENDER_API,
         profile_dir: None,
         full_backtraces: false,
+        trace_timer_suspensions: false,
     }
 }
 
@@ -547,6 +589,20 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
                     "");
     opts.optflagopt("m", "memory-profile", "Memory profiler flag and output interval", "10");
     opts.optflag("x", "exit", "Exit after load flag");
+    opts.optopt("", "page-load-timeout",
+                "Notify the embedder when a page hasn't loaded after this many seconds", "30");
+    opts.optopt("", "virtual-time-budget",
+                "Fire timers without waiting for them, up to this many milliseconds of skipped time", "5000");
+    opts.optopt("", "record-timer-events",
+                "Write the timer events of each page to this directory when it is closed", "timer-events");
+    opts.optopt("", "replay-timer-events",
+                "Fire timers as recorded in this directory by --record-timer-events", "timer-events");
+    opts.optopt("", "timer-log",
+                "Append a JSON record of everything that happens to the timers of each page to this file",
+                "timers.log");
+    opts.optflag("", "timer-chaos",
+                 "Shuffle timers due at the same time and delay timers a little (for testing timer ordering).");
+    opts.optopt("", "timer-chaos-seed", "A fixed seed for repeatability of the timer chaos mode.", "");
     opts.optopt("y", "layout-threads", "Number of threads to use for layout", "1");
     opts.optflag("i", "nonincremental-layout", "Enable to turn off incremental layout.");
     opts.optflagopt("", "userscripts",
@@ -715,6 +771,12 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
         })
     );
 
+    let timer_chaos_seed = opt_match.opt_str("timer-chaos-seed").map(|seed|
+        seed.parse().unwrap_or_else(|err| {
+            args_fail(&format!("Error parsing option: --timer-chaos-seed ({})", err))
+        })
+    );
+
     let mut bubble_inline_sizes_separately = debug_options.bubble_widths;
     if debug_options.trace_layout {
         paint_threads = 1;
@@ -722,6 +784,14 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
         bubble_inline_sizes_separately = true;
     }
 
+    let page_load_timeout = opt_match.opt_str("page-load-timeout").map(|timeout| {
+        timeout.parse().unwrap_or_else(|err| args_fail(&format!("Error parsing option: --page-load-timeout ({})", err)))
+    });
+
+    let virtual_time_budget = opt_match.opt_str("virtual-time-budget").map(|budget| {
+        budget.parse().unwrap_or_else(|err| args_fail(&format!("Error parsing option: --virtual-time-budget ({})", err)))
+    });
+
     let devtools_port = opt_match.opt_default("devtools", "6000").map(|port| {
         port.parse().unwrap_or_else(|err| args_fail(&format!("Error parsing option: --devtools ({})", err)))
     });
@@ -828,6 +898,13 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
         disable_share_style_cache: debug_options.disable_share_style_cache,
         convert_mouse_to_touch: debug_options.convert_mouse_to_touch,
         exit_after_load: opt_match.opt_present("x"),
+        page_load_timeout: page_load_timeout,
+        virtual_time_budget: virtual_time_budget,
+        record_timer_events: opt_match.opt_str("record-timer-events"),
+        replay_timer_events: opt_match.opt_str("replay-timer-events"),
+        timer_log: opt_match.opt_str("timer-log"),
+        timer_chaos: opt_match.opt_present("timer-chaos"),
+        timer_chaos_seed: timer_chaos_seed,
         no_native_titlebar: do_not_use_native_titlebar,
         enable_vsync: !debug_options.disable_vsync,
         use_webrender: use_webrender,
@@ -835,6 +912,7 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
         use_msaa: debug_options.use_msaa,
         profile_dir: opt_match.opt_str("profile-dir"),
         full_backtraces: debug_options.full_backtraces,
+        trace_timer_suspensions: debug_options.trace_timer_suspensions,
     };
 
     set_defaults(opts);
@@ -61,7 +61,9 @@ use webdriver::server::{self, Session, WebDriverHandler};
 fn extension_routes() -> Vec<(Method, &'static str, ServoExtensionRoute)> {
     return vec![(Post, "/session/{sessionId}/servo/prefs/get", ServoExtensionRoute::GetPrefs),
                 (Post, "/session/{sessionId}/servo/prefs/set", ServoExtensionRoute::SetPrefs),
-                (Post, "/session/{sessionId}/servo/prefs/reset", ServoExtensionRoute::ResetPrefs)]
+                (Post, "/session/{sessionId}/servo/prefs/reset", ServoExtensionRoute::ResetPrefs),
+                (Post, "/session/{sessionId}/servo/timers/advance", ServoExtensionRoute::AdvanceTimers),
+                (Post, "/session/{sessionId}/servo/timers/flush", ServoExtensionRoute::FlushTimers)]
 }
 
 pub fn start_server(port: u16, constellation_chan: Sender<ConstellationMsg>) {
@@ -92,6 +94,8 @@ enum ServoExtensionRoute {
     GetPrefs,
     SetPrefs,
     ResetPrefs,
+    AdvanceTimers,
+    FlushTimers,
 }
 
 impl WebDriverExtensionRoute for ServoExtensionRoute {
@@ -113,6 +117,14 @@ impl WebDriverExtensionRoute for ServoExtensionRoute {
                 let parameters: GetPrefsParameters = try!(Parameters::from_json(&body_data));
                 ServoExtensionCommand::ResetPrefs(parameters)
             }
+            ServoExtensionRoute::AdvanceTimers => {
+                let parameters: AdvanceTimersParameters = try!(Parameters::from_json(&body_data));
+                ServoExtensionCommand::AdvanceTimers(parameters)
+            }
+            ServoExtensionRoute::FlushTimers => {
+                // Flushing runs what is due without moving the clock.
+                ServoExtensionCommand::AdvanceTimers(AdvanceTimersParameters { ms: 0 })
+            }
         };
         Ok(WebDriverCommand::Extension(command))
     }
@@ -123,6 +135,7 @@ enum ServoExtensionCommand {
     GetPrefs(GetPrefsParameters),
     SetPrefs(SetPrefsParameters),
     ResetPrefs(GetPrefsParameters),
+    AdvanceTimers(AdvanceTimersParameters),
 }
 
 impl WebDriverExtensionCommand for ServoExtensionCommand {
@@ -131,6 +144,7 @@ impl WebDriverExtensionCommand for ServoExtensionCommand {
             ServoExtensionCommand::GetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::SetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::ResetPrefs(ref x) => Some(x.to_json()),
+            ServoExtensionCommand::AdvanceTimers(ref x) => Some(x.to_json()),
         }
     }
 }
@@ -207,6 +221,35 @@ impl ToJson for SetPrefsParameters {
     }
 }
 
+#[derive(Clone, PartialEq)]
+struct AdvanceTimersParameters {
+    ms: u64
+}
+
+impl Parameters for AdvanceTimersParameters {
+    fn from_json(body: &Json) -> WebDriverResult<AdvanceTimersParameters> {
+        let data = try!(body.as_object().ok_or(
+            WebDriverError::new(ErrorStatus::InvalidArgument,
+                                "Message body was not an object")));
+        let ms = try!(try!(data.get("ms").ok_or(
+            WebDriverError::new(ErrorStatus::InvalidArgument,
+                                "Missing ms key"))).as_u64().ok_or(
+            WebDriverError::new(ErrorStatus::InvalidArgument,
+                                "ms was not a non-negative integer")));
+        Ok(AdvanceTimersParameters {
+            ms: ms
+        })
+    }
+}
+
+impl ToJson for AdvanceTimersParameters {
+    fn to_json(&self) -> Json {
+        let mut data = BTreeMap::new();
+        data.insert("ms".to_owned(), self.ms.to_json());
+        Json::Object(data)
+    }
+}
+
 impl WebDriverSession {
     pub fn new() -> WebDriverSession {
         WebDriverSession {
@@ -772,6 +815,15 @@ impl Handler {
         };
         Ok(WebDriverResponse::Generic(ValueResponse::new(prefs.to_json())))
     }
+
+    fn handle_advance_timers(&self,
+                             parameters: &AdvanceTimersParameters) -> WebDriverResult<WebDriverResponse> {
+        // Later commands reach the script threads after this one, so they see the timers moved on.
+        let pipeline_id = try!(self.root_pipeline());
+        let cmd_msg = WebDriverCommandMsg::AdvanceTimers(pipeline_id, parameters.ms);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(WebDriverResponse::Void)
+    }
 }
 
 impl WebDriverHandler<ServoExtensionRoute> for Handler {
@@ -826,6 +878,7 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                     ServoExtensionCommand::GetPrefs(ref x) => self.handle_get_prefs(x),
                     ServoExtensionCommand::SetPrefs(ref x) => self.handle_set_prefs(x),
                     ServoExtensionCommand::ResetPrefs(ref x) => self.handle_reset_prefs(x),
+                    ServoExtensionCommand::AdvanceTimers(ref x) => self.handle_advance_timers(x),
                 }
             }
             _ => Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
@@ -28,6 +28,7 @@ use layers::geometry::DevicePixel;
 use layers::platform::surface::NativeDisplay;
 use msg::constellation_msg::{Key, KeyModifiers};
 use net_traits::net_error_list::NetError;
+use script_traits::TimerSchedulerBackend;
 use std::cell::RefCell;
 use std::ffi::CString;
 use std::os::raw::{c_char, c_void};
@@ -417,6 +418,9 @@ impl WindowMethods for Window {
         }
     }
 
+    fn load_timed_out(&self, _: Url) {
+    }
+
     fn head_parsed(&self) {
         let browser = self.cef_browser.borrow();
         let browser = match *browser {
@@ -506,6 +510,10 @@ impl WindowMethods for Window {
     fn supports_clipboard(&self) -> bool {
         true
     }
+
+    fn timer_scheduler_backend(&self) -> Option<Box<TimerSchedulerBackend>> {
+        None
+    }
 }
 
 struct CefCompositorProxy {
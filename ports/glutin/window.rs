@@ -23,7 +23,7 @@ use layers::platform::surface::NativeDisplay;
 use msg::constellation_msg::{KeyState, NONE, CONTROL, SHIFT, ALT, SUPER};
 use msg::constellation_msg::{self, Key};
 use net_traits::net_error_list::NetError;
-use script_traits::{TouchEventType, TouchpadPressurePhase};
+use script_traits::{TimerSchedulerBackend, TouchEventType, TouchpadPressurePhase};
 use std::cell::{Cell, RefCell};
 #[cfg(not(target_os = "android"))]
 use std::os::raw::c_void;
@@ -709,6 +709,9 @@ impl WindowMethods for Window {
     fn load_error(&self, _: NetError, _: String) {
     }
 
+    fn load_timed_out(&self, _: Url) {
+    }
+
     fn head_parsed(&self) {
     }
 
@@ -865,6 +868,10 @@ impl WindowMethods for Window {
     fn supports_clipboard(&self) -> bool {
         true
     }
+
+    fn timer_scheduler_backend(&self) -> Option<Box<TimerSchedulerBackend>> {
+        None
+    }
 }
 
 struct GlutinCompositorProxy {
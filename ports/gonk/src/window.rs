@@ -25,6 +25,7 @@ use libc::close;
 use libc::size_t;
 use msg::constellation_msg::{Key, KeyModifiers};
 use net_traits::net_error_list::NetError;
+use script_traits::TimerSchedulerBackend;
 use std::ffi::CString;
 use std::mem::{transmute, size_of, zeroed};
 use std::ptr;
@@ -826,6 +827,9 @@ impl WindowMethods for Window {
     fn load_error(&self, _: NetError, _: String) {
     }
 
+    fn load_timed_out(&self, _: Url) {
+    }
+
     fn head_parsed(&self) {
     }
 
@@ -863,6 +867,10 @@ impl WindowMethods for Window {
     fn supports_clipboard(&self) -> bool {
         true
     }
+
+    fn timer_scheduler_backend(&self) -> Option<Box<TimerSchedulerBackend>> {
+        None
+    }
 }
 
 struct GonkCompositorProxy {
@@ -0,0 +1,15 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![feature(test)]
+
+extern crate constellation;
+extern crate euclid;
+extern crate ipc_channel;
+extern crate msg;
+extern crate script_traits;
+extern crate test;
+
+#[cfg(test)] mod timer_scheduler;
+#[cfg(test)] mod timer_wheel;
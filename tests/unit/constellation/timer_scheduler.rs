@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use constellation::TimerScheduler;
+use euclid::length::Length;
+use ipc_channel::ipc;
+use msg::constellation_msg::PipelineId;
+use script_traits::{TimerEventId, TimerEventRequest, TimerPriority, TimerSchedulerMsg, TimerSource};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_user_blocking_events_are_never_early() {
+    let scheduler = TimerScheduler::start(None);
+    let pipeline_id = PipelineId::fake_root_pipeline_id();
+    let (sender, receiver) = ipc::channel().unwrap();
+    let delay = Duration::from_millis(5);
+
+    for id in 0..20 {
+        let requested_at = Instant::now();
+        let request = TimerEventRequest(sender.clone(), TimerSource::FromWindow(pipeline_id), TimerEventId(id),
+                                        Length::new(5), TimerPriority::UserBlocking, pipeline_id, None);
+        scheduler.send(TimerSchedulerMsg::Request(request)).unwrap();
+        receiver.recv().unwrap();
+        assert!(requested_at.elapsed() >= delay);
+    }
+
+    let (stats_sender, stats_receiver) = ipc::channel().unwrap();
+    scheduler.send(TimerSchedulerMsg::GetStats(stats_sender)).unwrap();
+    let latency = stats_receiver.recv().unwrap().user_blocking_latency;
+    assert_eq!(latency.events, 20);
+    assert!(latency.mean_ms <= latency.max_ms);
+    // Generous, so that a loaded machine doesn't fail the test; parking for
+    // the whole delay would be late by about a millisecond every time.
+    assert!(latency.max_ms < 50.);
+
+    let (exit_sender, exit_receiver) = ipc::channel().unwrap();
+    scheduler.send(TimerSchedulerMsg::Exit(exit_sender)).unwrap();
+    exit_receiver.recv().unwrap();
+}
@@ -0,0 +1,112 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use constellation::timer_wheel::TimerWheel;
+use test::Bencher;
+
+/// A small linear congruential generator, so the tests don't need a rand crate.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+#[test]
+fn test_entries_are_dispatched_at_their_tick_on_every_level() {
+    let mut wheel = TimerWheel::new();
+    // Level 0, level 1, level 2, level 3 and the overflow list.
+    let ticks = [5, 70, 5000, 300000, 20000000];
+    for &tick in ticks.iter().rev() {
+        wheel.insert(tick, tick);
+    }
+    assert_eq!(wheel.len(), ticks.len());
+
+    for &tick in &ticks {
+        assert_eq!(wheel.next_due(), Some(tick));
+        assert!(wheel.advance(tick - 1).is_empty());
+        assert_eq!(wheel.advance(tick), vec![tick]);
+    }
+    assert!(wheel.is_empty());
+    assert_eq!(wheel.next_due(), None);
+}
+
+#[test]
+fn test_past_deadlines_are_dispatched_by_the_next_advance() {
+    let mut wheel = TimerWheel::new();
+    assert!(wheel.advance(100).is_empty());
+    assert_eq!(wheel.insert(10, "late"), 101);
+    assert_eq!(wheel.advance(101), vec!["late"]);
+}
+
+#[test]
+fn test_remove_and_retain() {
+    let mut wheel = TimerWheel::new();
+    let first = wheel.insert(1000, 1);
+    let second = wheel.insert(1000, 2);
+    wheel.insert(30000000, 3);
+    wheel.insert(40, 4);
+
+    assert_eq!(wheel.remove(second, |&entry| entry == 2), Some(2));
+    assert_eq!(wheel.remove(second, |&entry| entry == 2), None);
+    wheel.retain(|&entry| entry != 3);
+    assert_eq!(wheel.len(), 2);
+
+    let mut entries = vec![];
+    wheel.for_each(|&entry| entries.push(entry));
+    entries.sort();
+    assert_eq!(entries, vec![1, 4]);
+    assert_eq!(wheel.advance(first), vec![4, 1]);
+}
+
+#[test]
+fn test_random_entries_are_never_early_and_never_lost() {
+    let mut wheel = TimerWheel::new();
+    let mut rng = Lcg(1);
+    let mut now = 0;
+    let mut outstanding = 0;
+    for id in 0..10000 {
+        let due = now + rng.next(20000000);
+        wheel.insert(due, (due, id));
+        outstanding += 1;
+        if id % 10 == 0 {
+            now += rng.next(100000);
+            let fired = wheel.advance(now);
+            assert!(fired.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+            assert!(fired.iter().all(|&(due, _)| due <= now));
+            outstanding -= fired.len();
+        }
+        assert_eq!(wheel.len(), outstanding);
+    }
+
+    while let Some(due) = wheel.next_due() {
+        let fired = wheel.advance(due);
+        assert!(!fired.is_empty());
+        assert!(fired.iter().all(|&(fired_due, _)| fired_due == due));
+        outstanding -= fired.len();
+    }
+    assert_eq!(outstanding, 0);
+}
+
+// 10k outstanding requests spread over a minute, dispatched frame by frame.
+#[bench]
+fn bench_insert_and_dispatch_10k(b: &mut Bencher) {
+    let mut rng = Lcg(1);
+    let deadlines: Vec<u64> = (0..10000).map(|_| rng.next(60000)).collect();
+    b.iter(|| {
+        let mut wheel = TimerWheel::new();
+        for (id, &due) in deadlines.iter().enumerate() {
+            wheel.insert(due, id);
+        }
+        let mut dispatched = 0;
+        let mut now = 0;
+        while !wheel.is_empty() {
+            now += 16;
+            dispatched += wheel.advance(now).len();
+        }
+        assert_eq!(dispatched, deadlines.len());
+    });
+}
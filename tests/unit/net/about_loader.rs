@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc;
+use msg::constellation_msg::{PipelineId, ReferrerPolicy};
+use net::about_loader::factory;
+use net::mime_classifier::MIMEClassifier;
+use net::resource_thread::CancellationListener;
+use net_traits::LoadConsumer::Channel;
+use net_traits::ProgressMsg::{Done, Payload};
+use net_traits::{LoadContext, LoadData, LoadOrigin, RequestSource};
+use std::sync::Arc;
+use url::Url;
+
+struct AboutLoadTest;
+
+impl LoadOrigin for AboutLoadTest {
+    fn referrer_url(&self) -> Option<Url> {
+        None
+    }
+    fn referrer_policy(&self) -> Option<ReferrerPolicy> {
+        None
+    }
+    fn request_source(&self) -> RequestSource {
+        RequestSource::None
+    }
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        None
+    }
+}
+
+#[test]
+fn test_about_timers_keeps_its_url() {
+    let url = Url::parse("about:timers").unwrap();
+    let (start_chan, start_port) = ipc::channel().unwrap();
+    factory(LoadData::new(LoadContext::Browsing, url.clone(), &AboutLoadTest),
+            Channel(start_chan),
+            Arc::new(MIMEClassifier::new()),
+            CancellationListener::new(None));
+
+    let response = start_port.recv().unwrap();
+    assert_eq!(response.metadata.final_url, url);
+    match response.progress_port.recv().unwrap() {
+        Payload(body) => assert!(String::from_utf8(body).unwrap().contains("collectTimerDiagnostics")),
+        progress => panic!("Expected the page, got {:?}", progress),
+    }
+    assert_eq!(response.progress_port.recv().unwrap(), Done(Ok(())));
+}
@@ -19,6 +19,7 @@ extern crate unicase;
 extern crate url;
 extern crate util;
 
+#[cfg(test)] mod about_loader;
 #[cfg(test)] mod chrome_loader;
 #[cfg(test)] mod cookie;
 #[cfg(test)] mod cookie_http_state;
@@ -27,5 +28,6 @@ extern crate util;
 #[cfg(test)] mod fetch;
 #[cfg(test)] mod mime_classifier;
 #[cfg(test)] mod resource_thread;
+#[cfg(test)] mod storage_thread;
 #[cfg(test)] mod hsts;
 #[cfg(test)] mod http_loader;
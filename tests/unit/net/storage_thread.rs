@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::{self, IpcSender};
+use net::storage_thread::StorageThreadFactory;
+use net_traits::storage_thread::{StorageThreadMsg, StorageType};
+use std::iter;
+use url::Url;
+
+const QUOTA_SIZE_LIMIT: usize = 5 * 1024 * 1024;
+
+fn set_item(thread: &IpcSender<StorageThreadMsg>, url: &Url, name: &str, value: String)
+            -> Result<(bool, Option<String>), ()> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread.send(StorageThreadMsg::SetItem(sender, url.clone(), StorageType::Session,
+                                          name.to_owned(), value)).unwrap();
+    receiver.recv().unwrap()
+}
+
+fn get_item(thread: &IpcSender<StorageThreadMsg>, url: &Url, name: &str) -> Option<String> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread.send(StorageThreadMsg::GetItem(sender, url.clone(), StorageType::Session, name.to_owned())).unwrap();
+    receiver.recv().unwrap()
+}
+
+#[test]
+fn test_set_item_over_quota_is_rejected() {
+    let thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new();
+    let url = Url::parse("http://example.com/").unwrap();
+
+    assert_eq!(set_item(&thread, &url, "key", "old".to_owned()), Ok((true, None)));
+    let huge: String = iter::repeat('x').take(QUOTA_SIZE_LIMIT).collect();
+    assert_eq!(set_item(&thread, &url, "key", huge), Err(()));
+    // The rejected write must leave the previous value in place.
+    assert_eq!(get_item(&thread, &url, "key"), Some("old".to_owned()));
+
+    thread.send(StorageThreadMsg::Exit).unwrap();
+}
+
+#[test]
+fn test_quota_is_shared_by_an_origin() {
+    let thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new();
+    let url = Url::parse("http://example.com/").unwrap();
+    let other_url = Url::parse("http://example.org/").unwrap();
+
+    let half: String = iter::repeat('x').take(QUOTA_SIZE_LIMIT / 2).collect();
+    assert!(set_item(&thread, &url, "a", half.clone()).is_ok());
+    assert_eq!(set_item(&thread, &url, "b", half.clone()), Err(()));
+    // Another origin has a quota of its own.
+    assert!(set_item(&thread, &other_url, "b", half).is_ok());
+
+    thread.send(StorageThreadMsg::Exit).unwrap();
+}
+
+#[test]
+fn test_remove_item_replies_with_old_value() {
+    let thread: IpcSender<StorageThreadMsg> = StorageThreadFactory::new();
+    let url = Url::parse("http://example.com/").unwrap();
+    assert!(set_item(&thread, &url, "a", "1".to_owned()).is_ok());
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread.send(StorageThreadMsg::RemoveItem(sender, url.clone(), StorageType::Session, "a".to_owned())).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Some("1".to_owned()));
+    // The key is gone as soon as the reply arrives.
+    assert_eq!(get_item(&thread, &url, "a"), None);
+
+    thread.send(StorageThreadMsg::Exit).unwrap();
+}
@@ -0,0 +1,71 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc;
+use script::document_loader::{DocumentLoader, LoadType};
+use std::sync::Arc;
+use url::Url;
+
+const SECOND: u64 = 1000 * 1000 * 1000;
+
+fn loader() -> DocumentLoader {
+    let (resource_thread, _) = ipc::channel().unwrap();
+    DocumentLoader::new_with_thread(Arc::new(resource_thread), None, None)
+}
+
+fn image(url: &str) -> LoadType {
+    LoadType::Image(Url::parse(url).unwrap())
+}
+
+#[test]
+fn test_blocking_loads_are_counted() {
+    let mut loader = loader();
+    let a = loader.add_blocking_load(image("http://example.com/a.png"));
+    loader.add_blocking_load(image("http://example.com/b.png"));
+    assert_eq!(loader.blocking_load_count(), 2);
+
+    assert_eq!(loader.finish_load(a), Some(image("http://example.com/a.png")));
+    assert_eq!(loader.blocking_load_count(), 1);
+    assert!(loader.is_blocked());
+}
+
+#[test]
+fn test_overdue_loads_are_reported_once() {
+    let mut loader = loader();
+    let a = loader.add_blocking_load(image("http://example.com/a.png"));
+    assert_eq!(loader.next_overdue_in(0, 30 * SECOND), Some(30 * SECOND));
+
+    let far_future = u64::max_value();
+    let overdue = loader.take_overdue_loads(far_future, 30 * SECOND);
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0].0, a);
+    assert_eq!(overdue[0].1, image("http://example.com/a.png"));
+
+    assert!(loader.take_overdue_loads(far_future, 30 * SECOND).is_empty());
+    assert_eq!(loader.next_overdue_in(far_future, 30 * SECOND), None);
+}
+
+#[test]
+fn test_released_load_finishes_quietly() {
+    let mut loader = loader();
+    let a = loader.add_blocking_load(image("http://example.com/a.png"));
+    loader.release_load(a);
+    assert!(!loader.is_blocked());
+
+    assert_eq!(loader.finish_load(a), None);
+    assert!(!loader.is_blocked());
+}
+
+#[test]
+fn test_released_load_is_told_apart_from_identical_load() {
+    let mut loader = loader();
+    let first = loader.add_blocking_load(image("http://example.com/a.png"));
+    loader.release_load(first);
+    let second = loader.add_blocking_load(image("http://example.com/a.png"));
+
+    // The load that is still blocking finishing first unblocks the document.
+    assert_eq!(loader.finish_load(second), Some(image("http://example.com/a.png")));
+    assert!(!loader.is_blocked());
+    assert_eq!(loader.finish_load(first), None);
+}
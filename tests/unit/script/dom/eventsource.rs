@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::dom::eventsource::{EventStreamItem, EventStreamParser, StreamEvent};
+
+fn event(type_: &str, data: &str, last_event_id: &str) -> EventStreamItem {
+    EventStreamItem::Dispatch {
+        last_event_id: last_event_id.to_owned(),
+        event: Some(StreamEvent {
+            type_: type_.to_owned(),
+            data: data.to_owned(),
+        }),
+    }
+}
+
+#[test]
+fn test_event_stream_fields() {
+    let mut parser = EventStreamParser::new();
+    let items = parser.feed(b": comment\nevent: update\ndata: first\ndata:second\nid: 7\nretry: 100\n\n");
+    assert_eq!(items, vec![EventStreamItem::Retry(100), event("update", "first\nsecond", "7")]);
+
+    // The event type is reset after every event, the last event ID isn't.
+    let items = parser.feed(b"data\n\n");
+    assert_eq!(items, vec![event("message", "", "7")]);
+}
+
+#[test]
+fn test_event_stream_without_data_only_sets_the_last_event_id() {
+    let mut parser = EventStreamParser::new();
+    let items = parser.feed(b"event: ignored\nid: 1\nretry: soon\n\n");
+    assert_eq!(items, vec![EventStreamItem::Dispatch { last_event_id: "1".to_owned(), event: None }]);
+}
+
+#[test]
+fn test_event_stream_split_across_chunks() {
+    let mut parser = EventStreamParser::new();
+    // A BOM, a CRLF split between chunks and a character split between chunks.
+    assert_eq!(parser.feed(b"\xEF\xBB\xBFdata: caf\xC3"), vec![]);
+    assert_eq!(parser.feed(b"\xA9\r"), vec![]);
+    assert_eq!(parser.feed(b"\n\r"), vec![event("message", "caf\u{e9}", "")]);
+
+    // An event the stream ends in the middle of is never dispatched.
+    assert_eq!(parser.feed(b"data: incomplete\n"), vec![]);
+}
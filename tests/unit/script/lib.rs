@@ -5,16 +5,28 @@
 #![feature(plugin)]
 #![plugin(plugins)]
 
+extern crate euclid;
+extern crate ipc_channel;
 extern crate msg;
+extern crate rustc_serialize;
 extern crate script;
+extern crate script_traits;
 extern crate url;
 extern crate util;
 
+#[cfg(test)] mod document_loader;
+#[cfg(test)] mod long_tasks;
+#[cfg(test)] mod microtask;
 #[cfg(test)] mod origin;
+#[cfg(test)] mod retry_timer;
 #[cfg(all(test, target_pointer_width = "64"))] mod size_of;
 #[cfg(test)] mod textinput;
+#[cfg(test)] mod time_resolution;
+#[cfg(test)] mod timers;
+#[cfg(test)] mod viewport_events;
 #[cfg(test)] mod dom {
     mod bindings;
     mod blob;
+    mod eventsource;
     mod xmlhttprequest;
 }
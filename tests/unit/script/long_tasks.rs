@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use msg::constellation_msg::{PipelineId, PipelineNamespace, PipelineNamespaceId};
+use script::long_tasks::{LONG_TASK_THRESHOLD_NS, LongTaskCulprits};
+use util::thread_state;
+
+fn culprits() -> (LongTaskCulprits, PipelineId, PipelineId) {
+    // The culprits are kept in a DOMRefCell, which checks for the script thread.
+    thread_state::initialize(thread_state::SCRIPT);
+    PipelineNamespace::install(PipelineNamespaceId(1));
+    (LongTaskCulprits::new(), PipelineId::new(), PipelineId::new())
+}
+
+#[test]
+fn test_long_task_is_reported_to_the_windows_whose_script_ran() {
+    let (culprits, first, second) = culprits();
+    culprits.script_ran(first);
+    culprits.script_ran(second);
+    culprits.script_ran(first);
+    assert_eq!(culprits.end_task(0, LONG_TASK_THRESHOLD_NS + 1), vec![first, second]);
+}
+
+#[test]
+fn test_short_task_is_not_reported() {
+    let (culprits, first, _) = culprits();
+    culprits.script_ran(first);
+    assert!(culprits.end_task(0, LONG_TASK_THRESHOLD_NS).is_empty());
+}
+
+#[test]
+fn test_long_task_is_not_reported_to_windows_that_ran_script_in_earlier_tasks() {
+    let (culprits, first, second) = culprits();
+    culprits.script_ran(first);
+    culprits.end_task(0, 1);
+
+    culprits.script_ran(second);
+    assert_eq!(culprits.end_task(0, LONG_TASK_THRESHOLD_NS + 1), vec![second]);
+}
@@ -0,0 +1,29 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::microtask::run_script;
+use std::cell::RefCell;
+
+#[test]
+fn test_microtasks_run_once_the_outermost_script_returns() {
+    let log = RefCell::new(vec![]);
+    run_script(|| {
+        log.borrow_mut().push("outer");
+        // E.g. an event listener called by dispatchEvent from the outer script.
+        run_script(|| log.borrow_mut().push("inner"), || log.borrow_mut().push("too early"));
+        log.borrow_mut().push("outer returns");
+    }, || log.borrow_mut().push("microtasks"));
+    assert_eq!(*log.borrow(), vec!["outer", "inner", "outer returns", "microtasks"]);
+}
+
+#[test]
+fn test_microtasks_run_between_callbacks_of_one_task() {
+    // E.g. two timers that are due in the same timer event, or two listeners
+    // of the same event: the microtasks of the first run before the second.
+    let log = RefCell::new(vec![]);
+    for callback in &["first", "second"] {
+        run_script(|| log.borrow_mut().push(*callback), || log.borrow_mut().push("microtasks"));
+    }
+    assert_eq!(*log.borrow(), vec!["first", "microtasks", "second", "microtasks"]);
+}
@@ -0,0 +1,46 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::retry_timer::RetryPolicy;
+
+fn policy(jitter: f64, max_attempts: Option<u32>) -> RetryPolicy {
+    RetryPolicy {
+        initial_delay_ms: 1000,
+        multiplier: 3,
+        max_delay_ms: 20000,
+        jitter: jitter,
+        max_attempts: max_attempts,
+    }
+}
+
+#[test]
+fn test_delay_grows_up_to_the_maximum() {
+    let policy = policy(0., None);
+    let delays: Vec<_> = (0..5).map(|attempt| policy.delay(attempt, 0.5).unwrap()).collect();
+    assert_eq!(delays, vec![1000, 3000, 9000, 20000, 20000]);
+    assert_eq!(policy.delay(1000, 0.5), Some(20000));
+}
+
+#[test]
+fn test_initial_delay_longer_than_the_maximum() {
+    let mut policy = policy(0., None);
+    policy.initial_delay_ms = 60000;
+    assert_eq!(policy.delay(0, 0.), Some(60000));
+    assert_eq!(policy.delay(3, 0.), Some(60000));
+}
+
+#[test]
+fn test_jitter_shortens_the_delay() {
+    let policy = policy(0.5, None);
+    assert_eq!(policy.delay(1, 0.), Some(3000));
+    assert_eq!(policy.delay(1, 0.5), Some(2250));
+    assert_eq!(policy.delay(1, 1.), Some(1500));
+}
+
+#[test]
+fn test_attempts_run_out() {
+    let policy = policy(0., Some(2));
+    assert_eq!(policy.delay(1, 0.), Some(3000));
+    assert_eq!(policy.delay(2, 0.), None);
+}
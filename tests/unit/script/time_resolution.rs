@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::time_resolution::{TimeResolutionPolicy, is_cross_origin_isolated};
+use url::Url;
+
+#[test]
+fn precise_policy_keeps_time() {
+    let policy = TimeResolutionPolicy::precise();
+    assert_eq!(policy.coarsen_ns(123456789), 123456789);
+}
+
+#[test]
+fn coarsens_down_to_resolution() {
+    let policy = TimeResolutionPolicy::new(100 * 1000);
+    assert_eq!(policy.coarsen_ns(123456789), 123400000);
+    assert_eq!(policy.coarsen_ns(100 * 1000), 100 * 1000);
+    assert_eq!(policy.coarsen_ns(99 * 1000), 0);
+}
+
+#[test]
+fn isolated_origins_are_more_precise() {
+    let isolated = TimeResolutionPolicy::for_origin(true);
+    let not_isolated = TimeResolutionPolicy::for_origin(false);
+    assert!(isolated.resolution_ns() < not_isolated.resolution_ns());
+}
+
+#[test]
+fn documents_opt_into_isolation_with_both_headers() {
+    let url = Url::parse("https://example.com/").unwrap();
+    assert!(is_cross_origin_isolated(&url, Some("same-origin"), Some("require-corp")));
+    assert!(is_cross_origin_isolated(&url, Some("same-origin"), Some("require-corp; report-to=\"endpoint\"")));
+    assert!(!is_cross_origin_isolated(&url, Some("same-origin"), None));
+    assert!(!is_cross_origin_isolated(&url, None, Some("require-corp")));
+    assert!(!is_cross_origin_isolated(&url, Some("same-origin-allow-popups"), Some("require-corp")));
+}
+
+#[test]
+fn only_secure_contexts_are_isolated() {
+    let insecure = Url::parse("http://example.com/").unwrap();
+    assert!(!is_cross_origin_isolated(&insecure, Some("same-origin"), Some("require-corp")));
+    let local = Url::parse("http://localhost:8000/").unwrap();
+    assert!(is_cross_origin_isolated(&local, Some("same-origin"), Some("require-corp")));
+}
@@ -0,0 +1,1523 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use euclid::length::Length;
+use ipc_channel::ipc::{self, IpcReceiver};
+use msg::constellation_msg::PipelineId;
+use rustc_serialize::json;
+use script::test::timers::{InputMonitor, IntervalCatchUp, IsInterval, OneshotTimers, ScheduledCallback};
+use script::test::timers::{SuspensionReason, TimerBudget, TimerClock, TimerEventRecord, TimerLogRecord};
+use script::test::timers::{TimerLogSink, TimerSchedulerChan, chain_async_stack};
+use script::time_resolution::TimeResolutionPolicy;
+use script_traits::{MsDuration, NsDuration, TimerEvent, TimerEventId, TimerEventRequest};
+use script_traits::{TimerSchedulerMsg, TimerSource};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::rc::Rc;
+use util::thread_state;
+
+/// Records the messages sent to the timer scheduler instead of sending them,
+/// so that the tests can check what `OneshotTimers` asks for.
+#[derive(Clone, Default)]
+struct DummyTimerSchedulerChan {
+    sent: Rc<RefCell<Vec<TimerSchedulerMsg>>>,
+}
+
+impl DummyTimerSchedulerChan {
+    /// Returns the messages sent since the last call, oldest first.
+    fn take_sent(&self) -> Vec<TimerSchedulerMsg> {
+        self.sent.borrow_mut().drain(..).collect()
+    }
+}
+
+impl TimerSchedulerChan for DummyTimerSchedulerChan {
+    fn send(&self, msg: TimerSchedulerMsg) -> Result<(), ()> {
+        self.sent.borrow_mut().push(msg);
+        Ok(())
+    }
+}
+
+/// Lets the tests say whether input is pending.
+#[derive(Clone, Default)]
+struct DummyInputMonitor {
+    pending: Rc<Cell<bool>>,
+}
+
+impl DummyInputMonitor {
+    fn new() -> DummyInputMonitor {
+        DummyInputMonitor::default()
+    }
+
+    fn set_input_pending(&self, pending: bool) {
+        self.pending.set(pending);
+    }
+}
+
+impl InputMonitor for DummyInputMonitor {
+    fn input_pending(&self) -> bool {
+        self.pending.get()
+    }
+}
+
+/// Keeps the records of the timer log, so that the tests can check what is logged.
+#[derive(Clone, Default)]
+struct DummyTimerLogSink {
+    records: Rc<RefCell<Vec<TimerLogRecord>>>,
+}
+
+impl DummyTimerLogSink {
+    fn new() -> DummyTimerLogSink {
+        DummyTimerLogSink::default()
+    }
+
+    /// Returns the records logged since the last call, oldest first.
+    fn take_records(&self) -> Vec<TimerLogRecord> {
+        self.records.borrow_mut().drain(..).collect()
+    }
+}
+
+impl TimerLogSink for DummyTimerLogSink {
+    fn log(&self, record: TimerLogRecord) {
+        self.records.borrow_mut().push(record);
+    }
+}
+
+/// A clock that only moves when it is told to, so that the tests can step
+/// through time. It can also be made to misbehave like real clocks do: to
+/// jump back, and to run fast or slow.
+#[derive(Clone, Default)]
+struct VirtualTimerClock {
+    now: Rc<Cell<u64>>,
+    /// How many ns per ms the clock gains, or loses if negative, as time passes.
+    skew: Rc<Cell<i64>>,
+}
+
+impl VirtualTimerClock {
+    fn new() -> VirtualTimerClock {
+        VirtualTimerClock::default()
+    }
+
+    /// Lets `duration` pass; the clock moves ahead by it, give or take its skew.
+    fn advance(&self, duration: MsDuration) {
+        let skew = duration.get() as i64 * self.skew.get();
+        let elapsed = cmp::max(0, (duration.get() * 1000 * 1000) as i64 + skew) as u64;
+        self.now.set(self.now.get() + elapsed);
+    }
+
+    /// Moves the clock back by `duration` at once, but not before its epoch.
+    fn jump_back(&self, duration: MsDuration) {
+        self.now.set(self.now.get().saturating_sub(duration.get() * 1000 * 1000));
+    }
+
+    /// Makes the clock gain `skew` ns per ms that passes from now on, or lose
+    /// them if it is negative; -1000 * 1000 stops it.
+    fn set_skew(&self, skew: i64) {
+        self.skew.set(skew);
+    }
+}
+
+impl TimerClock for VirtualTimerClock {
+    fn now(&self) -> NsDuration {
+        Length::new(self.now.get())
+    }
+}
+
+/// A callback that runs once, known by its number, so that the tests can
+/// check the order callbacks fire in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Plain(u32);
+
+impl ScheduledCallback for Plain {}
+
+/// A callback that says whether it may be throttled while hidden.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Throttleable(u32, bool);
+
+impl ScheduledCallback for Throttleable {
+    fn throttleable(&self) -> bool {
+        self.1
+    }
+}
+
+/// A callback that repeats with the given period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Interval(u32, MsDuration);
+
+impl ScheduledCallback for Interval {
+    fn repeat_period(&self) -> Option<MsDuration> {
+        Some(self.1)
+    }
+
+    fn next_occurrence(&self) -> Option<Interval> {
+        Some(*self)
+    }
+}
+
+/// A callback that repeats with the given period and catch-up policy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CatchingUp(u32, MsDuration, IntervalCatchUp);
+
+impl ScheduledCallback for CatchingUp {
+    fn repeat_period(&self) -> Option<MsDuration> {
+        Some(self.1)
+    }
+
+    fn next_occurrence(&self) -> Option<CatchingUp> {
+        Some(*self)
+    }
+
+    fn catch_up(&self) -> Option<IntervalCatchUp> {
+        Some(self.2)
+    }
+}
+
+fn timers_with_clock<C: ScheduledCallback>()
+                     -> (OneshotTimers<C>, DummyTimerSchedulerChan, IpcReceiver<TimerEvent>, VirtualTimerClock) {
+    // The timers are kept in a DOMRefCell, which checks for the script thread.
+    thread_state::initialize(thread_state::SCRIPT);
+    let (timer_event_chan, timer_event_port) = ipc::channel().unwrap();
+    let scheduler_chan = DummyTimerSchedulerChan::default();
+    let clock = VirtualTimerClock::new();
+    let timers = OneshotTimers::new(PipelineId::fake_root_pipeline_id(),
+                                    timer_event_chan,
+                                    Box::new(scheduler_chan.clone()),
+                                    Box::new(clock.clone()));
+    (timers, scheduler_chan, timer_event_port, clock)
+}
+
+// The clock of these timers stands still.
+fn timers<C: ScheduledCallback>() -> (OneshotTimers<C>, DummyTimerSchedulerChan, IpcReceiver<TimerEvent>) {
+    let (timers, scheduler_chan, timer_event_port, _) = timers_with_clock();
+    (timers, scheduler_chan, timer_event_port)
+}
+
+fn window() -> TimerSource {
+    TimerSource::FromWindow(PipelineId::fake_root_pipeline_id())
+}
+
+fn requested_id(msg: &TimerSchedulerMsg) -> TimerEventId {
+    match *msg {
+        TimerSchedulerMsg::Request(TimerEventRequest(_, _, id, _, _, _, _)) => id,
+        _ => panic!("expected a request"),
+    }
+}
+
+fn requested_delay(msg: &TimerSchedulerMsg) -> u64 {
+    match *msg {
+        TimerSchedulerMsg::Request(TimerEventRequest(_, _, _, delay, _, _, _)) => delay.get(),
+        _ => panic!("expected a request"),
+    }
+}
+
+fn requested_period(msg: &TimerSchedulerMsg) -> Option<u64> {
+    match *msg {
+        TimerSchedulerMsg::Request(TimerEventRequest(_, _, _, _, _, _, period)) => period.map(|period| period.get()),
+        _ => panic!("expected a request"),
+    }
+}
+
+fn cancelled_id(msg: &TimerSchedulerMsg) -> TimerEventId {
+    match *msg {
+        TimerSchedulerMsg::Cancel(_, id) => id,
+        _ => panic!("expected a cancel"),
+    }
+}
+
+#[test]
+fn test_first_timer_is_requested() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert!(requested_delay(&sent[0]) <= 1000);
+    assert!(requested_delay(&sent[0]) > 900);
+}
+
+#[test]
+fn test_later_timer_is_not_requested() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    scheduler.take_sent();
+
+    timers.schedule_callback(Plain(2), Length::new(2000), window());
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_earlier_timer_replaces_request() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(2000), window());
+    let first = requested_id(&scheduler.take_sent()[0]);
+
+    timers.schedule_callback(Plain(2), Length::new(1000), window());
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), first);
+    assert!(requested_id(&sent[1]) != first);
+    assert!(requested_delay(&sent[1]) <= 1000);
+}
+
+#[test]
+fn test_worker_requests_are_not_cancelled() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(2000), TimerSource::FromWorker);
+    scheduler.take_sent();
+
+    timers.schedule_callback(Plain(2), Length::new(1000), TimerSource::FromWorker);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(requested_delay(&sent[0]), 1000);
+}
+
+#[test]
+fn test_unscheduling_next_timer_requests_following_one() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    let first = timers.schedule_callback(Plain(1), Length::new(1000), window());
+    timers.schedule_callback(Plain(2), Length::new(2000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.unschedule_callback(first);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert!(requested_delay(&sent[1]) > 1000);
+}
+
+#[test]
+fn test_unscheduling_later_timer_keeps_request() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    let second = timers.schedule_callback(Plain(2), Length::new(2000), window());
+    scheduler.take_sent();
+
+    timers.unschedule_callback(second);
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_unscheduling_last_timer_only_cancels() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    let handle = timers.schedule_callback(Plain(1), Length::new(1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.unschedule_callback(handle);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+}
+
+#[test]
+fn test_suspend_cancels_and_resume_requests_again() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.suspend(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+
+    // Nothing is requested while suspended.
+    timers.schedule_callback(Plain(2), Length::new(500), window());
+    assert!(scheduler.take_sent().is_empty());
+
+    timers.resume(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert!(requested_id(&sent[0]) != requested);
+    assert!(requested_delay(&sent[0]) <= 500);
+}
+
+#[test]
+fn test_stale_event_is_ignored() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    let stale = requested_id(&scheduler.take_sent()[0]);
+    timers.suspend(SuspensionReason::Frozen);
+    timers.resume(SuspensionReason::Frozen);
+    scheduler.take_sent();
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(stale, |Plain(callback)| fired.borrow_mut().push(callback));
+    assert!(fired.borrow().is_empty());
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_due_timers_fire_in_order() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    timers.schedule_callback(Plain(2), Length::new(0), window());
+    timers.schedule_callback(Plain(3), Length::new(0), window());
+    timers.schedule_callback(Plain(4), Length::new(60000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested, |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2, 3]);
+
+    // The remaining timer is requested once the due ones have run.
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert!(requested_delay(&sent[0]) > 50000);
+}
+
+#[test]
+fn test_timer_scheduled_while_firing_waits_for_next_event() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested, |Plain(callback)| {
+        fired.borrow_mut().push(callback);
+        timers.schedule_callback(Plain(callback + 1), Length::new(0), window());
+    });
+    assert_eq!(*fired.borrow(), vec![1]);
+
+    let sent = scheduler.take_sent();
+    let next = requested_id(sent.last().unwrap());
+    timers.fire_timer_with(next, |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2]);
+}
+
+fn interval(id: u32, period: u64) -> Interval {
+    Interval(id, Length::new(period))
+}
+
+#[test]
+fn test_interval_is_requested_once() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(interval(1, 200), Length::new(0), window());
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(requested_period(&sent[0]), Some(200));
+    let requested = requested_id(&sent[0]);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested, |Interval(callback, _)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+    // The scheduler delivers the next run without being asked again.
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_worker_intervals_do_not_repeat_in_scheduler() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(interval(1, 200), Length::new(0), TimerSource::FromWorker);
+    assert_eq!(requested_period(&scheduler.take_sent()[0]), None);
+}
+
+#[test]
+fn test_clearing_interval_cancels_repeating_request() {
+    let (timers, scheduler, _port) = timers();
+    let handle = timers.schedule_callback(interval(1, 200), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+    timers.fire_timer_with(requested, |_| {});
+
+    timers.unschedule_callback(handle);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+}
+
+#[test]
+fn test_interval_unscheduled_by_earlier_timer_of_same_event_does_not_run() {
+    let (timers, scheduler, _port) = timers();
+    let first = timers.schedule_callback(interval(1, 200), Length::new(0), window());
+    let second = timers.schedule_callback(interval(2, 200), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested, |Interval(callback, _)| {
+        fired.borrow_mut().push(callback);
+        timers.unschedule_callback(second);
+    });
+    assert_eq!(*fired.borrow(), vec![1]);
+
+    // The unscheduled interval wasn't rearmed either, so nothing is left
+    // to request once the other one is cleared.
+    scheduler.take_sent();
+    timers.unschedule_callback(first);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+}
+
+#[test]
+fn test_suspend_cancels_repeating_request() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(interval(1, 200), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+    timers.fire_timer_with(requested, |_| {});
+
+    timers.suspend(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+
+    timers.resume(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(requested_period(&sent[0]), Some(200));
+}
+
+#[test]
+fn test_exempt_timer_is_not_throttled_while_hidden() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, false), Length::new(0), window());
+    assert_eq!(requested_delay(&scheduler.take_sent()[0]), 0);
+}
+
+#[test]
+fn test_exempt_timer_is_requested_behind_throttled_timer() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    scheduler.take_sent();
+
+    timers.schedule_callback(Throttleable(2, false), Length::new(0), window());
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 0);
+}
+
+#[test]
+fn test_pending_timers_are_requested_again_once_visible() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(1000 * 1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.set_hidden(false);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert_eq!(requested_delay(&sent[1]), 1000 * 1000);
+}
+
+#[test]
+fn test_overdue_timers_fire_right_away_once_visible_again() {
+    let (timers, scheduler, port) = timers::<Throttleable>();
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.set_hidden(false);
+    // The event is queued directly, rather than requested from the scheduler.
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+
+    let TimerEvent(_, id, _) = port.recv().unwrap();
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(id, |Throttleable(callback, _)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+}
+
+#[test]
+fn test_pending_timers_are_requested_again_once_frame_is_seen() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_frame_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(1000 * 1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.set_frame_hidden(false);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert_eq!(requested_delay(&sent[1]), 1000 * 1000);
+}
+
+#[test]
+fn test_budget_is_charged_for_running_timers() {
+    let mut budget = TimerBudget::new();
+    let full = budget.level(Length::new(1000));
+    budget.charge(Length::new(1000), Length::new(5 * 1000 * 1000));
+    assert_eq!(budget.level(Length::new(1000)), full - 5 * 1000 * 1000);
+    assert_eq!(budget.available_at(Length::new(1000)).get(), 1000);
+}
+
+#[test]
+fn test_budget_regenerates_slowly() {
+    let mut budget = TimerBudget::new();
+    let full = budget.level(Length::new(1000));
+    budget.charge(Length::new(1000), Length::new(full as u64 + 10 * 1000 * 1000));
+
+    // 10ms of running time take a second to make up for.
+    assert!(budget.level(Length::new(1999)) < 0);
+    assert_eq!(budget.level(Length::new(2000)), 0);
+    assert_eq!(budget.available_at(Length::new(1500)).get(), 2000);
+}
+
+#[test]
+fn test_budget_does_not_exceed_maximum() {
+    let mut budget = TimerBudget::new();
+    let full = budget.level(Length::new(0));
+    budget.charge(Length::new(0), Length::new(1000 * 1000));
+    assert_eq!(budget.level(Length::new(1000 * 1000)), full);
+}
+
+#[test]
+fn test_exemption_from_throttling_reschedules_hidden_timers() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.set_throttling_exempt(true);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert_eq!(requested_delay(&sent[1]), 0);
+}
+
+#[test]
+fn test_exemption_from_throttling_keeps_visible_timers() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    scheduler.take_sent();
+
+    timers.set_throttling_exempt(true);
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_exempt_document_is_not_throttled_while_hidden() {
+    let (timers, scheduler, _port) = timers::<Throttleable>();
+    timers.set_throttling_exempt(true);
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    assert_eq!(requested_delay(&scheduler.take_sent()[0]), 0);
+}
+
+#[test]
+fn test_overlapping_suspensions_resume_once_all_are_lifted() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    scheduler.take_sent();
+
+    timers.suspend(SuspensionReason::Frozen);
+    timers.suspend(SuspensionReason::ModalDialog);
+    scheduler.take_sent();
+
+    timers.resume(SuspensionReason::Frozen);
+    assert!(timers.is_suspended());
+    assert!(!timers.is_suspended_for(SuspensionReason::Frozen));
+    assert!(scheduler.take_sent().is_empty());
+
+    timers.resume(SuspensionReason::ModalDialog);
+    assert!(!timers.is_suspended());
+    assert_eq!(scheduler.take_sent().len(), 1);
+}
+
+#[test]
+fn test_nested_suspensions_for_the_same_reason_are_counted() {
+    let (timers, _scheduler, _port) = timers::<Plain>();
+    timers.suspend(SuspensionReason::Debugger);
+    timers.suspend(SuspensionReason::Debugger);
+
+    timers.resume(SuspensionReason::Debugger);
+    assert!(timers.is_suspended_for(SuspensionReason::Debugger));
+
+    timers.resume(SuspensionReason::Debugger);
+    assert!(!timers.is_suspended());
+}
+
+#[test]
+fn test_resuming_without_suspension_is_ignored() {
+    let (timers, _scheduler, _port) = timers::<Plain>();
+    timers.suspend(SuspensionReason::Frozen);
+    timers.resume(SuspensionReason::ModalDialog);
+    assert!(timers.is_suspended());
+}
+
+#[test]
+fn test_extra_resume_leaves_later_suspensions_balanced() {
+    let (timers, _scheduler, _port) = timers::<Plain>();
+    timers.suspend(SuspensionReason::Debugger);
+    timers.resume(SuspensionReason::Debugger);
+    timers.resume(SuspensionReason::Debugger);
+    assert!(!timers.is_suspended());
+
+    timers.suspend(SuspensionReason::Debugger);
+    assert!(timers.is_suspended());
+    timers.resume(SuspensionReason::Debugger);
+    assert!(!timers.is_suspended());
+}
+
+#[test]
+fn test_scoped_suspension_resumes_when_dropped() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    scheduler.take_sent();
+
+    {
+        let _suspension = timers.suspend_scoped(SuspensionReason::SyncXhr);
+        assert!(timers.is_suspended_for(SuspensionReason::SyncXhr));
+        scheduler.take_sent();
+    }
+    assert!(!timers.is_suspended());
+    assert_eq!(scheduler.take_sent().len(), 1);
+}
+
+#[test]
+fn test_timer_due_during_sync_xhr_fires_right_after_it() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    let start = timers.current_time();
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    scheduler.take_sent();
+
+    {
+        let _suspension = timers.suspend_scoped(SuspensionReason::SyncXhr);
+        clock.advance(Length::new(300));
+    }
+    // The time the request took passed for the timers as well.
+    assert_eq!(timers.current_time() - start, Length::new(300));
+    let sent = scheduler.take_sent();
+    let requested = sent.last().unwrap();
+    assert_eq!(requested_delay(requested), 0);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(requested), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+}
+
+#[test]
+fn test_time_spent_in_modal_dialog_is_left_out() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    let start = timers.current_time();
+    timers.suspend(SuspensionReason::ModalDialog);
+    clock.advance(Length::new(300));
+    timers.resume(SuspensionReason::ModalDialog);
+    assert_eq!(timers.current_time(), start);
+}
+
+/// A JS timer, an internal callback, or an internal callback that has to
+/// run even while the others are suspended.
+#[derive(Debug, PartialEq)]
+enum TestCallback {
+    Js(u32),
+    Internal(u32),
+    Exempt(u32),
+}
+
+impl ScheduledCallback for TestCallback {
+    fn throttleable(&self) -> bool {
+        true
+    }
+
+    fn exempt_from_suspension(&self) -> bool {
+        match *self {
+            TestCallback::Exempt(_) => true,
+            _ => false,
+        }
+    }
+
+    fn is_script_timer(&self) -> bool {
+        match *self {
+            TestCallback::Js(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn test_exempt_timer_fires_while_suspended() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(0), window());
+    timers.suspend(SuspensionReason::Frozen);
+    scheduler.take_sent();
+
+    timers.schedule_callback(TestCallback::Exempt(2), Length::new(0), window());
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(&sent[0]), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Exempt(2)]);
+    assert!(scheduler.take_sent().is_empty());
+
+    timers.resume(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Exempt(2), TestCallback::Js(1)]);
+}
+
+#[test]
+fn test_suspend_requests_pending_exempt_timer() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(0), window());
+    timers.schedule_callback(TestCallback::Exempt(2), Length::new(1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.suspend(SuspensionReason::ModalDialog);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert!(requested_delay(&sent[1]) > 900);
+}
+
+#[test]
+fn test_timer_exempt_from_suspension_is_not_throttled_while_hidden() {
+    // Even though the callback is throttleable, the timer has to fire on time.
+    let (timers, scheduler, _port) = timers();
+    timers.set_hidden(true);
+    timers.schedule_callback(TestCallback::Exempt(1), Length::new(0), window());
+    assert_eq!(requested_delay(&scheduler.take_sent()[0]), 0);
+}
+
+#[test]
+fn test_internal_callbacks_keep_running_while_script_is_suspended() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(0), window());
+    timers.schedule_callback(TestCallback::Internal(2), Length::new(0), window());
+    timers.suspend(SuspensionReason::ModalDialog);
+    let sent = scheduler.take_sent();
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Internal(2)]);
+}
+
+#[test]
+fn test_freezing_suspends_internal_callbacks() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Internal(1), Length::new(0), window());
+    scheduler.take_sent();
+
+    timers.suspend(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    cancelled_id(&sent[0]);
+}
+
+#[test]
+fn test_freezing_during_script_suspension_stops_internal_callbacks() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Internal(1), Length::new(0), window());
+    timers.suspend(SuspensionReason::Debugger);
+    let requested = requested_id(scheduler.take_sent().last().unwrap());
+
+    timers.suspend(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+
+    // Thawing lets the internal callback run again, though the debugger still pauses script.
+    timers.resume(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Internal(1)]);
+}
+
+#[test]
+fn test_freeze_detaches_from_scheduler() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.suspend(SuspensionReason::Frozen);
+    assert!(timers.is_frozen());
+    let sent = scheduler.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(cancelled_id(&sent[0]), requested);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested, |Plain(callback)| fired.borrow_mut().push(callback));
+    assert!(fired.borrow().is_empty());
+}
+
+#[test]
+fn test_timers_survive_several_freeze_and_thaw_cycles() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    timers.schedule_callback(Plain(2), Length::new(0), window());
+    scheduler.take_sent();
+
+    for _ in 0..3 {
+        timers.suspend(SuspensionReason::Frozen);
+        timers.resume(SuspensionReason::Frozen);
+        assert!(!timers.is_frozen());
+    }
+
+    let sent = scheduler.take_sent();
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![2]);
+
+    // The other timer still has most of its time to wait.
+    let sent = scheduler.take_sent();
+    assert!(requested_delay(sent.last().unwrap()) > 900);
+}
+
+#[test]
+fn test_frozen_timer_can_be_unscheduled() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    let handle = timers.schedule_callback(Plain(1), Length::new(0), window());
+    timers.suspend(SuspensionReason::Frozen);
+    timers.unschedule_callback(handle);
+    scheduler.take_sent();
+
+    timers.resume(SuspensionReason::Frozen);
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_frozen_timers_can_be_saved_and_restored() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(1000), window());
+    timers.suspend(SuspensionReason::Frozen);
+    let saved = json::encode(&timers.snapshot()).unwrap();
+
+    let (restored, scheduler, _port) = timers::<Plain>();
+    restored.restore_with(&json::decode(&saved).unwrap(), |_| Some(Plain(1)));
+    assert!(restored.is_frozen());
+    assert!(scheduler.take_sent().is_empty());
+
+    restored.resume(SuspensionReason::Frozen);
+    assert!(!restored.is_frozen());
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 1000);
+}
+
+#[test]
+fn test_freeze_keeps_exempt_timers() {
+    let (timers, scheduler, _port) = timers();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(0), window());
+    timers.schedule_callback(TestCallback::Exempt(2), Length::new(0), window());
+    timers.suspend(SuspensionReason::Frozen);
+    let sent = scheduler.take_sent();
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Exempt(2)]);
+}
+
+#[test]
+fn test_overdue_timers_are_spread_after_resume() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.set_overdue_batch_size(Some(2));
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    timers.schedule_callback(Plain(2), Length::new(0), window());
+    timers.schedule_callback(Plain(3), Length::new(0), window());
+    timers.suspend(SuspensionReason::Frozen);
+    timers.resume(SuspensionReason::Frozen);
+
+    let fired = RefCell::new(vec![]);
+    let sent = scheduler.take_sent();
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2]);
+
+    // The rest of the backlog is requested right away.
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(&sent[0]), 0);
+    timers.fire_timer_with(requested_id(&sent[0]), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_due_timers_yield_to_pending_input() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    let input = DummyInputMonitor::new();
+    timers.set_input_monitor(Some(Box::new(input.clone())));
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    timers.schedule_callback(Plain(2), Length::new(0), window());
+    timers.schedule_callback(Plain(3), Length::new(0), window());
+
+    // At least one timer runs per event, however much input is waiting.
+    input.set_input_pending(true);
+    let fired = RefCell::new(vec![]);
+    let sent = scheduler.take_sent();
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+
+    // The rest are requested right away, to run after the input.
+    input.set_input_pending(false);
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(&sent[0]), 0);
+    timers.fire_timer_with(requested_id(&sent[0]), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_timers_due_outside_backlog_are_not_spread() {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.set_overdue_batch_size(Some(1));
+    timers.schedule_callback(Plain(1), Length::new(0), window());
+    timers.schedule_callback(Plain(2), Length::new(0), window());
+
+    let fired = RefCell::new(vec![]);
+    let sent = scheduler.take_sent();
+    timers.fire_timer_with(requested_id(&sent[0]), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2]);
+}
+
+#[test]
+fn test_interval_on_time_keeps_its_cadence() {
+    for catch_up in &[IntervalCatchUp::Restart, IntervalCatchUp::Skip, IntervalCatchUp::Once] {
+        assert_eq!(catch_up.next_run(Length::new(1000), Length::new(100), Length::new(1050)).get(), 1100);
+    }
+}
+
+#[test]
+fn test_interval_restarts_after_missed_runs() {
+    let next = IntervalCatchUp::Restart.next_run(Length::new(1000), Length::new(100), Length::new(1650));
+    assert_eq!(next.get(), 1750);
+}
+
+#[test]
+fn test_interval_skips_missed_runs() {
+    let next = IntervalCatchUp::Skip.next_run(Length::new(1000), Length::new(100), Length::new(1650));
+    assert_eq!(next.get(), 1700);
+}
+
+#[test]
+fn test_interval_catches_up_once() {
+    let next = IntervalCatchUp::Once.next_run(Length::new(1000), Length::new(100), Length::new(1650));
+    assert_eq!(next.get(), 1650);
+}
+
+#[test]
+fn test_interval_without_period_runs_again_right_away() {
+    let next = IntervalCatchUp::Skip.next_run(Length::new(1000), Length::new(0), Length::new(1650));
+    assert_eq!(next.get(), 1650);
+}
+
+#[test]
+fn test_timers_fire_in_deadline_order() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(3), Length::new(300), window());
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    timers.schedule_callback(Plain(2), Length::new(200), window());
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 100);
+
+    clock.advance(Length::new(300));
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_suspension_does_not_count_towards_deadlines() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(500), window());
+    clock.advance(Length::new(200));
+
+    timers.suspend(SuspensionReason::Frozen);
+    clock.advance(Length::new(10 * 1000));
+    scheduler.take_sent();
+
+    timers.resume(SuspensionReason::Frozen);
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 300);
+}
+
+#[test]
+fn test_hidden_timers_are_aligned_to_whole_seconds() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Throttleable>();
+    clock.advance(Length::new(100));
+    timers.set_hidden(true);
+    timers.schedule_callback(Throttleable(1, true), Length::new(50), window());
+    assert_eq!(requested_delay(&scheduler.take_sent()[0]), 900);
+}
+
+#[test]
+fn test_timers_hidden_for_long_are_aligned_to_whole_minutes() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Throttleable>();
+    clock.advance(Length::new(100));
+    timers.set_hidden(true);
+    clock.advance(Length::new(5 * 60 * 1000));
+    timers.schedule_callback(Throttleable(1, true), Length::new(0), window());
+    assert_eq!(requested_delay(&scheduler.take_sent()[0]), 60 * 1000 - 100);
+}
+
+#[test]
+fn test_late_interval_runs_a_period_later_by_default() {
+    let (timers, scheduler, _port, clock) = timers_with_clock();
+    timers.schedule_callback(interval(1, 100), Length::new(100), window());
+    let sent = scheduler.take_sent();
+
+    clock.advance(Length::new(850));
+    timers.fire_timer_with(requested_id(&sent[0]), |_| {});
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 100);
+}
+
+#[test]
+fn test_late_interval_keeps_its_cadence() {
+    let (timers, scheduler, _port, clock) = timers_with_clock();
+    timers.set_interval_catch_up(IntervalCatchUp::Skip);
+    timers.schedule_callback(interval(1, 100), Length::new(100), window());
+    let sent = scheduler.take_sent();
+
+    clock.advance(Length::new(850));
+    timers.fire_timer_with(requested_id(&sent[0]), |_| {});
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 50);
+}
+
+#[test]
+fn test_interval_with_its_own_catch_up_policy() {
+    let (timers, scheduler, _port, clock) = timers_with_clock();
+    timers.schedule_callback(CatchingUp(1, Length::new(100), IntervalCatchUp::Once), Length::new(100), window());
+    let sent = scheduler.take_sent();
+
+    clock.advance(Length::new(850));
+    timers.fire_timer_with(requested_id(&sent[0]), |_| {});
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 0);
+}
+
+#[test]
+fn test_interval_is_rearmed_after_its_callback() {
+    let (timers, scheduler, _port, clock) = timers_with_clock();
+    timers.schedule_callback(interval(1, 100), Length::new(100), window());
+    let sent = scheduler.take_sent();
+
+    clock.advance(Length::new(100));
+    // The callback takes long enough to miss the next two runs.
+    timers.fire_timer_with(requested_id(&sent[0]), |_| clock.advance(Length::new(250)));
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 100);
+}
+
+#[test]
+fn test_interval_cleared_by_its_callback_does_not_run_again() {
+    let (timers, scheduler, _port) = timers();
+    let handle = timers.schedule_callback(interval(1, 100), Length::new(0), window());
+    let requested = requested_id(&scheduler.take_sent()[0]);
+
+    timers.fire_timer_with(requested, |_| timers.unschedule_callback(handle));
+    assert!(timers.time_until_next_due().is_none());
+}
+
+#[test]
+fn test_timer_events_are_recorded() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    clock.advance(Length::new(500));
+    // The times are recorded from when the recording starts.
+    timers.record_events();
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    timers.schedule_callback(Plain(2), Length::new(100), window());
+    let requested = requested_id(scheduler.take_sent().last().unwrap());
+
+    clock.advance(Length::new(100));
+    timers.fire_timer_with(requested, |_| {});
+    assert_eq!(timers.take_recorded_events(), vec![TimerEventRecord { time: 100, fired: vec![1, 2] }]);
+}
+
+#[test]
+fn test_timer_events_are_replayed() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    clock.advance(Length::new(500));
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    timers.schedule_callback(Plain(2), Length::new(100), window());
+    // The times are replayed from when the replay starts.
+    timers.replay_events(vec![TimerEventRecord { time: 50, fired: vec![2] }]);
+    let sent = scheduler.take_sent();
+    assert_eq!(requested_delay(sent.last().unwrap()), 50);
+
+    // The recorded timer fires at the recorded time, although it isn't due.
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![2]);
+
+    // Then the timers run live again.
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 50);
+}
+
+fn fire_in_chaos_mode(seed: usize) -> Vec<u32> {
+    let (timers, scheduler, _port) = timers::<Plain>();
+    timers.set_chaos_seed(Some(seed));
+    for id in 1..9 {
+        timers.schedule_callback(Plain(id), Length::new(0), window());
+    }
+    timers.schedule_callback(Plain(9), Length::new(1), window());
+    let sent = scheduler.take_sent();
+    // The event may be delayed, but by no more than a few ms.
+    assert!(requested_delay(sent.last().unwrap()) <= 4);
+
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |Plain(callback)| fired.borrow_mut().push(callback));
+    fired.into_inner()
+}
+
+#[test]
+fn test_chaos_mode_shuffles_timers_due_together() {
+    let mut fired = fire_in_chaos_mode(7);
+    // The timer due later isn't among them.
+    assert!(!fired.contains(&9));
+    // The same seed gives the same order.
+    assert_eq!(fired, fire_in_chaos_mode(7));
+    fired.sort();
+    assert_eq!(fired, (1..9).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_flushing_runs_due_timers_right_away() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(10), window());
+    timers.schedule_callback(Plain(2), Length::new(50), window());
+    let requested = requested_id(scheduler.take_sent().last().unwrap());
+
+    let fired = RefCell::new(vec![]);
+    // Nothing is due yet.
+    timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback));
+    assert!(fired.borrow().is_empty());
+    assert!(scheduler.take_sent().is_empty());
+
+    clock.advance(Length::new(10));
+    timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+    let sent = scheduler.take_sent();
+    assert_eq!(cancelled_id(&sent[0]), requested);
+    assert_eq!(requested_delay(&sent[1]), 40);
+
+    // The event the scheduler had been asked for is stale now.
+    timers.fire_timer_with(requested, |Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1]);
+}
+
+#[test]
+fn test_quiescent_until_a_timer_is_due_within_the_window() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    assert!(timers.is_quiescent(Length::new(1000)));
+
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    assert!(timers.is_quiescent(Length::new(50)));
+    assert!(!timers.is_quiescent(Length::new(100)));
+
+    clock.advance(Length::new(60));
+    assert!(!timers.is_quiescent(Length::new(50)));
+
+    // A timer that is due but whose event hasn't been handled is still pending.
+    clock.advance(Length::new(60));
+    assert!(!timers.is_quiescent(Length::new(0)));
+    timers.flush_due_timers_with(|_| ());
+    assert!(timers.is_quiescent(Length::new(1000)));
+}
+
+#[test]
+fn test_suspended_timers_keep_a_pipeline_from_being_quiescent() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<TestCallback>();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(100), window());
+    clock.advance(Length::new(60));
+
+    // The held timer is as close to due as it was when the suspension began.
+    timers.suspend(SuspensionReason::ModalDialog);
+    clock.advance(Length::new(1000));
+    assert!(!timers.is_quiescent(Length::new(50)));
+    assert!(timers.is_quiescent(Length::new(30)));
+    timers.resume(SuspensionReason::ModalDialog);
+
+    timers.suspend(SuspensionReason::Frozen);
+    clock.advance(Length::new(1000));
+    assert!(!timers.is_quiescent(Length::new(50)));
+    assert!(timers.is_quiescent(Length::new(30)));
+    timers.resume(SuspensionReason::Frozen);
+
+    clock.advance(Length::new(40));
+    timers.flush_due_timers_with(|_| ());
+    timers.suspend(SuspensionReason::ModalDialog);
+    assert!(timers.is_quiescent(Length::new(1000)));
+}
+
+#[test]
+fn test_callback_run_now_or_held_until_the_timers_resume() {
+    let (timers, scheduler, _port) = timers();
+    assert_eq!(timers.run_now_or_hold(TestCallback::Js(1), window()), Some(TestCallback::Js(1)));
+    assert!(scheduler.take_sent().is_empty());
+
+    timers.suspend(SuspensionReason::ModalDialog);
+    assert_eq!(timers.run_now_or_hold(TestCallback::Js(2), window()), None);
+    // Internal callbacks keep running while only script is suspended.
+    assert_eq!(timers.run_now_or_hold(TestCallback::Internal(3), window()), Some(TestCallback::Internal(3)));
+    timers.resume(SuspensionReason::ModalDialog);
+
+    let sent = scheduler.take_sent();
+    let fired = RefCell::new(vec![]);
+    timers.fire_timer_with(requested_id(sent.last().unwrap()), |callback| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![TestCallback::Js(2)]);
+}
+
+#[test]
+fn test_callback_held_while_frozen_is_discarded_with_the_frozen_timers() {
+    let (timers, scheduler, _port) = timers();
+    timers.suspend(SuspensionReason::Frozen);
+    assert_eq!(timers.run_now_or_hold(TestCallback::Js(1), window()), None);
+    assert_eq!(timers.run_now_or_hold(TestCallback::Internal(2), window()), None);
+    scheduler.take_sent();
+
+    timers.discard_frozen();
+    timers.resume(SuspensionReason::Frozen);
+    assert!(scheduler.take_sent().is_empty());
+}
+
+#[test]
+fn test_flushing_from_a_timer_callback_leaves_the_due_timers_in_order() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(10), window());
+    timers.schedule_callback(Plain(2), Length::new(20), window());
+    clock.advance(Length::new(20));
+
+    // Like a frame ticked by a timer that flushes the due timers first.
+    let fired = RefCell::new(vec![]);
+    assert!(timers.flush_due_timers_with(|Plain(callback)| {
+        fired.borrow_mut().push(callback);
+        if callback == 1 {
+            assert!(!timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback + 100)));
+        }
+    }));
+    assert_eq!(*fired.borrow(), vec![1, 2]);
+}
+
+#[test]
+fn test_no_idle_period_while_a_timer_is_due() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    assert_eq!(timers.idle_period(Length::new(50)).map(|period| period.get()), Some(50));
+
+    timers.schedule_callback(Plain(1), Length::new(30), window());
+    assert_eq!(timers.idle_period(Length::new(50)).map(|period| period.get()), Some(30));
+
+    // A zero-length idle period isn't one; it waits for the timer to run.
+    clock.advance(Length::new(30));
+    assert!(timers.idle_period(Length::new(50)).is_none());
+    timers.flush_due_timers_with(|_| ());
+    assert_eq!(timers.idle_period(Length::new(50)).map(|period| period.get()), Some(50));
+}
+
+#[test]
+fn test_time_until_next_due_counts_due_timers_as_due_now() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    assert!(timers.time_until_next_due().is_none());
+
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    clock.advance(Length::new(60));
+    assert_eq!(timers.time_until_next_due().map(|until| until.get()), Some(40));
+
+    clock.advance(Length::new(60));
+    assert_eq!(timers.time_until_next_due().map(|until| until.get()), Some(0));
+    timers.flush_due_timers_with(|_| ());
+    assert!(timers.time_until_next_due().is_none());
+}
+
+#[test]
+fn test_current_time_leaves_out_suspensions() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    clock.advance(Length::new(100));
+    assert_eq!(timers.current_time().get(), 100);
+
+    timers.suspend(SuspensionReason::Frozen);
+    clock.advance(Length::new(50));
+    assert_eq!(timers.current_time().get(), 100);
+
+    timers.resume(SuspensionReason::Frozen);
+    clock.advance(Length::new(10));
+    assert_eq!(timers.current_time().get(), 110);
+}
+
+#[test]
+fn test_time_resolution_coarsens_the_timer_clock() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.set_time_resolution(TimeResolutionPolicy::new(100 * 1000 * 1000));
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+
+    clock.advance(Length::new(60));
+    assert_eq!(timers.time_until_next_due().map(|until| until.get()), Some(100));
+
+    clock.advance(Length::new(40));
+    assert_eq!(timers.time_until_next_due().map(|until| until.get()), Some(0));
+}
+
+#[test]
+fn test_inspecting_the_timers_lists_the_next_due_first() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Interval>();
+    timers.schedule_callback(interval(1, 100), Length::new(100), window());
+    timers.schedule_callback(interval(2, 30), Length::new(30), window());
+    clock.advance(Length::new(10));
+
+    let described = timers.inspect_timers(|&Interval(id, period), remaining| (id, remaining.get(), period.get()));
+    assert_eq!(described, vec![(2, 20, 30), (1, 90, 100)]);
+}
+
+#[test]
+fn test_stats_sum_up_the_timers() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<TestCallback>();
+    timers.schedule_callback(TestCallback::Js(1), Length::new(100), window());
+    timers.schedule_callback(TestCallback::Internal(2), Length::new(30), window());
+    clock.advance(Length::new(10));
+    timers.suspend(SuspensionReason::ModalDialog);
+
+    let stats = timers.stats("about:blank".to_owned());
+    assert_eq!((stats.timers, stats.script_timers), (2, 1));
+    // Internal callbacks keep running while a dialog is shown.
+    assert_eq!(stats.next_due, Some(20));
+    assert_eq!(stats.suspensions, vec![("ModalDialog".to_owned(), 1)]);
+    assert!(!stats.throttled && !stats.frozen);
+}
+
+#[test]
+fn test_the_timer_log_follows_the_timers() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<TestCallback>();
+    let sink = DummyTimerLogSink::new();
+    timers.set_log_sink(Some(Box::new(sink.clone())));
+    timers.schedule_callback(TestCallback::Js(1), Length::new(10), window());
+    let internal = timers.schedule_callback(TestCallback::Internal(2), Length::new(20), window());
+    timers.unschedule_callback(internal);
+    timers.suspend(SuspensionReason::Debugger);
+    timers.resume(SuspensionReason::Debugger);
+    clock.advance(Length::new(15));
+    timers.flush_due_timers_with(|_| ());
+
+    let records = sink.take_records();
+    let logged: Vec<_> = records.iter().map(|record| {
+        (record.event, record.handle, record.delay, record.detail.as_ref().map(|detail| &**detail))
+    }).collect();
+    assert_eq!(logged, vec![("schedule", Some(1), Some(10), Some("script")),
+                            ("schedule", Some(2), Some(20), Some("internal")),
+                            ("cancel", Some(2), None, None),
+                            ("suspend", None, None, Some("Debugger")),
+                            ("resume", None, None, Some("Debugger")),
+                            // The timer was due 5 ms before the timers got to it.
+                            ("fire", Some(1), Some(5), Some("script"))]);
+    assert_eq!(records.last().unwrap().time, 15);
+}
+
+#[test]
+fn test_delay_multiplier_stretches_the_timers() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+    clock.advance(Length::new(20));
+    scheduler.take_sent();
+
+    // The 80 ms still to go become 320 ms, and new timers wait four times as long.
+    timers.set_delay_multiplier(4);
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 320);
+    timers.schedule_callback(Plain(2), Length::new(50), window());
+    let described = timers.inspect_timers(|&Plain(id), remaining| (id, remaining.get()));
+    assert_eq!(described, vec![(2, 200), (1, 320)]);
+
+    timers.set_delay_multiplier(1);
+    let described = timers.inspect_timers(|&Plain(id), remaining| (id, remaining.get()));
+    assert_eq!(described, vec![(2, 50), (1, 80)]);
+}
+
+#[test]
+fn test_restoring_a_snapshot_rolls_the_timers_back() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(10), window());
+    let second = timers.schedule_callback(Plain(2), Length::new(30), window());
+    let snapshot = timers.snapshot();
+
+    let fired = RefCell::new(vec![]);
+    clock.advance(Length::new(10));
+    timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback));
+    timers.unschedule_callback(second);
+    clock.advance(Length::new(100));
+    scheduler.take_sent();
+
+    // The timers go on from the time of the snapshot, with the callbacks bound anew.
+    timers.restore_with(&snapshot, |saved| Some(Plain(if saved.due == 10 { 1 } else { 2 })));
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 10);
+    clock.advance(Length::new(10));
+    timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 1]);
+    clock.advance(Length::new(20));
+    timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback));
+    assert_eq!(*fired.borrow(), vec![1, 1, 2]);
+}
+
+#[test]
+fn test_advancing_the_clock_past_an_interval_without_period_ends() {
+    let (timers, _scheduler, _port) = timers::<Interval>();
+    timers.schedule_callback(interval(1, 0), Length::new(0), window());
+
+    let fired = RefCell::new(0);
+    timers.advance_clock_with(Length::new(10), |_| *fired.borrow_mut() += 1);
+    // The interval runs at most once per ms.
+    assert_eq!(*fired.borrow(), 11);
+    assert_eq!(timers.skipped_time().get(), 10);
+}
+
+#[test]
+fn test_advancing_the_clock_runs_timers_that_become_due() {
+    let (timers, scheduler, _port) = timers::<Interval>();
+    timers.schedule_callback(interval(1, 30), Length::new(30), window());
+    timers.schedule_callback(interval(2, 50), Length::new(50), window());
+    scheduler.take_sent();
+
+    let fired = RefCell::new(vec![]);
+    timers.advance_clock_with(Length::new(100), |Interval(id, _)| fired.borrow_mut().push(id));
+    // The intervals ran as often as they would have in 100ms, in order.
+    assert_eq!(*fired.borrow(), vec![1, 2, 1, 1, 2]);
+
+    // The next run is requested for the clock as it is now.
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 20);
+}
+
+#[test]
+fn test_clock_going_back_stands_still_for_timers() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    clock.advance(Length::new(500));
+    timers.schedule_callback(Plain(1), Length::new(100), window());
+
+    clock.jump_back(Length::new(300));
+    clock.advance(Length::new(350));
+    let fired = RefCell::new(vec![]);
+    assert!(!timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback)));
+
+    // Once the clock has caught up, time moves on.
+    clock.advance(Length::new(50));
+    assert!(timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback)));
+    assert_eq!(*fired.borrow(), vec![1]);
+}
+
+#[test]
+fn test_clock_going_back_during_suspension() {
+    let (timers, scheduler, _port, clock) = timers_with_clock::<Plain>();
+    timers.schedule_callback(Plain(1), Length::new(500), window());
+    clock.advance(Length::new(200));
+
+    timers.suspend(SuspensionReason::Frozen);
+    clock.jump_back(Length::new(150));
+    scheduler.take_sent();
+
+    timers.resume(SuspensionReason::Frozen);
+    assert_eq!(requested_delay(scheduler.take_sent().last().unwrap()), 300);
+}
+
+#[test]
+fn test_clock_running_fast_brings_timers_forward() {
+    let (timers, _scheduler, _port, clock) = timers_with_clock::<Plain>();
+    clock.set_skew(500 * 1000);
+    timers.schedule_callback(Plain(1), Length::new(300), window());
+
+    clock.advance(Length::new(200));
+    let fired = RefCell::new(vec![]);
+    assert!(timers.flush_due_timers_with(|Plain(callback)| fired.borrow_mut().push(callback)));
+    assert_eq!(*fired.borrow(), vec![1]);
+}
+
+#[test]
+fn test_async_stack_names_what_set_the_running_timer() {
+    let stack = "inner@http://a/b.js:3:5".to_owned();
+    assert_eq!(chain_async_stack(stack.clone(), None), stack);
+
+    let running = (IsInterval::NonInterval, "set@http://a/b.js:1:1\ntop@http://a/b.js:9:1");
+    assert_eq!(chain_async_stack(stack.clone(), Some(running)),
+               "inner@http://a/b.js:3:5\nsetTimeout handler*set@http://a/b.js:1:1\ntop@http://a/b.js:9:1");
+
+    let running = (IsInterval::Interval, "set@http://a/b.js:1:1");
+    assert_eq!(chain_async_stack(stack, Some(running)),
+               "inner@http://a/b.js:3:5\nsetInterval handler*set@http://a/b.js:1:1");
+}
+
+#[test]
+fn test_async_stack_is_cut_short() {
+    let stack = (0..20).map(|line| format!("f@http://a/b.js:{}:1", line)).collect::<Vec<_>>().join("\n");
+    let running = stack.clone();
+    assert_eq!(chain_async_stack(stack, Some((IsInterval::Interval, &running))).lines().count(), 32);
+}
@@ -0,0 +1,42 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::viewport_events::{PendingViewportEvents, ViewportEvent};
+
+#[test]
+fn test_events_of_a_kind_are_merged() {
+    let events = PendingViewportEvents::new();
+    assert!(events.queue(ViewportEvent::Resize));
+    assert!(!events.queue(ViewportEvent::Resize));
+    assert!(!events.queue(ViewportEvent::Resize));
+    assert_eq!(events.take(), vec![ViewportEvent::Resize]);
+}
+
+#[test]
+fn test_resize_events_are_fired_before_scroll_events() {
+    let events = PendingViewportEvents::new();
+    assert!(events.queue(ViewportEvent::Scroll));
+    assert!(!events.queue(ViewportEvent::Resize));
+    assert!(!events.queue(ViewportEvent::Scroll));
+    assert_eq!(events.take(), vec![ViewportEvent::Resize, ViewportEvent::Scroll]);
+}
+
+#[test]
+fn test_taking_the_events_lets_the_timer_be_scheduled_again() {
+    let events = PendingViewportEvents::new();
+    events.queue(ViewportEvent::Resize);
+    events.take();
+    assert!(events.take().is_empty());
+    assert!(events.queue(ViewportEvent::Resize));
+}
+
+#[test]
+fn test_interval_can_be_changed_per_window() {
+    let events = PendingViewportEvents::new();
+    let other_events = PendingViewportEvents::new();
+    let default_interval = events.interval_ms();
+    events.set_interval_ms(default_interval + 100);
+    assert_eq!(events.interval_ms(), default_interval + 100);
+    assert_eq!(other_events.interval_ms(), default_interval);
+}